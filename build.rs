@@ -0,0 +1,35 @@
+//! Records the git revision and compiler version for `GET /version`.
+
+use std::{path::Path, process::Command};
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    // HEAD names the branch; the branch ref moves with each commit.
+    for git_file in [".git/HEAD", ".git/refs/heads"] {
+        if Path::new(git_file).exists() {
+            println!("cargo:rerun-if-changed={git_file}");
+        }
+    }
+
+    let git = command_output("git", &["rev-parse", "--short=12", "HEAD"]);
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_owned());
+    let rustc = command_output(&rustc, &["--version"]);
+
+    println!(
+        "cargo:rustc-env=BEAM_GIT_REVISION={}",
+        git.as_deref().unwrap_or("unknown")
+    );
+    println!(
+        "cargo:rustc-env=BEAM_RUSTC_VERSION={}",
+        rustc.as_deref().unwrap_or("unknown")
+    );
+}
+
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let text = String::from_utf8(output.stdout).ok()?;
+    Some(text.trim().to_owned()).filter(|text| !text.is_empty())
+}
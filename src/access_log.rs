@@ -0,0 +1,205 @@
+use std::{
+    pin::Pin,
+    sync::{
+        Arc, OnceLock,
+        atomic::{AtomicU64, Ordering},
+    },
+    task::{Context, Poll, ready},
+    time::Instant,
+};
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{ConnectInfo, MatchedPath, Request, State},
+    http::{Method, StatusCode, header},
+    middleware::Next,
+    response::Response,
+};
+use http_body::{Frame, SizeHint};
+use percent_encoding::percent_decode_str;
+use tracing::Level;
+
+use crate::ClientAddr;
+
+tokio::task_local! {
+    /// Who the request being handled authenticated as, once it has.
+    static USERNAME: OnceLock<String>;
+}
+
+/// Notes `username` as the one the current request authenticated as. Only
+/// the name is kept; credentials never reach the access log.
+pub(crate) fn record_username(username: &str) {
+    let _ = USERNAME.try_with(|slot| slot.set(username.to_owned()));
+}
+
+/// Logs one `beam::access` event at `level` per request with the peer
+/// address, authenticated username, method, path, filename, status, user
+/// agent, body sizes and duration. Transfers stream long after the handler
+/// returns, so the event is emitted when the response body is dropped.
+pub(crate) async fn log_request(
+    State(level): State<Level>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let started = Instant::now();
+    let method = request.method().clone();
+    let path = redact_path(&request);
+    let filename = filename(&request);
+    let client = request
+        .extensions()
+        .get::<ConnectInfo<ClientAddr>>()
+        .map(|ConnectInfo(ClientAddr(client))| client.ip());
+    let user_agent = request
+        .headers()
+        .get(header::USER_AGENT)
+        .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned());
+
+    let bytes_in = Arc::new(AtomicU64::new(0));
+    let request = request.map(|body| {
+        Body::new(CountingBody {
+            inner: body,
+            bytes: bytes_in.clone(),
+            record: None,
+        })
+    });
+
+    let (response, username) = USERNAME
+        .scope(OnceLock::new(), async {
+            let response = next.run(request).await;
+            (response, USERNAME.with(|slot| slot.get().cloned()))
+        })
+        .await;
+    let record = AccessRecord {
+        level,
+        client,
+        username,
+        method,
+        path,
+        filename,
+        status: response.status(),
+        user_agent,
+        started,
+        bytes_in,
+    };
+    response.map(|body| {
+        Body::new(CountingBody {
+            inner: body,
+            bytes: Arc::new(AtomicU64::new(0)),
+            record: Some(record),
+        })
+    })
+}
+
+/// Token links and resumable upload ids are secrets, so requests for their
+/// routes are logged by route, with the secret replaced.
+fn redact_path(request: &Request) -> String {
+    let secret_route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(MatchedPath::as_str)
+        .filter(|route| {
+            route.ends_with("/t/{token}")
+                || route.ends_with("/upload/{id}")
+                || route.ends_with("/upload/{id}/complete")
+        });
+    match secret_route {
+        Some(route) => route
+            .replace("{token}", "<token>")
+            .replace("{id}", "<session>"),
+        None => request.uri().path().to_owned(),
+    }
+}
+
+/// The file a `/{filename}` route names, decoded.
+fn filename(request: &Request) -> Option<String> {
+    let route = request.extensions().get::<MatchedPath>()?;
+    if !route.as_str().ends_with("{filename}") {
+        return None;
+    }
+    let (_, encoded) = request.uri().path().rsplit_once('/')?;
+    Some(percent_decode_str(encoded).decode_utf8_lossy().into_owned())
+}
+
+struct AccessRecord {
+    level: Level,
+    client: Option<std::net::IpAddr>,
+    username: Option<String>,
+    method: Method,
+    path: String,
+    filename: Option<String>,
+    status: StatusCode,
+    user_agent: Option<String>,
+    started: Instant,
+    bytes_in: Arc<AtomicU64>,
+}
+
+/// Passes a body through while counting its data bytes. When it carries an
+/// [`AccessRecord`], the access log line is written as it is dropped.
+struct CountingBody {
+    inner: Body,
+    bytes: Arc<AtomicU64>,
+    record: Option<AccessRecord>,
+}
+
+impl http_body::Body for CountingBody {
+    type Data = Bytes;
+    type Error = axum::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.get_mut();
+        let frame = ready!(Pin::new(&mut this.inner).poll_frame(cx));
+        if let Some(Ok(frame)) = &frame
+            && let Some(data) = frame.data_ref()
+        {
+            this.bytes.fetch_add(data.len() as u64, Ordering::Relaxed);
+        }
+        Poll::Ready(frame)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+impl Drop for CountingBody {
+    fn drop(&mut self) {
+        let Some(record) = self.record.take() else {
+            return;
+        };
+
+        // `event!` needs its level at compile time.
+        macro_rules! access_event {
+            ($level:ident) => {
+                tracing::event!(
+                    target: "beam::access",
+                    Level::$level,
+                    client = record.client.map(tracing::field::display),
+                    username = record.username.as_deref(),
+                    method = %record.method,
+                    path = %record.path,
+                    filename = record.filename.as_deref(),
+                    status = record.status.as_u16(),
+                    user_agent = record.user_agent.as_deref(),
+                    bytes_in = record.bytes_in.load(Ordering::Relaxed),
+                    bytes_out = self.bytes.load(Ordering::Relaxed),
+                    duration_ms = record.started.elapsed().as_millis() as u64,
+                    "request"
+                )
+            };
+        }
+        match record.level {
+            Level::ERROR => access_event!(ERROR),
+            Level::WARN => access_event!(WARN),
+            Level::INFO => access_event!(INFO),
+            Level::DEBUG => access_event!(DEBUG),
+            Level::TRACE => access_event!(TRACE),
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use axum::{
+    Json,
+    body::Body,
+    extract::{ConnectInfo, Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use tracing::warn;
+
+use crate::{
+    AppState, ClientAddr, StreamSource, StreamSummary,
+    auth::{Permission, auth_error_response, authenticate_user, extract_credentials},
+    filename::sanitize_filename,
+    invalid_filename_response, spool,
+};
+
+/// `POST /admin/evict/{filename}`: drops `filename` from the registry
+/// whatever state it is in, e.g. when its upload task died without cleaning
+/// up. Whatever still serves it is cancelled, a spooled file is deleted,
+/// and the response describes the stream as it was when evicted.
+pub(crate) async fn evict_handler(
+    State(state): State<AppState>,
+    ConnectInfo(ClientAddr(client)): ConnectInfo<ClientAddr>,
+    Path(filename): Path<String>,
+    headers: HeaderMap,
+) -> Response<Body> {
+    let auth = match extract_credentials(&headers) {
+        Ok(auth) => auth,
+        Err(err) => return auth_error_response(&state, err),
+    };
+
+    if let Err(err) = authenticate_user(&state, client, &auth, Permission::Admin).await {
+        return auth_error_response(&state, err);
+    }
+
+    let filename = match sanitize_filename(&filename, state.max_filename_len) {
+        Ok(filename) => filename,
+        Err(message) => return invalid_filename_response(message),
+    };
+
+    let Some(stream_data) = state.streams.remove(&filename) else {
+        return (StatusCode::NOT_FOUND, "No stream with this filename").into_response();
+    };
+
+    let evicted = StreamSummary::new(&filename, &stream_data);
+    stream_data.cancel.cancel();
+    if let StreamSource::Spooled(file) = &stream_data.source {
+        spool::discard(file).await;
+    }
+    warn!(%filename, state = evicted.state, username = auth.username(), "Stream evicted by admin");
+
+    Json(evicted).into_response()
+}
@@ -0,0 +1,462 @@
+use std::{
+    collections::HashMap, fmt, io, net::SocketAddr, ops::RangeInclusive, path::Path, time::Duration,
+};
+
+use argon2::password_hash::{SaltString, rand_core::OsRng};
+use argon2::{Algorithm, Argon2, Params, PasswordHash, PasswordHasher, PasswordVerifier, Version};
+use axum::{
+    body::Body,
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use futures_util::future::BoxFuture;
+use headers::{
+    Authorization, Header,
+    authorization::{Basic, Bearer},
+};
+use rand_core::RngCore;
+use subtle::ConstantTimeEq;
+use tracing::{error, warn};
+
+use crate::{
+    AppState, access_log,
+    bearer::Token,
+    config::{DEFAULT_AUTH_REALM, DEFAULT_PASSWORD_HASHING, PasswordHashing},
+    retry_after_secs,
+};
+
+/// A user's secret as supplied at startup.
+#[derive(Clone)]
+pub enum Secret {
+    /// Plaintext password, hashed when the server starts.
+    Password(String),
+    /// Argon2 PHC string, stored verbatim.
+    Hash(String),
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Secret::Password(_) => f.write_str("Password(<redacted>)"),
+            Secret::Hash(_) => f.write_str("Hash(<redacted>)"),
+        }
+    }
+}
+
+/// Which transfers a user's credentials authorize. Any user may view the
+/// metrics, stream list and event feed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    /// Uploads and downloads.
+    Full,
+    /// Uploads, including cancelling them and minting download links.
+    Upload,
+    /// Downloads only.
+    Download,
+    /// Everything [`Access::Full`] allows, plus the `/admin` endpoints.
+    Admin,
+}
+
+impl Access {
+    fn grants(self, permission: Permission) -> bool {
+        match permission {
+            Permission::View => true,
+            Permission::Upload => matches!(self, Access::Full | Access::Upload | Access::Admin),
+            Permission::Download => {
+                matches!(self, Access::Full | Access::Download | Access::Admin)
+            }
+            Permission::Admin => self == Access::Admin,
+        }
+    }
+}
+
+/// What a request needs its credentials to allow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Permission {
+    Upload,
+    Download,
+    /// Read-only views of server state, open to every user.
+    View,
+    /// Operator actions such as evicting a stream.
+    Admin,
+}
+
+/// Users allowed to upload and download, keyed by username with an argon2
+/// PHC string and their [`Access`] as the value.
+pub struct AuthConfig {
+    users: HashMap<String, (String, Access)>,
+    /// Hash of a random password, verified against when the username is
+    /// unknown so that case costs as much as a wrong password.
+    dummy_hash: String,
+    /// `WWW-Authenticate` value sent with every `401`.
+    challenge: HeaderValue,
+    /// Hashes new passwords; verifying takes its parameters from the hash.
+    argon2: Argon2<'static>,
+}
+
+impl AuthConfig {
+    /// Single-user configuration.
+    pub fn new(username: &str, password: &str) -> Result<Self, argon2::password_hash::Error> {
+        Self::with_users([(username.to_owned(), password.to_owned())])
+    }
+
+    /// Hashes each `(username, password)` pair. A repeated username keeps the
+    /// last password given for it.
+    pub fn with_users(
+        users: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Self, argon2::password_hash::Error> {
+        Self::with_secrets(
+            users
+                .into_iter()
+                .map(|(username, password)| (username, Secret::Password(password))),
+        )
+    }
+
+    /// Like [`AuthConfig::with_users`], but accepts pre-hashed secrets, which
+    /// are validated and kept as-is rather than re-hashed.
+    pub fn with_secrets(
+        users: impl IntoIterator<Item = (String, Secret)>,
+    ) -> Result<Self, argon2::password_hash::Error> {
+        Self::with_access(
+            users
+                .into_iter()
+                .map(|(username, secret)| (username, secret, Access::Full)),
+        )
+    }
+
+    /// Like [`AuthConfig::with_secrets`], but limits each user to `access`,
+    /// e.g. so producers can only upload and consumers only download.
+    pub fn with_access(
+        users: impl IntoIterator<Item = (String, Secret, Access)>,
+    ) -> Result<Self, argon2::password_hash::Error> {
+        Self::with_hashing(users, DEFAULT_PASSWORD_HASHING)
+    }
+
+    /// Like [`AuthConfig::with_access`], but hashes plaintext passwords with
+    /// `hashing` instead of argon2's defaults.
+    pub fn with_hashing(
+        users: impl IntoIterator<Item = (String, Secret, Access)>,
+        hashing: PasswordHashing,
+    ) -> Result<Self, argon2::password_hash::Error> {
+        let params = Params::new(
+            hashing.memory_kib,
+            hashing.iterations,
+            hashing.parallelism,
+            None,
+        )?;
+        let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, params);
+        let users = users
+            .into_iter()
+            .map(|(username, secret, access)| {
+                let password_hash = match secret {
+                    Secret::Password(password) => hash_password(&argon2, &password)?,
+                    Secret::Hash(hash) => {
+                        PasswordHash::new(&hash)?;
+                        hash
+                    }
+                };
+                Ok((username, (password_hash, access)))
+            })
+            .collect::<Result<_, argon2::password_hash::Error>>()?;
+
+        let mut dummy_password = [0u8; 32];
+        OsRng.fill_bytes(&mut dummy_password);
+        let dummy_hash = argon2
+            .hash_password(&dummy_password, &SaltString::generate(&mut OsRng))?
+            .to_string();
+
+        Ok(Self {
+            users,
+            dummy_hash,
+            challenge: basic_challenge(DEFAULT_AUTH_REALM),
+            argon2,
+        })
+    }
+
+    /// Names `realm` in the `WWW-Authenticate` challenge instead of
+    /// [`DEFAULT_AUTH_REALM`]. Quotes and backslashes are escaped and control
+    /// characters dropped, so any string makes a valid header.
+    pub fn with_realm(mut self, realm: &str) -> Self {
+        self.challenge = basic_challenge(realm);
+        self
+    }
+}
+
+fn basic_challenge(realm: &str) -> HeaderValue {
+    let mut challenge = String::from("Basic realm=\"");
+    for c in realm.chars().filter(|c| !c.is_control()) {
+        if matches!(c, '"' | '\\') {
+            challenge.push('\\');
+        }
+        challenge.push(c);
+    }
+    challenge.push('"');
+    HeaderValue::from_str(&challenge).expect("realm without control characters is a valid header")
+}
+
+/// Reads `username:secret` lines from `path`. A secret starting with
+/// `$argon2` is treated as a PHC hash; anything else is a plaintext password.
+/// Blank lines and lines starting with `#` are ignored.
+pub fn load_credentials_file(path: impl AsRef<Path>) -> io::Result<Vec<(String, Secret)>> {
+    let contents = std::fs::read_to_string(path)?;
+    let mut users = Vec::new();
+
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let invalid = |reason: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("credentials file line {}: {reason}", index + 1),
+            )
+        };
+
+        let (username, secret) = line
+            .split_once(':')
+            .ok_or_else(|| invalid("expected username:password"))?;
+        if username.is_empty() || secret.is_empty() {
+            return Err(invalid("username and password must not be empty"));
+        }
+
+        let secret = if secret.starts_with("$argon2") {
+            PasswordHash::new(secret).map_err(|_| invalid("malformed argon2 hash"))?;
+            Secret::Hash(secret.to_owned())
+        } else {
+            Secret::Password(secret.to_owned())
+        };
+        users.push((username.to_owned(), secret));
+    }
+
+    Ok(users)
+}
+
+fn hash_password(argon2: &Argon2, password: &str) -> Result<String, argon2::password_hash::Error> {
+    let salt = SaltString::generate(&mut OsRng);
+    Ok(argon2
+        .hash_password(password.as_bytes(), &salt)?
+        .to_string())
+}
+
+#[derive(Debug)]
+pub(crate) enum AuthError {
+    Unauthorized,
+    /// Valid credentials that do not allow this request.
+    Forbidden(Permission),
+    /// The client has failed too often lately; try again after this long.
+    RateLimited(Duration),
+    Internal,
+}
+
+pub(crate) fn auth_error_response(state: &AppState, error: AuthError) -> Response<Body> {
+    match error {
+        AuthError::Unauthorized => unauthorized_response(state, "Invalid username or password"),
+        AuthError::Forbidden(permission) => {
+            let message = match permission {
+                Permission::Upload => "These credentials may not upload",
+                Permission::Download => "These credentials may not download",
+                Permission::View => "These credentials may not view this page",
+                Permission::Admin => "These credentials are not an admin's",
+            };
+            (StatusCode::FORBIDDEN, message).into_response()
+        }
+        AuthError::RateLimited(retry_after) => Response::builder()
+            .status(StatusCode::TOO_MANY_REQUESTS)
+            .header(header::RETRY_AFTER, retry_after_secs(retry_after))
+            .body(Body::from("Too many failed login attempts"))
+            .expect("failed to build rate limit response"),
+        AuthError::Internal => Response::builder()
+            .status(StatusCode::INTERNAL_SERVER_ERROR)
+            .body(Body::from("Authentication failed"))
+            .expect("failed to build auth error response"),
+    }
+}
+
+fn unauthorized_response(state: &AppState, message: &str) -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::UNAUTHORIZED)
+        .header(header::WWW_AUTHENTICATE, state.auth.challenge.clone())
+        .body(Body::from(message.to_owned()))
+        .expect("failed to build unauthorized response")
+}
+
+/// What a request's `Authorization` header claims, before any
+/// [`AuthStrategy`] has checked it.
+pub(crate) enum Credentials {
+    Basic(Authorization<Basic>),
+    Bearer(Token),
+}
+
+impl Credentials {
+    /// The user these credentials name. Only to be trusted once
+    /// [`authenticate_user`] has accepted them.
+    pub(crate) fn username(&self) -> &str {
+        match self {
+            Credentials::Basic(basic) => basic.username(),
+            Credentials::Bearer(token) => token.subject(),
+        }
+    }
+}
+
+/// One way of checking [`Credentials`], such as the configured users'
+/// passwords or a bearer token issuer's signature.
+pub(crate) trait AuthStrategy: Send + Sync {
+    /// Checks `credentials` and resolves what they may do, or returns
+    /// `None` if they are of a kind this strategy doesn't handle.
+    fn verify<'a>(
+        &'a self,
+        credentials: &'a Credentials,
+    ) -> Option<BoxFuture<'a, Result<Access, AuthError>>>;
+}
+
+impl AuthStrategy for AuthConfig {
+    fn verify<'a>(
+        &'a self,
+        credentials: &'a Credentials,
+    ) -> Option<BoxFuture<'a, Result<Access, AuthError>>> {
+        match credentials {
+            Credentials::Basic(basic) => Some(Box::pin(std::future::ready(verify_credentials(
+                self, basic,
+            )))),
+            Credentials::Bearer(_) => None,
+        }
+    }
+}
+
+pub(crate) fn extract_credentials(headers: &HeaderMap) -> Result<Credentials, AuthError> {
+    let Some(header_value) = headers.get(header::AUTHORIZATION) else {
+        warn!("Missing Authorization header");
+        return Err(AuthError::Unauthorized);
+    };
+
+    decode_credentials(header_value).map_err(|reason| {
+        warn!(reason, "Failed to parse Authorization header");
+        AuthError::Unauthorized
+    })
+}
+
+fn decode_credentials(header_value: &HeaderValue) -> Result<Credentials, &'static str> {
+    let is_bearer = header_value
+        .as_bytes()
+        .get(..7)
+        .is_some_and(|scheme| scheme.eq_ignore_ascii_case(b"bearer "));
+    if !is_bearer {
+        return Authorization::<Basic>::decode(&mut std::iter::once(header_value))
+            .map(Credentials::Basic)
+            .map_err(|_| "not valid Basic credentials");
+    }
+
+    // The token itself is never logged.
+    let bearer = Authorization::<Bearer>::decode(&mut std::iter::once(header_value))
+        .map_err(|_| "not a valid Bearer token")?;
+    Token::parse(bearer.token())
+        .map(Credentials::Bearer)
+        .ok_or("Bearer token is not a JWT")
+}
+
+/// The username in the `Authorization` header of a request whose
+/// credentials were already checked, or `None` if it sent none.
+pub(crate) fn username(headers: &HeaderMap) -> Option<String> {
+    let credentials = decode_credentials(headers.get(header::AUTHORIZATION)?).ok()?;
+    Some(credentials.username().to_owned())
+}
+
+/// Checks `auth` on behalf of the client at `client` with whichever
+/// [`AuthStrategy`] handles it, and that the user has `permission`, refusing
+/// without hashing anything if that client is over its failure limit.
+pub(crate) async fn authenticate_user(
+    state: &AppState,
+    client: SocketAddr,
+    auth: &Credentials,
+    permission: Permission,
+) -> Result<(), AuthError> {
+    if let Some(retry_after) = state.auth_limiter.retry_after(client.ip()) {
+        warn!(%client, "Refusing login from client over its failure limit");
+        return Err(AuthError::RateLimited(retry_after));
+    }
+
+    let result = match state
+        .auth_strategies
+        .iter()
+        .find_map(|strategy| strategy.verify(auth))
+    {
+        Some(verified) => verified.await,
+        None => {
+            warn!("Credentials of a kind no configured strategy accepts");
+            Err(AuthError::Unauthorized)
+        }
+    };
+    if matches!(result, Err(AuthError::Unauthorized)) {
+        state.metrics.record_auth_failure();
+        state.auth_limiter.record_failure(client.ip());
+        if let Some(delay) = &state.auth_failure_delay {
+            tokio::time::sleep(random_delay(delay)).await;
+        }
+    }
+
+    // Checked only once the password is known to be right, so a refusal
+    // is no hint about it.
+    let access = result?;
+    access_log::record_username(auth.username());
+    if !access.grants(permission) {
+        warn!(username = %auth.username(), ?permission, "User lacks permission");
+        return Err(AuthError::Forbidden(permission));
+    }
+    Ok(())
+}
+
+fn random_delay(range: &RangeInclusive<Duration>) -> Duration {
+    let (min, max) = (*range.start(), *range.end());
+    let span = max.saturating_sub(min).as_nanos() as u64;
+    if span == 0 {
+        return min;
+    }
+    min + Duration::from_nanos(OsRng.next_u64() % (span + 1))
+}
+
+fn verify_credentials(
+    config: &AuthConfig,
+    auth: &Authorization<Basic>,
+) -> Result<Access, AuthError> {
+    let provided_username = auth.username();
+
+    let password = auth.password();
+    if password.is_empty() {
+        warn!(%provided_username, "Basic auth password is empty");
+        return Err(AuthError::Unauthorized);
+    }
+
+    // Returning early for unknown usernames would skip the argon2 work and
+    // let response times reveal which usernames exist. Instead, compare
+    // against every username in constant time and always run one
+    // verification, using a dummy hash when nobody matched.
+    let mut matched_user = None;
+    for (username, user) in &config.users {
+        if bool::from(username.as_bytes().ct_eq(provided_username.as_bytes())) {
+            matched_user = Some(user);
+        }
+    }
+    let password_hash = matched_user.map_or(&config.dummy_hash, |(hash, _)| hash);
+
+    let parsed_hash = PasswordHash::new(password_hash).map_err(|err| {
+        error!(%provided_username, %err, "Stored password hash is invalid");
+        AuthError::Internal
+    })?;
+
+    let verified = config
+        .argon2
+        .verify_password(password.as_bytes(), &parsed_hash)
+        .is_ok();
+
+    let Some((_, access)) = matched_user else {
+        warn!(attempted = %provided_username, "Unknown username supplied");
+        return Err(AuthError::Unauthorized);
+    };
+    if !verified {
+        return Err(AuthError::Unauthorized);
+    }
+
+    Ok(*access)
+}
@@ -0,0 +1,409 @@
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use axum::http::Uri;
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use bytes::Bytes;
+use futures_util::future::BoxFuture;
+use http_body_util::{BodyExt, Empty, Limited};
+use ring::{hmac, signature};
+use tokio::sync::Mutex;
+use tracing::{error, warn};
+
+use crate::{
+    auth::{Access, AuthError, AuthStrategy, Credentials},
+    config::{BearerKey, BearerPolicy},
+    https::{self, HttpsClient},
+};
+
+/// How far past `exp`, or before `nbf`, a token is still accepted, to allow
+/// for clock skew between beam and the token issuer.
+const CLOCK_LEEWAY_SECS: f64 = 30.0;
+
+/// How long a fetched key set is used before it is fetched again.
+const JWKS_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// The soonest beam tries the JWKS endpoint again after trying it, whether
+/// that fetch worked or not, so made-up key ids or an endpoint that is down
+/// can't make every bearer request wait on a fetch.
+const JWKS_MIN_REFRESH: Duration = Duration::from_secs(60);
+
+/// Time allowed for fetching the key set.
+const JWKS_FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Largest JWKS document beam reads.
+const JWKS_MAX_LEN: usize = 1024 * 1024;
+
+/// A JWT from an `Authorization: Bearer` header, decoded but not yet
+/// verified.
+pub(crate) struct Token {
+    header: Header,
+    claims: Claims,
+    /// `header.payload`, which the signature covers.
+    signing_input: String,
+    signature: Vec<u8>,
+}
+
+#[derive(serde::Deserialize)]
+struct Header {
+    alg: String,
+    kid: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+struct Claims {
+    sub: Option<String>,
+    exp: Option<f64>,
+    nbf: Option<f64>,
+    aud: Option<Audience>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum Audience {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Audience {
+    fn contains(&self, audience: &str) -> bool {
+        match self {
+            Audience::One(one) => one == audience,
+            Audience::Many(many) => many.iter().any(|one| one == audience),
+        }
+    }
+}
+
+impl Token {
+    /// Decodes a compact JWT, or `None` if it isn't one.
+    pub(crate) fn parse(token: &str) -> Option<Self> {
+        let (signing_input, signature) = token.rsplit_once('.')?;
+        let (header, claims) = signing_input.split_once('.')?;
+        Some(Self {
+            header: serde_json::from_slice(&URL_SAFE_NO_PAD.decode(header).ok()?).ok()?,
+            claims: serde_json::from_slice(&URL_SAFE_NO_PAD.decode(claims).ok()?).ok()?,
+            signing_input: signing_input.to_owned(),
+            signature: URL_SAFE_NO_PAD.decode(signature).ok()?,
+        })
+    }
+
+    /// The `sub` claim, which names the user the token was issued to.
+    pub(crate) fn subject(&self) -> &str {
+        self.claims.sub.as_deref().unwrap_or_default()
+    }
+}
+
+/// Checks bearer tokens against a [`BearerPolicy`].
+pub(crate) struct BearerAuth {
+    keys: Keys,
+    audience: String,
+    access: Access,
+}
+
+enum Keys {
+    Hmac(Vec<u8>),
+    Jwks(Box<Jwks>),
+}
+
+impl BearerAuth {
+    pub(crate) fn new(policy: &BearerPolicy) -> Result<Self, String> {
+        let keys = match &policy.key {
+            BearerKey::Hmac(secret) => Keys::Hmac(secret.clone()),
+            BearerKey::Jwks(url) => Keys::Jwks(Box::new(Jwks::new(url)?)),
+        };
+        Ok(Self {
+            keys,
+            audience: policy.audience.clone(),
+            access: policy.access,
+        })
+    }
+
+    async fn verify_token(&self, token: &Token) -> Result<Access, AuthError> {
+        let message = token.signing_input.as_bytes();
+        let verified = match &self.keys {
+            Keys::Hmac(secret) => {
+                let algorithm = match token.header.alg.as_str() {
+                    "HS256" => hmac::HMAC_SHA256,
+                    "HS384" => hmac::HMAC_SHA384,
+                    "HS512" => hmac::HMAC_SHA512,
+                    alg => {
+                        warn!(
+                            alg,
+                            "Bearer token uses an algorithm not accepted with a shared secret"
+                        );
+                        return Err(AuthError::Unauthorized);
+                    }
+                };
+                hmac::verify(
+                    &hmac::Key::new(algorithm, secret),
+                    message,
+                    &token.signature,
+                )
+                .is_ok()
+            }
+            Keys::Jwks(jwks) => {
+                let Some(key) = jwks.key_for(&token.header).await? else {
+                    warn!(alg = %token.header.alg, "No published key matches the bearer token");
+                    return Err(AuthError::Unauthorized);
+                };
+                key.verify(message, &token.signature)
+            }
+        };
+        if !verified {
+            warn!("Bearer token signature is invalid");
+            return Err(AuthError::Unauthorized);
+        }
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+        let claims = &token.claims;
+        let Some(exp) = claims.exp else {
+            warn!("Bearer token has no expiry");
+            return Err(AuthError::Unauthorized);
+        };
+        if now >= exp + CLOCK_LEEWAY_SECS {
+            warn!(subject = token.subject(), "Bearer token has expired");
+            return Err(AuthError::Unauthorized);
+        }
+        if claims.nbf.is_some_and(|nbf| now + CLOCK_LEEWAY_SECS < nbf) {
+            warn!(subject = token.subject(), "Bearer token is not valid yet");
+            return Err(AuthError::Unauthorized);
+        }
+        if !claims
+            .aud
+            .as_ref()
+            .is_some_and(|aud| aud.contains(&self.audience))
+        {
+            warn!(
+                subject = token.subject(),
+                "Bearer token was issued for another audience"
+            );
+            return Err(AuthError::Unauthorized);
+        }
+        // The subject is the username quotas and logs go by, so a token
+        // without one would pass as everyone else without one.
+        if token.subject().is_empty() {
+            warn!("Bearer token names no subject");
+            return Err(AuthError::Unauthorized);
+        }
+
+        Ok(self.access)
+    }
+}
+
+impl AuthStrategy for BearerAuth {
+    fn verify<'a>(
+        &'a self,
+        credentials: &'a Credentials,
+    ) -> Option<BoxFuture<'a, Result<Access, AuthError>>> {
+        match credentials {
+            Credentials::Bearer(token) => Some(Box::pin(self.verify_token(token))),
+            Credentials::Basic(_) => None,
+        }
+    }
+}
+
+/// Keys fetched from a JWKS endpoint.
+struct Jwks {
+    uri: Uri,
+    client: HttpsClient<Empty<Bytes>>,
+    /// Held across the fetch, so concurrent requests wait for one fetch
+    /// rather than each making their own.
+    cache: Mutex<KeyCache>,
+}
+
+#[derive(Default)]
+struct KeyCache {
+    keys: Option<KeySet>,
+    /// When the endpoint was last tried, whether or not that worked.
+    attempted: Option<Instant>,
+}
+
+struct KeySet {
+    fetched: Instant,
+    keys: Vec<VerifyingKey>,
+}
+
+#[derive(serde::Deserialize)]
+struct JwkSet {
+    keys: Vec<Jwk>,
+}
+
+#[derive(serde::Deserialize)]
+struct Jwk {
+    kty: String,
+    kid: Option<String>,
+    alg: Option<String>,
+    #[serde(rename = "use")]
+    usage: Option<String>,
+    n: Option<String>,
+    e: Option<String>,
+    crv: Option<String>,
+    x: Option<String>,
+    y: Option<String>,
+}
+
+#[derive(Clone)]
+struct VerifyingKey {
+    kid: Option<String>,
+    material: KeyMaterial,
+}
+
+#[derive(Clone)]
+enum KeyMaterial {
+    /// `RS256` modulus and exponent.
+    Rsa { n: Vec<u8>, e: Vec<u8> },
+    /// `ES256` public point, uncompressed.
+    P256(Vec<u8>),
+}
+
+impl Jwks {
+    fn new(url: &str) -> Result<Self, String> {
+        let uri: Uri = url
+            .parse()
+            .map_err(|error| format!("JWKS URL {url}: {error}"))?;
+        if !matches!(uri.scheme_str(), Some("http" | "https")) {
+            return Err(format!("JWKS URL {url} must be http or https"));
+        }
+        Ok(Self {
+            uri,
+            client: https::client().map_err(|error| format!("JWKS TLS: {error}"))?,
+            cache: Mutex::default(),
+        })
+    }
+
+    /// The published key that should have signed a token with `header`,
+    /// fetching the key set first if it is stale or lacks that key.
+    async fn key_for(&self, header: &Header) -> Result<Option<VerifyingKey>, AuthError> {
+        let mut cache = self.cache.lock().await;
+        let key = cache
+            .keys
+            .as_ref()
+            .and_then(|set| find_key(&set.keys, header));
+        let fresh = cache
+            .keys
+            .as_ref()
+            .is_some_and(|set| set.fetched.elapsed() < JWKS_TTL);
+        let tried_recently = cache
+            .attempted
+            .is_some_and(|attempted| attempted.elapsed() < JWKS_MIN_REFRESH);
+        if (fresh && key.is_some()) || tried_recently {
+            return cached_key(&cache, key);
+        }
+
+        cache.attempted = Some(Instant::now());
+        match self.fetch().await {
+            Ok(keys) => {
+                let key = find_key(&keys, header);
+                cache.keys = Some(KeySet {
+                    fetched: Instant::now(),
+                    keys,
+                });
+                Ok(key)
+            }
+            Err(message) => {
+                error!(uri = %self.uri, %message, "Failed to fetch JWKS");
+                cached_key(&cache, key)
+            }
+        }
+    }
+
+    async fn fetch(&self) -> Result<Vec<VerifyingKey>, String> {
+        let response = tokio::time::timeout(JWKS_FETCH_TIMEOUT, self.client.get(self.uri.clone()))
+            .await
+            .map_err(|_| "timed out".to_owned())?
+            .map_err(|error| error.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("status {}", response.status()));
+        }
+        let body = tokio::time::timeout(
+            JWKS_FETCH_TIMEOUT,
+            Limited::new(response.into_body(), JWKS_MAX_LEN).collect(),
+        )
+        .await
+        .map_err(|_| "timed out".to_owned())?
+        .map_err(|error| error.to_string())?
+        .to_bytes();
+        let set: JwkSet = serde_json::from_slice(&body).map_err(|error| error.to_string())?;
+
+        Ok(set
+            .keys
+            .into_iter()
+            .filter_map(VerifyingKey::from_jwk)
+            .collect())
+    }
+}
+
+/// `key`, found in the cached key set, when the endpoint can't be asked
+/// again: keys that were good a while ago beat refusing everyone while it is
+/// down, but without any beam can't check tokens at all.
+fn cached_key(
+    cache: &KeyCache,
+    key: Option<VerifyingKey>,
+) -> Result<Option<VerifyingKey>, AuthError> {
+    match cache.keys {
+        Some(_) => Ok(key),
+        None => Err(AuthError::Internal),
+    }
+}
+
+/// Looks a key up by the token's `kid` if it names one, else takes the only
+/// key that suits its algorithm.
+fn find_key(keys: &[VerifyingKey], header: &Header) -> Option<VerifyingKey> {
+    let mut candidates = keys.iter().filter(|key| key.material.suits(&header.alg));
+    match &header.kid {
+        Some(kid) => candidates.find(|key| key.kid.as_ref() == Some(kid)),
+        None => candidates.next().filter(|_| candidates.next().is_none()),
+    }
+    .cloned()
+}
+
+impl VerifyingKey {
+    /// Keeps the signing keys beam can verify with, skipping the rest.
+    fn from_jwk(jwk: Jwk) -> Option<Self> {
+        if jwk.usage.as_deref().is_some_and(|usage| usage != "sig") {
+            return None;
+        }
+        let decode = |value: Option<String>| URL_SAFE_NO_PAD.decode(value?).ok();
+        let material = match (jwk.kty.as_str(), jwk.alg.as_deref()) {
+            ("RSA", None | Some("RS256")) => KeyMaterial::Rsa {
+                n: decode(jwk.n)?,
+                e: decode(jwk.e)?,
+            },
+            ("EC", None | Some("ES256")) if jwk.crv.as_deref() == Some("P-256") => {
+                let mut point = vec![0x04];
+                point.extend(decode(jwk.x)?);
+                point.extend(decode(jwk.y)?);
+                KeyMaterial::P256(point)
+            }
+            _ => return None,
+        };
+        Some(Self {
+            kid: jwk.kid,
+            material,
+        })
+    }
+
+    fn verify(&self, message: &[u8], signature: &[u8]) -> bool {
+        match &self.material {
+            KeyMaterial::Rsa { n, e } => signature::RsaPublicKeyComponents { n, e }
+                .verify(&signature::RSA_PKCS1_2048_8192_SHA256, message, signature)
+                .is_ok(),
+            KeyMaterial::P256(point) => {
+                signature::UnparsedPublicKey::new(&signature::ECDSA_P256_SHA256_FIXED, point)
+                    .verify(message, signature)
+                    .is_ok()
+            }
+        }
+    }
+}
+
+impl KeyMaterial {
+    fn suits(&self, alg: &str) -> bool {
+        matches!(
+            (self, alg),
+            (KeyMaterial::Rsa { .. }, "RS256") | (KeyMaterial::P256(_), "ES256")
+        )
+    }
+}
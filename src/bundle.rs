@@ -0,0 +1,337 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{ConnectInfo, Query, State},
+    http::{HeaderMap, HeaderName, HeaderValue, Response, StatusCode, header},
+    response::IntoResponse,
+};
+use futures_util::StreamExt;
+use http_body::Frame;
+use http_body_util::StreamBody;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::{info, warn};
+
+use crate::{
+    AppState, ClientAddr, StreamSource,
+    auth::{Permission, auth_error_response, authenticate_user, extract_credentials},
+    filename::{Disposition, content_disposition},
+    serve_download, trailers,
+};
+
+/// Trailer saying which entry cut a bundle short, and why.
+const ERROR_TRAILER: HeaderName = HeaderName::from_static("x-bundle-error");
+
+/// Name suggested for the archive in `Content-Disposition`.
+const BUNDLE_FILENAME: &str = "bundle.zip";
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+const DATA_DESCRIPTOR_SIGNATURE: u32 = 0x0807_4b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0605_4b50;
+/// 2.0: the oldest version that knows data descriptors.
+const ZIP_VERSION: u16 = 20;
+/// Sizes and CRC follow the data in a descriptor (bit 3), and the name is
+/// UTF-8 (bit 11).
+const ENTRY_FLAGS: u16 = 0x0808;
+const STORED: u16 = 0;
+const TOO_LARGE: &str = "Bundle is too large for a zip without ZIP64";
+
+#[derive(serde::Deserialize)]
+pub(crate) struct BundleQuery {
+    prefix: String,
+}
+
+/// `GET /bundle?prefix=...`: every downloadable stream whose filename starts
+/// with `prefix`, as one zip streamed entry by entry in filename order.
+/// Streams are claimed up front as ordinary downloads would be; each waits,
+/// its channel full, until the archive reaches it.
+pub(crate) async fn bundle_handler(
+    State(state): State<AppState>,
+    ConnectInfo(ClientAddr(client)): ConnectInfo<ClientAddr>,
+    Query(query): Query<BundleQuery>,
+    headers: HeaderMap,
+) -> Response<Body> {
+    if !state.anonymous_downloads {
+        let auth = match extract_credentials(&headers) {
+            Ok(auth) => auth,
+            Err(err) => return auth_error_response(&state, err),
+        };
+
+        if let Err(err) = authenticate_user(&state, client, &auth, Permission::Download).await {
+            return auth_error_response(&state, err);
+        }
+    }
+
+    let mut filenames: Vec<String> = state
+        .streams
+        .iter()
+        .filter(|entry| {
+            entry.key().starts_with(&query.prefix)
+                && matches!(
+                    entry.value().source,
+                    StreamSource::Live(_) | StreamSource::Spooled(_)
+                )
+        })
+        .map(|entry| entry.key().clone())
+        .collect();
+    filenames.sort();
+
+    let mut entries = Vec::with_capacity(filenames.len());
+    for filename in filenames {
+        let response = serve_download(&state, filename.clone(), None, &HeaderMap::new()).await;
+        if response.status() == StatusCode::OK {
+            entries.push((filename, response.into_body()));
+        } else {
+            info!(%filename, status = %response.status(), "Left a stream out of a bundle");
+        }
+    }
+    if entries.is_empty() {
+        return (
+            StatusCode::NOT_FOUND,
+            "No downloadable streams match this prefix",
+        )
+            .into_response();
+    }
+    info!(entries = entries.len(), "Bundle started");
+
+    let trailers = trailers::requested(&headers);
+    let (tx, rx) = mpsc::channel(1);
+    tokio::spawn(write_zip(entries, tx, trailers));
+
+    let mut response = Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "application/zip")
+        .header(
+            header::CONTENT_DISPOSITION,
+            content_disposition(BUNDLE_FILENAME, Disposition::Attachment),
+        );
+    if trailers {
+        response = response.header(header::TRAILER, ERROR_TRAILER.as_str());
+    }
+    response
+        .body(Body::new(StreamBody::new(ReceiverStream::new(rx))))
+        .expect("failed to build bundle response")
+}
+
+type FrameSender = mpsc::Sender<Result<Frame<Bytes>, axum::Error>>;
+
+/// Sends `entries` to `tx` as a zip, stopping early if the downloader goes.
+/// An entry that fails ends the archive where it is, without the central
+/// directory: with an [`ERROR_TRAILER`] if the downloader takes trailers,
+/// else with an error that aborts the body.
+async fn write_zip(entries: Vec<(String, Body)>, tx: FrameSender, trailers: bool) {
+    let mut zip = ZipWriter::new(SystemTime::now());
+    for (filename, body) in entries {
+        if let Err(error) = write_entry(&mut zip, &filename, body, &tx).await {
+            let Some(error) = error else {
+                info!(%filename, "Bundle download disconnected");
+                return;
+            };
+            warn!(%filename, %error, "Bundle entry failed; truncating the archive");
+            let message = format!("{filename}: {error}");
+            let _ = if trailers {
+                let mut fields = HeaderMap::new();
+                fields.insert(
+                    ERROR_TRAILER,
+                    HeaderValue::from_str(&message)
+                        .unwrap_or_else(|_| HeaderValue::from_static("entry failed")),
+                );
+                tx.send(Ok(Frame::trailers(fields))).await
+            } else {
+                tx.send(Err(axum::Error::new(message))).await
+            };
+            return;
+        }
+    }
+    match zip.finish() {
+        Ok(central_directory) => {
+            let _ = tx.send(Ok(Frame::data(central_directory))).await;
+            info!("Bundle finished");
+        }
+        Err(error) => {
+            warn!(%error, "Bundle failed");
+            let _ = tx.send(Err(axum::Error::new(error))).await;
+        }
+    }
+}
+
+/// Writes one entry. Fails with `None` once the downloader has gone, since
+/// there is nobody left to tell why.
+async fn write_entry(
+    zip: &mut ZipWriter,
+    filename: &str,
+    body: Body,
+    tx: &FrameSender,
+) -> Result<(), Option<String>> {
+    let send =
+        |bytes: Bytes| async move { tx.send(Ok(Frame::data(bytes))).await.map_err(|_| None) };
+
+    send(
+        zip.start_entry(filename)
+            .map_err(|error| Some(error.to_owned()))?,
+    )
+    .await?;
+    let mut crc = crc32fast::Hasher::new();
+    let mut size = 0u64;
+    let mut data = body.into_data_stream();
+    while let Some(chunk) = data.next().await {
+        let chunk = chunk.map_err(|error| Some(error.to_string()))?;
+        crc.update(&chunk);
+        size += chunk.len() as u64;
+        send(chunk).await?;
+    }
+    send(
+        zip.finish_entry(crc.finalize(), size)
+            .map_err(|error| Some(error.to_owned()))?,
+    )
+    .await
+}
+
+/// Lays out a zip of stored (uncompressed) entries as they stream, each
+/// header written before its size is known. No ZIP64, so the archive is
+/// limited to 65535 entries of 4 GiB.
+struct ZipWriter {
+    dos_time: u16,
+    dos_date: u16,
+    /// Bytes written so far.
+    offset: u64,
+    central_directory: Vec<u8>,
+    entries: u16,
+    /// The entry being written: its name and where its header starts.
+    current: Option<(String, u32)>,
+}
+
+impl ZipWriter {
+    fn new(modified: SystemTime) -> Self {
+        let (dos_time, dos_date) = dos_datetime(modified);
+        Self {
+            dos_time,
+            dos_date,
+            offset: 0,
+            central_directory: Vec::new(),
+            entries: 0,
+            current: None,
+        }
+    }
+
+    fn start_entry(&mut self, filename: &str) -> Result<Bytes, &'static str> {
+        let header_offset = u32::try_from(self.offset).map_err(|_| TOO_LARGE)?;
+        let mut header = Vec::with_capacity(30 + filename.len());
+        put_u32(&mut header, LOCAL_HEADER_SIGNATURE);
+        put_u16(&mut header, ZIP_VERSION);
+        put_u16(&mut header, ENTRY_FLAGS);
+        put_u16(&mut header, STORED);
+        put_u16(&mut header, self.dos_time);
+        put_u16(&mut header, self.dos_date);
+        // CRC, compressed and uncompressed size, all in the descriptor.
+        header.extend_from_slice(&[0; 12]);
+        put_u16(&mut header, name_len(filename)?);
+        put_u16(&mut header, 0);
+        header.extend_from_slice(filename.as_bytes());
+
+        self.offset += header.len() as u64;
+        self.current = Some((filename.to_owned(), header_offset));
+        Ok(header.into())
+    }
+
+    fn finish_entry(&mut self, crc: u32, size: u64) -> Result<Bytes, &'static str> {
+        let (filename, header_offset) = self.current.take().expect("an entry was started");
+        let size = u32::try_from(size).map_err(|_| TOO_LARGE)?;
+        self.entries = self.entries.checked_add(1).ok_or(TOO_LARGE)?;
+
+        let mut descriptor = Vec::with_capacity(16);
+        put_u32(&mut descriptor, DATA_DESCRIPTOR_SIGNATURE);
+        put_u32(&mut descriptor, crc);
+        put_u32(&mut descriptor, size);
+        put_u32(&mut descriptor, size);
+        self.offset += u64::from(size) + descriptor.len() as u64;
+
+        let central = &mut self.central_directory;
+        put_u32(central, CENTRAL_HEADER_SIGNATURE);
+        put_u16(central, ZIP_VERSION);
+        put_u16(central, ZIP_VERSION);
+        put_u16(central, ENTRY_FLAGS);
+        put_u16(central, STORED);
+        put_u16(central, self.dos_time);
+        put_u16(central, self.dos_date);
+        put_u32(central, crc);
+        put_u32(central, size);
+        put_u32(central, size);
+        put_u16(central, name_len(&filename)?);
+        // Extra field and comment lengths, disk number, internal and
+        // external attributes.
+        central.extend_from_slice(&[0; 12]);
+        put_u32(central, header_offset);
+        central.extend_from_slice(filename.as_bytes());
+
+        Ok(descriptor.into())
+    }
+
+    /// The central directory and its end record, which close the archive.
+    fn finish(self) -> Result<Bytes, &'static str> {
+        let offset = u32::try_from(self.offset).map_err(|_| TOO_LARGE)?;
+        let size = u32::try_from(self.central_directory.len()).map_err(|_| TOO_LARGE)?;
+        let mut tail = self.central_directory;
+        put_u32(&mut tail, END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+        // This disk, and the one the directory starts on.
+        put_u16(&mut tail, 0);
+        put_u16(&mut tail, 0);
+        put_u16(&mut tail, self.entries);
+        put_u16(&mut tail, self.entries);
+        put_u32(&mut tail, size);
+        put_u32(&mut tail, offset);
+        put_u16(&mut tail, 0);
+        Ok(tail.into())
+    }
+}
+
+fn name_len(filename: &str) -> Result<u16, &'static str> {
+    u16::try_from(filename.len()).map_err(|_| "Filename is too long for a zip entry")
+}
+
+fn put_u16(buf: &mut Vec<u8>, value: u16) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(buf: &mut Vec<u8>, value: u32) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+/// `time` as an MS-DOS time and date in UTC, clamped to the format's range
+/// of 1980 to 2107.
+fn dos_datetime(time: SystemTime) -> (u16, u16) {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let days = (secs / 86_400) as i64;
+    let secs_of_day = secs % 86_400;
+
+    // Howard Hinnant's days-to-civil conversion.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    if year < 1980 {
+        return (0, (1 << 5) | 1);
+    }
+    if year > 2107 {
+        return ((23 << 11) | (59 << 5) | 29, (127 << 9) | (12 << 5) | 31);
+    }
+    let time =
+        ((secs_of_day / 3600) << 11) | ((secs_of_day % 3600 / 60) << 5) | (secs_of_day % 60 / 2);
+    let date = ((year - 1980) << 9) | (month << 5) | day;
+    (time as u16, date as u16)
+}
@@ -0,0 +1,91 @@
+use axum::http::HeaderMap;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use sha2::{Digest, Sha256};
+
+/// Header carrying the expected SHA-256 of an upload as hex; also set on
+/// spooled downloads.
+pub(crate) const CHECKSUM_HEADER: &str = "x-checksum-sha256";
+
+/// RFC 3230 instance digest header, read from uploads and sent as a
+/// download trailer.
+pub(crate) const DIGEST_HEADER: &str = "digest";
+
+/// The SHA-256 an uploader expects its body to hash to, from either
+/// `X-Checksum-SHA256: <hex>` or `Digest: sha-256=<base64>`.
+pub(crate) fn expected_sha256(headers: &HeaderMap) -> Result<Option<[u8; 32]>, &'static str> {
+    if let Some(value) = headers.get(CHECKSUM_HEADER) {
+        let hex = value
+            .to_str()
+            .map_err(|_| "X-Checksum-SHA256 must be 64 hex digits")?;
+        return parse_hex(hex.trim())
+            .map(Some)
+            .ok_or("X-Checksum-SHA256 must be 64 hex digits");
+    }
+
+    let Some(value) = headers.get(DIGEST_HEADER) else {
+        return Ok(None);
+    };
+    let value = value
+        .to_str()
+        .map_err(|_| "Digest header is not valid ASCII")?;
+    let Some(encoded) = value.split(',').find_map(|entry| {
+        let (algorithm, encoded) = entry.trim().split_once('=')?;
+        algorithm
+            .eq_ignore_ascii_case("sha-256")
+            .then_some(encoded.trim())
+    }) else {
+        // Only other algorithms were offered; nothing for beam to check.
+        return Ok(None);
+    };
+
+    STANDARD
+        .decode(encoded)
+        .ok()
+        .and_then(|digest| digest.try_into().ok())
+        .map(Some)
+        .ok_or("Digest sha-256 value must be a base64 SHA-256")
+}
+
+/// Hashes an upload as it streams past so it can be checked against the
+/// digest its uploader declared.
+pub(crate) struct ChecksumVerifier {
+    hasher: Sha256,
+    expected: [u8; 32],
+}
+
+impl ChecksumVerifier {
+    pub(crate) fn new(expected: [u8; 32]) -> Self {
+        Self {
+            hasher: Sha256::new(),
+            expected,
+        }
+    }
+
+    pub(crate) fn update(&mut self, bytes: &[u8]) {
+        self.hasher.update(bytes);
+    }
+
+    pub(crate) fn matches(self) -> bool {
+        self.hasher.finalize()[..] == self.expected
+    }
+}
+
+/// `digest` as an RFC 3230 `Digest` value, `sha-256=<base64>`.
+pub(crate) fn digest_value(digest: &[u8; 32]) -> String {
+    format!("sha-256={}", STANDARD.encode(digest))
+}
+
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn parse_hex(hex: &str) -> Option<[u8; 32]> {
+    if hex.len() != 64 || !hex.is_ascii() {
+        return None;
+    }
+    let mut digest = [0u8; 32];
+    for (byte, pair) in digest.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        *byte = u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()?;
+    }
+    Some(digest)
+}
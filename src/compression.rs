@@ -0,0 +1,254 @@
+use async_compression::{
+    Level,
+    tokio::write::{BrotliEncoder, GzipEncoder},
+};
+use axum::{
+    body::Bytes,
+    http::{HeaderMap, HeaderValue, header},
+};
+use futures_util::{FutureExt, Stream, StreamExt};
+use std::pin::Pin;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+/// A content coding live downloads can be compressed with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Coding {
+    Brotli,
+    Gzip,
+}
+
+impl Coding {
+    /// In order of preference when a client accepts several equally.
+    const SUPPORTED: [Coding; 2] = [Coding::Brotli, Coding::Gzip];
+
+    /// The coding's `Content-Encoding` token.
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Coding::Brotli => "br",
+            Coding::Gzip => "gzip",
+        }
+    }
+}
+
+/// The coding the client's `Accept-Encoding` rates highest, brotli winning
+/// ties. A coding the header doesn't name takes the `*` entry's q-value, and
+/// is refused without one; `q=0` refuses it outright. `None` without the
+/// header too.
+pub(crate) fn negotiate(headers: &HeaderMap) -> Option<Coding> {
+    let mut wildcard = None;
+    let mut named = [None; Coding::SUPPORTED.len()];
+    let entries = headers
+        .get_all(header::ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','));
+    for entry in entries {
+        let mut params = entry.split(';').map(str::trim);
+        let name = params.next().unwrap_or_default();
+        let q = params
+            .find_map(|param| param.strip_prefix("q="))
+            .map_or(1.0, |q| q.parse::<f32>().unwrap_or(1.0));
+        if name == "*" {
+            wildcard = Some(q);
+        } else if let Some(index) = Coding::SUPPORTED
+            .iter()
+            .position(|coding| name.eq_ignore_ascii_case(coding.name()))
+        {
+            named[index] = Some(q);
+        }
+    }
+
+    let mut best: Option<(Coding, f32)> = None;
+    for (coding, q) in Coding::SUPPORTED.into_iter().zip(named) {
+        let Some(q) = q.or(wildcard).filter(|q| *q > 0.0) else {
+            continue;
+        };
+        if best.is_none_or(|(_, best_q)| q > best_q) {
+            best = Some((coding, q));
+        }
+    }
+    best.map(|(coding, _)| coding)
+}
+
+/// Whether content of this type is compressed already, so compressing
+/// it again would cost CPU for nothing: archives, and most images, audio
+/// and video.
+pub(crate) fn is_compressed(content_type: &HeaderValue) -> bool {
+    let Ok(content_type) = content_type.to_str() else {
+        return false;
+    };
+    let essence = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    match essence.split_once('/') {
+        Some(("image", subtype)) => !matches!(subtype, "svg+xml" | "bmp" | "x-icon"),
+        Some(("audio" | "video", _)) => true,
+        _ => matches!(
+            essence.as_str(),
+            "application/zip"
+                | "application/gzip"
+                | "application/x-gzip"
+                | "application/zstd"
+                | "application/x-bzip2"
+                | "application/x-xz"
+                | "application/x-7z-compressed"
+                | "application/vnd.rar"
+                | "application/x-rar-compressed"
+        ),
+    }
+}
+
+/// Compresses `stream` with `coding` as it passes, flushing whenever the
+/// upload pauses so a slow upload still reaches its downloader promptly.
+/// An error from `stream` fails the compressed stream too, but only after
+/// flushing what came before it, so the downloader can still decompress the
+/// part that did arrive.
+pub(crate) fn compress<S>(
+    stream: S,
+    coding: Coding,
+) -> impl Stream<Item = Result<Bytes, axum::Error>> + Send
+where
+    S: Stream<Item = Result<Bytes, axum::Error>> + Send + 'static,
+{
+    let compressor = Compressor {
+        input: Box::pin(stream),
+        encoder: Encoder::new(coding),
+        unflushed: false,
+        failure: None,
+        finished: false,
+    };
+    futures_util::stream::unfold(compressor, |mut compressor| async move {
+        loop {
+            if let Some(error) = compressor.failure.take() {
+                compressor.finished = true;
+                return Some((Err(error), compressor));
+            }
+            if compressor.finished {
+                return None;
+            }
+
+            let item = match compressor.input.next().now_or_never() {
+                Some(item) => item,
+                None => {
+                    if let Some(output) = compressor.flush().await {
+                        return Some((Ok(output), compressor));
+                    }
+                    compressor.input.next().await
+                }
+            };
+            let written = match item {
+                Some(Ok(bytes)) => {
+                    compressor.unflushed = true;
+                    compressor.encoder.writer().write_all(&bytes).await
+                }
+                Some(Err(error)) => {
+                    compressor.failure = Some(error);
+                    compressor.encoder.writer().flush().await
+                }
+                None => {
+                    compressor.finished = true;
+                    compressor.encoder.writer().shutdown().await
+                }
+            };
+            if let Err(error) = written {
+                compressor.failure = Some(axum::Error::new(error));
+            }
+            let output = compressor.encoder.take_output();
+            if !output.is_empty() {
+                return Some((Ok(output), compressor));
+            }
+        }
+    })
+}
+
+/// State of one [`compress`]ed stream.
+struct Compressor<S> {
+    input: Pin<Box<S>>,
+    encoder: Encoder,
+    /// Set once bytes have been written since the last flush, so an idle
+    /// upload doesn't add an empty flush block every time it pauses.
+    unflushed: bool,
+    /// Sent once the output before it has been.
+    failure: Option<axum::Error>,
+    finished: bool,
+}
+
+impl<S> Compressor<S> {
+    /// Flushes what has been written since the last flush, returning the
+    /// compressed bytes to send, if any.
+    async fn flush(&mut self) -> Option<Bytes> {
+        if !std::mem::take(&mut self.unflushed) {
+            return None;
+        }
+        if let Err(error) = self.encoder.writer().flush().await {
+            self.failure = Some(axum::Error::new(error));
+        }
+        let output = self.encoder.take_output();
+        (!output.is_empty()).then_some(output)
+    }
+}
+
+/// An encoder writing into a buffer that is drained as output is sent.
+enum Encoder {
+    Brotli(Box<BrotliEncoder<Vec<u8>>>),
+    Gzip(GzipEncoder<Vec<u8>>),
+}
+
+impl Encoder {
+    fn new(coding: Coding) -> Self {
+        match coding {
+            // Brotli's densest levels are far too slow to keep up with a relay.
+            Coding::Brotli => Encoder::Brotli(Box::new(BrotliEncoder::with_quality(
+                Vec::new(),
+                Level::Precise(5),
+            ))),
+            Coding::Gzip => Encoder::Gzip(GzipEncoder::new(Vec::new())),
+        }
+    }
+
+    fn writer(&mut self) -> &mut (dyn AsyncWrite + Send + Unpin) {
+        match self {
+            Encoder::Brotli(encoder) => encoder.as_mut(),
+            Encoder::Gzip(encoder) => encoder,
+        }
+    }
+
+    fn take_output(&mut self) -> Bytes {
+        let output = match self {
+            Encoder::Brotli(encoder) => encoder.get_mut(),
+            Encoder::Gzip(encoder) => encoder.get_mut(),
+        };
+        std::mem::take(output).into()
+    }
+}
+
+/// Reads from `stream` until `min_size` bytes have arrived, returning what
+/// it read and whether that was enough to be worth compressing. Stops short
+/// at the end of the stream or its first error.
+pub(crate) async fn read_prefix<S>(
+    stream: &mut S,
+    min_size: u64,
+) -> (Vec<Result<Bytes, axum::Error>>, bool)
+where
+    S: Stream<Item = Result<Bytes, axum::Error>> + Unpin,
+{
+    let mut prefix = Vec::new();
+    let mut len = 0;
+    while len < min_size {
+        match stream.next().await {
+            Some(Ok(bytes)) => {
+                len += bytes.len() as u64;
+                prefix.push(Ok(bytes));
+            }
+            Some(Err(error)) => {
+                prefix.push(Err(error));
+                return (prefix, false);
+            }
+            None => return (prefix, false),
+        }
+    }
+    (prefix, true)
+}
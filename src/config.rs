@@ -0,0 +1,1117 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::net::{IpAddr, Ipv4Addr};
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::time::Duration;
+
+use crate::auth::{Access, Secret};
+use crate::filename::ExtensionPolicy;
+
+/// Port used when none is configured.
+pub const DEFAULT_PORT: u16 = 4000;
+
+/// Number of body frames buffered between an uploader and its downloader.
+pub const DEFAULT_CHANNEL_BUFFER: usize = 16;
+
+/// Largest per-downloader buffer an upload may ask for with
+/// `X-Buffer-Frames`.
+pub const DEFAULT_MAX_BUFFER_FRAMES: usize = 256;
+
+/// How long an upload waits for a download client before giving up.
+pub const DEFAULT_UPLOAD_READY_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// How long a transfer may go without receiving upload bytes before it is
+/// aborted.
+pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Largest `X-Receivers` value a broadcast upload may request.
+pub const MAX_BROADCAST_RECEIVERS: usize = 64;
+
+/// Prefix of the upload headers forwarded to downloads.
+pub const DEFAULT_METADATA_HEADER_PREFIX: &str = "X-Meta-";
+
+/// Most metadata headers one upload may carry, unless configured otherwise.
+pub const MAX_METADATA_HEADERS: usize = 16;
+
+/// Largest combined size, in bytes, of one upload's metadata header names
+/// and values, unless configured otherwise.
+pub const MAX_METADATA_BYTES: usize = 4096;
+
+/// Longest filename accepted, in bytes, matching common filesystem limits.
+pub const DEFAULT_MAX_FILENAME_LEN: usize = 255;
+
+/// How long a spooled upload stays downloadable.
+pub const DEFAULT_SPOOL_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// What a broadcast upload does when one of its downloaders stops reading.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LagPolicy {
+    /// Keep waiting, so the slowest downloader sets the pace for everyone.
+    Wait,
+    /// Disconnect a downloader whose buffer has stayed full for this long and
+    /// keep relaying to the rest.
+    Disconnect(Duration),
+}
+
+/// What a download parked by
+/// [`download_wait_timeout`](ServerConfigBuilder::download_wait_timeout)
+/// sends while it waits for its upload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParkedDownload {
+    /// Nothing: the connection stays quiet until the upload arrives or the
+    /// wait times out.
+    Silent,
+    /// Downloads that accept `text/event-stream` get an event stream
+    /// instead of the file, with a `: waiting` comment this often so
+    /// proxies don't time out the idle connection. It ends with a `ready`
+    /// event once the upload has started, or `timeout`, and the client then
+    /// fetches the file with a plain `GET`. Other downloads park silently.
+    Heartbeat(Duration),
+}
+
+/// Broadcast uploads drop a downloader that stalls for 30 seconds.
+pub const DEFAULT_LAG_POLICY: LagPolicy = LagPolicy::Disconnect(Duration::from_secs(30));
+
+/// How many failed logins a client address may make within `window` before
+/// further attempts are refused with `429 Too Many Requests`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AuthFailureLimit {
+    pub max_failures: u32,
+    pub window: Duration,
+}
+
+/// Ten failed logins per client address per minute.
+pub const DEFAULT_AUTH_FAILURE_LIMIT: AuthFailureLimit = AuthFailureLimit {
+    max_failures: 10,
+    window: Duration::from_secs(60),
+};
+
+/// Slowest an upload may send its body: at least `bytes_per_sec` on average
+/// over every `window`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MinUploadRate {
+    pub bytes_per_sec: u64,
+    pub window: Duration,
+}
+
+/// What one user may have in beam at once: at most `max_streams` streams,
+/// and at most `max_bytes` received between them, counting uploads in
+/// progress and, with a spool, stored files. `None` leaves that side
+/// unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct UploadQuota {
+    pub max_streams: Option<usize>,
+    pub max_bytes: Option<u64>,
+}
+
+/// How long a live upload waits for its downloader to reconnect after the
+/// connection drops, and how many of the most recently relayed bytes it keeps
+/// so the new download can resume where the old one's copy ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReconnectGrace {
+    pub window: Duration,
+    pub replay_bytes: usize,
+}
+
+/// Which browser origins may call beam from scripts, and with which methods
+/// and request headers. An entry of `"*"` in any of the lists allows
+/// everything of that kind.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CorsPolicy {
+    pub(crate) origins: Vec<String>,
+    pub(crate) methods: Option<Vec<String>>,
+    pub(crate) headers: Option<Vec<String>>,
+}
+
+impl CorsPolicy {
+    /// Allows `origins`, such as `https://app.example.com`, to make
+    /// `GET`, `HEAD`, `PUT`, `POST` and `DELETE` requests with any request
+    /// headers.
+    pub fn new(origins: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            origins: origins.into_iter().map(Into::into).collect(),
+            methods: None,
+            headers: None,
+        }
+    }
+
+    /// Allows only these methods instead of the defaults.
+    pub fn methods(mut self, methods: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.methods = Some(methods.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Allows only these request headers instead of whichever a preflight
+    /// asks for.
+    pub fn headers(mut self, headers: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.headers = Some(headers.into_iter().map(Into::into).collect());
+        self
+    }
+}
+
+/// Accepts `Authorization: Bearer <jwt>` alongside Basic credentials, for
+/// tokens issued by an existing auth service. A token must carry an `exp`
+/// claim that has not passed and name the configured audience in `aud`;
+/// its `sub` claim is the username it authenticates as.
+#[derive(Clone)]
+pub struct BearerPolicy {
+    pub(crate) key: BearerKey,
+    pub(crate) audience: String,
+    pub(crate) access: Access,
+}
+
+#[derive(Clone)]
+pub(crate) enum BearerKey {
+    /// Shared secret for `HS256`, `HS384` and `HS512` tokens.
+    Hmac(Vec<u8>),
+    /// URL of a JWKS document holding the `RS256` and `ES256` keys.
+    Jwks(String),
+}
+
+impl BearerPolicy {
+    /// Accepts tokens signed with `secret` using HMAC and issued for
+    /// `audience`.
+    pub fn hmac(secret: impl Into<Vec<u8>>, audience: impl Into<String>) -> Self {
+        Self {
+            key: BearerKey::Hmac(secret.into()),
+            audience: audience.into(),
+            access: Access::Full,
+        }
+    }
+
+    /// Accepts `RS256` and `ES256` tokens issued for `audience` and signed
+    /// with one of the keys published at `url`. The key set is fetched when
+    /// first needed, cached for a while, and fetched again early for a
+    /// token naming a key it doesn't have.
+    pub fn jwks(url: impl Into<String>, audience: impl Into<String>) -> Self {
+        Self {
+            key: BearerKey::Jwks(url.into()),
+            audience: audience.into(),
+            access: Access::Full,
+        }
+    }
+
+    /// What every accepted token may do. Defaults to [`Access::Full`].
+    pub fn access(mut self, access: Access) -> Self {
+        self.access = access;
+        self
+    }
+}
+
+impl fmt::Debug for BearerPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let key = match &self.key {
+            BearerKey::Hmac(_) => "Hmac(<redacted>)".to_owned(),
+            BearerKey::Jwks(url) => format!("Jwks({url})"),
+        };
+        f.debug_struct("BearerPolicy")
+            .field("key", &key)
+            .field("audience", &self.audience)
+            .field("access", &self.access)
+            .finish()
+    }
+}
+
+/// Where beam reports each finished transfer, as a JSON `POST` of its
+/// `event` (`transfer-completed` or `transfer-failed`), `filename`, `bytes`,
+/// `duration_ms`, the uploader's response `status` and, for failures,
+/// `error`. A delivery that errors or gets a non-`2xx` answer is retried a
+/// couple of times.
+#[derive(Clone)]
+pub struct Webhook {
+    pub(crate) url: String,
+    pub(crate) secret: Option<Vec<u8>>,
+}
+
+impl Webhook {
+    /// Posts reports to `url`, which may be `http` or `https`.
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            secret: None,
+        }
+    }
+
+    /// Signs every report with `secret`, sending the hex HMAC-SHA256 of the
+    /// body as `X-Beam-Signature: sha256=<hex>` so the receiver can tell it
+    /// came from beam.
+    pub fn secret(mut self, secret: impl Into<Vec<u8>>) -> Self {
+        self.secret = Some(secret.into());
+        self
+    }
+}
+
+impl fmt::Debug for Webhook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Webhook")
+            .field("url", &self.url)
+            .field("secret", &self.secret.as_ref().map(|_| "<redacted>"))
+            .finish()
+    }
+}
+
+/// Cost of the argon2id hash beam computes for each plaintext password at
+/// startup. Every Basic login verifies against that hash, so the same cost is
+/// paid per authenticated request: more memory and iterations make a stolen
+/// hash slower to brute-force, but also make each request slower and let a
+/// flood of logins tie up more CPU and memory. Pre-hashed secrets keep the
+/// parameters they were hashed with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PasswordHashing {
+    /// Memory each hash fills, in KiB.
+    pub memory_kib: u32,
+    /// Passes over that memory.
+    pub iterations: u32,
+    /// Lanes the memory is split into.
+    pub parallelism: u32,
+}
+
+/// argon2's own defaults: 19 MiB, two passes and one lane, as OWASP
+/// recommends for argon2id.
+pub const DEFAULT_PASSWORD_HASHING: PasswordHashing = PasswordHashing {
+    memory_kib: argon2::Params::DEFAULT_M_COST,
+    iterations: argon2::Params::DEFAULT_T_COST,
+    parallelism: argon2::Params::DEFAULT_P_COST,
+};
+
+/// Realm named in the `WWW-Authenticate` challenge, which browsers show in
+/// their login prompt.
+pub const DEFAULT_AUTH_REALM: &str = "beam";
+
+/// Future that resolves when the server should begin a graceful shutdown.
+pub type ShutdownSignal = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// Configuration for a beam server, built with [`ServerConfig::builder`].
+pub struct ServerConfig {
+    pub(crate) bind_addr: IpAddr,
+    pub(crate) port: u16,
+    pub(crate) users: Vec<(String, Secret, Access)>,
+    pub(crate) auth_realm: String,
+    pub(crate) password_hashing: PasswordHashing,
+    pub(crate) auth_failure_limit: Option<AuthFailureLimit>,
+    pub(crate) auth_failure_delay: Option<RangeInclusive<Duration>>,
+    pub(crate) channel_buffer: usize,
+    pub(crate) max_buffer_frames: usize,
+    pub(crate) upload_ready_timeout: Option<Duration>,
+    pub(crate) max_pending_age: Option<Duration>,
+    pub(crate) idle_timeout: Option<Duration>,
+    pub(crate) tcp_nodelay: bool,
+    pub(crate) tcp_keepalive: Option<Duration>,
+    pub(crate) min_upload_rate: Option<MinUploadRate>,
+    pub(crate) max_body_size: Option<u64>,
+    pub(crate) reject_empty_uploads: bool,
+    pub(crate) enforce_content_length: bool,
+    pub(crate) max_transfer_rate: Option<u64>,
+    pub(crate) max_in_flight_bytes: Option<usize>,
+    pub(crate) max_filename_len: Option<usize>,
+    pub(crate) extension_policy: ExtensionPolicy,
+    pub(crate) content_types: Vec<(String, String)>,
+    pub(crate) metadata_header_prefix: Option<String>,
+    pub(crate) max_metadata_headers: usize,
+    pub(crate) max_metadata_bytes: usize,
+    pub(crate) max_concurrent_streams: Option<usize>,
+    pub(crate) max_concurrent_requests: Option<usize>,
+    pub(crate) allowed_ips: Vec<String>,
+    pub(crate) denied_ips: Vec<String>,
+    pub(crate) upload_quota: Option<UploadQuota>,
+    pub(crate) user_upload_quotas: HashMap<String, UploadQuota>,
+    pub(crate) anonymous_downloads: bool,
+    pub(crate) compress_downloads: bool,
+    pub(crate) compress_min_size: Option<u64>,
+    pub(crate) download_wait_timeout: Option<Duration>,
+    pub(crate) parked_download: ParkedDownload,
+    pub(crate) not_found_retry_after: Option<Duration>,
+    pub(crate) base_path: String,
+    pub(crate) cors: Option<CorsPolicy>,
+    pub(crate) bearer: Option<BearerPolicy>,
+    pub(crate) webhook: Option<Webhook>,
+    pub(crate) access_log: Option<tracing::Level>,
+    pub(crate) shutdown_signal: Option<ShutdownSignal>,
+    pub(crate) drain_delay: Option<Duration>,
+    pub(crate) runtime: Option<tokio::runtime::Handle>,
+    pub(crate) one_shot: bool,
+    pub(crate) lag_policy: LagPolicy,
+    pub(crate) reconnect_grace: Option<ReconnectGrace>,
+    pub(crate) spool_dir: Option<PathBuf>,
+    pub(crate) spool_ttl: Duration,
+    pub(crate) max_spool_ttl: Option<Duration>,
+    pub(crate) compress_spool: bool,
+    pub(crate) tls: Option<TlsFiles>,
+}
+
+/// PEM files for serving HTTPS.
+#[derive(Debug)]
+pub(crate) struct TlsFiles {
+    pub(crate) cert: PathBuf,
+    pub(crate) key: PathBuf,
+}
+
+impl ServerConfig {
+    pub fn builder() -> ServerConfigBuilder {
+        ServerConfigBuilder::default()
+    }
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            bind_addr: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            port: DEFAULT_PORT,
+            users: Vec::new(),
+            auth_realm: DEFAULT_AUTH_REALM.to_owned(),
+            password_hashing: DEFAULT_PASSWORD_HASHING,
+            auth_failure_limit: Some(DEFAULT_AUTH_FAILURE_LIMIT),
+            auth_failure_delay: None,
+            channel_buffer: DEFAULT_CHANNEL_BUFFER,
+            max_buffer_frames: DEFAULT_MAX_BUFFER_FRAMES,
+            upload_ready_timeout: Some(DEFAULT_UPLOAD_READY_TIMEOUT),
+            max_pending_age: None,
+            idle_timeout: Some(DEFAULT_IDLE_TIMEOUT),
+            tcp_nodelay: true,
+            tcp_keepalive: None,
+            min_upload_rate: None,
+            max_body_size: None,
+            reject_empty_uploads: false,
+            enforce_content_length: true,
+            max_transfer_rate: None,
+            max_in_flight_bytes: None,
+            max_filename_len: Some(DEFAULT_MAX_FILENAME_LEN),
+            extension_policy: ExtensionPolicy::default(),
+            content_types: Vec::new(),
+            metadata_header_prefix: Some(DEFAULT_METADATA_HEADER_PREFIX.to_ascii_lowercase()),
+            max_metadata_headers: MAX_METADATA_HEADERS,
+            max_metadata_bytes: MAX_METADATA_BYTES,
+            max_concurrent_streams: None,
+            max_concurrent_requests: None,
+            allowed_ips: Vec::new(),
+            denied_ips: Vec::new(),
+            upload_quota: None,
+            user_upload_quotas: HashMap::new(),
+            anonymous_downloads: false,
+            compress_downloads: false,
+            compress_min_size: None,
+            download_wait_timeout: None,
+            parked_download: ParkedDownload::Silent,
+            not_found_retry_after: None,
+            base_path: String::new(),
+            cors: None,
+            bearer: None,
+            webhook: None,
+            access_log: Some(tracing::Level::INFO),
+            shutdown_signal: None,
+            drain_delay: None,
+            runtime: None,
+            one_shot: false,
+            lag_policy: DEFAULT_LAG_POLICY,
+            reconnect_grace: None,
+            spool_dir: None,
+            spool_ttl: DEFAULT_SPOOL_TTL,
+            max_spool_ttl: None,
+            compress_spool: false,
+            tls: None,
+        }
+    }
+}
+
+impl fmt::Debug for ServerConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ServerConfig")
+            .field("bind_addr", &self.bind_addr)
+            .field("port", &self.port)
+            .field(
+                "users",
+                &self
+                    .users
+                    .iter()
+                    .map(|(user, _, access)| (user, access))
+                    .collect::<Vec<_>>(),
+            )
+            .field("auth_realm", &self.auth_realm)
+            .field("password_hashing", &self.password_hashing)
+            .field("auth_failure_limit", &self.auth_failure_limit)
+            .field("auth_failure_delay", &self.auth_failure_delay)
+            .field("channel_buffer", &self.channel_buffer)
+            .field("max_buffer_frames", &self.max_buffer_frames)
+            .field("upload_ready_timeout", &self.upload_ready_timeout)
+            .field("max_pending_age", &self.max_pending_age)
+            .field("idle_timeout", &self.idle_timeout)
+            .field("tcp_nodelay", &self.tcp_nodelay)
+            .field("tcp_keepalive", &self.tcp_keepalive)
+            .field("min_upload_rate", &self.min_upload_rate)
+            .field("max_body_size", &self.max_body_size)
+            .field("reject_empty_uploads", &self.reject_empty_uploads)
+            .field("enforce_content_length", &self.enforce_content_length)
+            .field("max_transfer_rate", &self.max_transfer_rate)
+            .field("max_in_flight_bytes", &self.max_in_flight_bytes)
+            .field("max_filename_len", &self.max_filename_len)
+            .field("extension_policy", &self.extension_policy)
+            .field("content_types", &self.content_types)
+            .field("metadata_header_prefix", &self.metadata_header_prefix)
+            .field("max_metadata_headers", &self.max_metadata_headers)
+            .field("max_metadata_bytes", &self.max_metadata_bytes)
+            .field("max_concurrent_streams", &self.max_concurrent_streams)
+            .field("max_concurrent_requests", &self.max_concurrent_requests)
+            .field("allowed_ips", &self.allowed_ips)
+            .field("denied_ips", &self.denied_ips)
+            .field("upload_quota", &self.upload_quota)
+            .field("user_upload_quotas", &self.user_upload_quotas)
+            .field("anonymous_downloads", &self.anonymous_downloads)
+            .field("compress_downloads", &self.compress_downloads)
+            .field("compress_min_size", &self.compress_min_size)
+            .field("download_wait_timeout", &self.download_wait_timeout)
+            .field("parked_download", &self.parked_download)
+            .field("not_found_retry_after", &self.not_found_retry_after)
+            .field("base_path", &self.base_path)
+            .field("cors", &self.cors)
+            .field("bearer", &self.bearer)
+            .field("webhook", &self.webhook)
+            .field("access_log", &self.access_log)
+            .field("shutdown_signal", &self.shutdown_signal.is_some())
+            .field("drain_delay", &self.drain_delay)
+            .field("runtime", &self.runtime.is_some())
+            .field("one_shot", &self.one_shot)
+            .field("lag_policy", &self.lag_policy)
+            .field("reconnect_grace", &self.reconnect_grace)
+            .field("spool_dir", &self.spool_dir)
+            .field("spool_ttl", &self.spool_ttl)
+            .field("max_spool_ttl", &self.max_spool_ttl)
+            .field("compress_spool", &self.compress_spool)
+            .field("tls", &self.tls)
+            .finish()
+    }
+}
+
+/// Builder for [`ServerConfig`]. Unset fields keep the defaults of a plain
+/// `beam <username> <password>` invocation.
+#[derive(Debug, Default)]
+pub struct ServerConfigBuilder {
+    config: ServerConfig,
+}
+
+impl ServerConfigBuilder {
+    /// Address of the interface to listen on. Defaults to `0.0.0.0`, every
+    /// IPv4 interface; use `127.0.0.1` or `::1` to keep beam reachable only
+    /// from this machine, or `::` to listen on every interface over both
+    /// IPv6 and IPv4.
+    pub fn bind_addr(mut self, addr: impl Into<IpAddr>) -> Self {
+        self.config.bind_addr = addr.into();
+        self
+    }
+
+    /// TCP port to listen on. Defaults to [`DEFAULT_PORT`].
+    pub fn port(mut self, port: u16) -> Self {
+        self.config.port = port;
+        self
+    }
+
+    /// Mounts every route under `path`, e.g. `/beam` when a reverse proxy
+    /// forwards `https://host/beam/` here. Leading and trailing slashes are
+    /// normalised; the default empty path serves from the root.
+    pub fn base_path(mut self, path: impl AsRef<str>) -> Self {
+        let path = path.as_ref().trim_matches('/');
+        self.config.base_path = if path.is_empty() {
+            String::new()
+        } else {
+            format!("/{path}")
+        };
+        self
+    }
+
+    /// Sends CORS headers so scripts on the origins `policy` allows can
+    /// upload and download from the browser. Preflight `OPTIONS` requests
+    /// are answered without authentication. Off by default, leaving browsers
+    /// to block cross-origin requests.
+    pub fn cors(mut self, policy: impl Into<Option<CorsPolicy>>) -> Self {
+        self.config.cors = policy.into();
+        self
+    }
+
+    /// Also accepts bearer tokens as `policy` describes. Off by default, so
+    /// only Basic credentials authenticate.
+    pub fn bearer_auth(mut self, policy: impl Into<Option<BearerPolicy>>) -> Self {
+        self.config.bearer = policy.into();
+        self
+    }
+
+    /// Level of the `beam::access` event logged for every request, or
+    /// `None` to log none. `INFO` by default.
+    pub fn access_log(mut self, level: impl Into<Option<tracing::Level>>) -> Self {
+        self.config.access_log = level.into();
+        self
+    }
+
+    /// Reports every finished upload, successful or not, to `webhook`. Off
+    /// by default.
+    pub fn webhook(mut self, webhook: impl Into<Option<Webhook>>) -> Self {
+        self.config.webhook = webhook.into();
+        self
+    }
+
+    /// Serves HTTPS with the PEM certificate chain at `cert` and private key
+    /// at `key` instead of plain HTTP. Both files are read at startup.
+    pub fn tls(mut self, cert: impl Into<PathBuf>, key: impl Into<PathBuf>) -> Self {
+        self.config.tls = Some(TlsFiles {
+            cert: cert.into(),
+            key: key.into(),
+        });
+        self
+    }
+
+    /// Adds a user allowed to upload and download. Call repeatedly to
+    /// register several users; repeating a username replaces its password.
+    pub fn credentials(self, username: impl Into<String>, password: impl Into<String>) -> Self {
+        self.user(username, Secret::Password(password.into()))
+    }
+
+    /// Adds a user who may upload but not download, e.g. the producer in a
+    /// producer/consumer setup.
+    pub fn upload_credentials(
+        self,
+        username: impl Into<String>,
+        password: impl Into<String>,
+    ) -> Self {
+        self.user_with_access(username, Secret::Password(password.into()), Access::Upload)
+    }
+
+    /// Adds a user who may download but not upload.
+    pub fn download_credentials(
+        self,
+        username: impl Into<String>,
+        password: impl Into<String>,
+    ) -> Self {
+        self.user_with_access(
+            username,
+            Secret::Password(password.into()),
+            Access::Download,
+        )
+    }
+
+    /// Adds an operator who may do everything a normal user can and also use
+    /// the `/admin` endpoints.
+    pub fn admin_credentials(
+        self,
+        username: impl Into<String>,
+        password: impl Into<String>,
+    ) -> Self {
+        self.user_with_access(username, Secret::Password(password.into()), Access::Admin)
+    }
+
+    /// Adds a user whose secret may already be an argon2 hash, e.g. one read
+    /// with [`load_credentials_file`](crate::load_credentials_file).
+    pub fn user(self, username: impl Into<String>, secret: Secret) -> Self {
+        self.user_with_access(username, secret, Access::Full)
+    }
+
+    /// Adds a user limited to `access`. Repeating a username replaces both
+    /// its secret and its access.
+    pub fn user_with_access(
+        mut self,
+        username: impl Into<String>,
+        secret: Secret,
+        access: Access,
+    ) -> Self {
+        let username = username.into();
+        self.config
+            .users
+            .retain(|(existing, _, _)| *existing != username);
+        self.config.users.push((username, secret, access));
+        self
+    }
+
+    /// Adds every `(username, secret)` pair from `users`.
+    pub fn users(self, users: impl IntoIterator<Item = (String, Secret)>) -> Self {
+        users.into_iter().fold(self, |builder, (username, secret)| {
+            builder.user(username, secret)
+        })
+    }
+
+    /// Names the realm in the `WWW-Authenticate` challenge sent with `401`,
+    /// which browsers show in their login prompt. Defaults to
+    /// [`DEFAULT_AUTH_REALM`].
+    pub fn auth_realm(mut self, realm: impl Into<String>) -> Self {
+        self.config.auth_realm = realm.into();
+        self
+    }
+
+    /// argon2id parameters plaintext passwords are hashed with; see
+    /// [`PasswordHashing`] for the tradeoff. Parameters argon2 rejects, such
+    /// as less than 8 KiB of memory per lane, fail server startup. Defaults
+    /// to [`DEFAULT_PASSWORD_HASHING`].
+    pub fn password_hashing(mut self, hashing: PasswordHashing) -> Self {
+        self.config.password_hashing = hashing;
+        self
+    }
+
+    /// Throttles password guessing per client address. Once a client has
+    /// failed `max_failures` times within `window`, its requests get
+    /// `429 Too Many Requests` with `Retry-After` until the window closes,
+    /// without its credentials being checked. Defaults to
+    /// [`DEFAULT_AUTH_FAILURE_LIMIT`]; `None` disables the limit.
+    pub fn auth_failure_limit(mut self, limit: impl Into<Option<AuthFailureLimit>>) -> Self {
+        self.config.auth_failure_limit = limit.into();
+        self
+    }
+
+    /// Holds back each `401` for a random time within `delay`, e.g.
+    /// `Duration::from_millis(100)..=Duration::from_millis(500)`, so failed
+    /// logins reveal less through their timing and cost a guesser more.
+    /// Successful logins are never delayed. Off by default.
+    pub fn auth_failure_delay(
+        mut self,
+        delay: impl Into<Option<RangeInclusive<Duration>>>,
+    ) -> Self {
+        self.config.auth_failure_delay = delay.into();
+        self
+    }
+
+    /// Lets `GET /{filename}` through without credentials while uploads
+    /// still require them. Anyone who can reach the server and guess or
+    /// learn a filename can then download it, so only enable this where
+    /// filenames are not secret or the network is trusted. Off by default.
+    pub fn anonymous_downloads(mut self, enabled: bool) -> Self {
+        self.config.anonymous_downloads = enabled;
+        self
+    }
+
+    /// Compresses live downloads in transit for clients that accept brotli
+    /// or gzip, picking whichever their `Accept-Encoding` rates higher and
+    /// brotli on a tie. Uploads that declared their own `Content-Encoding`,
+    /// or whose type is compressed already, such as images, video and
+    /// archives, are sent as-is. Compressed downloads carry no `Content-Length`,
+    /// and compression costs CPU per downloader, so it is off by default.
+    /// Spooled downloads are always sent as stored.
+    pub fn compress_downloads(mut self, enabled: bool) -> Self {
+        self.config.compress_downloads = enabled;
+        self
+    }
+
+    /// Sends downloads smaller than `bytes` uncompressed even when
+    /// [`compress_downloads`](Self::compress_downloads) is on, since
+    /// compressing a few bytes costs CPU and framing overhead can make them
+    /// larger. An upload's declared `Content-Length` decides up front;
+    /// without one, beam holds back the response until `bytes` have arrived
+    /// or the upload has ended, and compresses only in the first case.
+    /// `None` or zero, the default, compresses downloads of any size.
+    pub fn compress_min_size(mut self, bytes: impl Into<Option<u64>>) -> Self {
+        self.config.compress_min_size = bytes.into().filter(|&bytes| bytes > 0);
+        self
+    }
+
+    /// Number of body frames buffered per stream. Values below one are
+    /// raised to one.
+    ///
+    /// The buffer is the only slack between an uploader and its downloader:
+    /// once it is full, beam stops reading the upload until the downloader
+    /// catches up. A larger buffer absorbs short downloader stalls and keeps
+    /// fast links busy, at the cost of up to `frames` body chunks of memory
+    /// per downloader; a smaller one holds memory flat across many streams.
+    pub fn channel_buffer(mut self, frames: usize) -> Self {
+        self.config.channel_buffer = frames.max(1);
+        self
+    }
+
+    /// Largest buffer a live upload may ask for in place of
+    /// [`channel_buffer`](Self::channel_buffer) by sending
+    /// `X-Buffer-Frames: n`, e.g. `1` to keep a latency-sensitive transfer
+    /// from queueing frames, or more for a bulk one. Requests outside
+    /// `1..=frames` are clamped into it. Defaults to
+    /// [`DEFAULT_MAX_BUFFER_FRAMES`]; values below one are raised to one.
+    pub fn max_buffer_frames(mut self, frames: usize) -> Self {
+        self.config.max_buffer_frames = frames.max(1);
+        self
+    }
+
+    /// How long an upload waits for a download client. `None` or a zero
+    /// duration waits forever.
+    pub fn upload_ready_timeout(mut self, timeout: impl Into<Option<Duration>>) -> Self {
+        self.config.upload_ready_timeout = timeout.into().filter(|timeout| !timeout.is_zero());
+        self
+    }
+
+    /// Sweeps out live uploads that have waited longer than `age` for their
+    /// download clients, cancelling them and freeing their filenames. This
+    /// backs up [`upload_ready_timeout`](Self::upload_ready_timeout): it also
+    /// catches uploads started with no timeout and entries left behind by an
+    /// upload task that died. `None`, the default, runs no sweep.
+    pub fn max_pending_age(mut self, age: impl Into<Option<Duration>>) -> Self {
+        self.config.max_pending_age = age.into().filter(|age| !age.is_zero());
+        self
+    }
+
+    /// Lets a download that arrives before its upload wait up to `timeout`
+    /// for the upload to start instead of failing with `404` right away.
+    /// `None` or a zero duration, the default, disables waiting.
+    pub fn download_wait_timeout(mut self, timeout: impl Into<Option<Duration>>) -> Self {
+        self.config.download_wait_timeout = timeout.into().filter(|timeout| !timeout.is_zero());
+        self
+    }
+
+    /// Whether parked downloads stay silent, the default, or can ask for
+    /// heartbeats. An HTTP response can't carry filler bytes ahead of a
+    /// file, and beam can't send `1xx` hints, so heartbeats come as a
+    /// separate event stream; see [`ParkedDownload::Heartbeat`]. Only
+    /// matters with [`download_wait_timeout`](Self::download_wait_timeout).
+    pub fn parked_download(mut self, parked: ParkedDownload) -> Self {
+        self.config.parked_download = match parked {
+            ParkedDownload::Heartbeat(interval) if interval.is_zero() => ParkedDownload::Silent,
+            parked => parked,
+        };
+        self
+    }
+
+    /// Sends `Retry-After` with the `404` a download gets when no upload is
+    /// registered for its filename, so clients polling ahead of the
+    /// uploader know how long to back off. Rounded up to whole seconds.
+    /// Not sent by default.
+    pub fn not_found_retry_after(mut self, delay: impl Into<Option<Duration>>) -> Self {
+        self.config.not_found_retry_after = delay.into();
+        self
+    }
+
+    /// How long a transfer may wait for the next chunk of the upload body
+    /// before it is aborted, its downloaders get an error and the stream is
+    /// removed. Time spent waiting on a slow downloader does not count.
+    /// `None` or a zero duration waits forever.
+    pub fn idle_timeout(mut self, timeout: impl Into<Option<Duration>>) -> Self {
+        self.config.idle_timeout = timeout.into().filter(|timeout| !timeout.is_zero());
+        self
+    }
+
+    /// Sets `TCP_NODELAY` on accepted connections, so small writes such as
+    /// response headers and the last chunk of a transfer go out at once
+    /// instead of waiting to be coalesced. On by default.
+    pub fn tcp_nodelay(mut self, nodelay: bool) -> Self {
+        self.config.tcp_nodelay = nodelay;
+        self
+    }
+
+    /// Enables TCP keepalive on accepted connections, probing a connection
+    /// once it has been silent for `idle`, and again every `idle` where the
+    /// platform allows. A peer that vanished without closing, such as an
+    /// uploader still waiting for its downloader, is then noticed and its
+    /// transfer failed instead of lingering until the upload ready timeout.
+    /// Probes carry no data, so they don't hold off
+    /// [`idle_timeout`](Self::idle_timeout), which still aborts uploads
+    /// from peers that are alive but silent. `None` or zero, the default,
+    /// leaves keepalive off.
+    pub fn tcp_keepalive(mut self, idle: impl Into<Option<Duration>>) -> Self {
+        self.config.tcp_keepalive = idle.into().filter(|idle| !idle.is_zero());
+        self
+    }
+
+    /// Aborts uploads that send their body slower than `bytes_per_sec` on
+    /// average over any `window`, with `408 Request Timeout`, so a client
+    /// trickling bytes cannot hold a filename and stream slot indefinitely.
+    /// Unlike [`idle_timeout`](Self::idle_timeout) this catches uploads that
+    /// send a little now and then. Only time spent waiting on the uploader
+    /// counts. Off by default.
+    pub fn min_upload_rate(mut self, rate: impl Into<Option<MinUploadRate>>) -> Self {
+        self.config.min_upload_rate = rate
+            .into()
+            .filter(|rate| rate.bytes_per_sec > 0 && !rate.window.is_zero());
+        self
+    }
+
+    /// Largest upload body accepted, in bytes. Uploads declaring a larger
+    /// `Content-Length` are refused up front; others are aborted with
+    /// `413 Payload Too Large` once they cross the limit. Unlimited by
+    /// default.
+    pub fn max_body_size(mut self, bytes: impl Into<Option<u64>>) -> Self {
+        self.config.max_body_size = bytes.into();
+        self
+    }
+
+    /// Refuses uploads that end without sending any data with
+    /// `400 Bad Request`, so an uploader that connects and hangs up isn't
+    /// mistaken for a legitimately empty file. One declaring
+    /// `Content-Length: 0` is turned away before it registers; any other is
+    /// failed once its body ends, and its live downloads end with an error.
+    /// Off by default, where empty uploads succeed like any other.
+    pub fn reject_empty_uploads(mut self, reject: bool) -> Self {
+        self.config.reject_empty_uploads = reject;
+        self
+    }
+
+    /// Fails uploads whose body turns out longer or shorter than the
+    /// `Content-Length` they declared with `400 Bad Request`. A live
+    /// download, which was promised that length, ends with an error as soon
+    /// as the body overruns it or ends short, or with an `X-Upload-Error`
+    /// trailer if it asked for trailers; a spooled upload isn't stored. On by
+    /// default; turning it off relays whatever arrives.
+    pub fn enforce_content_length(mut self, enforce: bool) -> Self {
+        self.config.enforce_content_length = enforce;
+        self
+    }
+
+    /// Caps each transfer at `bytes_per_sec`, so one upload cannot saturate
+    /// a shared link: beam holds back reading the upload body, and sending
+    /// spooled downloads, whenever the transfer gets ahead of the rate.
+    /// `None` or zero, the default, leaves transfers unthrottled.
+    pub fn max_transfer_rate(mut self, bytes_per_sec: impl Into<Option<u64>>) -> Self {
+        self.config.max_transfer_rate = bytes_per_sec.into().filter(|&rate| rate > 0);
+        self
+    }
+
+    /// Caps the bytes live uploads may have relayed but their downloaders
+    /// not yet taken, summed across every stream; each stream's channel
+    /// otherwise holds up to [`channel_buffer`](Self::channel_buffer) chunks
+    /// of whatever size the uploader sends. An upload that would go over
+    /// stops reading its body until downloaders catch up. A broadcast upload
+    /// counts once per downloader. `None` or zero, the default, leaves it
+    /// unlimited.
+    pub fn max_in_flight_bytes(mut self, bytes: impl Into<Option<usize>>) -> Self {
+        self.config.max_in_flight_bytes = bytes.into().filter(|&bytes| bytes > 0);
+        self
+    }
+
+    /// Longest filename accepted, counted in UTF-8 bytes after surrounding
+    /// whitespace is trimmed; longer names get `400 Bad Request`. Defaults to
+    /// [`DEFAULT_MAX_FILENAME_LEN`]; `None` accepts any length.
+    pub fn max_filename_len(mut self, bytes: impl Into<Option<usize>>) -> Self {
+        self.config.max_filename_len = bytes.into();
+        self
+    }
+
+    /// Refuses uploads and downloads of filenames ending in any of
+    /// `extensions` with `403 Forbidden`. Extensions are given with or
+    /// without the leading dot, compared without regard to case, and may be
+    /// compound: `tar.gz` blocks `backup.tar.gz` but not `notes.gz`.
+    pub fn denied_extensions(
+        mut self,
+        extensions: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> Self {
+        self.config.extension_policy.denied = extensions
+            .into_iter()
+            .filter_map(|extension| ExtensionPolicy::normalize(extension.as_ref()))
+            .collect();
+        self
+    }
+
+    /// Refuses uploads and downloads of filenames that don't end in one of
+    /// `extensions`, matched as for
+    /// [`denied_extensions`](Self::denied_extensions), with
+    /// `403 Forbidden`. A denied extension is refused even if it is also
+    /// allowed. Every extension is allowed by default.
+    pub fn allowed_extensions(
+        mut self,
+        extensions: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> Self {
+        self.config.extension_policy.allowed = Some(
+            extensions
+                .into_iter()
+                .filter_map(|extension| ExtensionPolicy::normalize(extension.as_ref()))
+                .collect(),
+        );
+        self
+    }
+
+    /// Downloads of uploads that declared no `Content-Type` and whose
+    /// filename ends in `extension`, matched as for
+    /// [`denied_extensions`](Self::denied_extensions), are sent as
+    /// `content_type`. Extensions without a mapping of their own get the
+    /// usual type for them, such as `image/png` for `.png`, and unknown
+    /// ones `application/octet-stream`. May be called repeatedly.
+    pub fn content_type(
+        mut self,
+        extension: impl Into<String>,
+        content_type: impl Into<String>,
+    ) -> Self {
+        self.config
+            .content_types
+            .push((extension.into(), content_type.into()));
+        self
+    }
+
+    /// Upload headers whose names start with `prefix`, matched without
+    /// regard to case, are kept with the stream and sent on every download
+    /// of it, e.g. `X-Meta-Commit`. Uploads with more of them than
+    /// [`metadata_limits`](Self::metadata_limits) allow get
+    /// `431 Request Header Fields Too Large`. Defaults to
+    /// [`DEFAULT_METADATA_HEADER_PREFIX`]; `None` or an empty prefix
+    /// forwards nothing.
+    pub fn metadata_header_prefix<'a>(mut self, prefix: impl Into<Option<&'a str>>) -> Self {
+        self.config.metadata_header_prefix = prefix
+            .into()
+            .filter(|prefix| !prefix.is_empty())
+            .map(str::to_ascii_lowercase);
+        self
+    }
+
+    /// Most metadata headers one upload may carry, and the most bytes their
+    /// names and values may add up to, before it is refused with
+    /// `431 Request Header Fields Too Large`. Every download of the stream
+    /// repeats them, so these bound what an uploader can make beam hold
+    /// and send. Default to [`MAX_METADATA_HEADERS`] and
+    /// [`MAX_METADATA_BYTES`].
+    pub fn metadata_limits(mut self, headers: usize, bytes: usize) -> Self {
+        self.config.max_metadata_headers = headers;
+        self.config.max_metadata_bytes = bytes;
+        self
+    }
+
+    /// Most streams registered at once, counting uploads waiting for or
+    /// relaying to downloaders and, with a spool, stored files. Further
+    /// uploads get `503 Service Unavailable` with `Retry-After` until one
+    /// finishes. Unlimited by default.
+    pub fn max_concurrent_streams(mut self, streams: impl Into<Option<usize>>) -> Self {
+        self.config.max_concurrent_streams = streams.into();
+        self
+    }
+
+    /// Most requests handled at once, counting every request rather than
+    /// only transfers: dashboard views, health checks and metrics scrapes
+    /// take a slot too. A response holds its slot until its body is fully
+    /// sent or dropped, so a streaming download keeps one for its whole
+    /// transfer. Requests beyond the limit get `503 Service Unavailable`
+    /// with `Retry-After`. `None` or zero, the default, is unlimited.
+    pub fn max_concurrent_requests(mut self, requests: impl Into<Option<usize>>) -> Self {
+        self.config.max_concurrent_requests = requests.into().filter(|&requests| requests > 0);
+        self
+    }
+
+    /// Only lets peers whose address is in one of these IPv4 or IPv6 CIDR
+    /// blocks, such as `10.0.0.0/8` or `fd00::/8`, make requests; a bare
+    /// address allows just that one. Everyone else gets `403 Forbidden`
+    /// before authentication. Empty, the default, allows every address.
+    pub fn allowed_ips(mut self, cidrs: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.config.allowed_ips = cidrs.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Refuses peers whose address is in one of these CIDR blocks with
+    /// `403 Forbidden`, even if [`allowed_ips`](Self::allowed_ips) lets
+    /// them in.
+    pub fn denied_ips(mut self, cidrs: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.config.denied_ips = cidrs.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Quota for every user without one of their own. An upload that would
+    /// give its user more streams than the quota allows gets
+    /// `429 Too Many Requests`; one that declares a `Content-Length` that
+    /// won't fit, or passes the byte limit while streaming, gets
+    /// `413 Payload Too Large`. A stream stops counting once it is done:
+    /// relayed, or for a stored file, deleted or expired. None by default.
+    pub fn upload_quota(mut self, quota: impl Into<Option<UploadQuota>>) -> Self {
+        self.config.upload_quota = quota.into();
+        self
+    }
+
+    /// Gives `username` its own quota in place of the
+    /// [`upload_quota`](Self::upload_quota) default.
+    pub fn user_upload_quota(mut self, username: impl Into<String>, quota: UploadQuota) -> Self {
+        self.config
+            .user_upload_quotas
+            .insert(username.into(), quota);
+        self
+    }
+
+    /// How broadcast uploads (those sent with `X-Receivers` above one) treat a
+    /// downloader that stops reading. Single-downloader transfers always wait.
+    pub fn lag_policy(mut self, policy: LagPolicy) -> Self {
+        self.config.lag_policy = policy;
+        self
+    }
+
+    /// Lets a live download that drops reconnect within `grace.window`
+    /// instead of failing the upload. The upload pauses meanwhile, and the
+    /// next `GET` of the filename resumes with `Range: bytes=N-`, `N` being
+    /// how much the client already has; it gets `206 Partial Content` when
+    /// the length is known and plain `200` otherwise, either way starting at
+    /// byte `N`. Each live upload then holds up to `grace.replay_bytes` of
+    /// already relayed data (plus one chunk) in memory, on top of the
+    /// channel buffer, and `N` must fall within it. Broadcast uploads and
+    /// compressed downloads can't resume. Off by default.
+    pub fn reconnect_grace(mut self, grace: impl Into<Option<ReconnectGrace>>) -> Self {
+        self.config.reconnect_grace = grace.into().filter(|grace| !grace.window.is_zero());
+        self
+    }
+
+    /// Stores uploads in `dir` instead of relaying them live. A spooling
+    /// upload is answered with `201 Created` once its body is on disk, and
+    /// the file can then be downloaded any number of times, with `Range`
+    /// support, until [`spool_ttl`](Self::spool_ttl) runs out.
+    ///
+    /// The directory is created if missing. Leftover spool files from an
+    /// earlier run are deleted at startup, so give each server its own
+    /// directory.
+    pub fn spool_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.config.spool_dir = Some(dir.into());
+        self
+    }
+
+    /// How long a spooled upload stays downloadable before it is deleted.
+    /// Defaults to [`DEFAULT_SPOOL_TTL`].
+    pub fn spool_ttl(mut self, ttl: Duration) -> Self {
+        self.config.spool_ttl = ttl;
+        self
+    }
+
+    /// Longest an upload may ask to be kept by sending
+    /// `X-Expires-In: <seconds>` in place of [`spool_ttl`](Self::spool_ttl);
+    /// longer requests are clamped to it. `None`, the default, caps them at
+    /// the spool TTL, so uploads can only shorten their stay.
+    pub fn max_spool_ttl(mut self, ttl: impl Into<Option<Duration>>) -> Self {
+        self.config.max_spool_ttl = ttl.into();
+        self
+    }
+
+    /// Stores spooled uploads zstd-compressed, decompressing them as they
+    /// are downloaded. This is transparent to clients: downloads carry the
+    /// bytes as uploaded, with their original `Content-Length`, whatever
+    /// their `Accept-Encoding`. `Range` requests decompress and skip the
+    /// bytes before the range, so they cost more than on a plain file.
+    /// Resumable uploads are stored uncompressed. Off by default.
+    pub fn compress_spool(mut self, compress: bool) -> Self {
+        self.config.compress_spool = compress;
+        self
+    }
+
+    /// Future that triggers a graceful shutdown when it resolves. The server
+    /// stops accepting connections, uploads still waiting for a downloader
+    /// are answered with `503 Service Unavailable`, and transfers already in
+    /// progress run to completion before the server task exits.
+    pub fn shutdown_signal(mut self, signal: impl Future<Output = ()> + Send + 'static) -> Self {
+        self.config.shutdown_signal = Some(Box::pin(signal));
+        self
+    }
+
+    /// How long the server keeps serving as usual after the shutdown signal,
+    /// answering `GET /readyz` with `503` so load balancers stop routing to
+    /// it before it stops accepting connections. `None`, the default, starts
+    /// shutting down straight away.
+    pub fn drain_delay(mut self, delay: impl Into<Option<Duration>>) -> Self {
+        self.config.drain_delay = delay.into().filter(|delay| !delay.is_zero());
+        self
+    }
+
+    /// Shuts the server down gracefully once its first live transfer
+    /// completes, for one-off sharing: the task returned with the server
+    /// then finishes as it would on the
+    /// [`shutdown_signal`](Self::shutdown_signal), after any
+    /// [`drain_delay`](Self::drain_delay). Failed transfers don't count, nor
+    /// does storing an upload in the spool. Off by default.
+    pub fn one_shot(mut self, enabled: bool) -> Self {
+        self.config.one_shot = enabled;
+        self
+    }
+
+    /// Runtime the server, its per-connection and background tasks, and
+    /// [`BeamHandle::publish`](crate::BeamHandle::publish) uploads run on,
+    /// for embedders that keep beam off their main runtime. Its listener is
+    /// registered there too, so it keeps serving however busy the caller's
+    /// runtime is. `None`, the default, uses the runtime
+    /// [`setup_server_with_config`](crate::setup_server_with_config) is
+    /// called from.
+    pub fn runtime(mut self, runtime: impl Into<Option<tokio::runtime::Handle>>) -> Self {
+        self.config.runtime = runtime.into();
+        self
+    }
+
+    pub fn build(self) -> ServerConfig {
+        self.config
+    }
+}
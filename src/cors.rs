@@ -0,0 +1,79 @@
+use axum::http::{HeaderName, HeaderValue, Method};
+use tower_http::cors::{AllowHeaders, AllowMethods, AllowOrigin, Any, CorsLayer};
+
+use crate::{BeamError, config::CorsPolicy};
+
+/// Methods allowed cross-origin unless the policy names its own.
+const DEFAULT_METHODS: [Method; 6] = [
+    Method::GET,
+    Method::HEAD,
+    Method::PUT,
+    Method::POST,
+    Method::PATCH,
+    Method::DELETE,
+];
+
+/// Builds the layer enforcing `policy`, failing if it names an origin,
+/// method or header that isn't valid in an HTTP header.
+///
+/// Preflight `OPTIONS` requests are answered by the layer itself, before
+/// routing, so they never reach the `/{filename}` routes. Every response
+/// header is exposed, so scripts can read `Content-Disposition`,
+/// `Content-Range`, `Upload-Offset` and the metadata headers.
+pub(crate) fn layer(policy: &CorsPolicy) -> Result<CorsLayer, BeamError> {
+    let origins = if wildcard(&policy.origins) {
+        AllowOrigin::any()
+    } else {
+        AllowOrigin::list(
+            policy
+                .origins
+                .iter()
+                .map(|origin| {
+                    HeaderValue::from_str(origin)
+                        .map_err(|_| BeamError::Cors(format!("invalid origin `{origin}`")))
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+        )
+    };
+
+    let methods = match &policy.methods {
+        None => AllowMethods::list(DEFAULT_METHODS),
+        Some(methods) if wildcard(methods) => AllowMethods::any(),
+        Some(methods) => AllowMethods::list(
+            methods
+                .iter()
+                .map(|method| {
+                    Method::from_bytes(method.as_bytes())
+                        .map_err(|_| BeamError::Cors(format!("invalid method `{method}`")))
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+        ),
+    };
+
+    // Mirroring lets through whatever the browser asks for, including
+    // `Authorization`, which a `*` would not cover, and every metadata
+    // header whatever the configured prefix.
+    let headers = match &policy.headers {
+        None => AllowHeaders::mirror_request(),
+        Some(headers) if wildcard(headers) => AllowHeaders::any(),
+        Some(headers) => AllowHeaders::list(
+            headers
+                .iter()
+                .map(|header| {
+                    HeaderName::from_bytes(header.as_bytes())
+                        .map_err(|_| BeamError::Cors(format!("invalid header `{header}`")))
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+        ),
+    };
+
+    Ok(CorsLayer::new()
+        .allow_origin(origins)
+        .allow_methods(methods)
+        .allow_headers(headers)
+        .expose_headers(Any))
+}
+
+fn wildcard(values: &[String]) -> bool {
+    values.iter().any(|value| value == "*")
+}
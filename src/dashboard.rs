@@ -0,0 +1,177 @@
+use std::{fmt, sync::atomic::Ordering};
+
+use axum::{extract::State, response::Html};
+
+use crate::{AppState, StreamSource};
+
+/// What the dashboard shows. Text in it is escaped as the page is rendered,
+/// so a filename can't inject markup.
+struct Page<'a> {
+    anonymous_downloads: bool,
+    stores_uploads: bool,
+    base_path: &'a str,
+    rows: Vec<Row>,
+}
+
+/// One line of the active streams table.
+struct Row {
+    filename: String,
+    bytes_transferred: u64,
+    /// Connected out of expected downloaders, or what a spool entry is doing.
+    downloaders: String,
+    age_secs: u64,
+}
+
+/// Text written into the page with HTML's special characters escaped.
+struct Escaped<'a>(&'a str);
+
+impl fmt::Display for Escaped<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut rest = self.0;
+        while let Some(at) = rest.find(['&', '<', '>', '"', '\'']) {
+            f.write_str(&rest[..at])?;
+            f.write_str(match rest.as_bytes()[at] {
+                b'&' => "&amp;",
+                b'<' => "&lt;",
+                b'>' => "&gt;",
+                b'"' => "&quot;",
+                _ => "&#39;",
+            })?;
+            rest = &rest[at + 1..];
+        }
+        f.write_str(rest)
+    }
+}
+
+pub(crate) async fn dashboard(State(state): State<AppState>) -> Html<String> {
+    let mut rows = state
+        .streams
+        .iter()
+        .map(|entry| {
+            let (filename, stream_data) = entry.pair();
+            let downloaders = match &stream_data.source {
+                StreamSource::Live(live) => {
+                    format!("{}/{}", live.connected_downloaders(), live.receiver_count)
+                }
+                StreamSource::Spooling => "uploading".to_owned(),
+                StreamSource::Spooled(_) => "stored".to_owned(),
+                StreamSource::Reserved(_) => "reserved".to_owned(),
+            };
+            Row {
+                filename: filename.clone(),
+                bytes_transferred: stream_data.stats.bytes_transferred.load(Ordering::Relaxed),
+                downloaders,
+                age_secs: stream_data.stats.started.elapsed().as_secs(),
+            }
+        })
+        .collect::<Vec<_>>();
+    rows.sort_by(|a, b| a.filename.cmp(&b.filename));
+
+    Html(render(&Page {
+        anonymous_downloads: state.anonymous_downloads,
+        stores_uploads: state.spool.is_some(),
+        base_path: &state.base_path,
+        rows,
+    }))
+}
+
+fn render(page: &Page) -> String {
+    let auth_notice = if page.anonymous_downloads {
+        "  <p><strong>Anonymous downloads are enabled:</strong> uploads require HTTP Basic auth, but anyone who can reach this server and knows a filename can download it.</p>"
+    } else {
+        "  <p>Start Beam with <code>beam &lt;username&gt; &lt;password&gt;</code> then authenticate uploads and downloads using HTTP Basic auth.</p>"
+    };
+    let upload_note = if page.stores_uploads {
+        "The file is stored for later download."
+    } else {
+        "The upload finishes once someone downloads the file, so keep this page open until then."
+    };
+    let active_streams = page
+        .rows
+        .iter()
+        .map(|row| {
+            format!(
+                "      <tr><td>{}</td><td>{}</td><td>{}</td><td>{}s</td></tr>",
+                Escaped(&row.filename),
+                row.bytes_transferred,
+                Escaped(&row.downloaders),
+                row.age_secs,
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let base_path = Escaped(page.base_path);
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+  <meta charset="utf-8" />
+  <title>Beam Dashboard</title>
+  <style>
+    body {{ font-family: sans-serif; margin: 2rem; max-width: 40rem; }}
+    h1 {{ margin-bottom: 0.5rem; }}
+    section {{ margin-top: 1.5rem; }}
+    code {{ background: #f4f4f4; padding: 0.2rem 0.4rem; border-radius: 3px; }}
+    table {{ border-collapse: collapse; width: 100%; }}
+    th, td {{ text-align: left; padding: 0.25rem 0.5rem; border-bottom: 1px solid #ddd; }}
+  </style>
+</head>
+<body>
+  <h1>Beam Dashboard</h1>
+{auth_notice}
+  <section>
+    <h2>Active Streams</h2>
+    <table>
+      <tr><th>Filename</th><th>Bytes transferred</th><th>Downloaders connected</th><th>Age</th></tr>
+{active_streams}
+    </table>
+  </section>
+  <section>
+    <h2>Usage</h2>
+    <ol>
+      <li>Upload: <code>curl -u USER:PASS -T file.zip http://localhost:4000{base_path}/file.zip</code></li>
+      <li>Download: <code>curl -u USER:PASS http://localhost:4000{base_path}/file.zip -o file.zip</code></li>
+    </ol>
+  </section>
+  <section>
+    <h2>Upload from the browser</h2>
+    <form id="upload-form" method="post" action="{base_path}/" enctype="multipart/form-data">
+      <p><label>Filename <input type="text" name="filename" placeholder="defaults to the file's name" /></label></p>
+      <p><input type="file" name="file" required /></p>
+      <p><button type="submit">Upload</button> <progress id="upload-progress" max="1" value="0" hidden></progress> <span id="upload-status"></span></p>
+    </form>
+    <p>{upload_note} Your browser asks for the upload credentials.</p>
+  </section>
+  <script>
+    // Without JavaScript the form posts normally and the browser shows the reply.
+    document.getElementById("upload-form").addEventListener("submit", function (event) {{
+      event.preventDefault();
+      var form = event.target;
+      var progress = document.getElementById("upload-progress");
+      var status = document.getElementById("upload-status");
+      var request = new XMLHttpRequest();
+      request.open("POST", form.action);
+      request.upload.onprogress = function (progressEvent) {{
+        if (progressEvent.lengthComputable) {{
+          progress.value = progressEvent.loaded / progressEvent.total;
+        }}
+      }};
+      request.onload = function () {{
+        progress.hidden = true;
+        status.textContent = request.status + " " + request.responseText;
+      }};
+      request.onerror = function () {{
+        progress.hidden = true;
+        status.textContent = "Upload failed: the connection was lost";
+      }};
+      progress.value = 0;
+      progress.hidden = false;
+      status.textContent = "Uploading...";
+      request.send(new FormData(form));
+    }});
+  </script>
+</body>
+</html>"#
+    )
+}
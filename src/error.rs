@@ -0,0 +1,84 @@
+use std::{fmt, io};
+
+/// Why a beam server could not be started.
+#[derive(Debug)]
+pub enum BeamError {
+    /// A configured password could not be hashed, or a pre-hashed secret
+    /// is not a valid PHC string.
+    Credentials(argon2::password_hash::Error),
+    /// The spool directory could not be created or cleared.
+    Spool(io::Error),
+    /// The TLS certificate or private key could not be loaded.
+    Tls(io::Error),
+    /// The listening socket could not be bound.
+    Bind(io::Error),
+    /// The CORS policy names an origin, method or header that isn't valid.
+    Cors(String),
+    /// The bearer token policy names a JWKS URL that isn't valid.
+    Bearer(String),
+    /// An allowed or denied IP block isn't a valid address or CIDR.
+    IpFilter(String),
+    /// The webhook URL isn't a valid `http` or `https` URL.
+    Webhook(String),
+    /// A configured content type isn't a valid header value.
+    ContentType(String),
+}
+
+impl fmt::Display for BeamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BeamError::Credentials(error) => write!(f, "failed to prepare credentials: {error}"),
+            BeamError::Spool(error) => write!(f, "failed to prepare spool directory: {error}"),
+            BeamError::Tls(error) => write!(f, "failed to load TLS certificate and key: {error}"),
+            BeamError::Bind(error) => write!(f, "failed to bind TCP listener: {error}"),
+            BeamError::Cors(message) => write!(f, "invalid CORS policy: {message}"),
+            BeamError::Bearer(message) => write!(f, "invalid bearer token policy: {message}"),
+            BeamError::IpFilter(message) => write!(f, "invalid IP filter: {message}"),
+            BeamError::Webhook(message) => write!(f, "invalid webhook: {message}"),
+            BeamError::ContentType(message) => write!(f, "invalid content type mapping: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for BeamError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BeamError::Credentials(error) => Some(error),
+            BeamError::Spool(error) | BeamError::Tls(error) | BeamError::Bind(error) => Some(error),
+            BeamError::Cors(_)
+            | BeamError::Bearer(_)
+            | BeamError::IpFilter(_)
+            | BeamError::Webhook(_)
+            | BeamError::ContentType(_) => None,
+        }
+    }
+}
+
+/// Why [`BeamHandle::publish`](crate::BeamHandle::publish) could not relay a
+/// stream.
+#[derive(Debug)]
+pub enum PublishError {
+    /// The filename could not be used as a stream key.
+    InvalidFilename(&'static str),
+    /// The filename's extension is blocked by the server's configuration.
+    ForbiddenExtension,
+    /// The upload was refused or failed, with the status and message an
+    /// HTTP uploader would have been answered with.
+    Failed { status: u16, message: String },
+}
+
+impl fmt::Display for PublishError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PublishError::InvalidFilename(message) => write!(f, "invalid filename: {message}"),
+            PublishError::ForbiddenExtension => {
+                f.write_str("files with this extension are not allowed")
+            }
+            PublishError::Failed { status, message } => {
+                write!(f, "upload failed with status {status}: {message}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PublishError {}
@@ -0,0 +1,139 @@
+use std::{
+    convert::Infallible,
+    net::SocketAddr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, State},
+    http::HeaderMap,
+    response::{
+        IntoResponse, Response,
+        sse::{Event, KeepAlive, Sse},
+    },
+};
+use futures_util::StreamExt;
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::{BroadcastStream, errors::BroadcastStreamRecvError};
+use tracing::warn;
+
+use crate::{
+    AppState, ClientAddr,
+    auth::{Permission, auth_error_response, authenticate_user, extract_credentials},
+};
+
+/// Events buffered for each subscriber; one that falls further behind skips
+/// ahead and is told how many it missed.
+const EVENT_BUFFER: usize = 256;
+
+/// A stream state transition, as published on `GET /events`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum EventKind {
+    UploadStarted,
+    DownloaderConnected,
+    TransferCompleted,
+    TransferFailed,
+}
+
+impl EventKind {
+    fn name(self) -> &'static str {
+        match self {
+            Self::UploadStarted => "upload-started",
+            Self::DownloaderConnected => "downloader-connected",
+            Self::TransferCompleted => "transfer-completed",
+            Self::TransferFailed => "transfer-failed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub(crate) struct TransferEvent {
+    event: EventKind,
+    filename: String,
+    /// Milliseconds since the Unix epoch.
+    timestamp: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Fans stream state transitions out to every `/events` subscriber.
+pub(crate) struct EventBus {
+    sender: broadcast::Sender<TransferEvent>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self {
+            sender: broadcast::channel(EVENT_BUFFER).0,
+        }
+    }
+}
+
+impl EventBus {
+    pub(crate) fn publish(&self, event: EventKind, filename: &str) {
+        self.send(event, filename, None);
+    }
+
+    pub(crate) fn publish_failure(&self, filename: &str, error: impl ToString) {
+        self.send(EventKind::TransferFailed, filename, Some(error.to_string()));
+    }
+
+    fn send(&self, event: EventKind, filename: &str, error: Option<String>) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_millis() as u64);
+        // Nobody listening is the common case, not an error.
+        let _ = self.sender.send(TransferEvent {
+            event,
+            filename: filename.to_owned(),
+            timestamp,
+            error,
+        });
+    }
+}
+
+/// `GET /events`: a Server-Sent Events feed of stream state transitions,
+/// each sent as a JSON object named after its kind. The feed ends when the
+/// server shuts down.
+pub(crate) async fn events_handler(
+    State(state): State<AppState>,
+    ConnectInfo(ClientAddr(client)): ConnectInfo<ClientAddr>,
+    headers: HeaderMap,
+) -> Response<Body> {
+    let auth = match extract_credentials(&headers) {
+        Ok(auth) => auth,
+        Err(err) => return auth_error_response(&state, err),
+    };
+
+    if let Err(err) = authenticate_user(&state, client, &auth, Permission::View).await {
+        return auth_error_response(&state, err);
+    }
+
+    // Subscribe before answering so nothing after the response headers is
+    // missed.
+    let receiver = state.events.sender.subscribe();
+    let events = BroadcastStream::new(receiver)
+        .map(move |event| Ok::<_, Infallible>(sse_event(event, client)))
+        .take_until(state.shutdown.clone().cancelled_owned());
+
+    Sse::new(events)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+fn sse_event(event: Result<TransferEvent, BroadcastStreamRecvError>, client: SocketAddr) -> Event {
+    match event {
+        Ok(event) => Event::default()
+            .event(event.event.name())
+            .json_data(&event)
+            .expect("transfer events serialize to JSON"),
+        Err(BroadcastStreamRecvError::Lagged(missed)) => {
+            warn!(%client, missed, "Event subscriber fell behind");
+            Event::default()
+                .event("lagged")
+                .data(format!("{{\"missed\":{missed}}}"))
+        }
+    }
+}
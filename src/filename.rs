@@ -0,0 +1,183 @@
+use axum::http::{HeaderMap, HeaderValue};
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC, utf8_percent_encode};
+
+/// Upload header choosing how browsers should present the download.
+const DISPOSITION_HEADER: &str = "x-content-disposition";
+
+/// Characters allowed unencoded in an RFC 5987 `ext-value` (`attr-char`).
+const ATTR_CHAR: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'!')
+    .remove(b'#')
+    .remove(b'$')
+    .remove(b'&')
+    .remove(b'+')
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'^')
+    .remove(b'_')
+    .remove(b'`')
+    .remove(b'|')
+    .remove(b'~');
+
+/// Validates a filename taken from the request path, returning the name used
+/// as the stream key. Surrounding whitespace is stripped; names that could
+/// act as a path or corrupt response headers, or that are longer than
+/// `max_len` bytes, are rejected with a message suitable for a
+/// `400 Bad Request` body.
+pub(crate) fn sanitize_filename(raw: &str, max_len: Option<usize>) -> Result<String, &'static str> {
+    let filename = raw.trim();
+
+    if filename.is_empty() {
+        return Err("Filename must not be empty");
+    }
+    if max_len.is_some_and(|max_len| filename.len() > max_len) {
+        return Err("Filename is too long");
+    }
+    if filename == "." || filename == ".." {
+        return Err("Filename must not be a relative path component");
+    }
+    if filename.contains(['/', '\\']) {
+        return Err("Filename must not contain path separators");
+    }
+    if filename.chars().any(char::is_control) {
+        return Err("Filename must not contain control characters");
+    }
+
+    Ok(filename.to_owned())
+}
+
+/// Which filename extensions the operator lets through, as lowercase
+/// suffixes without the leading dot, e.g. `exe` or `tar.gz`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ExtensionPolicy {
+    pub(crate) denied: Vec<String>,
+    /// When set, only names ending in one of these are let through.
+    pub(crate) allowed: Option<Vec<String>>,
+}
+
+impl ExtensionPolicy {
+    /// Normalises a configured extension: surrounding whitespace and leading
+    /// dots go, the rest is lowercased. `None` if nothing is left.
+    pub(crate) fn normalize(extension: &str) -> Option<String> {
+        let extension = extension.trim().trim_start_matches('.');
+        (!extension.is_empty()).then(|| extension.to_lowercase())
+    }
+
+    /// Whether `filename` may be uploaded and downloaded. An extension
+    /// matches any name ending in a dot followed by it, whatever the case,
+    /// so `gz` matches `backup.tar.gz` and `tar.gz` does too.
+    pub(crate) fn permits(&self, filename: &str) -> bool {
+        let filename = filename.to_lowercase();
+        let matches = |extension: &String| {
+            filename
+                .strip_suffix(extension.as_str())
+                .is_some_and(|stem| stem.ends_with('.'))
+        };
+        !self.denied.iter().any(matches)
+            && self
+                .allowed
+                .as_ref()
+                .is_none_or(|allowed| allowed.iter().any(matches))
+    }
+}
+
+/// Content types given to uploads that don't declare one, by filename
+/// extension: the operator's own mappings first, then `mime_guess`'s.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ContentTypes {
+    /// Normalised extensions, as for [`ExtensionPolicy`], with their types.
+    overrides: Vec<(String, HeaderValue)>,
+}
+
+impl ContentTypes {
+    /// Checks the configured `(extension, type)` pairs, with a message
+    /// naming the first that isn't a valid header value.
+    pub(crate) fn new(mappings: &[(String, String)]) -> Result<Self, String> {
+        let overrides = mappings
+            .iter()
+            .filter_map(|(extension, content_type)| {
+                let extension = ExtensionPolicy::normalize(extension)?;
+                Some(
+                    HeaderValue::from_str(content_type)
+                        .map(|value| (extension, value))
+                        .map_err(|_| format!("invalid content type `{content_type}`")),
+                )
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { overrides })
+    }
+
+    /// The type for `filename`, if its extension has one. Extensions match
+    /// as for [`ExtensionPolicy::permits`], the longest configured one
+    /// winning, so `tar.gz` can be told apart from `gz`.
+    pub(crate) fn guess(&self, filename: &str) -> Option<HeaderValue> {
+        let lowercase = filename.to_lowercase();
+        let configured = self
+            .overrides
+            .iter()
+            .filter(|(extension, _)| {
+                lowercase
+                    .strip_suffix(extension.as_str())
+                    .is_some_and(|stem| stem.ends_with('.'))
+            })
+            .max_by_key(|(extension, _)| extension.len());
+        if let Some((_, content_type)) = configured {
+            return Some(content_type.clone());
+        }
+        mime_guess::from_path(filename)
+            .first_raw()
+            .map(HeaderValue::from_static)
+    }
+}
+
+/// Whether a download is saved to disk or shown in the browser.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum Disposition {
+    #[default]
+    Attachment,
+    Inline,
+}
+
+impl Disposition {
+    /// The disposition an uploader asked for with `X-Content-Disposition`,
+    /// defaulting to `attachment`.
+    pub(crate) fn requested(headers: &HeaderMap) -> Result<Self, &'static str> {
+        let Some(value) = headers.get(DISPOSITION_HEADER) else {
+            return Ok(Self::Attachment);
+        };
+        match value.to_str().map(str::trim) {
+            Ok(value) if value.eq_ignore_ascii_case("attachment") => Ok(Self::Attachment),
+            Ok(value) if value.eq_ignore_ascii_case("inline") => Ok(Self::Inline),
+            _ => Err("X-Content-Disposition must be inline or attachment"),
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Attachment => "attachment",
+            Self::Inline => "inline",
+        }
+    }
+}
+
+/// Builds an RFC 6266 Content-Disposition value for a sanitized
+/// filename. In the plain `filename` parameter, non-ASCII characters become
+/// `_` and quotes and backslashes are escaped. Clients disagree on those
+/// escapes, so any name that needs them is also sent in full as an RFC 5987
+/// `filename*` parameter, which compliant clients prefer.
+pub(crate) fn content_disposition(filename: &str, disposition: Disposition) -> String {
+    let quoted = filename
+        .chars()
+        .map(|c| if c.is_ascii() { c } else { '_' })
+        .collect::<String>()
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"");
+    let mut value = format!("{}; filename=\"{quoted}\"", disposition.as_str());
+
+    if !filename.is_ascii() || filename.contains(['"', '\\']) {
+        value.push_str("; filename*=UTF-8''");
+        value.extend(utf8_percent_encode(filename, ATTR_CHAR));
+    }
+
+    value
+}
@@ -0,0 +1,149 @@
+use std::{
+    convert::Infallible,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+};
+
+use axum::{body::Body, http::HeaderMap};
+use bytes::Bytes;
+use futures_util::{Stream, StreamExt};
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC, utf8_percent_encode};
+use tokio_util::sync::CancellationToken;
+
+use crate::{AppState, error::PublishError, filename::sanitize_filename, receive_upload};
+
+/// Characters left unencoded in a URL path segment.
+const PATH_SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// Largest error body read back from a refused publication.
+const MAX_ERROR_BODY: usize = 64 * 1024;
+
+/// A running server, for embedding applications that want to feed it
+/// directly. Returned by [`setup_server_with_handle`](crate::setup_server_with_handle).
+#[derive(Clone)]
+pub struct BeamHandle {
+    state: AppState,
+    local_addr: SocketAddr,
+    tls: bool,
+}
+
+impl BeamHandle {
+    pub(crate) fn new(state: AppState, local_addr: SocketAddr, tls: bool) -> Self {
+        Self {
+            state,
+            local_addr,
+            tls,
+        }
+    }
+
+    /// The address the server is bound to.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Where `filename` can be downloaded from this machine. A server bound
+    /// to every interface is addressed through loopback; other hosts need
+    /// a name of their own for it. Downloading still takes credentials
+    /// unless anonymous downloads are enabled.
+    pub fn download_url(&self, filename: &str) -> String {
+        let host = match self.local_addr.ip() {
+            IpAddr::V4(ip) if ip.is_unspecified() => IpAddr::V4(Ipv4Addr::LOCALHOST),
+            IpAddr::V6(ip) if ip.is_unspecified() => IpAddr::V6(Ipv6Addr::LOCALHOST),
+            ip => ip,
+        };
+        format!(
+            "{}://{}{}/{}",
+            if self.tls { "https" } else { "http" },
+            SocketAddr::new(host, self.local_addr.port()),
+            self.state.base_path,
+            utf8_percent_encode(filename, PATH_SEGMENT)
+        )
+    }
+
+    /// Relays `stream` under `filename` as if it had been uploaded with a
+    /// plain `PUT`, so it is downloaded, spooled, limited and logged like
+    /// any other upload. Returns as soon as the upload has started; a name
+    /// that is already taken shows up as a failure in
+    /// [`Publication::finished`].
+    pub fn publish<S>(&self, filename: &str, stream: S) -> Result<Publication, PublishError>
+    where
+        S: Stream<Item = Bytes> + Send + 'static,
+    {
+        let filename = sanitize_filename(filename, self.state.max_filename_len)
+            .map_err(PublishError::InvalidFilename)?;
+        if !self.state.extension_policy.permits(&filename) {
+            return Err(PublishError::ForbiddenExtension);
+        }
+
+        let url = self.download_url(&filename);
+        let body = Body::from_stream(stream.map(Ok::<_, Infallible>));
+        let state = self.state.clone();
+        let cancel = CancellationToken::new();
+        let upload_cancel = cancel.clone();
+        let upload = async move {
+            let response =
+                receive_upload(state, filename, &HeaderMap::new(), body, upload_cancel).await;
+            let status = response.status();
+            if status.is_success() {
+                return Ok(());
+            }
+            let message = axum::body::to_bytes(response.into_body(), MAX_ERROR_BODY)
+                .await
+                .map(|body| String::from_utf8_lossy(&body).into_owned())
+                .unwrap_or_default();
+            Err(PublishError::Failed {
+                status: status.as_u16(),
+                message,
+            })
+        };
+        let task = match &self.state.runtime {
+            Some(runtime) => runtime.spawn(upload),
+            None => tokio::spawn(upload),
+        };
+
+        Ok(Publication { url, task, cancel })
+    }
+}
+
+/// A stream handed to [`BeamHandle::publish`].
+pub struct Publication {
+    url: String,
+    task: tokio::task::JoinHandle<Result<(), PublishError>>,
+    cancel: CancellationToken,
+}
+
+impl Publication {
+    /// Where the stream can be downloaded; see [`BeamHandle::download_url`].
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Aborts the transfer if it is still in flight: its downloaders get an
+    /// error rather than a clean end, the stream is removed, and
+    /// [`finished`](Self::finished) reports a `409` failure. Does nothing
+    /// once the stream has been relayed or stored.
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+
+    /// A token that cancels the transfer as [`cancel`](Self::cancel) does,
+    /// e.g. to tie it to a wider shutdown or hand it to another task.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancel.clone()
+    }
+
+    /// Waits for the stream to be relayed, or stored when spooling, and
+    /// reports how it went, as the uploader of a `PUT` would learn from
+    /// its response.
+    pub async fn finished(self) -> Result<(), PublishError> {
+        self.task.await.unwrap_or_else(|_| {
+            Err(PublishError::Failed {
+                status: 500,
+                message: "Upload task failed".to_owned(),
+            })
+        })
+    }
+}
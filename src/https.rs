@@ -0,0 +1,26 @@
+use bytes::Bytes;
+use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
+use hyper_util::{
+    client::legacy::{Client, connect::HttpConnector},
+    rt::TokioExecutor,
+};
+use tokio_rustls::rustls;
+
+/// Client for beam's own outgoing requests, such as webhooks and JWKS
+/// fetches.
+pub(crate) type HttpsClient<B> = Client<HttpsConnector<HttpConnector>, B>;
+
+/// A client for `http` and `https` URLs, verifying servers against the
+/// Mozilla root certificates with the same `ring` provider the server's TLS
+/// uses.
+pub(crate) fn client<B>() -> Result<HttpsClient<B>, rustls::Error>
+where
+    B: http_body::Body<Data = Bytes> + Send,
+{
+    let connector = HttpsConnectorBuilder::new()
+        .with_provider_and_webpki_roots(rustls::crypto::ring::default_provider())?
+        .https_or_http()
+        .enable_http1()
+        .build();
+    Ok(Client::builder(TokioExecutor::new()).build(connector))
+}
@@ -0,0 +1,72 @@
+use std::{net::IpAddr, sync::Arc};
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use ipnet::IpNet;
+use tracing::warn;
+
+use crate::{BeamError, ClientAddr};
+
+/// Which peer addresses may make requests at all.
+pub(crate) struct IpFilter {
+    /// Empty allows every address not denied.
+    allowed: Vec<IpNet>,
+    denied: Vec<IpNet>,
+}
+
+impl IpFilter {
+    /// Parses the configured blocks, or `None` when there are none to
+    /// enforce.
+    pub(crate) fn new(allowed: &[String], denied: &[String]) -> Result<Option<Self>, BeamError> {
+        if allowed.is_empty() && denied.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            allowed: parse_blocks(allowed)?,
+            denied: parse_blocks(denied)?,
+        }))
+    }
+
+    fn permits(&self, ip: IpAddr) -> bool {
+        // An IPv4 peer of a dual-stack socket shows up as `::ffff:a.b.c.d`.
+        let ip = ip.to_canonical();
+        let within = |blocks: &[IpNet]| blocks.iter().any(|block| block.contains(&ip));
+        !within(&self.denied) && (self.allowed.is_empty() || within(&self.allowed))
+    }
+}
+
+fn parse_blocks(blocks: &[String]) -> Result<Vec<IpNet>, BeamError> {
+    blocks
+        .iter()
+        .map(|block| {
+            let block = block.trim();
+            block
+                .parse::<IpNet>()
+                .or_else(|_| block.parse::<IpAddr>().map(IpNet::from))
+                .map_err(|_| BeamError::IpFilter(format!("`{block}` is not an address or CIDR")))
+        })
+        .collect()
+}
+
+/// Answers requests from peers `filter` doesn't permit with `403 Forbidden`,
+/// before they reach authentication or any handler.
+pub(crate) async fn filter_peers(
+    State(filter): State<Arc<IpFilter>>,
+    ConnectInfo(ClientAddr(client)): ConnectInfo<ClientAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !filter.permits(client.ip()) {
+        warn!(%client, "Request refused: peer address not allowed");
+        return (
+            StatusCode::FORBIDDEN,
+            "Requests from this address are not allowed",
+        )
+            .into_response();
+    }
+    next.run(request).await
+}
@@ -0,0 +1,80 @@
+use axum::{
+    body::{Body, to_bytes},
+    extract::Request,
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    middleware::Next,
+    response::Response,
+};
+
+/// Error bodies are short messages; anything longer is not one of beam's.
+const MAX_ERROR_BODY: usize = 64 * 1024;
+
+/// Rewrites plain-text error responses as
+/// `{"error": "<code>", "message": "..."}` for clients whose `Accept` header
+/// asks for JSON. The code is the status's reason phrase in snake case,
+/// e.g. `not_found` or `payload_too_large`, so it stays stable while the
+/// messages change. Other headers, such as `WWW-Authenticate` and
+/// `Retry-After`, are kept.
+pub(crate) async fn negotiate(request: Request, next: Next) -> Response {
+    let wants_json = accepts_json(request.headers());
+    let response = next.run(request).await;
+    if !wants_json || !is_plain_error(&response) {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let code = error_code(parts.status);
+    let message = match to_bytes(body, MAX_ERROR_BODY).await {
+        Ok(bytes) if !bytes.trim_ascii().is_empty() => {
+            String::from_utf8_lossy(bytes.trim_ascii()).into_owned()
+        }
+        _ => parts
+            .status
+            .canonical_reason()
+            .unwrap_or_default()
+            .to_owned(),
+    };
+    let body = serde_json::json!({ "error": code, "message": message }).to_string();
+
+    parts.headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+    parts
+        .headers
+        .insert(header::CONTENT_LENGTH, body.len().into());
+    Response::from_parts(parts, Body::from(body))
+}
+
+fn accepts_json(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|media_type| {
+            let media_type = media_type.split(';').next().unwrap_or_default().trim();
+            media_type.eq_ignore_ascii_case("application/json")
+        })
+}
+
+/// Errors carrying a text message, or no declared type at all; JSON and
+/// HTML bodies are left as they are.
+fn is_plain_error(response: &Response) -> bool {
+    if !(response.status().is_client_error() || response.status().is_server_error()) {
+        return false;
+    }
+    response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_none_or(|content_type| content_type.starts_with("text/plain"))
+}
+
+fn error_code(status: StatusCode) -> String {
+    status
+        .canonical_reason()
+        .unwrap_or("error")
+        .to_ascii_lowercase()
+        .replace([' ', '-'], "_")
+}
@@ -1,334 +1,2066 @@
 use axum::{
     Router,
     body::{Body, Bytes},
-    extract::{Path, State},
-    http::{HeaderMap, StatusCode, header},
-    response::{Html, IntoResponse, Response},
-    routing::get,
+    extract::{ConnectInfo, Path, Query, State, connect_info::Connected},
+    http::{HeaderMap, HeaderValue, Method, StatusCode, header},
+    response::{IntoResponse, Json, Response},
+    routing::{get, post, put},
+    serve::IncomingStream,
 };
 use futures_util::stream::StreamExt;
 use http_body::Frame;
 use http_body_util::{BodyStream, StreamBody};
-use std::{collections::HashMap, sync::Arc, time::Duration};
-use tokio::sync::{RwLock, mpsc};
+use sha2::{Digest, Sha256};
+use std::{
+    fmt,
+    net::{IpAddr, Ipv6Addr, SocketAddr},
+    sync::{
+        Arc, OnceLock,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+use tokio::sync::{Semaphore, mpsc};
 use tokio_stream::wrappers::ReceiverStream;
+use tokio_util::sync::CancellationToken;
+use tower_http::catch_panic::CatchPanicLayer;
 
-use argon2::password_hash::{SaltString, rand_core::OsRng};
-use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
-use headers::{Authorization, Header, authorization::Basic};
 use tracing::{error, info, warn};
 
-pub async fn setup_server(username: &str, password: &str) -> tokio::task::JoinHandle<()> {
-    setup_server_with_port(4000, username, password).await
+mod access_log;
+mod admin;
+mod auth;
+mod bearer;
+mod bundle;
+mod checksum;
+mod compression;
+mod config;
+mod cors;
+mod dashboard;
+mod error;
+mod events;
+mod filename;
+mod handle;
+mod https;
+mod ip_filter;
+mod json_errors;
+mod memory;
+mod metadata;
+mod metrics;
+mod multipart;
+mod panics;
+mod progress;
+mod quota;
+mod random_name;
+mod range;
+mod rate_floor;
+mod rate_limit;
+mod reconnect;
+mod registry;
+mod request_limit;
+mod reservation;
+mod resumable;
+mod spool;
+mod stale;
+mod tcp;
+mod throttle;
+mod tls;
+mod token;
+mod trailers;
+mod waiters;
+mod webhook;
+mod websocket;
+
+use auth::{AuthStrategy, Permission, auth_error_response, authenticate_user, extract_credentials};
+use bearer::BearerAuth;
+use checksum::ChecksumVerifier;
+use events::{EventBus, EventKind};
+use filename::{
+    ContentTypes, Disposition, ExtensionPolicy, content_disposition, sanitize_filename,
+};
+use ip_filter::IpFilter;
+use memory::{Chunk, MemoryBudget};
+use metadata::{Metadata, MetadataPolicy};
+use metrics::Metrics;
+use quota::{QuotaLease, UploadQuotas};
+use rate_floor::RateFloor;
+use rate_limit::AuthLimiter;
+use reconnect::{Replay, ResumePoint};
+use registry::{Holder, Refusal, StreamRegistry};
+use resumable::UploadSessions;
+use spool::{Spool, SpooledFile};
+use tcp::{TcpOptions, TunedListener};
+use throttle::Throttle;
+use tls::TlsListener;
+use token::TokenStore;
+use waiters::UploadWaiters;
+use webhook::Notifier;
+
+pub use auth::{Access, AuthConfig, Secret, load_credentials_file};
+pub use config::{
+    AuthFailureLimit, BearerPolicy, CorsPolicy, DEFAULT_AUTH_FAILURE_LIMIT, DEFAULT_AUTH_REALM,
+    DEFAULT_CHANNEL_BUFFER, DEFAULT_IDLE_TIMEOUT, DEFAULT_LAG_POLICY, DEFAULT_MAX_BUFFER_FRAMES,
+    DEFAULT_MAX_FILENAME_LEN, DEFAULT_METADATA_HEADER_PREFIX, DEFAULT_PASSWORD_HASHING,
+    DEFAULT_PORT, DEFAULT_SPOOL_TTL, DEFAULT_UPLOAD_READY_TIMEOUT, LagPolicy,
+    MAX_BROADCAST_RECEIVERS, MAX_METADATA_BYTES, MAX_METADATA_HEADERS, MinUploadRate,
+    ParkedDownload, PasswordHashing, ReconnectGrace, ServerConfig, ServerConfigBuilder,
+    ShutdownSignal, UploadQuota, Webhook,
+};
+pub use error::{BeamError, PublishError};
+pub use handle::{BeamHandle, Publication};
+
+/// Starts a server on [`DEFAULT_PORT`] for callers that don't need the bound
+/// address.
+pub async fn setup_server(
+    username: &str,
+    password: &str,
+) -> Result<tokio::task::JoinHandle<()>, BeamError> {
+    let (_, handle) = setup_server_with_port(DEFAULT_PORT, username, password).await?;
+    Ok(handle)
 }
 
+/// Starts a server on `port`. Pass `0` to let the OS pick a free port and
+/// read it back from the returned address.
 pub async fn setup_server_with_port(
     port: u16,
     username: &str,
     password: &str,
-) -> tokio::task::JoinHandle<()> {
-    let auth = AuthConfig::new(username, password).expect("failed to hash startup password");
-    let state = AppState::new(auth);
+) -> Result<(SocketAddr, tokio::task::JoinHandle<()>), BeamError> {
+    let config = ServerConfig::builder()
+        .port(port)
+        .credentials(username, password)
+        .build();
+    setup_server_with_config(config).await
+}
+
+/// Like [`setup_server_with_config`], but panics if the server can't start,
+/// for callers with nothing better to do than exit.
+pub async fn setup_server_with_config_or_panic(
+    config: ServerConfig,
+) -> (SocketAddr, tokio::task::JoinHandle<()>) {
+    setup_server_with_config(config)
+        .await
+        .unwrap_or_else(|error| panic!("{error}"))
+}
+
+/// Starts a server as `config` describes and returns its bound address
+/// with the task serving it. Fails without starting anything if the
+/// credentials, spool directory, TLS files, CORS policy or listening
+/// address can't be set up.
+pub async fn setup_server_with_config(
+    config: ServerConfig,
+) -> Result<(SocketAddr, tokio::task::JoinHandle<()>), BeamError> {
+    let (beam, handle) = setup_server_with_handle(config).await?;
+    Ok((beam.local_addr(), handle))
+}
+
+/// Like [`setup_server_with_config`], but returns a [`BeamHandle`] through
+/// which the embedding application can publish streams without going
+/// through HTTP.
+pub async fn setup_server_with_handle(
+    config: ServerConfig,
+) -> Result<(BeamHandle, tokio::task::JoinHandle<()>), BeamError> {
+    // Entering the configured runtime makes every listener, timer and
+    // `tokio::spawn` below, and so every task those spawn in turn, its.
+    // Nothing here awaits, so the guard never has to cross threads.
+    let runtime = config.runtime.clone();
+    let _runtime = runtime.as_ref().map(tokio::runtime::Handle::enter);
+    let auth = AuthConfig::with_hashing(config.users.clone(), config.password_hashing)
+        .map_err(BeamError::Credentials)?
+        .with_realm(&config.auth_realm);
+    let bearer = config
+        .bearer
+        .as_ref()
+        .map(BearerAuth::new)
+        .transpose()
+        .map_err(BeamError::Bearer)?;
+    let spool = config
+        .spool_dir
+        .clone()
+        .map(|dir| {
+            Spool::open(
+                dir,
+                config.spool_ttl,
+                config.max_spool_ttl.unwrap_or(config.spool_ttl),
+                config.compress_spool,
+            )
+        })
+        .transpose()
+        .map_err(BeamError::Spool)?;
+    let tls_acceptor = config
+        .tls
+        .as_ref()
+        .map(|tls| tls::load_acceptor(&tls.cert, &tls.key))
+        .transpose()
+        .map_err(BeamError::Tls)?;
+    let cors = config.cors.as_ref().map(cors::layer).transpose()?;
+    let ip_filter = IpFilter::new(&config.allowed_ips, &config.denied_ips)?;
+    let webhook = config
+        .webhook
+        .as_ref()
+        .map(Notifier::new)
+        .transpose()
+        .map_err(BeamError::Webhook)?;
+    let content_types = ContentTypes::new(&config.content_types).map_err(BeamError::ContentType)?;
+    let listener =
+        bind_listener(SocketAddr::new(config.bind_addr, config.port)).map_err(BeamError::Bind)?;
+    let local_addr = listener.local_addr().map_err(BeamError::Bind)?;
+    let tcp = TcpOptions {
+        nodelay: config.tcp_nodelay,
+        keepalive: config.tcp_keepalive,
+    };
+    let listener = TunedListener::new(listener, tcp);
+
+    let state = AppState::new(auth, bearer, spool, webhook, content_types, &config);
+    let shutdown_signal = config.shutdown_signal;
+    let drain_delay = config.drain_delay;
 
     let app = Router::new()
-        .route("/", get(dashboard))
-        .route("/{filename}", get(download_handler).put(upload_handler))
+        .route(
+            "/",
+            get(dashboard::dashboard)
+                .post(multipart::form_upload_handler)
+                .put(random_name::upload_handler),
+        )
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .route("/version", get(version))
+        .route("/metrics", get(metrics_handler))
+        .route("/api/streams", get(list_streams))
+        .route("/events", get(events::events_handler))
+        .route("/bundle", get(bundle::bundle_handler))
+        .route("/admin/evict/{filename}", post(admin::evict_handler))
+        .route("/progress/{filename}", get(progress::progress_handler))
+        .route("/new", post(new_token))
+        .route("/reserve", post(reservation::reserve_handler))
+        .route("/t/{token}", get(token_download_handler))
+        .route("/upload", post(resumable::create_handler))
+        .route(
+            "/upload/{id}",
+            put(resumable::append_handler).head(resumable::status_handler),
+        )
+        .route("/upload/{id}/complete", post(resumable::complete_handler))
+        .route("/ws/upload/{filename}", get(websocket::upload_handler))
+        .route("/ws/download/{filename}", get(websocket::download_handler))
+        .route(
+            "/{filename}",
+            get(download_handler)
+                .head(head_handler)
+                .put(upload_handler)
+                .post(multipart::form_upload_handler)
+                .patch(resumable::patch_handler)
+                .delete(delete_handler),
+        )
         .with_state(state.clone());
+    let app = match state.base_path.as_str() {
+        "" => app,
+        // Proxies commonly forward `/beam/`, which `nest` alone leaves
+        // unrouted.
+        base_path => Router::new()
+            .route(
+                &format!("{base_path}/"),
+                get(dashboard::dashboard)
+                    .post(multipart::form_upload_handler)
+                    .put(random_name::upload_handler),
+            )
+            .with_state(state.clone())
+            .nest(base_path, app),
+    }
+    .method_not_allowed_fallback(method_not_allowed);
+    let app = match config.max_concurrent_requests {
+        Some(limit) => app.layer(axum::middleware::from_fn_with_state(
+            Arc::new(Semaphore::new(limit)),
+            request_limit::limit_requests,
+        )),
+        None => app,
+    };
+    let app = match ip_filter {
+        Some(filter) => app.layer(axum::middleware::from_fn_with_state(
+            Arc::new(filter),
+            ip_filter::filter_peers,
+        )),
+        None => app,
+    }
+    .layer(axum::middleware::from_fn(json_errors::negotiate))
+    .layer(CatchPanicLayer::custom(panics::handler_panicked));
+    let app = match cors {
+        Some(cors) => app.layer(cors),
+        None => app,
+    };
+    let app = match config.access_log {
+        Some(level) => app.layer(axum::middleware::from_fn_with_state(
+            level,
+            access_log::log_request,
+        )),
+        None => app,
+    };
 
-    let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{port}"))
-        .await
-        .expect("failed to bind TCP listener");
-    info!("Listening on {}", listener.local_addr().unwrap());
+    info!(tls = tls_acceptor.is_some(), "Listening on {local_addr}");
+    let beam = BeamHandle::new(state.clone(), local_addr, tls_acceptor.is_some());
 
-    tokio::spawn(async move {
-        axum::serve(listener, app)
-            .await
-            .expect("server task failed");
-    })
+    if let Some(spool) = state.spool.clone() {
+        tokio::spawn(spool::run_reaper(state.clone(), spool));
+    }
+    if let Some(max_age) = config.max_pending_age {
+        tokio::spawn(stale::run_reaper(state.clone(), max_age));
+    }
+
+    let shutdown = state.shutdown.clone();
+    let one_shot = state.one_shot.clone();
+    let ready = state.ready.clone();
+    // Cancelling on drop also stops background tasks if the server task is
+    // aborted rather than shut down.
+    let cancel_on_drop = state.shutdown.clone().drop_guard();
+    let handle = tokio::spawn(async move {
+        let _cancel_on_drop = cancel_on_drop;
+        // The listener is bound and about to be served; the shutdown future
+        // below only runs once serving has started.
+        ready.store(true, Ordering::Release);
+        let graceful_shutdown = async move {
+            let signal = async move {
+                match shutdown_signal {
+                    Some(signal) => signal.await,
+                    None => std::future::pending().await,
+                }
+            };
+            let transferred = async move {
+                match one_shot {
+                    Some(one_shot) => one_shot.cancelled().await,
+                    None => std::future::pending().await,
+                }
+            };
+            tokio::select! {
+                _ = signal => {}
+                _ = transferred => info!("One-shot transfer completed"),
+            }
+            ready.store(false, Ordering::Release);
+            if let Some(delay) = drain_delay {
+                info!(
+                    ?delay,
+                    "Shutdown requested; reporting not ready before draining"
+                );
+                tokio::time::sleep(delay).await;
+            }
+            info!("Shutdown requested; draining in-flight transfers");
+            shutdown.cancel();
+        };
+
+        match tls_acceptor {
+            Some(acceptor) => {
+                let listener = TlsListener::new(listener, local_addr, acceptor);
+                serve(listener, app, graceful_shutdown).await;
+            }
+            None => serve(listener, app, graceful_shutdown).await,
+        }
+    });
+
+    Ok((beam, handle))
+}
+
+/// Binds `addr` the way `TcpListener::bind` would, except that the IPv6
+/// wildcard `[::]` always accepts IPv4 connections too rather than depending
+/// on the host's `bindv6only` setting.
+fn bind_listener(addr: SocketAddr) -> std::io::Result<tokio::net::TcpListener> {
+    use socket2::{Domain, Protocol, Socket, Type};
+
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+    if addr.ip() == IpAddr::V6(Ipv6Addr::UNSPECIFIED) {
+        socket.set_only_v6(false)?;
+    }
+    #[cfg(unix)]
+    socket.set_reuse_address(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(1024)?;
+    tokio::net::TcpListener::from_std(socket.into())
+}
+
+async fn serve<L>(listener: L, app: Router, shutdown: impl Future<Output = ()> + Send + 'static)
+where
+    L: axum::serve::Listener<Addr = SocketAddr>,
+    ClientAddr: for<'a> Connected<IncomingStream<'a, L>>,
+{
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<ClientAddr>(),
+    )
+    .with_graceful_shutdown(shutdown)
+    .await
+    .expect("server task failed");
+}
+
+/// Peer address of a connection, plain or TLS, for handlers to extract with
+/// `ConnectInfo`.
+#[derive(Debug, Clone, Copy)]
+struct ClientAddr(SocketAddr);
+
+impl Connected<IncomingStream<'_, TunedListener>> for ClientAddr {
+    fn connect_info(stream: IncomingStream<'_, TunedListener>) -> Self {
+        Self(*stream.remote_addr())
+    }
 }
 
+impl Connected<IncomingStream<'_, TlsListener>> for ClientAddr {
+    fn connect_info(stream: IncomingStream<'_, TlsListener>) -> Self {
+        Self(*stream.remote_addr())
+    }
+}
+
+/// `Retry-After` sent when the concurrent stream limit turns an upload away.
+const STREAM_LIMIT_RETRY_AFTER_SECS: u64 = 5;
+
 #[derive(Clone)]
 struct AppState {
-    streams: Arc<RwLock<HashMap<String, StreamData>>>,
+    streams: Arc<StreamRegistry>,
     auth: Arc<AuthConfig>,
+    /// Tried in turn until one handles the request's credentials.
+    auth_strategies: Arc<[Arc<dyn AuthStrategy>]>,
+    auth_limiter: Arc<AuthLimiter>,
+    auth_failure_delay: Option<std::ops::RangeInclusive<Duration>>,
+    channel_buffer: usize,
+    max_buffer_frames: usize,
+    upload_ready_timeout: Option<Duration>,
+    max_pending_age: Option<Duration>,
+    idle_timeout: Option<Duration>,
+    min_upload_rate: Option<MinUploadRate>,
+    max_body_size: Option<u64>,
+    reject_empty_uploads: bool,
+    enforce_content_length: bool,
+    max_transfer_rate: Option<u64>,
+    max_filename_len: Option<usize>,
+    extension_policy: Arc<ExtensionPolicy>,
+    content_types: Arc<ContentTypes>,
+    metadata: Arc<MetadataPolicy>,
+    max_concurrent_streams: Option<usize>,
+    upload_quotas: Arc<UploadQuotas>,
+    anonymous_downloads: bool,
+    compress_downloads: bool,
+    compress_min_size: Option<u64>,
+    shutdown: CancellationToken,
+    /// Where [`BeamHandle::publish`] spawns uploads, if not on the caller's
+    /// runtime.
+    runtime: Option<tokio::runtime::Handle>,
+    /// Cancelled when the first transfer of a one-shot server completes.
+    one_shot: Option<CancellationToken>,
+    /// Set while the listener accepts connections and no shutdown has been
+    /// requested.
+    ready: Arc<AtomicBool>,
+    lag_policy: LagPolicy,
+    reconnect_grace: Option<ReconnectGrace>,
+    /// Shared by every live stream when in-flight bytes are capped.
+    memory_budget: Option<Arc<MemoryBudget>>,
+    metrics: Arc<Metrics>,
+    events: Arc<EventBus>,
+    spool: Option<Arc<Spool>>,
+    webhook: Option<Arc<Notifier>>,
+    tokens: Arc<TokenStore>,
+    upload_sessions: Arc<UploadSessions>,
+    download_wait_timeout: Option<Duration>,
+    parked_download: ParkedDownload,
+    not_found_retry_after: Option<Duration>,
+    upload_waiters: Arc<UploadWaiters>,
+    /// Path every route is mounted under, e.g. `/beam`; empty for the root.
+    base_path: String,
 }
 
 impl AppState {
-    fn new(auth: AuthConfig) -> Self {
+    /// Removes `filename` if it still belongs to the upload that owns
+    /// `stats`; a `DELETE` may already have freed the name for a new upload.
+    fn remove_stream(&self, filename: &str, stats: &Arc<StreamStats>) {
+        self.streams.remove_if(filename, |stream_data| {
+            Arc::ptr_eq(&stream_data.stats, stats)
+        });
+    }
+
+    /// Announces that the upload owning `stats` was relayed or stored, and
+    /// its uploader answered `status`.
+    fn transfer_completed(&self, filename: &str, stats: &StreamStats, status: StatusCode) {
+        self.events.publish(EventKind::TransferCompleted, filename);
+        if let Some(webhook) = &self.webhook {
+            webhook.notify(filename, stats, status, None);
+        }
+    }
+
+    /// Announces that the upload owning `stats` failed with `error`, and its
+    /// uploader answered `status`.
+    fn transfer_failed(
+        &self,
+        filename: &str,
+        stats: &StreamStats,
+        status: StatusCode,
+        error: impl ToString,
+    ) {
+        let error = error.to_string();
+        if let Some(webhook) = &self.webhook {
+            webhook.notify(filename, stats, status, Some(error.clone()));
+        }
+        self.events.publish_failure(filename, error);
+    }
+
+    /// `403 Forbidden` for a filename whose extension the operator blocked,
+    /// or `None` if it may be transferred.
+    fn forbidden_extension_response(&self, filename: &str) -> Option<Response<Body>> {
+        if self.extension_policy.permits(filename) {
+            return None;
+        }
+        warn!(%filename, "Refused a blocked file extension");
+        Some(
+            (
+                StatusCode::FORBIDDEN,
+                "Files with this extension are not allowed",
+            )
+                .into_response(),
+        )
+    }
+
+    /// Registers a new upload under `filename`, taking it over from the
+    /// reservation the request names. Returns the response turning the
+    /// upload away instead when the filename is in use (`409`) or the server
+    /// is full (`503`).
+    fn register_stream(
+        &self,
+        filename: &str,
+        headers: &HeaderMap,
+        stream_data: StreamData,
+    ) -> Option<Response<Body>> {
+        match self.streams.register(
+            filename.to_owned(),
+            self.max_concurrent_streams,
+            reservation::requested(headers),
+            stream_data,
+        ) {
+            Ok(()) => None,
+            Err(Refusal::Taken(holder)) => {
+                let message = match holder {
+                    Holder::Upload {
+                        age,
+                        downloader_connected,
+                        waiting,
+                    } => self.upload_conflict_message(age, downloader_connected, waiting),
+                    Holder::Stored => "A file with this name is already stored".to_owned(),
+                    Holder::Reservation => {
+                        "This filename is reserved for another upload".to_owned()
+                    }
+                };
+                Some((StatusCode::CONFLICT, message).into_response())
+            }
+            Err(Refusal::Full { limit }) => {
+                warn!(limit, "Upload rejected: concurrent stream limit reached");
+                Some(
+                    Response::builder()
+                        .status(StatusCode::SERVICE_UNAVAILABLE)
+                        .header(header::RETRY_AFTER, STREAM_LIMIT_RETRY_AFTER_SECS)
+                        .body(Body::from("Too many concurrent streams; try again shortly"))
+                        .expect("failed to build 503 response"),
+                )
+            }
+        }
+    }
+
+    /// Describes the upload holding a filename, so the refused uploader can
+    /// tell whether waiting for it to finish or expire is worthwhile.
+    fn upload_conflict_message(
+        &self,
+        age: Duration,
+        downloader_connected: bool,
+        waiting: bool,
+    ) -> String {
+        let downloader = if downloader_connected {
+            "downloader attached"
+        } else {
+            "no downloader attached"
+        };
+        let mut message = format!(
+            "An upload is already in progress for this filename (age {}s, {downloader}",
+            age.as_secs()
+        );
+        if let Some(max_age) = self.max_pending_age.filter(|_| waiting) {
+            message.push_str(&format!(
+                ", expires in {}s",
+                max_age.saturating_sub(age).as_secs()
+            ));
+        }
+        message.push(')');
+        message
+    }
+
+    /// The `404` for a download with no upload to serve it.
+    fn no_upload_response(&self) -> Response<Body> {
+        let mut response = Response::builder().status(StatusCode::NOT_FOUND);
+        if let Some(delay) = self.not_found_retry_after {
+            response = response.header(header::RETRY_AFTER, retry_after_secs(delay));
+        }
+        response
+            .body(Body::from("No active upload stream for this file"))
+            .expect("failed to build 404 response")
+    }
+
+    fn new(
+        auth: AuthConfig,
+        bearer: Option<BearerAuth>,
+        spool: Option<Spool>,
+        webhook: Option<Notifier>,
+        content_types: ContentTypes,
+        config: &ServerConfig,
+    ) -> Self {
+        let auth = Arc::new(auth);
+        let mut auth_strategies: Vec<Arc<dyn AuthStrategy>> = vec![auth.clone()];
+        if let Some(bearer) = bearer {
+            auth_strategies.push(Arc::new(bearer));
+        }
+
         Self {
-            streams: Arc::new(RwLock::new(HashMap::new())),
-            auth: Arc::new(auth),
+            streams: Arc::new(StreamRegistry::default()),
+            auth,
+            auth_strategies: auth_strategies.into(),
+            auth_limiter: Arc::new(AuthLimiter::new(config.auth_failure_limit)),
+            auth_failure_delay: config.auth_failure_delay.clone(),
+            channel_buffer: config.channel_buffer,
+            max_buffer_frames: config.max_buffer_frames,
+            upload_ready_timeout: config.upload_ready_timeout,
+            max_pending_age: config.max_pending_age,
+            idle_timeout: config.idle_timeout,
+            min_upload_rate: config.min_upload_rate,
+            max_body_size: config.max_body_size,
+            reject_empty_uploads: config.reject_empty_uploads,
+            enforce_content_length: config.enforce_content_length,
+            max_transfer_rate: config.max_transfer_rate,
+            max_filename_len: config.max_filename_len,
+            extension_policy: Arc::new(config.extension_policy.clone()),
+            content_types: Arc::new(content_types),
+            metadata: Arc::new(MetadataPolicy::new(config)),
+            max_concurrent_streams: config.max_concurrent_streams,
+            upload_quotas: Arc::new(UploadQuotas::new(
+                config.upload_quota,
+                config.user_upload_quotas.clone(),
+            )),
+            anonymous_downloads: config.anonymous_downloads,
+            compress_downloads: config.compress_downloads,
+            compress_min_size: config.compress_min_size,
+            shutdown: CancellationToken::new(),
+            runtime: config.runtime.clone(),
+            one_shot: config.one_shot.then(CancellationToken::new),
+            ready: Arc::new(AtomicBool::new(false)),
+            lag_policy: config.lag_policy,
+            reconnect_grace: config.reconnect_grace,
+            memory_budget: config
+                .max_in_flight_bytes
+                .map(|limit| Arc::new(MemoryBudget::new(limit))),
+            metrics: Arc::new(Metrics::default()),
+            events: Arc::new(EventBus::default()),
+            spool: spool.map(Arc::new),
+            webhook: webhook.map(Arc::new),
+            tokens: Arc::new(TokenStore::default()),
+            upload_sessions: Arc::new(UploadSessions::default()),
+            download_wait_timeout: config.download_wait_timeout,
+            parked_download: config.parked_download,
+            not_found_retry_after: config.not_found_retry_after,
+            upload_waiters: Arc::new(UploadWaiters::default()),
+            base_path: config.base_path.clone(),
         }
     }
 }
 
-struct AuthConfig {
-    username: String,
-    password_hash: String,
+/// Why an upload task stopped before relaying the whole body.
+#[derive(Debug)]
+enum UploadError {
+    ReadyTimeout,
+    ReadyDropped,
+    ShuttingDown,
+    IdleTimeout,
+    /// Fell below the configured minimum rate, in bytes per second.
+    TooSlow(u64),
+    TooLarge(u64),
+    /// The body's length differed from the `Content-Length` it declared.
+    LengthMismatch(u64),
+    Cancelled,
+    DownloaderGone,
+    ChecksumMismatch,
+    /// Ended without sending any data while empty uploads are refused.
+    Empty,
+    /// The uploader already has its quota's number of streams.
+    StreamQuota(usize),
+    /// The uploader's streams would hold more than its quota's bytes.
+    ByteQuota(u64),
+    Body(String),
+    Spool(std::io::Error),
 }
 
-impl AuthConfig {
-    fn new(username: &str, password: &str) -> Result<Self, argon2::password_hash::Error> {
-        let salt = SaltString::generate(&mut OsRng);
-        let password_hash = Argon2::default()
-            .hash_password(password.as_bytes(), &salt)?
-            .to_string();
+impl UploadError {
+    fn status(&self) -> StatusCode {
+        match self {
+            UploadError::ShuttingDown => StatusCode::SERVICE_UNAVAILABLE,
+            UploadError::Spool(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            UploadError::IdleTimeout | UploadError::TooSlow(_) => StatusCode::REQUEST_TIMEOUT,
+            UploadError::TooLarge(_) | UploadError::ByteQuota(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            UploadError::StreamQuota(_) => StatusCode::TOO_MANY_REQUESTS,
+            UploadError::Cancelled => StatusCode::CONFLICT,
+            UploadError::DownloaderGone => StatusCode::BAD_GATEWAY,
+            UploadError::ChecksumMismatch => StatusCode::UNPROCESSABLE_ENTITY,
+            // Nobody came to download; the upload itself was fine.
+            UploadError::ReadyTimeout => StatusCode::GATEWAY_TIMEOUT,
+            UploadError::ReadyDropped => StatusCode::INTERNAL_SERVER_ERROR,
+            UploadError::Empty | UploadError::LengthMismatch(_) | UploadError::Body(_) => {
+                StatusCode::BAD_REQUEST
+            }
+        }
+    }
+}
 
-        Ok(Self {
-            username: username.to_owned(),
-            password_hash,
-        })
+impl fmt::Display for UploadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UploadError::ReadyTimeout => f.write_str("Timeout waiting for download client"),
+            UploadError::ReadyDropped => f.write_str("Ready channel dropped"),
+            UploadError::ShuttingDown => f.write_str("Server is shutting down"),
+            UploadError::IdleTimeout => {
+                f.write_str("No upload data received within the idle timeout")
+            }
+            UploadError::TooSlow(rate) => {
+                write!(
+                    f,
+                    "Upload fell below the minimum rate of {rate} bytes per second"
+                )
+            }
+            UploadError::TooLarge(limit) => {
+                write!(f, "Upload exceeds the maximum size of {limit} bytes")
+            }
+            UploadError::LengthMismatch(declared) => {
+                write!(
+                    f,
+                    "Upload body does not match its Content-Length of {declared} bytes"
+                )
+            }
+            UploadError::Cancelled => f.write_str("Upload was cancelled"),
+            UploadError::DownloaderGone => {
+                f.write_str("Download client disconnected before the upload completed")
+            }
+            UploadError::ChecksumMismatch => {
+                f.write_str("Upload body does not match its SHA-256 checksum")
+            }
+            UploadError::Empty => f.write_str("Upload ended without sending any data"),
+            UploadError::StreamQuota(limit) => {
+                write!(f, "Upload quota of {limit} concurrent streams reached")
+            }
+            UploadError::ByteQuota(limit) => {
+                write!(f, "Upload exceeds the quota of {limit} bytes")
+            }
+            UploadError::Body(error) => write!(f, "Stream error: {error}"),
+            UploadError::Spool(error) => write!(f, "Spool error: {error}"),
+        }
     }
 }
 
+/// `delay` as a `Retry-After` value, rounded up so a client that honours it
+/// isn't turned away again.
+fn retry_after_secs(delay: Duration) -> u64 {
+    delay.as_secs() + u64::from(delay.subsec_nanos() > 0)
+}
+
+/// Waits for the next frame of an upload body, failing with
+/// [`UploadError::IdleTimeout`] if none arrives within `idle_timeout`, or
+/// with [`UploadError::TooSlow`] once `rate_floor` finds the upload has
+/// fallen below its minimum rate.
+async fn next_frame(
+    body_stream: &mut BodyStream<Body>,
+    idle_timeout: Option<Duration>,
+    rate_floor: &mut Option<RateFloor>,
+) -> Result<Option<Result<Frame<Bytes>, axum::Error>>, UploadError> {
+    let idle_deadline = idle_timeout.map(|timeout| Instant::now() + timeout);
+    loop {
+        let started = Instant::now();
+        let window_deadline = rate_floor
+            .as_ref()
+            .map(|rate_floor| started + rate_floor.remaining());
+        let next = match idle_deadline.into_iter().chain(window_deadline).min() {
+            Some(deadline) => tokio::time::timeout(
+                deadline.saturating_duration_since(started),
+                body_stream.next(),
+            )
+            .await
+            .ok(),
+            None => Some(body_stream.next().await),
+        };
+
+        let waited = started.elapsed();
+        match next {
+            Some(Some(frame)) => {
+                if let Some(rate_floor) = rate_floor {
+                    let len = frame
+                        .as_ref()
+                        .ok()
+                        .and_then(Frame::data_ref)
+                        .map_or(0, Bytes::len);
+                    rate_floor.record(waited, len)?;
+                }
+                return Ok(Some(frame));
+            }
+            Some(None) => return Ok(None),
+            None if idle_deadline.is_some_and(|deadline| Instant::now() >= deadline) => {
+                return Err(UploadError::IdleTimeout);
+            }
+            // The window ran out with nothing arriving; judge it and wait on.
+            None => {
+                if let Some(rate_floor) = rate_floor {
+                    rate_floor.record(waited, 0)?;
+                }
+            }
+        }
+    }
+}
+
+/// Fails with [`UploadError::TooLarge`] once `received` bytes pass the
+/// configured limit.
+fn check_body_size(received: u64, max_body_size: Option<u64>) -> Result<(), UploadError> {
+    match max_body_size {
+        Some(limit) if received > limit => Err(UploadError::TooLarge(limit)),
+        _ => Ok(()),
+    }
+}
+
+/// Fails with [`UploadError::LengthMismatch`] once `received` bytes pass the
+/// declared length, or, when the body has `ended`, if they fall short of it.
+fn check_declared_length(
+    received: u64,
+    declared_length: Option<u64>,
+    ended: bool,
+) -> Result<(), UploadError> {
+    match declared_length {
+        Some(declared) if received > declared || (ended && received < declared) => {
+            Err(UploadError::LengthMismatch(declared))
+        }
+        _ => Ok(()),
+    }
+}
+
+type ChunkSender = mpsc::Sender<Result<Chunk, axum::Error>>;
+type ChunkReceiver = mpsc::Receiver<Result<Chunk, axum::Error>>;
+
+/// An upload registered under a filename.
 struct StreamData {
-    receiver: mpsc::Receiver<Result<Bytes, axum::Error>>,
+    meta: StreamMeta,
+    stats: Arc<StreamStats>,
+    /// Cancelled by `DELETE` to stop the upload task.
+    cancel: CancellationToken,
+    source: StreamSource,
+}
+
+/// Where a registered upload's bytes come from.
+enum StreamSource {
+    /// Relayed from the uploader's connection as it arrives.
+    Live(LiveStream),
+    /// Still being written to the spool; not downloadable yet.
+    Spooling,
+    /// Stored in the spool until the reaper expires it.
+    Spooled(SpooledFile),
+    /// Held by `POST /reserve` for the upload presenting this token.
+    Reserved(String),
+}
+
+/// Each downloader takes one receiver; once the last one is taken the
+/// uploader is told to start relaying. The entry stays in the map until the
+/// upload finishes.
+struct LiveStream {
+    receivers: Vec<ChunkReceiver>,
+    receiver_count: usize,
+    /// Frames buffered per downloader.
+    buffer_frames: usize,
     ready_tx: Option<tokio::sync::oneshot::Sender<()>>,
+    /// Set while the upload waits for its dropped downloader to reconnect.
+    resume: Option<ResumePoint>,
 }
 
-#[derive(Debug)]
-enum AuthError {
-    Unauthorized,
-    Internal,
+impl LiveStream {
+    fn connected_downloaders(&self) -> usize {
+        self.receiver_count - self.receivers.len()
+    }
+}
+
+/// Progress of one stream, updated by its upload task without locking its
+/// entry.
+struct StreamStats {
+    /// When the stream was registered, which its reported age counts from.
+    started: Instant,
+    bytes_transferred: AtomicU64,
+    /// Set once the whole upload body has been read, so a downloader whose
+    /// channel closes before then knows its copy is truncated.
+    finished: AtomicBool,
+    /// Set by a downloader that asked for trailers, so the upload hashes
+    /// its body even without a declared digest.
+    digest_wanted: AtomicBool,
+    /// The upload's SHA-256, set before `finished` if it was worked out.
+    sha256: OnceLock<[u8; 32]>,
+    /// The uploader's quota, charged for every byte received.
+    quota: Option<QuotaLease>,
 }
 
-fn auth_error_response(error: AuthError) -> Response<Body> {
-    match error {
-        AuthError::Unauthorized => unauthorized_response("Invalid username or password"),
-        AuthError::Internal => Response::builder()
-            .status(StatusCode::INTERNAL_SERVER_ERROR)
-            .body(Body::from("Authentication failed"))
-            .expect("failed to build auth error response"),
+impl StreamStats {
+    fn with_quota(quota: Option<QuotaLease>) -> Self {
+        Self {
+            quota,
+            ..Self::default()
+        }
+    }
+
+    /// Counts `len` more received bytes against the uploader's quota.
+    fn charge_quota(&self, len: usize) -> Result<(), UploadError> {
+        self.quota
+            .as_ref()
+            .map_or(Ok(()), |quota| quota.charge(len))
     }
 }
 
-fn unauthorized_response(message: &str) -> Response<Body> {
-    Response::builder()
-        .status(StatusCode::UNAUTHORIZED)
-        .header(header::WWW_AUTHENTICATE, "Basic realm=\"beam\"")
-        .body(Body::from(message.to_owned()))
-        .expect("failed to build unauthorized response")
+impl Default for StreamStats {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            bytes_transferred: AtomicU64::default(),
+            finished: AtomicBool::default(),
+            digest_wanted: AtomicBool::default(),
+            sha256: OnceLock::new(),
+            quota: None,
+        }
+    }
+}
+
+/// Upload request headers mirrored onto the download response.
+#[derive(Clone)]
+struct StreamMeta {
+    content_type: Option<HeaderValue>,
+    /// Set when the uploader already compressed the body, which beam then
+    /// relays as-is.
+    content_encoding: Option<HeaderValue>,
+    content_length: Option<u64>,
+    disposition: Disposition,
+    /// Digest the uploader declared, if any; checked as the body passes.
+    sha256: Option<[u8; 32]>,
+    metadata: Metadata,
+}
+
+impl StreamMeta {
+    /// The stream's metadata from its upload's headers. Without a declared
+    /// `Content-Type`, one is guessed from `filename`'s extension.
+    fn from_upload_headers(state: &AppState, filename: &str, headers: &HeaderMap) -> Self {
+        Self {
+            content_type: headers
+                .get(header::CONTENT_TYPE)
+                .cloned()
+                .or_else(|| state.content_types.guess(filename)),
+            content_encoding: headers.get(header::CONTENT_ENCODING).cloned(),
+            content_length: headers
+                .get(header::CONTENT_LENGTH)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse().ok()),
+            // Uploads asking for anything else are refused before this.
+            disposition: Disposition::requested(headers).unwrap_or_default(),
+            sha256: None,
+            // So are uploads with too much metadata.
+            metadata: metadata::collect(headers, &state.metadata).unwrap_or_default(),
+        }
+    }
+
+    fn response_content_type(&self) -> HeaderValue {
+        self.content_type
+            .clone()
+            .unwrap_or_else(|| HeaderValue::from_static("application/octet-stream"))
+    }
 }
 
-fn extract_basic_auth(headers: &HeaderMap) -> Result<Authorization<Basic>, AuthError> {
-    let Some(header_value) = headers.get(header::AUTHORIZATION) else {
-        warn!("Missing Authorization header");
-        return Err(AuthError::Unauthorized);
+/// Header an uploader sets to broadcast one upload to several downloaders.
+const RECEIVERS_HEADER: &str = "x-receivers";
+
+fn requested_receivers(headers: &HeaderMap) -> Result<usize, String> {
+    let Some(value) = headers.get(RECEIVERS_HEADER) else {
+        return Ok(1);
     };
 
-    let mut values = std::iter::once(header_value);
-    Authorization::<Basic>::decode(&mut values).map_err(|error| {
-        warn!(%error, "Failed to parse Authorization header");
-        AuthError::Unauthorized
-    })
+    value
+        .to_str()
+        .ok()
+        .and_then(|value| value.trim().parse::<usize>().ok())
+        .filter(|count| (1..=MAX_BROADCAST_RECEIVERS).contains(count))
+        .ok_or_else(|| {
+            format!("X-Receivers must be a number between 1 and {MAX_BROADCAST_RECEIVERS}")
+        })
+}
+
+/// Header an uploader sets to pick its downloaders' buffer size.
+const BUFFER_FRAMES_HEADER: &str = "x-buffer-frames";
+
+/// The per-downloader buffer the upload asked for, clamped to
+/// `1..=max_frames`, or `None` to use the configured default.
+fn requested_buffer_frames(
+    headers: &HeaderMap,
+    max_frames: usize,
+) -> Result<Option<usize>, &'static str> {
+    let Some(value) = headers.get(BUFFER_FRAMES_HEADER) else {
+        return Ok(None);
+    };
+
+    value
+        .to_str()
+        .ok()
+        .and_then(|value| value.trim().parse::<usize>().ok())
+        .map(|frames| Some(frames.clamp(1, max_frames)))
+        .ok_or("X-Buffer-Frames must be a number of frames")
+}
+
+/// Tells every downloader that the upload was aborted. Best effort: a
+/// downloader whose buffer is full still sees its stream end when the
+/// sender is dropped.
+fn abort_downloaders(senders: &[ChunkSender], error: &UploadError) {
+    for sender in senders {
+        let _ = sender.try_send(Err(axum::Error::new(error.to_string())));
+    }
 }
 
-async fn authenticate_user(state: &AppState, auth: &Authorization<Basic>) -> Result<(), AuthError> {
-    let expected_username = &state.auth.username;
-    let provided_username = auth.username();
+/// Sends `bytes` to every downloader and returns the senders whose
+/// downloaders are still connected. With a `lag_timeout`, a downloader whose
+/// buffer stays full for that long is dropped as well. Each downloader's
+/// copy is charged to `budget` separately.
+async fn fan_out(
+    senders: Vec<ChunkSender>,
+    bytes: Bytes,
+    lag_timeout: Option<Duration>,
+    budget: Option<&Arc<MemoryBudget>>,
+    filename: &str,
+) -> Vec<ChunkSender> {
+    if let [sender] = senders.as_slice() {
+        let chunk = Chunk::charged(bytes, budget).await;
+        if sender.send(Ok(chunk)).await.is_err() {
+            return Vec::new();
+        }
+        return senders;
+    }
+
+    let sends = senders.into_iter().map(|sender| {
+        let bytes = bytes.clone();
+        async move {
+            let chunk = Chunk::charged(bytes, budget).await;
+            let sent = match lag_timeout {
+                Some(timeout) => {
+                    match tokio::time::timeout(timeout, sender.send(Ok(chunk))).await {
+                        Ok(sent) => sent.is_ok(),
+                        Err(_) => {
+                            warn!(%filename, ?timeout, "Dropping lagging download client");
+                            false
+                        }
+                    }
+                }
+                None => sender.send(Ok(chunk)).await.is_ok(),
+            };
+            sent.then_some(sender)
+        }
+    });
+
+    futures_util::future::join_all(sends)
+        .await
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+fn invalid_filename_response(message: &'static str) -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::BAD_REQUEST)
+        .body(Body::from(message))
+        .expect("failed to build invalid filename response")
+}
 
-    if provided_username != expected_username {
-        warn!(attempted = %provided_username, "Unknown username supplied");
-        return Err(AuthError::Unauthorized);
+/// `405` for a method the route doesn't take. The router adds an `Allow`
+/// header listing the ones it does.
+async fn method_not_allowed(method: Method) -> Response<Body> {
+    (
+        StatusCode::METHOD_NOT_ALLOWED,
+        format!("{method} is not supported here; see the Allow header for what is"),
+    )
+        .into_response()
+}
+
+/// Liveness probe; deliberately unauthenticated.
+async fn healthz() -> Json<serde_json::Value> {
+    Json(serde_json::json!({ "status": "ok" }))
+}
+
+/// Readiness probe; unauthenticated like `/healthz`. Answers `503` once a
+/// shutdown has been requested, so load balancers drain the server.
+async fn readyz(State(state): State<AppState>) -> Response<Body> {
+    if state.ready.load(Ordering::Acquire) {
+        Json(serde_json::json!({ "status": "ready" })).into_response()
+    } else {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!({ "status": "shutting down" })),
+        )
+            .into_response()
     }
+}
 
-    let password = auth.password();
-    if password.is_empty() {
-        warn!(%provided_username, "Basic auth password is empty");
-        return Err(AuthError::Unauthorized);
+/// Identifies the running build; unauthenticated like `/healthz`.
+async fn version() -> Json<serde_json::Value> {
+    Json(serde_json::json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "git": env!("BEAM_GIT_REVISION"),
+        "rustc": env!("BEAM_RUSTC_VERSION"),
+    }))
+}
+
+async fn metrics_handler(
+    State(state): State<AppState>,
+    ConnectInfo(ClientAddr(client)): ConnectInfo<ClientAddr>,
+    headers: HeaderMap,
+) -> Response<Body> {
+    let auth = match extract_credentials(&headers) {
+        Ok(auth) => auth,
+        Err(err) => return auth_error_response(&state, err),
+    };
+
+    if let Err(err) = authenticate_user(&state, client, &auth, Permission::View).await {
+        return auth_error_response(&state, err);
     }
 
-    let parsed_hash = PasswordHash::new(&state.auth.password_hash).map_err(|err| {
-        error!(%provided_username, %err, "Stored password hash is invalid");
-        AuthError::Internal
-    })?;
+    let active_streams = state.streams.len();
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "text/plain; version=0.0.4")
+        .body(Body::from(state.metrics.render(active_streams)))
+        .expect("failed to build metrics response")
+}
 
-    Argon2::default()
-        .verify_password(password.as_bytes(), &parsed_hash)
-        .map_err(|_| AuthError::Unauthorized)?;
+/// One registered stream as reported by `GET /api/streams`.
+#[derive(serde::Serialize)]
+pub(crate) struct StreamSummary {
+    filename: String,
+    /// `live`, `uploading` (to the spool) or `stored`.
+    state: &'static str,
+    bytes_transferred: u64,
+    downloader_connected: bool,
+    age_secs: u64,
+}
 
-    Ok(())
+impl StreamSummary {
+    pub(crate) fn new(filename: &str, stream_data: &StreamData) -> Self {
+        let (state, downloader_connected) = match &stream_data.source {
+            StreamSource::Live(live) => ("live", live.connected_downloaders() > 0),
+            StreamSource::Spooling => ("uploading", false),
+            StreamSource::Spooled(_) => ("stored", false),
+            StreamSource::Reserved(_) => ("reserved", false),
+        };
+        Self {
+            filename: filename.to_owned(),
+            state,
+            bytes_transferred: stream_data.stats.bytes_transferred.load(Ordering::Relaxed),
+            downloader_connected,
+            age_secs: stream_data.stats.started.elapsed().as_secs(),
+        }
+    }
 }
 
-async fn dashboard(State(state): State<AppState>) -> Html<String> {
-    let streams = state.streams.read().await;
-    let active_streams = streams.keys().cloned().collect::<Vec<_>>();
-
-    let body = format!(
-        r#"<!DOCTYPE html>
-<html lang=\"en\">
-<head>
-  <meta charset=\"utf-8\" />
-  <title>Beam Dashboard</title>
-  <style>
-    body {{ font-family: sans-serif; margin: 2rem; max-width: 40rem; }}
-    h1 {{ margin-bottom: 0.5rem; }}
-    section {{ margin-top: 1.5rem; }}
-    code {{ background: #f4f4f4; padding: 0.2rem 0.4rem; border-radius: 3px; }}
-  </style>
-</head>
-<body>
-  <h1>Beam Dashboard</h1>
-  <p>Start Beam with <code>beam &lt;username&gt; &lt;password&gt;</code> then authenticate uploads and downloads using HTTP Basic auth.</p>
-  <section>
-    <h2>Active Streams</h2>
-    <pre>{active_streams:#?}</pre>
-  </section>
-  <section>
-    <h2>Usage</h2>
-    <ol>
-      <li>Upload: <code>curl -u USER:PASS -T file.zip http://localhost:4000/file.zip</code></li>
-      <li>Download: <code>curl -u USER:PASS http://localhost:4000/file.zip -o file.zip</code></li>
-    </ol>
-  </section>
-</body>
-</html>"#
-    );
+/// Machine-readable counterpart of the dashboard's stream table.
+async fn list_streams(
+    State(state): State<AppState>,
+    ConnectInfo(ClientAddr(client)): ConnectInfo<ClientAddr>,
+    headers: HeaderMap,
+) -> Response<Body> {
+    let auth = match extract_credentials(&headers) {
+        Ok(auth) => auth,
+        Err(err) => return auth_error_response(&state, err),
+    };
+
+    if let Err(err) = authenticate_user(&state, client, &auth, Permission::View).await {
+        return auth_error_response(&state, err);
+    }
+
+    let mut summaries = state
+        .streams
+        .iter()
+        .map(|entry| StreamSummary::new(entry.key(), entry.value()))
+        .collect::<Vec<_>>();
+    summaries.sort_by(|a, b| a.filename.cmp(&b.filename));
+
+    Json(summaries).into_response()
+}
 
-    Html(body)
+#[derive(serde::Deserialize)]
+struct DownloadQuery {
+    /// Read only this many bytes from the start, leaving the file in place.
+    peek: Option<u64>,
+    /// Name to suggest in `Content-Disposition` instead of the path's.
+    #[serde(rename = "as")]
+    shown_as: Option<String>,
 }
 
 async fn download_handler(
     State(state): State<AppState>,
+    ConnectInfo(ClientAddr(client)): ConnectInfo<ClientAddr>,
     Path(filename): Path<String>,
+    Query(query): Query<DownloadQuery>,
     headers: HeaderMap,
 ) -> Response<Body> {
-    let auth = match extract_basic_auth(&headers) {
+    if !state.anonymous_downloads {
+        let auth = match extract_credentials(&headers) {
+            Ok(auth) => auth,
+            Err(err) => return auth_error_response(&state, err),
+        };
+
+        if let Err(err) = authenticate_user(&state, client, &auth, Permission::Download).await {
+            return auth_error_response(&state, err);
+        }
+    }
+
+    let filename = match sanitize_filename(&filename, state.max_filename_len) {
+        Ok(filename) => filename,
+        Err(message) => return invalid_filename_response(message),
+    };
+    let shown_as = match query.shown_as {
+        Some(shown_as) => match sanitize_filename(&shown_as, state.max_filename_len) {
+            Ok(shown_as) => Some(shown_as),
+            Err(message) => return invalid_filename_response(message),
+        },
+        None => None,
+    };
+
+    if let Some(limit) = query.peek {
+        return peek(&state, filename, shown_as.as_deref(), limit).await;
+    }
+    serve_download(&state, filename, shown_as.as_deref(), &headers).await
+}
+
+/// `GET /{filename}?peek=N`: the first `limit` bytes of a stored upload,
+/// which stays available for full downloads. A live stream can only be
+/// read once, so peeking needs the spool.
+async fn peek(
+    state: &AppState,
+    filename: String,
+    shown_as: Option<&str>,
+    limit: u64,
+) -> Response<Body> {
+    if state.spool.is_none() {
+        return (
+            StatusCode::NOT_IMPLEMENTED,
+            "Peeking requires a spool directory",
+        )
+            .into_response();
+    }
+    if limit == 0 {
+        return (StatusCode::BAD_REQUEST, "peek must be at least 1 byte").into_response();
+    }
+    if let Some(response) = state.forbidden_extension_response(&filename) {
+        return response;
+    }
+
+    let (file, meta) = {
+        let Some(stream_data) = state.streams.get(&filename) else {
+            return state.no_upload_response();
+        };
+        match &stream_data.source {
+            StreamSource::Spooled(file) => (file.clone(), stream_data.meta.clone()),
+            StreamSource::Spooling => {
+                return (StatusCode::CONFLICT, "This file is still being uploaded").into_response();
+            }
+            StreamSource::Live(_) | StreamSource::Reserved(_) => {
+                return state.no_upload_response();
+            }
+        }
+    };
+    let shown_as = shown_as.unwrap_or(&filename);
+    spool::peek(state, &filename, shown_as, file, meta, limit).await
+}
+
+#[derive(serde::Deserialize)]
+struct NewTokenRequest {
+    filename: String,
+}
+
+/// Mints a one-time link to the upload that will arrive as `filename`.
+async fn new_token(
+    State(state): State<AppState>,
+    ConnectInfo(ClientAddr(client)): ConnectInfo<ClientAddr>,
+    headers: HeaderMap,
+    Json(request): Json<NewTokenRequest>,
+) -> Response<Body> {
+    let auth = match extract_credentials(&headers) {
         Ok(auth) => auth,
-        Err(err) => return auth_error_response(err),
+        Err(err) => return auth_error_response(&state, err),
     };
 
-    if let Err(err) = authenticate_user(&state, &auth).await {
-        return auth_error_response(err);
+    if let Err(err) = authenticate_user(&state, client, &auth, Permission::Upload).await {
+        return auth_error_response(&state, err);
     }
 
-    let stream_data = match state.streams.write().await.remove(&filename) {
-        Some(data) => data,
-        None => {
+    let filename = match sanitize_filename(&request.filename, state.max_filename_len) {
+        Ok(filename) => filename,
+        Err(message) => return invalid_filename_response(message),
+    };
+
+    let token = state.tokens.mint(filename.clone()).await;
+    info!(%filename, "Minted download token");
+    (
+        StatusCode::CREATED,
+        Json(serde_json::json!({ "url": format!("{}/t/{token}", state.base_path), "filename": filename })),
+    )
+        .into_response()
+}
+
+/// Downloads through a token from `POST /new`. The token is the secret, so
+/// no credentials are needed; it is spent once a download starts.
+async fn token_download_handler(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    headers: HeaderMap,
+) -> Response<Body> {
+    let Some(filename) = state.tokens.take(&token).await else {
+        return (StatusCode::NOT_FOUND, "Unknown or already used token").into_response();
+    };
+
+    let response = serve_download(&state, filename.clone(), None, &headers).await;
+    if !response.status().is_success() {
+        state.tokens.restore(token, filename).await;
+    }
+    response
+}
+
+/// Hands the stream registered under `filename` to an already authorized
+/// downloader, suggesting it be saved as `shown_as` if given.
+async fn serve_download(
+    state: &AppState,
+    filename: String,
+    shown_as: Option<&str>,
+    headers: &HeaderMap,
+) -> Response<Body> {
+    if let Some(response) = state.forbidden_extension_response(&filename) {
+        return response;
+    }
+    if let Some(timeout) = state.download_wait_timeout {
+        if let ParkedDownload::Heartbeat(interval) = state.parked_download
+            && waiters::accepts_event_stream(headers)
+        {
+            return waiters::heartbeat_response(state, filename, timeout, interval);
+        }
+        if !waiters::wait_for_upload(state, &filename, timeout).await {
+            info!(%filename, ?timeout, "Gave up waiting for an upload");
+        }
+    }
+
+    let (receiver, meta, stats, resumed_from) = {
+        let Some(mut stream_data) = state.streams.get_mut(&filename) else {
             warn!(%filename, "Download rejected: no active upload");
+            return state.no_upload_response();
+        };
+
+        let meta = stream_data.meta.clone();
+        let stats = stream_data.stats.clone();
+        let live = match &mut stream_data.source {
+            StreamSource::Live(live) => live,
+            StreamSource::Spooling => {
+                return (StatusCode::CONFLICT, "This file is still being uploaded").into_response();
+            }
+            StreamSource::Reserved(_) => {
+                warn!(%filename, "Download rejected: upload reserved but not started");
+                return state.no_upload_response();
+            }
+            StreamSource::Spooled(file) => {
+                let file = file.clone();
+                drop(stream_data);
+                let shown_as = shown_as.unwrap_or(&filename);
+                return spool::download(state, &filename, shown_as, file, meta, headers).await;
+            }
+        };
+
+        let resumed_from = match live.resume.take() {
+            Some(resume) => {
+                let offset = reconnect::requested_offset(headers)
+                    .filter(|offset| resume.available.contains(offset));
+                let Some(offset) = offset else {
+                    let message = format!(
+                        "This download can only resume from bytes {}-{}",
+                        resume.available.start(),
+                        resume.available.end()
+                    );
+                    live.resume = Some(resume);
+                    return (StatusCode::RANGE_NOT_SATISFIABLE, message).into_response();
+                };
+                if resume.offset_tx.send(offset).is_err() {
+                    // The upload stopped waiting a moment ago.
+                    return state.no_upload_response();
+                }
+                Some(offset)
+            }
+            None => None,
+        };
+
+        let Some(receiver) = live.receivers.pop() else {
+            warn!(%filename, "Download rejected: stream already has its downloaders");
             return Response::builder()
-                .status(StatusCode::NOT_FOUND)
-                .body(Body::from("No active upload stream for this file"))
-                .expect("failed to build 404 response");
+                .status(StatusCode::CONFLICT)
+                .body(Body::from("This stream is already being downloaded"))
+                .expect("failed to build 409 response");
+        };
+
+        if trailers::requested(headers) {
+            stats.digest_wanted.store(true, Ordering::Relaxed);
+        }
+        if live.receivers.is_empty()
+            && let Some(ready_tx) = live.ready_tx.take()
+        {
+            let _ = ready_tx.send(());
         }
+
+        (receiver, meta, stats, resumed_from)
     };
 
-    if let Some(ready_tx) = stream_data.ready_tx {
-        let _ = ready_tx.send(());
+    state.metrics.record_download();
+    if resumed_from.is_none() && headers.contains_key(header::RANGE) {
+        info!(%filename, "Ignoring Range header on a live stream");
     }
-
+    state
+        .events
+        .publish(EventKind::DownloaderConnected, &filename);
     info!(%filename, "Download started");
 
-    let receiver_stream = ReceiverStream::new(stream_data.receiver);
-    let stream_body = StreamBody::new(receiver_stream.map(|res| res.map(Frame::data)));
+    // The channel also closes when the upload is aborted or this downloader
+    // is dropped for lagging; end the body with an error rather than let a
+    // truncated copy look complete.
+    let finished_stats = stats.clone();
+    let truncated = futures_util::stream::once(async move {
+        (!finished_stats.finished.load(Ordering::Relaxed))
+            .then(|| Err(axum::Error::new("Upload ended before it completed")))
+    })
+    .filter_map(std::future::ready);
+    let mut receiver_stream = ReceiverStream::new(receiver)
+        .map(|res| res.map(|chunk| chunk.bytes))
+        .chain(truncated)
+        .boxed();
 
-    Response::builder()
+    let shown_as = shown_as.unwrap_or(&filename);
+    let (mut response, mut coding) = match resumed_from {
+        Some(offset) => {
+            info!(%filename, offset, "Download resumed");
+            let trailers = trailers::requested(headers);
+            (
+                resumed_download_headers(shown_as, &meta, offset, trailers),
+                None,
+            )
+        }
+        None => live_download_headers(state, shown_as, &meta, headers),
+    };
+    // A declared length already settled this; otherwise only the bytes can.
+    if let Some(min_size) = state.compress_min_size
+        && coding.is_some()
+        && meta.content_length.is_none()
+    {
+        let (prefix, worth_compressing) =
+            compression::read_prefix(&mut receiver_stream, min_size).await;
+        if !worth_compressing {
+            coding = None;
+            if let Some(response_headers) = response.headers_mut() {
+                response_headers.remove(header::CONTENT_ENCODING);
+                // Ended cleanly, so the whole body is in hand.
+                if prefix.iter().all(Result::is_ok) {
+                    let len: usize = prefix.iter().flatten().map(Bytes::len).sum();
+                    response_headers.insert(header::CONTENT_LENGTH, HeaderValue::from(len));
+                }
+            }
+        }
+        receiver_stream = futures_util::stream::iter(prefix)
+            .chain(receiver_stream)
+            .boxed();
+    }
+    let trailers_announced = response
+        .headers_ref()
+        .is_some_and(|response_headers| response_headers.contains_key(header::TRAILER));
+    let body = if let Some(coding) = coding {
+        info!(%filename, coding = coding.name(), "Compressing download");
+        Body::from_stream(compression::compress(receiver_stream, coding))
+    } else if trailers_announced {
+        // A failed upload ends the body with an error trailer in place of
+        // the usual ones, which follow the last chunk of a complete one.
+        let frames = receiver_stream
+            .map(Some)
+            .chain(futures_util::stream::once(async { None }))
+            .scan(false, move |failed, item| {
+                let frame = match item {
+                    _ if *failed => None,
+                    Some(Ok(bytes)) => Some(Frame::data(bytes)),
+                    Some(Err(error)) => {
+                        *failed = true;
+                        Some(Frame::trailers(trailers::failure(&error)))
+                    }
+                    None => Some(Frame::trailers(trailers::fields(&stats))),
+                };
+                std::future::ready(frame.map(Ok::<_, axum::Error>))
+            });
+        Body::new(StreamBody::new(frames))
+    } else {
+        Body::new(StreamBody::new(
+            receiver_stream.map(|res| res.map(Frame::data)),
+        ))
+    };
+
+    response
+        .body(body)
+        .expect("failed to build download response")
+}
+
+/// Response headers for a live download picking up at byte `offset` after
+/// its predecessor dropped, announcing trailers if `trailers` is set.
+fn resumed_download_headers(
+    filename: &str,
+    meta: &StreamMeta,
+    offset: u64,
+    trailers: bool,
+) -> axum::http::response::Builder {
+    let mut response = Response::builder()
+        .header(header::CONTENT_TYPE, meta.response_content_type())
+        .header(
+            header::CONTENT_DISPOSITION,
+            content_disposition(filename, meta.disposition),
+        )
+        .header(header::ACCEPT_RANGES, "none");
+    if let Some(content_encoding) = &meta.content_encoding {
+        response = response.header(header::CONTENT_ENCODING, content_encoding);
+    }
+    for (name, value) in &meta.metadata {
+        response = response.header(name, value);
+    }
+    if trailers {
+        response = response.header(header::TRAILER, trailers::announcement());
+    }
+
+    match meta.content_length {
+        Some(content_length) if offset < content_length => {
+            response = response.status(StatusCode::PARTIAL_CONTENT).header(
+                header::CONTENT_RANGE,
+                format!("bytes {offset}-{}/{content_length}", content_length - 1),
+            );
+            // As in `live_download_headers`, a declared digest or trailers
+            // leave the length off.
+            if meta.sha256.is_none() && !trailers {
+                response = response.header(header::CONTENT_LENGTH, content_length - offset);
+            }
+            response
+        }
+        // Without the total there is no valid `Content-Range` to send.
+        _ => response.status(StatusCode::OK),
+    }
+}
+
+/// Response headers for a live download, shared by `GET` and `HEAD`, and
+/// the coding to compress the body with, if any. A downloader sending `TE: trailers`
+/// gets the upload's length and SHA-256 as trailers after an uncompressed
+/// body.
+fn live_download_headers(
+    state: &AppState,
+    filename: &str,
+    meta: &StreamMeta,
+    request_headers: &HeaderMap,
+) -> (axum::http::response::Builder, Option<compression::Coding>) {
+    let mut response = Response::builder()
         .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, meta.response_content_type())
         .header(
             header::CONTENT_DISPOSITION,
-            format!("attachment; filename=\"{filename}\""),
+            content_disposition(filename, meta.disposition),
         )
-        .body(Body::new(stream_body))
-        .expect("failed to build download response")
+        // A live stream can only be read once, front to back.
+        .header(header::ACCEPT_RANGES, "none");
+    if state.compress_downloads {
+        response = response.header(header::VARY, "accept-encoding");
+    }
+
+    let coding = state
+        .compress_downloads
+        .then(|| compression::negotiate(request_headers))
+        .flatten()
+        .filter(|_| meta.content_encoding.is_none())
+        .filter(|_| {
+            meta.content_length
+                .zip(state.compress_min_size)
+                .is_none_or(|(len, min_size)| len >= min_size)
+        })
+        .filter(|_| {
+            meta.content_type
+                .as_ref()
+                .is_none_or(|content_type| !compression::is_compressed(content_type))
+        });
+    if let Some(coding) = coding {
+        response = response.header(header::CONTENT_ENCODING, coding.name());
+    } else if let Some(content_encoding) = &meta.content_encoding {
+        response = response.header(header::CONTENT_ENCODING, content_encoding);
+    }
+
+    let trailers = coding.is_none() && trailers::requested(request_headers);
+    if trailers {
+        response = response.header(header::TRAILER, trailers::announcement());
+    }
+
+    if let Some(sha256) = &meta.sha256 {
+        // The digest is only known to hold once the last byte has passed, so
+        // leave the length off: a mismatch can then still fail the body
+        // instead of arriving after a complete-looking copy.
+        response = response.header(checksum::CHECKSUM_HEADER, checksum::to_hex(sha256));
+    } else if let Some(content_length) = meta.content_length
+        // The compressed length isn't known until the stream ends, and
+        // trailers can only follow a chunked body.
+        && coding.is_none()
+        && !trailers
+    {
+        response = response.header(header::CONTENT_LENGTH, content_length);
+    }
+
+    for (name, value) in &meta.metadata {
+        response = response.header(name, value);
+    }
+
+    (response, coding)
+}
+
+/// `HEAD /{filename}`: answers as a `GET` would, without claiming the
+/// stream or waiting for an upload to arrive.
+async fn head_handler(
+    State(state): State<AppState>,
+    ConnectInfo(ClientAddr(client)): ConnectInfo<ClientAddr>,
+    Path(filename): Path<String>,
+    headers: HeaderMap,
+) -> Response<Body> {
+    if !state.anonymous_downloads {
+        let auth = match extract_credentials(&headers) {
+            Ok(auth) => auth,
+            Err(err) => return auth_error_response(&state, err),
+        };
+
+        if let Err(err) = authenticate_user(&state, client, &auth, Permission::Download).await {
+            return auth_error_response(&state, err);
+        }
+    }
+
+    let filename = match sanitize_filename(&filename, state.max_filename_len) {
+        Ok(filename) => filename,
+        Err(message) => return invalid_filename_response(message),
+    };
+    if let Some(response) = state.forbidden_extension_response(&filename) {
+        return response;
+    }
+
+    let Some(stream_data) = state.streams.get(&filename) else {
+        return state.no_upload_response();
+    };
+    match &stream_data.source {
+        StreamSource::Live(live) if live.receivers.is_empty() => {
+            StatusCode::CONFLICT.into_response()
+        }
+        StreamSource::Live(_) => {
+            let (response, _) =
+                live_download_headers(&state, &filename, &stream_data.meta, &headers);
+            response
+                .body(Body::empty())
+                .expect("failed to build HEAD response")
+        }
+        StreamSource::Spooling => StatusCode::CONFLICT.into_response(),
+        StreamSource::Spooled(file) => spool::head(&filename, file, &stream_data.meta, &headers),
+        StreamSource::Reserved(_) => state.no_upload_response(),
+    }
 }
 
 async fn upload_handler(
     State(state): State<AppState>,
+    ConnectInfo(ClientAddr(client)): ConnectInfo<ClientAddr>,
     Path(filename): Path<String>,
     headers: HeaderMap,
     body: Body,
-) -> impl IntoResponse {
-    let auth = match extract_basic_auth(&headers) {
+) -> Response<Body> {
+    let auth = match extract_credentials(&headers) {
         Ok(auth) => auth,
-        Err(err) => return auth_error_response(err),
+        Err(err) => return auth_error_response(&state, err),
     };
 
-    if let Err(err) = authenticate_user(&state, &auth).await {
-        return auth_error_response(err);
+    if let Err(err) = authenticate_user(&state, client, &auth, Permission::Upload).await {
+        return auth_error_response(&state, err);
     }
 
-    let (tx, rx) = mpsc::channel(16);
-    let (ready_tx, ready_rx) = tokio::sync::oneshot::channel();
-    let (complete_tx, complete_rx) = tokio::sync::oneshot::channel::<Result<(), String>>();
+    let filename = match sanitize_filename(&filename, state.max_filename_len) {
+        Ok(filename) => filename,
+        Err(message) => return invalid_filename_response(message),
+    };
+
+    if let Some(boundary) = multipart::boundary(&headers) {
+        return multipart::receive(state, Some(filename), &headers, boundary, body).await;
+    }
+    receive_upload(state, filename, &headers, body, CancellationToken::new()).await
+}
 
+/// Registers an already authorized upload of `body` under `filename` and
+/// answers once it has been relayed or stored. The body may come from a
+/// `PUT`, a form's file part or WebSocket frames. Cancelling `cancel`, as
+/// `DELETE` does through the stream's entry, aborts the transfer, with an
+/// error for its downloaders.
+async fn receive_upload(
+    state: AppState,
+    filename: String,
+    headers: &HeaderMap,
+    body: Body,
+    cancel: CancellationToken,
+) -> Response<Body> {
+    if let Some(response) = state.forbidden_extension_response(&filename) {
+        return response;
+    }
+    let declared_length =
+        StreamMeta::from_upload_headers(&state, &filename, headers).content_length;
+    if let Some(declared_length) = declared_length
+        && let Err(error) = check_body_size(declared_length, state.max_body_size)
     {
-        let mut streams = state.streams.write().await;
-        if streams.contains_key(&filename) {
-            return (
-                StatusCode::CONFLICT,
-                "An upload is already in progress for this filename",
-            )
-                .into_response();
+        warn!(%filename, declared_length, "Upload rejected: Content-Length over the limit");
+        return (error.status(), format!("Upload failed: {error}")).into_response();
+    }
+    if declared_length == Some(0) && state.reject_empty_uploads {
+        warn!(%filename, "Upload rejected: declared an empty body");
+        let error = UploadError::Empty;
+        return (error.status(), format!("Upload failed: {error}")).into_response();
+    }
+
+    let expected_sha256 = match checksum::expected_sha256(headers) {
+        Ok(expected) => expected,
+        Err(message) => return (StatusCode::BAD_REQUEST, message).into_response(),
+    };
+    if let Err(message) = Disposition::requested(headers) {
+        return (StatusCode::BAD_REQUEST, message).into_response();
+    }
+    if let Err(message) = metadata::collect(headers, &state.metadata) {
+        warn!(%filename, message, "Upload rejected: metadata over the limits");
+        return (metadata::TOO_LARGE, message).into_response();
+    }
+    let uploader = auth::username(headers);
+    let quota = match state
+        .upload_quotas
+        .admit(uploader.as_deref(), declared_length)
+    {
+        Ok(quota) => quota,
+        Err(error) => {
+            warn!(%filename, uploader, %error, "Upload rejected: over quota");
+            return (error.status(), format!("Upload failed: {error}")).into_response();
         }
+    };
 
-        streams.insert(
-            filename.clone(),
-            StreamData {
-                receiver: rx,
-                ready_tx: Some(ready_tx),
-            },
-        );
+    if let Some(spool) = state.spool.clone() {
+        return spool::upload(&state, spool, filename, headers, body, quota, cancel).await;
     }
 
-    info!(%filename, "Upload connection accepted. Waiting for download client.");
+    let receiver_count = match requested_receivers(headers) {
+        Ok(count) => count,
+        Err(message) => return (StatusCode::BAD_REQUEST, message).into_response(),
+    };
+
+    let buffer_frames = match requested_buffer_frames(headers, state.max_buffer_frames) {
+        Ok(frames) => frames.unwrap_or(state.channel_buffer),
+        Err(message) => return (StatusCode::BAD_REQUEST, message).into_response(),
+    };
 
-    let filename_task = filename.clone();
+    let (senders, receivers): (Vec<_>, Vec<_>) = (0..receiver_count)
+        .map(|_| mpsc::channel(buffer_frames))
+        .unzip();
+    let (ready_tx, ready_rx) = tokio::sync::oneshot::channel();
+    let stats = Arc::new(StreamStats::with_quota(quota));
+    let (complete_tx, complete_rx) = tokio::sync::oneshot::channel::<Result<(), UploadError>>();
 
+    let meta = StreamMeta {
+        sha256: expected_sha256,
+        ..StreamMeta::from_upload_headers(&state, &filename, headers)
+    };
+    let refused = state.register_stream(
+        &filename,
+        headers,
+        StreamData {
+            meta: meta.clone(),
+            stats: stats.clone(),
+            cancel: cancel.clone(),
+            source: StreamSource::Live(LiveStream {
+                receivers,
+                receiver_count,
+                buffer_frames,
+                ready_tx: Some(ready_tx),
+                resume: None,
+            }),
+        },
+    );
+    if let Some(response) = refused {
+        return response;
+    }
+    let _cleanup = panics::UnwindCleanup {
+        state: &state,
+        filename: &filename,
+        stats: &stats,
+    };
+
+    state.upload_waiters.notify(&filename);
+    state.metrics.record_upload();
+    state.events.publish(EventKind::UploadStarted, &filename);
+    info!(%filename, receiver_count, "Upload connection accepted. Waiting for download client.");
+
+    let task_state = state.clone();
+    let task_filename = filename.clone();
+    let task_stats = stats.clone();
+    // Weak, so a downloader the relay lets go of still sees its stream end.
+    let downloaders: Vec<_> = senders.iter().map(mpsc::Sender::downgrade).collect();
     tokio::spawn(async move {
-        match tokio::time::timeout(Duration::from_secs(300), ready_rx).await {
-            Ok(Ok(())) => {
-                info!(%filename_task, "Download client connected");
-            }
-            Ok(Err(_)) => {
-                warn!(%filename_task, "Ready channel dropped without signal");
-                let _ = complete_tx.send(Err("Ready channel dropped".to_string()));
-                return;
+        let cancelled = async {
+            cancel.cancelled().await;
+            // Taken while the relay still holds them, so dropping it can't
+            // end the downloads before they hear why.
+            downloaders
+                .iter()
+                .filter_map(mpsc::WeakSender::upgrade)
+                .collect::<Vec<_>>()
+        };
+        let relay = relay_upload(
+            &task_state,
+            &task_filename,
+            ready_rx,
+            body,
+            senders,
+            &task_stats,
+            &meta,
+        );
+        let result = tokio::select! {
+            biased;
+            senders = cancelled => {
+                info!(filename = %task_filename, "Upload cancelled");
+                abort_downloaders(&senders, &UploadError::Cancelled);
+                Err(UploadError::Cancelled)
             }
-            Err(_) => {
-                warn!(%filename_task, "Upload timed out waiting for download client (300s)");
-                let _ = complete_tx.send(Err("Timeout waiting for download client".to_string()));
-                return;
+            result = relay => result,
+        };
+        let _ = complete_tx.send(result);
+    });
+
+    let response = match complete_rx.await {
+        Ok(Ok(())) => {
+            state.transfer_completed(&filename, &stats, StatusCode::OK);
+            if let Some(one_shot) = &state.one_shot {
+                one_shot.cancel();
             }
+            (StatusCode::OK, "Upload completed successfully").into_response()
         }
+        Ok(Err(error)) => {
+            state.transfer_failed(&filename, &stats, error.status(), &error);
+            (error.status(), format!("Upload failed: {error}")).into_response()
+        }
+        Err(_) => {
+            let response = panics::task_panicked();
+            state.transfer_failed(&filename, &stats, response.status(), "Upload task failed");
+            response
+        }
+    };
+    state.remove_stream(&filename, &stats);
+    response
+}
 
-        let mut body_stream = BodyStream::new(body);
+/// Waits for every downloader to connect, then relays `body` to them until
+/// it ends, the downloaders leave, or something goes wrong.
+async fn relay_upload(
+    state: &AppState,
+    filename: &str,
+    ready_rx: tokio::sync::oneshot::Receiver<()>,
+    body: Body,
+    mut senders: Vec<ChunkSender>,
+    stats: &StreamStats,
+    meta: &StreamMeta,
+) -> Result<(), UploadError> {
+    let expected_sha256 = meta.sha256;
+    let declared_length = meta.content_length.filter(|_| state.enforce_content_length);
+    let ready_timeout = state.upload_ready_timeout;
+    let wait_for_ready = async {
+        match ready_timeout {
+            Some(timeout) => tokio::time::timeout(timeout, ready_rx).await,
+            None => Ok(ready_rx.await),
+        }
+    };
+    let ready = tokio::select! {
+        ready = wait_for_ready => ready,
+        _ = state.shutdown.cancelled() => {
+            info!(%filename, "Shutdown requested before a download client connected");
+            return Err(UploadError::ShuttingDown);
+        }
+    };
 
-        while let Some(chunk_result) = body_stream.next().await {
-            match chunk_result {
-                Ok(frame) => {
-                    if let Ok(bytes) = frame.into_data() {
-                        if tx.send(Ok(bytes)).await.is_err() {
-                            info!(%filename_task, "Download client disconnected. Stopping upload.");
-                            break;
-                        }
+    match ready {
+        Ok(Ok(())) => {
+            info!(%filename, "Download client connected");
+        }
+        Ok(Err(_)) => {
+            warn!(%filename, "Ready channel dropped without signal");
+            return Err(UploadError::ReadyDropped);
+        }
+        Err(_) => {
+            warn!(%filename, ?ready_timeout, "Upload timed out waiting for download client");
+            return Err(UploadError::ReadyTimeout);
+        }
+    }
+
+    let lag_timeout = match state.lag_policy {
+        LagPolicy::Disconnect(timeout) if senders.len() > 1 => Some(timeout),
+        LagPolicy::Disconnect(_) | LagPolicy::Wait => None,
+    };
+    let idle_timeout = state.idle_timeout;
+    let mut rate_floor = state.min_upload_rate.map(RateFloor::new);
+    let mut body_stream = BodyStream::new(body);
+    let mut received = 0;
+    let mut verifier = expected_sha256.map(ChecksumVerifier::new);
+    // A declared digest is the upload's SHA-256 once verified, so there is
+    // only something to work out without one.
+    let mut hasher = (expected_sha256.is_none() && stats.digest_wanted.load(Ordering::Relaxed))
+        .then(Sha256::new);
+    let mut throttle = state.max_transfer_rate.map(Throttle::new);
+    let reconnect_grace = state.reconnect_grace.filter(|_| senders.len() == 1);
+    let mut replay = reconnect_grace.map(|grace| Replay::new(grace.replay_bytes));
+
+    loop {
+        let chunk_result = match next_frame(&mut body_stream, idle_timeout, &mut rate_floor).await {
+            Ok(Some(chunk_result)) => chunk_result,
+            Ok(None) => {
+                if let Err(upload_error) = check_declared_length(received, declared_length, true) {
+                    warn!(%filename, received, %upload_error, "Upload ended short. Aborting transfer.");
+                    abort_downloaders(&senders, &upload_error);
+                    return Err(upload_error);
+                }
+                if let Some(verifier) = verifier.take()
+                    && !verifier.matches()
+                {
+                    warn!(%filename, "Upload failed its SHA-256 check. Aborting transfer.");
+                    abort_downloaders(&senders, &UploadError::ChecksumMismatch);
+                    return Err(UploadError::ChecksumMismatch);
+                }
+                if received == 0 {
+                    if state.reject_empty_uploads {
+                        warn!(%filename, "Upload closed before sending any data. Aborting transfer.");
+                        abort_downloaders(&senders, &UploadError::Empty);
+                        return Err(UploadError::Empty);
                     }
+                    info!(%filename, "Upload closed before sending any data; relaying an empty file");
                 }
-                Err(error) => {
-                    let error_msg = format!("Stream error: {error}");
-                    error!(%filename_task, %error, "Error reading upload stream");
-                    let _ = tx.send(Err(error)).await;
-                    let _ = complete_tx.send(Err(error_msg));
-                    return;
+                if let Some(sha256) =
+                    expected_sha256.or_else(|| hasher.take().map(|hasher| hasher.finalize().into()))
+                {
+                    let _ = stats.sha256.set(sha256);
                 }
+                stats.finished.store(true, Ordering::Relaxed);
+                break;
+            }
+            Err(upload_error) => {
+                warn!(%filename, %upload_error, "Upload stalled. Aborting transfer.");
+                abort_downloaders(&senders, &upload_error);
+                return Err(upload_error);
+            }
+        };
+
+        match chunk_result {
+            Ok(frame) => {
+                if let Ok(bytes) = frame.into_data() {
+                    received += bytes.len() as u64;
+                    if let Err(upload_error) = check_body_size(received, state.max_body_size)
+                        .and_then(|()| check_declared_length(received, declared_length, false))
+                        .and_then(|()| stats.charge_quota(bytes.len()))
+                    {
+                        warn!(%filename, received, %upload_error, "Upload rejected. Aborting transfer.");
+                        abort_downloaders(&senders, &upload_error);
+                        return Err(upload_error);
+                    }
+                    if let Some(verifier) = &mut verifier {
+                        verifier.update(&bytes);
+                    }
+                    if let Some(hasher) = &mut hasher {
+                        hasher.update(&bytes);
+                    }
+                    throttle::pace(&mut throttle, bytes.len()).await;
+                    state.metrics.record_bytes(bytes.len());
+                    stats
+                        .bytes_transferred
+                        .fetch_add(bytes.len() as u64, Ordering::Relaxed);
+                    if let Some(replay) = &mut replay {
+                        replay.push(bytes.clone());
+                    }
+                    senders = fan_out(
+                        senders,
+                        bytes,
+                        lag_timeout,
+                        state.memory_budget.as_ref(),
+                        filename,
+                    )
+                    .await;
+                    if senders.is_empty()
+                        && let (Some(grace), Some(replay)) = (reconnect_grace, &replay)
+                        && let Some(sender) =
+                            reconnect::await_downloader(state, filename, replay, grace.window).await
+                    {
+                        senders.push(sender);
+                    }
+                    if senders.is_empty() {
+                        info!(%filename, "Download client disconnected. Stopping upload.");
+                        return Err(UploadError::DownloaderGone);
+                    }
+                }
+            }
+            Err(error) => {
+                error!(%filename, %error, "Error reading upload stream");
+                for sender in &senders {
+                    let _ = sender.send(Err(axum::Error::new(error.to_string()))).await;
+                }
+                return Err(UploadError::Body(error.to_string()));
             }
         }
+    }
 
-        info!(%filename_task, "Upload stream finished.");
-        let _ = complete_tx.send(Ok(()));
-    });
+    info!(%filename, "Upload stream finished.");
+    Ok(())
+}
 
-    match complete_rx.await {
-        Ok(Ok(())) => {
-            state.streams.write().await.remove(&filename);
-            (StatusCode::OK, "Upload completed successfully").into_response()
-        }
-        Ok(Err(error)) => {
-            state.streams.write().await.remove(&filename);
-            (StatusCode::BAD_REQUEST, format!("Upload failed: {error}")).into_response()
-        }
-        Err(_) => {
-            state.streams.write().await.remove(&filename);
-            (StatusCode::INTERNAL_SERVER_ERROR, "Upload task failed").into_response()
-        }
+async fn delete_handler(
+    State(state): State<AppState>,
+    ConnectInfo(ClientAddr(client)): ConnectInfo<ClientAddr>,
+    Path(filename): Path<String>,
+    headers: HeaderMap,
+) -> Response<Body> {
+    let auth = match extract_credentials(&headers) {
+        Ok(auth) => auth,
+        Err(err) => return auth_error_response(&state, err),
+    };
+
+    if let Err(err) = authenticate_user(&state, client, &auth, Permission::Upload).await {
+        return auth_error_response(&state, err);
+    }
+
+    let filename = match sanitize_filename(&filename, state.max_filename_len) {
+        Ok(filename) => filename,
+        Err(message) => return invalid_filename_response(message),
+    };
+
+    let Some(stream_data) = state.streams.remove(&filename) else {
+        return (StatusCode::NOT_FOUND, "No pending upload for this file").into_response();
+    };
+
+    stream_data.cancel.cancel();
+    if let StreamSource::Spooled(file) = &stream_data.source {
+        spool::discard(file).await;
+    }
+    info!(%filename, "Upload cancelled by DELETE");
+
+    (StatusCode::OK, "Upload cancelled").into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Every path that drops an upload's entry cancels its task first, so a
+    // dropped ready channel can't be provoked over HTTP.
+    #[test]
+    fn waiting_for_a_downloader_fails_with_gateway_timeout_or_server_error() {
+        assert_eq!(
+            UploadError::ReadyTimeout.status(),
+            StatusCode::GATEWAY_TIMEOUT
+        );
+        assert_eq!(
+            UploadError::ReadyDropped.status(),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
     }
 }
@@ -0,0 +1,87 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+
+use bytes::Bytes;
+use tokio::sync::Notify;
+
+/// Bytes relayed by live uploads but not yet taken by their downloaders,
+/// counted across every stream so the total can be capped.
+pub(crate) struct MemoryBudget {
+    limit: usize,
+    used: AtomicUsize,
+    released: Notify,
+}
+
+impl MemoryBudget {
+    pub(crate) fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            used: AtomicUsize::new(0),
+            released: Notify::new(),
+        }
+    }
+
+    /// Waits until the budget has room, then charges `len` bytes to it
+    /// until the returned [`Charge`] is dropped. A chunk is let in whenever
+    /// the total is under the limit, so the total overshoots by at most one
+    /// chunk and a chunk larger than the whole budget still gets through.
+    async fn charge(self: &Arc<Self>, len: usize) -> Charge {
+        loop {
+            let released = self.released.notified();
+            tokio::pin!(released);
+            // Registered before checking, so a release in between still
+            // wakes this waiter.
+            released.as_mut().enable();
+
+            let admitted = self
+                .used
+                .fetch_update(Ordering::AcqRel, Ordering::Acquire, |used| {
+                    (used < self.limit).then_some(used + len)
+                });
+            if admitted.is_ok() {
+                return Charge {
+                    budget: self.clone(),
+                    len,
+                };
+            }
+            released.await;
+        }
+    }
+}
+
+/// A share of the [`MemoryBudget`], given back when dropped.
+pub(crate) struct Charge {
+    budget: Arc<MemoryBudget>,
+    len: usize,
+}
+
+impl Drop for Charge {
+    fn drop(&mut self) {
+        self.budget.used.fetch_sub(self.len, Ordering::AcqRel);
+        self.budget.released.notify_waiters();
+    }
+}
+
+/// A relayed chunk on its way to one downloader. It holds its share of the
+/// budget while it waits in the channel, including when the channel is
+/// dropped with it still inside.
+pub(crate) struct Chunk {
+    pub(crate) bytes: Bytes,
+    _charge: Option<Charge>,
+}
+
+impl Chunk {
+    /// Wraps `bytes`, first waiting for room in `budget` if there is one.
+    pub(crate) async fn charged(bytes: Bytes, budget: Option<&Arc<MemoryBudget>>) -> Self {
+        let charge = match budget {
+            Some(budget) => Some(budget.charge(bytes.len()).await),
+            None => None,
+        };
+        Self {
+            bytes,
+            _charge: charge,
+        }
+    }
+}
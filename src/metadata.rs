@@ -0,0 +1,60 @@
+use axum::http::{HeaderMap, HeaderName, HeaderValue, StatusCode};
+
+use crate::{ServerConfig, reservation::RESERVATION_HEADER};
+
+/// Status for uploads whose metadata goes over the limits.
+pub(crate) const TOO_LARGE: StatusCode = StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE;
+
+/// Upload headers passed on to downloads unchanged, in the order sent.
+pub(crate) type Metadata = Vec<(HeaderName, HeaderValue)>;
+
+/// Which upload headers are forwarded, and how many of them one upload
+/// may carry.
+#[derive(Debug)]
+pub(crate) struct MetadataPolicy {
+    /// Lowercase prefix of the upload headers forwarded to downloads.
+    prefix: Option<String>,
+    max_headers: usize,
+    /// Counting names and values together.
+    max_bytes: usize,
+}
+
+impl MetadataPolicy {
+    pub(crate) fn new(config: &ServerConfig) -> Self {
+        Self {
+            prefix: config.metadata_header_prefix.clone(),
+            max_headers: config.max_metadata_headers,
+            max_bytes: config.max_metadata_bytes,
+        }
+    }
+}
+
+/// The upload headers `policy` forwards, or none when forwarding is off.
+/// Refuses more than its limit of them, or more than its limit of bytes of
+/// names and values together, with a message to send with [`TOO_LARGE`].
+pub(crate) fn collect(
+    headers: &HeaderMap,
+    policy: &MetadataPolicy,
+) -> Result<Metadata, &'static str> {
+    let Some(prefix) = policy.prefix.as_deref() else {
+        return Ok(Metadata::new());
+    };
+
+    let mut metadata = Metadata::new();
+    let mut size = 0;
+    for (name, value) in headers {
+        // The reservation token is a secret, whatever the prefix.
+        if !name.as_str().starts_with(prefix) || name == RESERVATION_HEADER {
+            continue;
+        }
+        if metadata.len() == policy.max_headers {
+            return Err("Too many metadata headers");
+        }
+        size += name.as_str().len() + value.len();
+        if size > policy.max_bytes {
+            return Err("Metadata headers are too large");
+        }
+        metadata.push((name.clone(), value.clone()));
+    }
+    Ok(metadata)
+}
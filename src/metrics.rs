@@ -0,0 +1,74 @@
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Process-wide counters exported at `/metrics`. Names are part of beam's
+/// public surface; rename only with a release note.
+#[derive(Default)]
+pub(crate) struct Metrics {
+    uploads: AtomicU64,
+    downloads: AtomicU64,
+    bytes_transferred: AtomicU64,
+    auth_failures: AtomicU64,
+}
+
+impl Metrics {
+    pub(crate) fn record_upload(&self) {
+        self.uploads.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_download(&self) {
+        self.downloads.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_bytes(&self, bytes: usize) {
+        self.bytes_transferred
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_auth_failure(&self) {
+        self.auth_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Renders the counters in the Prometheus text exposition format.
+    pub(crate) fn render(&self, active_streams: usize) -> String {
+        let mut out = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, value: u64| {
+            let _ = writeln!(out, "# HELP {name} {help}");
+            let _ = writeln!(out, "# TYPE {name} {kind}");
+            let _ = writeln!(out, "{name} {value}");
+        };
+
+        metric(
+            "beam_uploads_total",
+            "counter",
+            "Uploads accepted and registered as streams.",
+            self.uploads.load(Ordering::Relaxed),
+        );
+        metric(
+            "beam_downloads_total",
+            "counter",
+            "Downloads attached to an upload stream.",
+            self.downloads.load(Ordering::Relaxed),
+        );
+        metric(
+            "beam_active_streams",
+            "gauge",
+            "Uploads currently waiting for their downloaders.",
+            active_streams as u64,
+        );
+        metric(
+            "beam_bytes_transferred_total",
+            "counter",
+            "Body bytes read from uploaders and relayed to downloaders.",
+            self.bytes_transferred.load(Ordering::Relaxed),
+        );
+        metric(
+            "beam_auth_failures_total",
+            "counter",
+            "Requests rejected because their credentials did not verify.",
+            self.auth_failures.load(Ordering::Relaxed),
+        );
+
+        out
+    }
+}
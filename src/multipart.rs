@@ -0,0 +1,145 @@
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, Path, State},
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    AppState, ClientAddr,
+    auth::{Permission, auth_error_response, authenticate_user, extract_credentials},
+    filename::sanitize_filename,
+    invalid_filename_response, receive_upload,
+};
+
+/// Form field that may name the upload, read if it comes before the file.
+const FILENAME_FIELD: &str = "filename";
+
+/// Longest `filename` field read; names are checked more strictly later.
+const FILENAME_FIELD_LIMIT: u64 = 4096;
+
+/// Boundary of a `multipart/form-data` body, or `None` for any other kind.
+pub(crate) fn boundary(headers: &HeaderMap) -> Option<String> {
+    let content_type = headers.get(header::CONTENT_TYPE)?.to_str().ok()?;
+    multer::parse_boundary(content_type).ok()
+}
+
+/// `POST /` and `POST /{filename}`: uploads the first file of an HTML form,
+/// under the part's own filename when the path doesn't name one.
+pub(crate) async fn form_upload_handler(
+    State(state): State<AppState>,
+    ConnectInfo(ClientAddr(client)): ConnectInfo<ClientAddr>,
+    filename: Option<Path<String>>,
+    headers: HeaderMap,
+    body: Body,
+) -> Response<Body> {
+    let auth = match extract_credentials(&headers) {
+        Ok(auth) => auth,
+        Err(err) => return auth_error_response(&state, err),
+    };
+
+    if let Err(err) = authenticate_user(&state, client, &auth, Permission::Upload).await {
+        return auth_error_response(&state, err);
+    }
+
+    let filename = match filename
+        .map(|Path(filename)| sanitize_filename(&filename, state.max_filename_len))
+        .transpose()
+    {
+        Ok(filename) => filename,
+        Err(message) => return invalid_filename_response(message),
+    };
+    let Some(boundary) = boundary(&headers) else {
+        return (
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "Expected a multipart/form-data body",
+        )
+            .into_response();
+    };
+
+    receive(state, filename, &headers, boundary, body).await
+}
+
+/// Uploads the first file part of the form in `body`, under `filename`,
+/// else a non-empty `filename` field sent before the file, else the part's
+/// own filename. The part streams through as it arrives; only the fields
+/// before it are read ahead.
+pub(crate) async fn receive(
+    state: AppState,
+    filename: Option<String>,
+    headers: &HeaderMap,
+    boundary: String,
+    body: Body,
+) -> Response<Body> {
+    let constraints = multer::Constraints::new()
+        .size_limit(multer::SizeLimit::new().for_field(FILENAME_FIELD, FILENAME_FIELD_LIMIT));
+    let mut form =
+        multer::Multipart::with_constraints(body.into_data_stream(), boundary, constraints);
+    let mut filename = filename;
+    let part = loop {
+        match form.next_field().await {
+            Ok(Some(field)) if field.file_name().is_some() => break field,
+            Ok(Some(field)) if filename.is_none() && field.name() == Some(FILENAME_FIELD) => {
+                let named = match field.text().await {
+                    Ok(named) => named,
+                    Err(error) => {
+                        return (
+                            StatusCode::BAD_REQUEST,
+                            format!("Invalid form body: {error}"),
+                        )
+                            .into_response();
+                    }
+                };
+                if named.trim().is_empty() {
+                    continue;
+                }
+                filename = match sanitize_filename(&named, state.max_filename_len) {
+                    Ok(filename) => Some(filename),
+                    Err(message) => return invalid_filename_response(message),
+                };
+            }
+            Ok(Some(_)) => continue,
+            Ok(None) => {
+                return (StatusCode::BAD_REQUEST, "The form has no file part").into_response();
+            }
+            Err(error) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    format!("Invalid form body: {error}"),
+                )
+                    .into_response();
+            }
+        }
+    };
+
+    let filename = match filename {
+        Some(filename) => filename,
+        None => {
+            match sanitize_filename(part.file_name().unwrap_or_default(), state.max_filename_len) {
+                Ok(filename) => filename,
+                Err(message) => return invalid_filename_response(message),
+            }
+        }
+    };
+
+    // The download gets the part's type; its length isn't known up front.
+    let mut part_headers = headers.clone();
+    part_headers.remove(header::CONTENT_LENGTH);
+    match part
+        .content_type()
+        .and_then(|mime| HeaderValue::from_str(mime.as_ref()).ok())
+    {
+        Some(content_type) => part_headers.insert(header::CONTENT_TYPE, content_type),
+        None => part_headers.remove(header::CONTENT_TYPE),
+    };
+
+    receive_upload(
+        state,
+        filename,
+        &part_headers,
+        Body::from_stream(part),
+        CancellationToken::new(),
+    )
+    .await
+}
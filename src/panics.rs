@@ -0,0 +1,59 @@
+use std::{any::Any, sync::Arc};
+
+use axum::{
+    body::Body,
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use tracing::error;
+
+use crate::{AppState, StreamStats};
+
+/// `Retry-After` sent with a `503` for a request that panicked.
+const RETRY_AFTER_SECS: u64 = 1;
+
+/// Answers a request whose handler panicked. The panic only takes down that
+/// request; every other transfer carries on.
+pub(crate) fn handler_panicked(panic: Box<dyn Any + Send + 'static>) -> Response<Body> {
+    error!(message = panic_message(&*panic), "Request handler panicked");
+    unavailable("The server hit an internal error handling this request; try again")
+}
+
+/// Answers an upload whose relay task panicked partway.
+pub(crate) fn task_panicked() -> Response<Body> {
+    unavailable("Upload task failed unexpectedly; try again")
+}
+
+fn unavailable(message: &'static str) -> Response<Body> {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        [(header::RETRY_AFTER, RETRY_AFTER_SECS)],
+        message,
+    )
+        .into_response()
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    panic
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("non-string panic payload")
+}
+
+/// Frees `filename` if the handler that registered it unwinds before it
+/// gets to do so itself, so a panic doesn't leave the name taken by a
+/// stream nothing serves.
+pub(crate) struct UnwindCleanup<'a> {
+    pub(crate) state: &'a AppState,
+    pub(crate) filename: &'a str,
+    pub(crate) stats: &'a Arc<StreamStats>,
+}
+
+impl Drop for UnwindCleanup<'_> {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            self.state.remove_stream(self.filename, self.stats);
+        }
+    }
+}
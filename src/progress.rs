@@ -0,0 +1,77 @@
+use axum::{
+    Json,
+    body::Body,
+    extract::{ConnectInfo, Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use std::sync::atomic::Ordering;
+
+use crate::{
+    AppState, ClientAddr, StreamSource,
+    auth::{Permission, auth_error_response, authenticate_user, extract_credentials},
+    filename::sanitize_filename,
+    invalid_filename_response,
+};
+
+/// How far one transfer has got, as reported by `GET /progress/{filename}`.
+#[derive(serde::Serialize)]
+struct Progress {
+    bytes_transferred: u64,
+    /// The upload's declared `Content-Length`, if it sent one.
+    total: Option<u64>,
+    downloader_connected: bool,
+    elapsed_secs: u64,
+}
+
+/// `GET /progress/{filename}`: the counters of a transfer still in flight,
+/// for its uploader and downloaders to poll. A stored file has finished
+/// transferring, so it answers `404` like a name with no upload at all.
+pub(crate) async fn progress_handler(
+    State(state): State<AppState>,
+    ConnectInfo(ClientAddr(client)): ConnectInfo<ClientAddr>,
+    Path(filename): Path<String>,
+    headers: HeaderMap,
+) -> Response<Body> {
+    if !state.anonymous_downloads {
+        let auth = match extract_credentials(&headers) {
+            Ok(auth) => auth,
+            Err(err) => return auth_error_response(&state, err),
+        };
+
+        if let Err(err) = authenticate_user(&state, client, &auth, Permission::View).await {
+            return auth_error_response(&state, err);
+        }
+    }
+
+    let filename = match sanitize_filename(&filename, state.max_filename_len) {
+        Ok(filename) => filename,
+        Err(message) => return invalid_filename_response(message),
+    };
+
+    let Some(stream_data) = state.streams.get(&filename) else {
+        return no_transfer_response();
+    };
+    let downloader_connected = match &stream_data.source {
+        StreamSource::Live(live) => live.connected_downloaders() > 0,
+        StreamSource::Spooling => false,
+        StreamSource::Spooled(_) | StreamSource::Reserved(_) => return no_transfer_response(),
+    };
+    let stats = &stream_data.stats;
+
+    Json(Progress {
+        bytes_transferred: stats.bytes_transferred.load(Ordering::Relaxed),
+        total: stream_data.meta.content_length,
+        downloader_connected,
+        elapsed_secs: stats.started.elapsed().as_secs(),
+    })
+    .into_response()
+}
+
+fn no_transfer_response() -> Response<Body> {
+    (
+        StatusCode::NOT_FOUND,
+        "No transfer in progress for this file",
+    )
+        .into_response()
+}
@@ -0,0 +1,117 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+    },
+};
+
+use dashmap::DashMap;
+
+use crate::{UploadError, config::UploadQuota};
+
+/// Per-user [`UploadQuota`]s and what each user is currently using of them.
+pub(crate) struct UploadQuotas {
+    default: Option<UploadQuota>,
+    per_user: HashMap<String, UploadQuota>,
+    /// Only users with a quota get an entry, so this never outgrows the
+    /// configured users.
+    usage: DashMap<String, Arc<Usage>>,
+}
+
+#[derive(Default)]
+struct Usage {
+    streams: AtomicUsize,
+    bytes: AtomicU64,
+}
+
+/// One stream's share of its uploader's quota, given back when dropped.
+/// It lives in the stream's [`StreamStats`](crate::StreamStats), so a stored
+/// upload keeps counting until it is deleted or expires.
+pub(crate) struct QuotaLease {
+    quota: UploadQuota,
+    usage: Arc<Usage>,
+    charged: AtomicU64,
+}
+
+impl UploadQuotas {
+    pub(crate) fn new(
+        default: Option<UploadQuota>,
+        per_user: HashMap<String, UploadQuota>,
+    ) -> Self {
+        Self {
+            default,
+            per_user,
+            usage: DashMap::new(),
+        }
+    }
+
+    /// Takes one stream off `username`'s quota, refusing if they already
+    /// have as many as it allows or if `declared_len` more bytes would not
+    /// fit. `None` when the user has no quota, or the upload no user.
+    pub(crate) fn admit(
+        &self,
+        username: Option<&str>,
+        declared_len: Option<u64>,
+    ) -> Result<Option<QuotaLease>, UploadError> {
+        let Some(username) = username else {
+            return Ok(None);
+        };
+        let Some(quota) = self.per_user.get(username).copied().or(self.default) else {
+            return Ok(None);
+        };
+        let usage = self.usage.entry(username.to_owned()).or_default().clone();
+
+        if let (Some(max_bytes), Some(declared_len)) = (quota.max_bytes, declared_len)
+            && usage
+                .bytes
+                .load(Ordering::Acquire)
+                .saturating_add(declared_len)
+                > max_bytes
+        {
+            return Err(UploadError::ByteQuota(max_bytes));
+        }
+        let admitted = usage
+            .streams
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |streams| {
+                quota
+                    .max_streams
+                    .is_none_or(|max_streams| streams < max_streams)
+                    .then_some(streams + 1)
+            });
+        if admitted.is_err() {
+            return Err(UploadError::StreamQuota(
+                quota.max_streams.unwrap_or_default(),
+            ));
+        }
+
+        Ok(Some(QuotaLease {
+            quota,
+            usage,
+            charged: AtomicU64::new(0),
+        }))
+    }
+}
+
+impl QuotaLease {
+    /// Counts `len` more received bytes, failing once the uploader's streams
+    /// hold more between them than the quota allows.
+    pub(crate) fn charge(&self, len: usize) -> Result<(), UploadError> {
+        let len = len as u64;
+        self.charged.fetch_add(len, Ordering::Relaxed);
+        let used = self.usage.bytes.fetch_add(len, Ordering::AcqRel) + len;
+        match self.quota.max_bytes {
+            Some(max_bytes) if used > max_bytes => Err(UploadError::ByteQuota(max_bytes)),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Drop for QuotaLease {
+    fn drop(&mut self) {
+        self.usage.streams.fetch_sub(1, Ordering::AcqRel);
+        self.usage
+            .bytes
+            .fetch_sub(self.charged.load(Ordering::Relaxed), Ordering::AcqRel);
+    }
+}
@@ -0,0 +1,147 @@
+use axum::{
+    body::{Body, Bytes, to_bytes},
+    extract::{ConnectInfo, State},
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use futures_util::{StreamExt, stream};
+use rand_core::{OsRng, RngCore};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::{
+    AppState, ClientAddr, StreamSource,
+    auth::{Permission, auth_error_response, authenticate_user, extract_credentials},
+    multipart, panics, receive_upload,
+    reservation::{self, RESERVATION_HEADER, RESERVATION_TTL},
+    waiters,
+};
+
+/// Characters random names are drawn from, so they survive being read out
+/// or typed.
+const ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789";
+
+const NAME_LEN: usize = 8;
+
+/// Names tried before giving up, should every one somehow be taken.
+const ATTEMPTS: usize = 8;
+
+/// The longest outcome message read back from the upload's own response.
+const MAX_OUTCOME_BODY: usize = 64 * 1024;
+
+/// `PUT /`: uploads under a random name beam picks. The name has to reach
+/// the uploader before its download can start, so once the upload is
+/// registered beam answers `201 Created` at once, with the name's path in
+/// `Location` and the name as the first line of the body. The body's last
+/// line is the outcome a named `PUT` would have got; a failed upload ends
+/// the body with an error instead. Refusals that come before registration,
+/// such as `413` for a declared length over the limit, are answered as
+/// usual.
+pub(crate) async fn upload_handler(
+    State(state): State<AppState>,
+    ConnectInfo(ClientAddr(client)): ConnectInfo<ClientAddr>,
+    mut headers: HeaderMap,
+    body: Body,
+) -> Response<Body> {
+    let auth = match extract_credentials(&headers) {
+        Ok(auth) => auth,
+        Err(err) => return auth_error_response(&state, err),
+    };
+
+    if let Err(err) = authenticate_user(&state, client, &auth, Permission::Upload).await {
+        return auth_error_response(&state, err);
+    }
+
+    // A form names its own file, as it does for `POST /`.
+    if let Some(boundary) = multipart::boundary(&headers) {
+        return multipart::receive(state, None, &headers, boundary, body).await;
+    }
+
+    let token = reservation::new_token();
+    let mut claimed = None;
+    for _ in 0..ATTEMPTS {
+        let candidate = random_name();
+        match reservation::reserve(&state, &candidate, &token) {
+            None => {
+                claimed = Some(candidate);
+                break;
+            }
+            Some(refusal) if refusal.status() == StatusCode::CONFLICT => continue,
+            Some(refusal) => return refusal,
+        }
+    }
+    let Some(filename) = claimed else {
+        warn!("Every random filename tried was taken");
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Could not find a free filename; try again",
+        )
+            .into_response();
+    };
+    info!(%filename, "Assigned a random filename");
+
+    headers.insert(
+        RESERVATION_HEADER,
+        HeaderValue::from_str(&token).expect("tokens are base64url"),
+    );
+    let task_state = state.clone();
+    let task_filename = filename.clone();
+    let mut upload = tokio::spawn(async move {
+        receive_upload(
+            task_state,
+            task_filename,
+            &headers,
+            body,
+            CancellationToken::new(),
+        )
+        .await
+    });
+
+    let mut finished = tokio::select! {
+        biased;
+        finished = &mut upload => Some(finished.unwrap_or_else(|_| panics::task_panicked())),
+        _ = waiters::wait_for_upload(&state, &filename, RESERVATION_TTL) => None,
+    };
+    if let Some(refusal) = finished.take_if(|response| !response.status().is_success()) {
+        // Refused before it took over the name, which is free again.
+        state.streams.remove_if(&filename, |stream_data| {
+            matches!(&stream_data.source, StreamSource::Reserved(held) if *held == token)
+        });
+        return refusal;
+    }
+
+    let outcome = async move {
+        let response = match finished {
+            Some(response) => response,
+            None => upload.await.unwrap_or_else(|_| panics::task_panicked()),
+        };
+        let status = response.status();
+        let message = to_bytes(response.into_body(), MAX_OUTCOME_BODY)
+            .await
+            .unwrap_or_default();
+        let message = String::from_utf8_lossy(&message).into_owned();
+        if status.is_success() {
+            Ok(Bytes::from(format!("{message}\n")))
+        } else {
+            Err(axum::Error::new(message))
+        }
+    };
+    let body =
+        stream::iter([Ok(Bytes::from(format!("{filename}\n")))]).chain(stream::once(outcome));
+
+    Response::builder()
+        .status(StatusCode::CREATED)
+        .header(header::LOCATION, format!("{}/{filename}", state.base_path))
+        .header(header::CONTENT_TYPE, "text/plain; charset=utf-8")
+        .body(Body::from_stream(body))
+        .expect("failed to build random name response")
+}
+
+fn random_name() -> String {
+    let mut bytes = [0u8; NAME_LEN];
+    OsRng.fill_bytes(&mut bytes);
+    bytes
+        .iter()
+        .map(|byte| char::from(ALPHABET[usize::from(*byte) % ALPHABET.len()]))
+        .collect()
+}
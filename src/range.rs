@@ -0,0 +1,107 @@
+use axum::http::HeaderValue;
+
+/// A satisfiable byte range, inclusive at both ends as in `Content-Range`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ByteRange {
+    pub(crate) start: u64,
+    pub(crate) end: u64,
+}
+
+impl ByteRange {
+    pub(crate) fn len(&self) -> u64 {
+        self.end - self.start + 1
+    }
+
+    pub(crate) fn content_range(&self, total: u64) -> String {
+        format!("bytes {}-{}/{total}", self.start, self.end)
+    }
+}
+
+/// How a stored file of a known length should answer a `Range` header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RangeRequest {
+    /// Serve the whole file with `200 OK`.
+    Full,
+    /// Serve one slice with `206 Partial Content`.
+    Partial(ByteRange),
+    /// Answer `416 Range Not Satisfiable`.
+    Unsatisfiable,
+}
+
+/// Interprets a single `bytes=` range against a file of `total` bytes.
+///
+/// Malformed headers, other units and multi-range requests fall back to the
+/// full file, which RFC 9110 allows, rather than failing the download.
+pub(crate) fn parse_range(value: &HeaderValue, total: u64) -> RangeRequest {
+    let Some(spec) = value.to_str().ok().and_then(|value| {
+        let (unit, spec) = value.trim().split_once('=')?;
+        unit.eq_ignore_ascii_case("bytes").then_some(spec)
+    }) else {
+        return RangeRequest::Full;
+    };
+
+    if spec.contains(',') {
+        return RangeRequest::Full;
+    }
+    let Some((start, end)) = spec.split_once('-') else {
+        return RangeRequest::Full;
+    };
+    let (start, end) = (start.trim(), end.trim());
+
+    if start.is_empty() {
+        // Suffix range: the last `end` bytes.
+        let Ok(suffix) = end.parse::<u64>() else {
+            return RangeRequest::Full;
+        };
+        if suffix == 0 || total == 0 {
+            return RangeRequest::Unsatisfiable;
+        }
+        return RangeRequest::Partial(ByteRange {
+            start: total.saturating_sub(suffix),
+            end: total - 1,
+        });
+    }
+
+    let Ok(start) = start.parse::<u64>() else {
+        return RangeRequest::Full;
+    };
+    let end = if end.is_empty() {
+        None
+    } else {
+        match end.parse::<u64>() {
+            Ok(end) if end >= start => Some(end),
+            _ => return RangeRequest::Full,
+        }
+    };
+
+    if start >= total {
+        return RangeRequest::Unsatisfiable;
+    }
+    RangeRequest::Partial(ByteRange {
+        start,
+        end: end.map_or(total - 1, |end| end.min(total - 1)),
+    })
+}
+
+/// Reads the `Content-Range: bytes a-b/total` an upload segment is sent
+/// with, where the total may be `*` while the uploader doesn't know it yet.
+/// `None` if the header is malformed or the range doesn't fit the total.
+pub(crate) fn parse_content_range(value: &HeaderValue) -> Option<(ByteRange, Option<u64>)> {
+    let (unit, spec) = value.to_str().ok()?.trim().split_once(' ')?;
+    if !unit.eq_ignore_ascii_case("bytes") {
+        return None;
+    }
+    let (range, total) = spec.trim().split_once('/')?;
+    let (start, end) = range.split_once('-')?;
+    let range = ByteRange {
+        start: start.trim().parse().ok()?,
+        end: end.trim().parse().ok()?,
+    };
+    let total = match total.trim() {
+        "*" => None,
+        total => Some(total.parse::<u64>().ok()?),
+    };
+
+    let fits = range.start <= range.end && total.is_none_or(|total| range.end < total);
+    fits.then_some((range, total))
+}
@@ -0,0 +1,49 @@
+use std::time::Duration;
+
+use crate::{UploadError, config::MinUploadRate};
+
+/// Checks that an upload keeps up a minimum rate, judged over windows of
+/// time spent waiting for the body. Time spent on anything else, such as a
+/// slow downloader, doesn't count against the uploader.
+pub(crate) struct RateFloor {
+    min: MinUploadRate,
+    /// Time spent waiting for the body in the current window.
+    waited: Duration,
+    /// Bytes received in the current window.
+    received: u64,
+}
+
+impl RateFloor {
+    pub(crate) fn new(min: MinUploadRate) -> Self {
+        Self {
+            min,
+            waited: Duration::ZERO,
+            received: 0,
+        }
+    }
+
+    /// How much longer the body may be waited on before the current window
+    /// is judged.
+    pub(crate) fn remaining(&self) -> Duration {
+        self.min.window.saturating_sub(self.waited)
+    }
+
+    /// Counts `len` bytes that arrived after waiting `waited`. Once the
+    /// window is over, fails with [`UploadError::TooSlow`] if it fell short
+    /// of the rate, and otherwise starts the next one.
+    pub(crate) fn record(&mut self, waited: Duration, len: usize) -> Result<(), UploadError> {
+        self.waited += waited;
+        self.received += len as u64;
+        if self.waited < self.min.window {
+            return Ok(());
+        }
+
+        let expected = self.min.bytes_per_sec as f64 * self.waited.as_secs_f64();
+        if (self.received as f64) < expected {
+            return Err(UploadError::TooSlow(self.min.bytes_per_sec));
+        }
+        self.waited = Duration::ZERO;
+        self.received = 0;
+        Ok(())
+    }
+}
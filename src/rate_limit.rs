@@ -0,0 +1,65 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use crate::config::AuthFailureLimit;
+
+/// Counts failed logins per client address so a client that keeps guessing
+/// is turned away before it can cost another argon2 verification. A
+/// successful login does not reset the count, so one valid account cannot be
+/// used to keep guessing at others.
+pub(crate) struct AuthLimiter {
+    limit: Option<AuthFailureLimit>,
+    /// Recovered if poisoned: at worst a panicking holder's client loses or
+    /// keeps one counted failure.
+    failures: Mutex<HashMap<IpAddr, FailureWindow>>,
+}
+
+struct FailureWindow {
+    started: Instant,
+    count: u32,
+}
+
+impl AuthLimiter {
+    pub(crate) fn new(limit: Option<AuthFailureLimit>) -> Self {
+        Self {
+            limit,
+            failures: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// How long `client` must wait before its next attempt, if it has used
+    /// up its failures for the current window.
+    pub(crate) fn retry_after(&self, client: IpAddr) -> Option<Duration> {
+        let limit = self.limit?;
+        let failures = self.failures.lock().unwrap_or_else(PoisonError::into_inner);
+        let window = failures.get(&client)?;
+        let elapsed = window.started.elapsed();
+        (window.count >= limit.max_failures && elapsed < limit.window)
+            .then(|| limit.window - elapsed)
+    }
+
+    pub(crate) fn record_failure(&self, client: IpAddr) {
+        let Some(limit) = self.limit else {
+            return;
+        };
+        let mut failures = self.failures.lock().unwrap_or_else(PoisonError::into_inner);
+        if !failures.contains_key(&client) {
+            // Forget clients whose window has closed so the map only holds
+            // recent offenders.
+            failures.retain(|_, window| window.started.elapsed() < limit.window);
+        }
+        let window = failures.entry(client).or_insert(FailureWindow {
+            started: Instant::now(),
+            count: 0,
+        });
+        if window.started.elapsed() >= limit.window {
+            window.started = Instant::now();
+            window.count = 0;
+        }
+        window.count += 1;
+    }
+}
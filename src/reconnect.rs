@@ -0,0 +1,131 @@
+use std::{collections::VecDeque, ops::RangeInclusive, time::Duration};
+
+use axum::http::{HeaderMap, header};
+use bytes::Bytes;
+use tokio::sync::{mpsc, oneshot};
+use tracing::info;
+
+use crate::{AppState, ChunkSender, StreamSource, memory::Chunk};
+
+/// The most recently relayed bytes of a live upload, kept so a downloader
+/// that drops can reconnect and pick up where its copy ends. Holds up to
+/// `capacity` bytes, plus the newest chunk however large it is.
+pub(crate) struct Replay {
+    /// Offset in the upload of the first byte held.
+    start: u64,
+    chunks: VecDeque<Bytes>,
+    len: usize,
+    capacity: usize,
+}
+
+impl Replay {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            start: 0,
+            chunks: VecDeque::new(),
+            len: 0,
+            capacity,
+        }
+    }
+
+    pub(crate) fn push(&mut self, bytes: Bytes) {
+        self.len += bytes.len();
+        self.chunks.push_back(bytes);
+        while self.len > self.capacity && self.chunks.len() > 1 {
+            let dropped = self.chunks.pop_front().expect("checked above");
+            self.start += dropped.len() as u64;
+            self.len -= dropped.len();
+        }
+    }
+
+    /// Offsets a downloader can resume from: any byte still held, or the
+    /// end of what has been relayed.
+    fn available(&self) -> RangeInclusive<u64> {
+        self.start..=self.start + self.len as u64
+    }
+
+    /// The chunks from `offset` on, which must be in [`Self::available`].
+    fn since(&self, offset: u64) -> Vec<Bytes> {
+        let mut skip = (offset - self.start) as usize;
+        let mut chunks = Vec::with_capacity(self.chunks.len());
+        for chunk in &self.chunks {
+            if skip >= chunk.len() {
+                skip -= chunk.len();
+                continue;
+            }
+            chunks.push(chunk.slice(skip..));
+            skip = 0;
+        }
+        chunks
+    }
+}
+
+/// Offered to the next download of a live stream whose downloader dropped.
+pub(crate) struct ResumePoint {
+    pub(crate) available: RangeInclusive<u64>,
+    /// Tells the upload which offset the new downloader resumes from.
+    pub(crate) offset_tx: oneshot::Sender<u64>,
+}
+
+/// The offset a resuming download asks for with `Range: bytes=N-`, or `0`
+/// without one. `None` for any other kind of range.
+pub(crate) fn requested_offset(headers: &HeaderMap) -> Option<u64> {
+    let Some(range) = headers.get(header::RANGE) else {
+        return Some(0);
+    };
+    range
+        .to_str()
+        .ok()?
+        .trim()
+        .strip_prefix("bytes=")?
+        .strip_suffix('-')?
+        .parse()
+        .ok()
+}
+
+/// Holds a live upload whose only downloader dropped for up to `window`, so
+/// that a new download of `filename` can resume from any offset `replay`
+/// still covers. Returns the sender feeding the new downloader, already
+/// caught up, or `None` if nobody came back in time.
+pub(crate) async fn await_downloader(
+    state: &AppState,
+    filename: &str,
+    replay: &Replay,
+    window: Duration,
+) -> Option<ChunkSender> {
+    'offer: loop {
+        let (sender, offset_rx) = {
+            let mut stream_data = state.streams.get_mut(filename)?;
+            let StreamSource::Live(live) = &mut stream_data.source else {
+                return None;
+            };
+            let (sender, receiver) = mpsc::channel(live.buffer_frames);
+            let (offset_tx, offset_rx) = oneshot::channel();
+            live.receivers.push(receiver);
+            live.resume = Some(ResumePoint {
+                available: replay.available(),
+                offset_tx,
+            });
+            (sender, offset_rx)
+        };
+        info!(%filename, ?window, "Download client disconnected. Holding the upload for a reconnect.");
+
+        let offset = tokio::select! {
+            offset = tokio::time::timeout(window, offset_rx) => offset,
+            _ = state.shutdown.cancelled() => return None,
+        };
+        let Ok(Ok(offset)) = offset else {
+            info!(%filename, "No download client reconnected in time");
+            return None;
+        };
+
+        info!(%filename, offset, "Download client reconnected");
+        for bytes in replay.since(offset) {
+            let chunk = Chunk::charged(bytes, state.memory_budget.as_ref()).await;
+            if sender.send(Ok(chunk)).await.is_err() {
+                continue 'offer;
+            }
+        }
+        return Some(sender);
+    }
+}
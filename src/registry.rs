@@ -0,0 +1,153 @@
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use dashmap::{
+    DashMap,
+    iter::Iter,
+    mapref::{
+        entry::Entry,
+        one::{Ref, RefMut},
+    },
+};
+
+use subtle::ConstantTimeEq;
+
+use crate::{StreamData, StreamSource};
+
+/// Registered streams keyed by filename. The map is sharded so transfers of
+/// different files don't contend on one lock. Entry guards lock their shard,
+/// so never hold one across an `.await`.
+#[derive(Default)]
+pub(crate) struct StreamRegistry {
+    streams: DashMap<String, StreamData>,
+    /// Kept in step with `streams`, so the concurrency limit can be checked
+    /// without locking every shard.
+    len: AtomicUsize,
+}
+
+/// Why [`StreamRegistry::register`] turned a new stream away.
+pub(crate) enum Refusal {
+    /// The filename is in use.
+    Taken(Holder),
+    /// `limit` streams are already registered.
+    Full { limit: usize },
+}
+
+/// What holds a filename a new stream was refused for.
+pub(crate) enum Holder {
+    Upload {
+        age: Duration,
+        downloader_connected: bool,
+        /// Still waiting for its downloaders, so the stale-upload reaper
+        /// may evict it.
+        waiting: bool,
+    },
+    Stored,
+    Reservation,
+}
+
+impl StreamRegistry {
+    /// Registers `stream_data` under `filename` unless the name is taken or
+    /// `limit` streams are already registered. Both are checked in the same
+    /// step as the insert. A name held by the reservation `reservation`
+    /// is taken over, without counting against the limit again.
+    pub(crate) fn register(
+        &self,
+        filename: String,
+        limit: Option<usize>,
+        reservation: Option<&str>,
+        stream_data: StreamData,
+    ) -> Result<(), Refusal> {
+        match self.streams.entry(filename) {
+            Entry::Occupied(mut existing) => match &existing.get().source {
+                StreamSource::Reserved(token)
+                    if reservation.is_some_and(|reservation| {
+                        bool::from(reservation.as_bytes().ct_eq(token.as_bytes()))
+                    }) =>
+                {
+                    existing.insert(stream_data);
+                    Ok(())
+                }
+                StreamSource::Reserved(_) => Err(Refusal::Taken(Holder::Reservation)),
+                StreamSource::Spooled(_) => Err(Refusal::Taken(Holder::Stored)),
+                StreamSource::Live(live) => Err(Refusal::Taken(Holder::Upload {
+                    age: existing.get().stats.started.elapsed(),
+                    downloader_connected: live.connected_downloaders() > 0,
+                    waiting: live.ready_tx.is_some(),
+                })),
+                StreamSource::Spooling => Err(Refusal::Taken(Holder::Upload {
+                    age: existing.get().stats.started.elapsed(),
+                    downloader_connected: false,
+                    waiting: false,
+                })),
+            },
+            Entry::Vacant(slot) => {
+                let reserved = self
+                    .len
+                    .fetch_update(Ordering::AcqRel, Ordering::Acquire, |len| {
+                        limit.is_none_or(|limit| len < limit).then_some(len + 1)
+                    });
+                if reserved.is_err() {
+                    return Err(Refusal::Full {
+                        limit: limit.unwrap_or_default(),
+                    });
+                }
+                slot.insert(stream_data);
+                Ok(())
+            }
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len.load(Ordering::Acquire)
+    }
+
+    /// Whether an upload, rather than just a reservation, holds `filename`.
+    pub(crate) fn has_upload(&self, filename: &str) -> bool {
+        self.streams
+            .get(filename)
+            .is_some_and(|entry| !matches!(entry.source, StreamSource::Reserved(_)))
+    }
+
+    pub(crate) fn get(&self, filename: &str) -> Option<Ref<'_, String, StreamData>> {
+        self.streams.get(filename)
+    }
+
+    pub(crate) fn get_mut(&self, filename: &str) -> Option<RefMut<'_, String, StreamData>> {
+        self.streams.get_mut(filename)
+    }
+
+    pub(crate) fn iter(&self) -> Iter<'_, String, StreamData> {
+        self.streams.iter()
+    }
+
+    pub(crate) fn remove(&self, filename: &str) -> Option<StreamData> {
+        self.remove_if(filename, |_| true)
+    }
+
+    /// Removes `filename` if `predicate` holds for its entry.
+    pub(crate) fn remove_if(
+        &self,
+        filename: &str,
+        predicate: impl FnOnce(&StreamData) -> bool,
+    ) -> Option<StreamData> {
+        let (_, stream_data) = self
+            .streams
+            .remove_if(filename, |_, stream_data| predicate(stream_data))?;
+        self.len.fetch_sub(1, Ordering::AcqRel);
+        Some(stream_data)
+    }
+
+    /// Keeps only the entries `keep` returns `true` for.
+    pub(crate) fn retain(&self, mut keep: impl FnMut(&str, &mut StreamData) -> bool) {
+        self.streams.retain(|filename, stream_data| {
+            let kept = keep(filename, stream_data);
+            if !kept {
+                self.len.fetch_sub(1, Ordering::AcqRel);
+            }
+            kept
+        });
+    }
+}
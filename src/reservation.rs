@@ -0,0 +1,137 @@
+use std::{sync::Arc, time::Duration};
+
+use axum::{
+    Json,
+    body::Body,
+    extract::{ConnectInfo, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use rand_core::{OsRng, RngCore};
+use tokio_util::sync::CancellationToken;
+use tracing::info;
+
+use crate::{
+    AppState, ClientAddr, StreamData, StreamMeta, StreamSource, StreamStats,
+    auth::{Permission, auth_error_response, authenticate_user, extract_credentials},
+    filename::{Disposition, sanitize_filename},
+    invalid_filename_response,
+    metadata::Metadata,
+};
+
+/// How long a reservation holds its filename for the upload to start.
+pub(crate) const RESERVATION_TTL: Duration = Duration::from_secs(60);
+
+/// Request header an upload names its reservation in.
+pub(crate) const RESERVATION_HEADER: &str = "x-reservation";
+
+/// Random bytes per reservation token.
+const RESERVATION_BYTES: usize = 16;
+
+/// The reservation an upload request claims, if any.
+pub(crate) fn requested(headers: &HeaderMap) -> Option<&str> {
+    headers.get(RESERVATION_HEADER)?.to_str().ok()
+}
+
+#[derive(serde::Deserialize)]
+pub(crate) struct ReserveRequest {
+    filename: String,
+}
+
+/// `POST /reserve`: claims `filename` for an upload that has not started
+/// yet, so the uploader learns it is taken before sending any of the body.
+/// The `PUT` that follows names the returned token in `X-Reservation`; the
+/// reservation lapses if no upload claims it within [`RESERVATION_TTL`].
+pub(crate) async fn reserve_handler(
+    State(state): State<AppState>,
+    ConnectInfo(ClientAddr(client)): ConnectInfo<ClientAddr>,
+    headers: HeaderMap,
+    Json(request): Json<ReserveRequest>,
+) -> Response<Body> {
+    let auth = match extract_credentials(&headers) {
+        Ok(auth) => auth,
+        Err(err) => return auth_error_response(&state, err),
+    };
+
+    if let Err(err) = authenticate_user(&state, client, &auth, Permission::Upload).await {
+        return auth_error_response(&state, err);
+    }
+
+    let filename = match sanitize_filename(&request.filename, state.max_filename_len) {
+        Ok(filename) => filename,
+        Err(message) => return invalid_filename_response(message),
+    };
+    if let Some(response) = state.forbidden_extension_response(&filename) {
+        return response;
+    }
+
+    let token = new_token();
+    if let Some(response) = reserve(&state, &filename, &token) {
+        return response;
+    }
+    info!(%filename, "Filename reserved");
+
+    (
+        StatusCode::CREATED,
+        Json(serde_json::json!({
+            "filename": filename,
+            "reservation": token,
+            "expires_in_secs": RESERVATION_TTL.as_secs(),
+        })),
+    )
+        .into_response()
+}
+
+/// A fresh reservation token.
+pub(crate) fn new_token() -> String {
+    let mut bytes = [0u8; RESERVATION_BYTES];
+    OsRng.fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// Holds `filename` for [`RESERVATION_TTL`] for the upload presenting
+/// `token`. Returns the response refusing it instead if it is taken.
+pub(crate) fn reserve(state: &AppState, filename: &str, token: &str) -> Option<Response<Body>> {
+    let stats = Arc::new(StreamStats::default());
+    let refused = state.register_stream(
+        filename,
+        &HeaderMap::new(),
+        StreamData {
+            meta: StreamMeta {
+                content_type: None,
+                content_encoding: None,
+                content_length: None,
+                disposition: Disposition::default(),
+                sha256: None,
+                metadata: Metadata::new(),
+            },
+            stats: stats.clone(),
+            cancel: CancellationToken::new(),
+            source: StreamSource::Reserved(token.to_owned()),
+        },
+    );
+    if refused.is_some() {
+        return refused;
+    }
+
+    let expiry_state = state.clone();
+    let expiry_filename = filename.to_owned();
+    tokio::spawn(async move {
+        tokio::select! {
+            _ = tokio::time::sleep(RESERVATION_TTL) => {}
+            _ = expiry_state.shutdown.cancelled() => return,
+        }
+        let lapsed = expiry_state
+            .streams
+            .remove_if(&expiry_filename, |stream_data| {
+                Arc::ptr_eq(&stream_data.stats, &stats)
+                    && matches!(stream_data.source, StreamSource::Reserved(_))
+            });
+        if lapsed.is_some() {
+            info!(filename = %expiry_filename, "Reservation lapsed");
+        }
+    });
+
+    None
+}
@@ -0,0 +1,693 @@
+use std::{
+    collections::HashMap,
+    io::{self, SeekFrom},
+    path::PathBuf,
+    sync::{Arc, Mutex, PoisonError, atomic::Ordering},
+    time::Duration,
+};
+
+use axum::{
+    Json,
+    body::Body,
+    extract::{ConnectInfo, Path, Query, State},
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use http_body_util::BodyStream;
+use rand_core::{OsRng, RngCore};
+use sha2::{Digest, Sha256};
+use tokio::{
+    fs::{File, OpenOptions},
+    io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt},
+    time::Instant,
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+use crate::{
+    AppState, ClientAddr, StreamData, StreamMeta, StreamSource, StreamStats, UploadError,
+    auth::{Permission, auth_error_response, authenticate_user, extract_credentials},
+    check_body_size, checksum,
+    events::EventKind,
+    filename::{Disposition, sanitize_filename},
+    invalid_filename_response, metadata, next_frame,
+    range::parse_content_range,
+    rate_floor::RateFloor,
+    spool::{self, Spool},
+    throttle::{self, Throttle},
+};
+
+/// Random bytes per session id; like download tokens, ids are the only
+/// thing tying a chunk to its upload.
+const SESSION_ID_BYTES: usize = 16;
+
+/// Response header carrying the bytes a session has committed so far.
+pub(crate) const UPLOAD_OFFSET_HEADER: &str = "upload-offset";
+
+/// Open resumable uploads, keyed by session id. Each one holds its filename
+/// as a `Spooling` entry until it is completed, deleted or left idle for the
+/// spool TTL.
+#[derive(Default)]
+pub(crate) struct UploadSessions {
+    sessions: Mutex<HashMap<String, Arc<UploadSession>>>,
+}
+
+struct UploadSession {
+    filename: String,
+    path: PathBuf,
+    stats: Arc<StreamStats>,
+    cancel: CancellationToken,
+    /// Held while a chunk is written, so chunks for one session never
+    /// interleave and a status check waits for the chunk in flight.
+    progress: tokio::sync::Mutex<Progress>,
+}
+
+struct Progress {
+    /// Bytes written to the spool file; the next chunk must start here.
+    offset: u64,
+    last_active: Instant,
+}
+
+impl UploadSessions {
+    fn get(&self, id: &str) -> Option<Arc<UploadSession>> {
+        self.lock().get(id).cloned()
+    }
+
+    fn insert(&self, session: UploadSession) -> (String, Arc<UploadSession>) {
+        let mut bytes = [0u8; SESSION_ID_BYTES];
+        OsRng.fill_bytes(&mut bytes);
+        let id = URL_SAFE_NO_PAD.encode(bytes);
+        let session = Arc::new(session);
+        self.lock().insert(id.clone(), session.clone());
+        (id, session)
+    }
+
+    /// The open session uploading `filename`, if any.
+    fn find(&self, filename: &str) -> Option<(String, Arc<UploadSession>)> {
+        self.lock()
+            .iter()
+            .find(|(_, session)| session.filename == filename)
+            .map(|(id, session)| (id.clone(), session.clone()))
+    }
+
+    fn remove(&self, id: &str) {
+        self.lock().remove(id);
+    }
+
+    /// Sessions are only inserted and removed whole, so the map is still
+    /// sound after a panic poisoned the lock.
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Arc<UploadSession>>> {
+        self.sessions.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub(crate) struct NewSessionRequest {
+    filename: String,
+    content_type: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+pub(crate) struct ChunkQuery {
+    offset: u64,
+}
+
+/// `POST /upload`: reserves `filename` for a resumable upload and returns
+/// the session id its chunks are sent to. Needs the spool, which holds the
+/// chunks until the upload is completed.
+pub(crate) async fn create_handler(
+    State(state): State<AppState>,
+    ConnectInfo(ClientAddr(client)): ConnectInfo<ClientAddr>,
+    headers: HeaderMap,
+    Json(request): Json<NewSessionRequest>,
+) -> Response<Body> {
+    let auth = match extract_credentials(&headers) {
+        Ok(auth) => auth,
+        Err(err) => return auth_error_response(&state, err),
+    };
+
+    if let Err(err) = authenticate_user(&state, client, &auth, Permission::Upload).await {
+        return auth_error_response(&state, err);
+    }
+
+    let Some(spool) = state.spool.clone() else {
+        return (
+            StatusCode::NOT_IMPLEMENTED,
+            "Resumable uploads require a spool directory",
+        )
+            .into_response();
+    };
+
+    let filename = match sanitize_filename(&request.filename, state.max_filename_len) {
+        Ok(filename) => filename,
+        Err(message) => return invalid_filename_response(message),
+    };
+    if let Some(response) = state.forbidden_extension_response(&filename) {
+        return response;
+    }
+    let content_type = match request.content_type.map(|value| value.parse()).transpose() {
+        Ok(content_type) => content_type,
+        Err(_) => return (StatusCode::BAD_REQUEST, "Invalid content_type").into_response(),
+    };
+    let meta = match session_meta(&state, &filename, &headers, content_type) {
+        Ok(meta) => meta,
+        Err(refusal) => return refusal.into_response(),
+    };
+
+    let id = match open_session(&state, &spool, &filename, meta, &headers, auth.username()).await {
+        Ok((id, _)) => id,
+        Err(response) => return response,
+    };
+
+    (
+        StatusCode::CREATED,
+        Json(serde_json::json!({
+            "id": id,
+            "url": format!("{}/upload/{id}", state.base_path),
+            "filename": filename,
+            "offset": 0,
+        })),
+    )
+        .into_response()
+}
+
+/// What downloads of a session's file will be sent, from the request that
+/// opened it, or how to refuse that request.
+fn session_meta(
+    state: &AppState,
+    filename: &str,
+    headers: &HeaderMap,
+    content_type: Option<HeaderValue>,
+) -> Result<StreamMeta, (StatusCode, &'static str)> {
+    let disposition =
+        Disposition::requested(headers).map_err(|message| (StatusCode::BAD_REQUEST, message))?;
+    let metadata = metadata::collect(headers, &state.metadata)
+        .map_err(|message| (metadata::TOO_LARGE, message))?;
+    Ok(StreamMeta {
+        content_type: content_type.or_else(|| state.content_types.guess(filename)),
+        content_encoding: None,
+        content_length: None,
+        disposition,
+        sha256: None,
+        metadata,
+    })
+}
+
+/// Registers `filename` for a new session with an empty spool file and
+/// returns its id, or the response refusing it.
+async fn open_session(
+    state: &AppState,
+    spool: &Spool,
+    filename: &str,
+    meta: StreamMeta,
+    headers: &HeaderMap,
+    uploader: &str,
+) -> Result<(String, Arc<UploadSession>), Response<Body>> {
+    let quota = match state.upload_quotas.admit(Some(uploader), None) {
+        Ok(quota) => quota,
+        Err(error) => {
+            warn!(%filename, uploader, %error, "Resumable upload rejected: over quota");
+            return Err((error.status(), format!("Upload failed: {error}")).into_response());
+        }
+    };
+
+    let path = spool.new_path();
+    if let Err(error) = File::create(&path).await {
+        error!(%filename, %error, "Failed to create spool file");
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to create upload session",
+        )
+            .into_response());
+    }
+
+    let stats = Arc::new(StreamStats::with_quota(quota));
+    let cancel = CancellationToken::new();
+    let refused = state.register_stream(
+        filename,
+        headers,
+        StreamData {
+            meta,
+            stats: stats.clone(),
+            cancel: cancel.clone(),
+            source: StreamSource::Spooling,
+        },
+    );
+    if let Some(response) = refused {
+        spool::remove_spool_file(&path).await;
+        return Err(response);
+    }
+
+    let (id, session) = state.upload_sessions.insert(UploadSession {
+        filename: filename.to_owned(),
+        path,
+        stats,
+        cancel,
+        progress: tokio::sync::Mutex::new(Progress {
+            offset: 0,
+            last_active: Instant::now(),
+        }),
+    });
+
+    state.upload_waiters.notify(filename);
+    state.metrics.record_upload();
+    state.events.publish(EventKind::UploadStarted, filename);
+    info!(%filename, "Resumable upload session opened");
+    Ok((id, session))
+}
+
+/// `HEAD /upload/{id}`: reports the committed offset in `Upload-Offset`, so
+/// an interrupted client knows where to resume. Waits for a chunk still
+/// being written.
+pub(crate) async fn status_handler(
+    State(state): State<AppState>,
+    ConnectInfo(ClientAddr(client)): ConnectInfo<ClientAddr>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> Response<Body> {
+    let session = match authorized_session(&state, client, &id, &headers).await {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+
+    let offset = session.progress.lock().await.offset;
+    offset_response(StatusCode::OK, offset, Body::empty())
+}
+
+/// `PUT /upload/{id}?offset=N`: appends the body to the session, provided
+/// `N` is exactly the committed offset. Whatever part of the body arrived is
+/// kept even if the request fails, and the response reports the new offset.
+pub(crate) async fn append_handler(
+    State(state): State<AppState>,
+    ConnectInfo(ClientAddr(client)): ConnectInfo<ClientAddr>,
+    Path(id): Path<String>,
+    Query(query): Query<ChunkQuery>,
+    headers: HeaderMap,
+    body: Body,
+) -> Response<Body> {
+    let session = match authorized_session(&state, client, &id, &headers).await {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+
+    let mut progress = session.progress.lock().await;
+    if session.cancel.is_cancelled() {
+        drop(progress);
+        discard(&state, &id, &session).await;
+        return unknown_session_response();
+    }
+    if query.offset != progress.offset {
+        // A duplicate or out-of-order chunk; the client should resume from
+        // the committed offset instead.
+        info!(filename = %session.filename, offset = query.offset, committed = progress.offset, "Chunk offset rejected");
+        return offset_response(
+            StatusCode::CONFLICT,
+            progress.offset,
+            Body::from("Chunk offset does not match the committed offset"),
+        );
+    }
+
+    let written = tokio::select! {
+        biased;
+        _ = session.cancel.cancelled() => Err(UploadError::Cancelled),
+        written = write_chunk(&state, &session, &mut progress.offset, body) => written,
+    };
+    progress.last_active = Instant::now();
+    let offset = progress.offset;
+    drop(progress);
+
+    match written {
+        Ok(()) => offset_response(StatusCode::OK, offset, Body::empty()),
+        Err(UploadError::Cancelled) => {
+            discard(&state, &id, &session).await;
+            (StatusCode::CONFLICT, "Upload was cancelled").into_response()
+        }
+        Err(error) => {
+            warn!(filename = %session.filename, %error, offset, "Chunk interrupted");
+            offset_response(
+                error.status(),
+                offset,
+                Body::from(format!("Chunk failed: {error}")),
+            )
+        }
+    }
+}
+
+/// `PATCH /{filename}` with `Content-Range: bytes a-b/total`: appends one
+/// segment to the resumable session uploading `filename`, opening one for a
+/// segment starting at `0`. A segment must start at the committed offset,
+/// else it gets `416` with that offset. The segment that brings the upload
+/// to its declared total completes it, storing the file as
+/// `POST /upload/{id}/complete` would; the total may be `*` until then.
+pub(crate) async fn patch_handler(
+    State(state): State<AppState>,
+    ConnectInfo(ClientAddr(client)): ConnectInfo<ClientAddr>,
+    Path(filename): Path<String>,
+    headers: HeaderMap,
+    body: Body,
+) -> Response<Body> {
+    let auth = match extract_credentials(&headers) {
+        Ok(auth) => auth,
+        Err(err) => return auth_error_response(&state, err),
+    };
+
+    if let Err(err) = authenticate_user(&state, client, &auth, Permission::Upload).await {
+        return auth_error_response(&state, err);
+    }
+
+    let Some(spool) = state.spool.clone() else {
+        return (
+            StatusCode::NOT_IMPLEMENTED,
+            "Segmented uploads require a spool directory",
+        )
+            .into_response();
+    };
+
+    let filename = match sanitize_filename(&filename, state.max_filename_len) {
+        Ok(filename) => filename,
+        Err(message) => return invalid_filename_response(message),
+    };
+    if let Some(response) = state.forbidden_extension_response(&filename) {
+        return response;
+    }
+    let Some((range, total)) = headers
+        .get(header::CONTENT_RANGE)
+        .and_then(parse_content_range)
+    else {
+        return (
+            StatusCode::BAD_REQUEST,
+            "PATCH needs a Content-Range of bytes start-end/total",
+        )
+            .into_response();
+    };
+    let declared_length = headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok()?.parse::<u64>().ok());
+    if declared_length.is_some_and(|declared_length| declared_length != range.len()) {
+        return (
+            StatusCode::BAD_REQUEST,
+            "Content-Length does not match the Content-Range",
+        )
+            .into_response();
+    }
+    let expected_sha256 = match checksum::expected_sha256(&headers) {
+        Ok(expected) => expected,
+        Err(message) => return (StatusCode::BAD_REQUEST, message).into_response(),
+    };
+
+    let (id, session) = match state.upload_sessions.find(&filename) {
+        Some(found) => found,
+        None if range.start == 0 => {
+            let content_type = headers.get(header::CONTENT_TYPE).cloned();
+            let meta = match session_meta(&state, &filename, &headers, content_type) {
+                Ok(meta) => meta,
+                Err(refusal) => return refusal.into_response(),
+            };
+            match open_session(&state, &spool, &filename, meta, &headers, auth.username()).await {
+                Ok(opened) => opened,
+                Err(response) => return response,
+            }
+        }
+        None => return unsatisfiable_segment_response(0),
+    };
+
+    let mut progress = session.progress.lock().await;
+    if session.cancel.is_cancelled() {
+        drop(progress);
+        discard(&state, &id, &session).await;
+        return (StatusCode::CONFLICT, "Upload was cancelled").into_response();
+    }
+    // Completed by a concurrent request while this one waited.
+    if state.upload_sessions.get(&id).is_none() {
+        return unsatisfiable_segment_response(progress.offset);
+    }
+    if range.start != progress.offset {
+        info!(%filename, start = range.start, committed = progress.offset, "Segment does not continue the upload");
+        return unsatisfiable_segment_response(progress.offset);
+    }
+
+    let written = tokio::select! {
+        biased;
+        _ = session.cancel.cancelled() => Err(UploadError::Cancelled),
+        written = write_chunk(&state, &session, &mut progress.offset, body) => written,
+    };
+    progress.last_active = Instant::now();
+    let offset = progress.offset;
+
+    match written {
+        Ok(()) if offset != range.end + 1 => offset_response(
+            StatusCode::BAD_REQUEST,
+            offset,
+            Body::from("Segment length does not match its Content-Range"),
+        ),
+        Ok(()) if total == Some(offset) => {
+            finish(&state, &spool, &id, &session, offset, expected_sha256).await
+        }
+        Ok(()) => offset_response(StatusCode::OK, offset, Body::empty()),
+        Err(UploadError::Cancelled) => {
+            drop(progress);
+            discard(&state, &id, &session).await;
+            (StatusCode::CONFLICT, "Upload was cancelled").into_response()
+        }
+        Err(error) => {
+            warn!(%filename, %error, offset, "Segment interrupted");
+            offset_response(
+                error.status(),
+                offset,
+                Body::from(format!("Segment failed: {error}")),
+            )
+        }
+    }
+}
+
+/// `416` for a segment that doesn't start where the upload has got to.
+fn unsatisfiable_segment_response(offset: u64) -> Response<Body> {
+    let mut response = offset_response(
+        StatusCode::RANGE_NOT_SATISFIABLE,
+        offset,
+        Body::from(format!("Segments must continue from byte {offset}")),
+    );
+    response.headers_mut().insert(
+        header::CONTENT_RANGE,
+        HeaderValue::from_str(&format!("bytes */{offset}")).expect("offset is a valid header"),
+    );
+    response
+}
+
+/// `POST /upload/{id}/complete`: stores the assembled file under the
+/// session's filename, where it can be downloaded like any spooled upload.
+/// Checks `X-Checksum-SHA256` or `Digest` against the whole file if sent.
+pub(crate) async fn complete_handler(
+    State(state): State<AppState>,
+    ConnectInfo(ClientAddr(client)): ConnectInfo<ClientAddr>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> Response<Body> {
+    let session = match authorized_session(&state, client, &id, &headers).await {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+    let expected_sha256 = match checksum::expected_sha256(&headers) {
+        Ok(expected) => expected,
+        Err(message) => return (StatusCode::BAD_REQUEST, message).into_response(),
+    };
+    let Some(spool) = state.spool.clone() else {
+        return unknown_session_response();
+    };
+
+    let progress = session.progress.lock().await;
+    // Completed by a concurrent request while this one waited.
+    if state.upload_sessions.get(&id).is_none() {
+        return unknown_session_response();
+    }
+    finish(
+        &state,
+        &spool,
+        &id,
+        &session,
+        progress.offset,
+        expected_sha256,
+    )
+    .await
+}
+
+/// Stores session `id`'s first `len` bytes under its filename, once its
+/// progress lock is held and it has been checked to still be open.
+async fn finish(
+    state: &AppState,
+    spool: &Spool,
+    id: &str,
+    session: &UploadSession,
+    len: u64,
+    expected_sha256: Option<[u8; 32]>,
+) -> Response<Body> {
+    state.upload_sessions.remove(id);
+    let filename = session.filename.clone();
+
+    let stored = match hash_file(session, len).await {
+        Ok(sha256) if expected_sha256.is_some_and(|expected| expected != sha256) => {
+            Err(UploadError::ChecksumMismatch)
+        }
+        Ok(sha256) => {
+            spool::store(
+                state,
+                spool,
+                &filename,
+                &session.stats,
+                session.path.clone(),
+                spool::Written {
+                    len,
+                    sha256,
+                    // Chunks are appended in place, so sessions are never
+                    // compressed.
+                    compressed: false,
+                },
+                None,
+            )
+            .await
+        }
+        Err(error) => Err(UploadError::Spool(error)),
+    };
+
+    match stored {
+        Ok(()) => {
+            state.transfer_completed(&filename, &session.stats, StatusCode::CREATED);
+            (StatusCode::CREATED, "Upload stored").into_response()
+        }
+        Err(error) => {
+            error!(%filename, %error, "Error completing resumable upload");
+            discard(state, id, session).await;
+            state.transfer_failed(&filename, &session.stats, error.status(), &error);
+            (error.status(), format!("Upload failed: {error}")).into_response()
+        }
+    }
+}
+
+/// Drops sessions whose filename was deleted, and those no chunk has
+/// reached for `ttl`, along with their partial files.
+pub(crate) async fn expire_sessions(state: &AppState, ttl: Duration) {
+    let sessions: Vec<_> = state
+        .upload_sessions
+        .lock()
+        .iter()
+        .map(|(id, session)| (id.clone(), session.clone()))
+        .collect();
+
+    for (id, session) in sessions {
+        let idle = match session.progress.try_lock() {
+            Ok(progress) => progress.last_active.elapsed() >= ttl,
+            // A chunk is being written right now.
+            Err(_) => false,
+        };
+        if idle || session.cancel.is_cancelled() {
+            info!(filename = %session.filename, "Resumable upload session expired");
+            discard(state, &id, &session).await;
+        }
+    }
+}
+
+async fn authorized_session(
+    state: &AppState,
+    client: std::net::SocketAddr,
+    id: &str,
+    headers: &HeaderMap,
+) -> Result<Arc<UploadSession>, Response<Body>> {
+    let auth = extract_credentials(headers).map_err(|err| auth_error_response(state, err))?;
+    authenticate_user(state, client, &auth, Permission::Upload)
+        .await
+        .map_err(|err| auth_error_response(state, err))?;
+
+    state
+        .upload_sessions
+        .get(id)
+        .ok_or_else(unknown_session_response)
+}
+
+async fn write_chunk(
+    state: &AppState,
+    session: &UploadSession,
+    offset: &mut u64,
+    body: Body,
+) -> Result<(), UploadError> {
+    let mut file = OpenOptions::new()
+        .write(true)
+        .open(&session.path)
+        .await
+        .map_err(UploadError::Spool)?;
+    // Drop anything past the committed offset that a failed write left.
+    file.set_len(*offset).await.map_err(UploadError::Spool)?;
+    file.seek(SeekFrom::Start(*offset))
+        .await
+        .map_err(UploadError::Spool)?;
+
+    let mut body_stream = BodyStream::new(body);
+    let mut throttle = state.max_transfer_rate.map(Throttle::new);
+    let mut rate_floor = state.min_upload_rate.map(RateFloor::new);
+    let result = async {
+        while let Some(frame) =
+            next_frame(&mut body_stream, state.idle_timeout, &mut rate_floor).await?
+        {
+            let frame = frame.map_err(|error| UploadError::Body(error.to_string()))?;
+            if let Ok(bytes) = frame.into_data() {
+                check_body_size(*offset + bytes.len() as u64, state.max_body_size)?;
+                session.stats.charge_quota(bytes.len())?;
+                throttle::pace(&mut throttle, bytes.len()).await;
+                file.write_all(&bytes).await.map_err(UploadError::Spool)?;
+                state.metrics.record_bytes(bytes.len());
+                session
+                    .stats
+                    .bytes_transferred
+                    .fetch_add(bytes.len() as u64, Ordering::Relaxed);
+                *offset += bytes.len() as u64;
+            }
+        }
+        Ok(())
+    }
+    .await;
+
+    file.flush().await.map_err(UploadError::Spool)?;
+    result
+}
+
+async fn hash_file(session: &UploadSession, len: u64) -> io::Result<[u8; 32]> {
+    let file = OpenOptions::new()
+        .write(true)
+        .read(true)
+        .open(&session.path)
+        .await?;
+    file.set_len(len).await?;
+
+    let mut reader = file.take(len);
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = reader.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher.finalize().into())
+}
+
+/// Forgets session `id` and frees its filename unless a `DELETE` already
+/// did.
+async fn discard(state: &AppState, id: &str, session: &UploadSession) {
+    state.upload_sessions.remove(id);
+    state.remove_stream(&session.filename, &session.stats);
+    spool::remove_spool_file(&session.path).await;
+}
+
+fn offset_response(status: StatusCode, offset: u64, body: Body) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(UPLOAD_OFFSET_HEADER, offset)
+        .header(header::CACHE_CONTROL, "no-store")
+        .body(body)
+        .expect("failed to build upload session response")
+}
+
+fn unknown_session_response() -> Response<Body> {
+    (StatusCode::NOT_FOUND, "Unknown upload session").into_response()
+}
@@ -0,0 +1,593 @@
+use std::{
+    io::{self, SeekFrom},
+    path::{Path, PathBuf},
+    sync::{Arc, atomic::Ordering},
+    time::Duration,
+};
+
+use async_compression::tokio::{bufread::ZstdDecoder, write::ZstdEncoder};
+use axum::{
+    body::Body,
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use http_body_util::BodyStream;
+use rand_core::{OsRng, RngCore};
+use sha2::{Digest, Sha256};
+use tokio::{
+    fs::File,
+    io::{AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWrite, AsyncWriteExt, BufReader},
+    sync::Notify,
+    time::Instant,
+};
+use tokio_util::{io::ReaderStream, sync::CancellationToken};
+use tracing::{error, info, warn};
+
+use crate::checksum::{self, CHECKSUM_HEADER, to_hex};
+use crate::events::EventKind;
+use crate::filename::content_disposition;
+use crate::quota::QuotaLease;
+use crate::range::{ByteRange, RangeRequest, parse_range};
+use crate::rate_floor::RateFloor;
+use crate::resumable;
+use crate::throttle::{self, Throttle};
+use crate::{
+    AppState, StreamData, StreamMeta, StreamSource, StreamStats, UploadError, check_body_size,
+    next_frame, panics,
+};
+
+const SPOOL_FILE_PREFIX: &str = "beam-";
+const SPOOL_FILE_EXTENSION: &str = "spool";
+
+/// Header an uploader sets to keep its file for this many seconds instead
+/// of the spool's TTL.
+const EXPIRES_IN_HEADER: &str = "x-expires-in";
+
+/// Directory that spooled uploads are written to, and how long they are
+/// kept there.
+pub(crate) struct Spool {
+    dir: PathBuf,
+    ttl: Duration,
+    /// Longest expiry an upload may ask for with `X-Expires-In`.
+    max_ttl: Duration,
+    /// Wakes the reaper when a file is stored with its own expiry, which may
+    /// come before the reaper's next sweep.
+    stored: Notify,
+    /// Whether uploads are written zstd-compressed.
+    compress: bool,
+}
+
+/// A finished upload stored in the spool.
+#[derive(Clone)]
+pub(crate) struct SpooledFile {
+    path: PathBuf,
+    /// Length of the upload as sent, whether or not it is stored compressed.
+    len: u64,
+    sha256: [u8; 32],
+    compressed: bool,
+    expires_at: Instant,
+}
+
+/// What was written to a spool file: the upload's length and digest as
+/// sent, and whether the file holds it zstd-compressed.
+pub(crate) struct Written {
+    pub(crate) len: u64,
+    pub(crate) sha256: [u8; 32],
+    pub(crate) compressed: bool,
+}
+
+impl Spool {
+    /// Creates `dir` if needed and deletes spool files an earlier run left
+    /// behind; their entries did not survive the restart.
+    pub(crate) fn open(
+        dir: PathBuf,
+        ttl: Duration,
+        max_ttl: Duration,
+        compress: bool,
+    ) -> io::Result<Self> {
+        std::fs::create_dir_all(&dir)?;
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if is_spool_file(&path) {
+                std::fs::remove_file(&path)?;
+            }
+        }
+        Ok(Self {
+            dir,
+            ttl,
+            max_ttl,
+            stored: Notify::new(),
+            compress,
+        })
+    }
+
+    /// How long the upload sending `headers` asked to be kept, clamped to
+    /// the configured maximum, or `None` to use the spool's TTL.
+    fn requested_ttl(&self, headers: &HeaderMap) -> Result<Option<Duration>, &'static str> {
+        let Some(value) = headers.get(EXPIRES_IN_HEADER) else {
+            return Ok(None);
+        };
+
+        value
+            .to_str()
+            .ok()
+            .and_then(|value| value.trim().parse::<u64>().ok())
+            .filter(|&secs| secs > 0)
+            .map(|secs| Some(Duration::from_secs(secs).min(self.max_ttl)))
+            .ok_or("X-Expires-In must be a positive number of seconds")
+    }
+
+    pub(crate) fn new_path(&self) -> PathBuf {
+        self.dir.join(format!(
+            "{SPOOL_FILE_PREFIX}{:016x}.{SPOOL_FILE_EXTENSION}",
+            OsRng.next_u64()
+        ))
+    }
+}
+
+fn is_spool_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext == SPOOL_FILE_EXTENSION)
+        && path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with(SPOOL_FILE_PREFIX))
+}
+
+/// Writes the upload body to a new spool file and registers it under
+/// `filename` once complete. The write runs in its own task so an uploader
+/// that hangs up mid-body still has its entry and partial file cleaned up.
+pub(crate) async fn upload(
+    state: &AppState,
+    spool: Arc<Spool>,
+    filename: String,
+    headers: &HeaderMap,
+    body: Body,
+    quota: Option<QuotaLease>,
+    cancel: CancellationToken,
+) -> Response<Body> {
+    let expires_in = match spool.requested_ttl(headers) {
+        Ok(expires_in) => expires_in,
+        Err(message) => return (StatusCode::BAD_REQUEST, message).into_response(),
+    };
+    // Uploads with a malformed digest are refused before this.
+    let expected_sha256 = checksum::expected_sha256(headers).unwrap_or_default();
+    let meta = StreamMeta::from_upload_headers(state, &filename, headers);
+    let declared_length = meta.content_length.filter(|_| state.enforce_content_length);
+    let stats = Arc::new(StreamStats::with_quota(quota));
+
+    let refused = state.register_stream(
+        &filename,
+        headers,
+        StreamData {
+            meta,
+            stats: stats.clone(),
+            cancel: cancel.clone(),
+            source: StreamSource::Spooling,
+        },
+    );
+    if let Some(response) = refused {
+        return response;
+    }
+
+    state.upload_waiters.notify(&filename);
+    state.metrics.record_upload();
+    state.events.publish(EventKind::UploadStarted, &filename);
+    info!(%filename, "Upload connection accepted. Spooling to disk.");
+
+    let path = spool.new_path();
+    let task_state = state.clone();
+    let task_filename = filename.clone();
+    let task_stats = stats.clone();
+    let task_path = path.clone();
+    let task = tokio::spawn(async move {
+        let (state, filename, stats, path) = (task_state, task_filename, task_stats, task_path);
+        let written = tokio::select! {
+            biased;
+            _ = cancel.cancelled() => Err(UploadError::Cancelled),
+            written = write_body(&path, spool.compress, body, &state, &stats, expected_sha256) => written,
+        };
+        let written = written.and_then(|written| match declared_length {
+            Some(declared) if written.len != declared => Err(UploadError::LengthMismatch(declared)),
+            _ => Ok(written),
+        });
+        let written = match written {
+            Ok(Written { len: 0, .. }) if state.reject_empty_uploads => Err(UploadError::Empty),
+            Ok(written) if written.len == 0 => {
+                info!(%filename, "Upload closed before sending any data; storing an empty file");
+                Ok(written)
+            }
+            written => written,
+        };
+
+        match written {
+            Ok(written) => {
+                store(&state, &spool, &filename, &stats, path, written, expires_in).await
+            }
+            Err(error) => {
+                error!(%filename, %error, "Error spooling upload");
+                state.remove_stream(&filename, &stats);
+                remove_spool_file(&path).await;
+                Err(error)
+            }
+        }
+    });
+
+    match task.await {
+        Ok(Ok(())) => {
+            state.transfer_completed(&filename, &stats, StatusCode::CREATED);
+            (StatusCode::CREATED, "Upload stored").into_response()
+        }
+        Ok(Err(error)) => {
+            state.transfer_failed(&filename, &stats, error.status(), &error);
+            (error.status(), format!("Upload failed: {error}")).into_response()
+        }
+        Err(_) => {
+            let response = panics::task_panicked();
+            state.transfer_failed(&filename, &stats, response.status(), "Upload task failed");
+            state.remove_stream(&filename, &stats);
+            remove_spool_file(&path).await;
+            response
+        }
+    }
+}
+
+/// Turns the `Spooling` entry owned by `stats` into a stored upload of the
+/// file at `path`, kept for `expires_in` or else the spool's TTL, or removes
+/// the file if the entry was deleted meanwhile.
+pub(crate) async fn store(
+    state: &AppState,
+    spool: &Spool,
+    filename: &str,
+    stats: &Arc<StreamStats>,
+    path: PathBuf,
+    written: Written,
+    expires_in: Option<Duration>,
+) -> Result<(), UploadError> {
+    if let Some(mut stream_data) = state.streams.get_mut(filename)
+        && Arc::ptr_eq(&stream_data.stats, stats)
+    {
+        let Written {
+            len,
+            sha256,
+            compressed,
+        } = written;
+        stream_data.meta.content_length = Some(len);
+        stream_data.source = StreamSource::Spooled(SpooledFile {
+            path,
+            len,
+            sha256,
+            compressed,
+            expires_at: Instant::now() + expires_in.unwrap_or(spool.ttl),
+        });
+        drop(stream_data);
+        if expires_in.is_some() {
+            spool.stored.notify_one();
+        }
+        info!(%filename, len, compressed, ?expires_in, "Upload spooled.");
+        return Ok(());
+    }
+
+    // Deleted while the last bytes were being written.
+    remove_spool_file(&path).await;
+    Err(UploadError::Cancelled)
+}
+
+/// Writes `body` to a new file at `path`, through a zstd encoder if
+/// `compress` is set.
+async fn write_body(
+    path: &Path,
+    compress: bool,
+    body: Body,
+    state: &AppState,
+    stats: &StreamStats,
+    expected_sha256: Option<[u8; 32]>,
+) -> Result<Written, UploadError> {
+    let file = File::create(path).await.map_err(UploadError::Spool)?;
+    let mut file: Box<dyn AsyncWrite + Send + Unpin> = if compress {
+        Box::new(ZstdEncoder::new(file))
+    } else {
+        Box::new(file)
+    };
+    let mut body_stream = BodyStream::new(body);
+    let mut len = 0;
+    let mut hasher = Sha256::new();
+    let mut throttle = state.max_transfer_rate.map(Throttle::new);
+    let mut rate_floor = state.min_upload_rate.map(RateFloor::new);
+
+    while let Some(frame) =
+        next_frame(&mut body_stream, state.idle_timeout, &mut rate_floor).await?
+    {
+        let frame = frame.map_err(|error| UploadError::Body(error.to_string()))?;
+        if let Ok(bytes) = frame.into_data() {
+            check_body_size(len + bytes.len() as u64, state.max_body_size)?;
+            stats.charge_quota(bytes.len())?;
+            throttle::pace(&mut throttle, bytes.len()).await;
+            file.write_all(&bytes).await.map_err(UploadError::Spool)?;
+            hasher.update(&bytes);
+            state.metrics.record_bytes(bytes.len());
+            stats
+                .bytes_transferred
+                .fetch_add(bytes.len() as u64, Ordering::Relaxed);
+            len += bytes.len() as u64;
+        }
+    }
+
+    let sha256: [u8; 32] = hasher.finalize().into();
+    if expected_sha256.is_some_and(|expected| expected != sha256) {
+        return Err(UploadError::ChecksumMismatch);
+    }
+
+    // Shutting down finishes the zstd frame; a plain file is just flushed.
+    file.shutdown().await.map_err(UploadError::Spool)?;
+    Ok(Written {
+        len,
+        sha256,
+        compressed: compress,
+    })
+}
+
+/// Streams a stored upload, honouring a single `Range` request, and
+/// suggests saving it as `shown_as`.
+pub(crate) async fn download(
+    state: &AppState,
+    filename: &str,
+    shown_as: &str,
+    file: SpooledFile,
+    meta: StreamMeta,
+    headers: &HeaderMap,
+) -> Response<Body> {
+    if file.expires_at <= Instant::now() {
+        return state.no_upload_response();
+    }
+    if let Some(response) = not_modified(headers, &file) {
+        info!(%filename, "Spooled upload not modified");
+        return response;
+    }
+
+    let range = match headers.get(header::RANGE) {
+        Some(value) => parse_range(value, file.len),
+        None => RangeRequest::Full,
+    };
+    let range = match range {
+        RangeRequest::Full => None,
+        RangeRequest::Partial(range) => Some(range),
+        RangeRequest::Unsatisfiable => {
+            return Response::builder()
+                .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                .header(header::CONTENT_RANGE, format!("bytes */{}", file.len))
+                .body(Body::empty())
+                .expect("failed to build 416 response");
+        }
+    };
+
+    let response = send(state, filename, shown_as, &file, &meta, range).await;
+    if response.status().is_success() {
+        state.metrics.record_download();
+        state
+            .events
+            .publish(EventKind::DownloaderConnected, filename);
+        info!(%filename, ?range, "Spooled download started");
+    }
+    response
+}
+
+/// Answers `?peek=N` with the first `limit` bytes of a stored upload, as a
+/// `206` slice of it. Peeks don't count as downloads.
+pub(crate) async fn peek(
+    state: &AppState,
+    filename: &str,
+    shown_as: &str,
+    file: SpooledFile,
+    meta: StreamMeta,
+    limit: u64,
+) -> Response<Body> {
+    if file.expires_at <= Instant::now() {
+        return state.no_upload_response();
+    }
+
+    // An empty file has no bytes to slice, so it is sent whole.
+    let range = (limit > 0 && file.len > 0).then(|| ByteRange {
+        start: 0,
+        end: limit.min(file.len) - 1,
+    });
+    info!(%filename, limit, "Spooled upload peeked");
+    send(state, filename, shown_as, &file, &meta, range).await
+}
+
+/// Sends `range` of a stored upload, or all of it.
+async fn send(
+    state: &AppState,
+    filename: &str,
+    shown_as: &str,
+    file: &SpooledFile,
+    meta: &StreamMeta,
+    range: Option<ByteRange>,
+) -> Response<Body> {
+    let (start, len) = range.map_or((0, file.len), |range| (range.start, range.len()));
+    let reader = match open_at(file, start).await {
+        Ok(reader) => reader,
+        Err(error) => {
+            error!(%filename, %error, "Failed to read spooled upload");
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to read stored upload",
+            )
+                .into_response();
+        }
+    };
+
+    let mut response = stored_headers(shown_as, file, meta)
+        .status(if range.is_some() {
+            StatusCode::PARTIAL_CONTENT
+        } else {
+            StatusCode::OK
+        })
+        .header(header::CONTENT_LENGTH, len);
+    if let Some(range) = range {
+        response = response.header(header::CONTENT_RANGE, range.content_range(file.len));
+    }
+
+    response
+        .body(Body::from_stream(throttle::paced(
+            ReaderStream::new(reader.take(len)),
+            state.max_transfer_rate,
+        )))
+        .expect("failed to build download response")
+}
+
+/// Opens a stored upload positioned `start` bytes into it as sent. A
+/// compressed file can't seek, so the bytes before `start` are decompressed
+/// and skipped.
+async fn open_at(file: &SpooledFile, start: u64) -> io::Result<Box<dyn AsyncRead + Send + Unpin>> {
+    let mut reader = File::open(&file.path).await?;
+    if !file.compressed {
+        if start > 0 {
+            reader.seek(SeekFrom::Start(start)).await?;
+        }
+        return Ok(Box::new(reader));
+    }
+
+    let mut decoder = ZstdDecoder::new(BufReader::new(reader));
+    let skipped = tokio::io::copy(&mut (&mut decoder).take(start), &mut tokio::io::sink()).await?;
+    if skipped < start {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(Box::new(decoder))
+}
+
+/// Answers `HEAD` for a stored upload with the headers a full `GET` would
+/// get.
+pub(crate) fn head(
+    filename: &str,
+    file: &SpooledFile,
+    meta: &StreamMeta,
+    headers: &HeaderMap,
+) -> Response<Body> {
+    if file.expires_at <= Instant::now() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    if let Some(response) = not_modified(headers, file) {
+        return response;
+    }
+
+    stored_headers(filename, file, meta)
+        .status(StatusCode::OK)
+        .header(header::CONTENT_LENGTH, file.len)
+        .body(Body::empty())
+        .expect("failed to build HEAD response")
+}
+
+/// The stored upload's entity tag: its SHA-256, which changes whenever a
+/// new upload replaces it.
+fn etag(file: &SpooledFile) -> String {
+    format!("\"{}\"", to_hex(&file.sha256))
+}
+
+/// `304 Not Modified` if the request's `If-None-Match` names the stored
+/// upload's entity tag, or is `*`. Tags are compared weakly, ignoring `W/`.
+fn not_modified(headers: &HeaderMap, file: &SpooledFile) -> Option<Response<Body>> {
+    let etag = etag(file);
+    let matches = headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag.strip_prefix("W/").unwrap_or(tag) == etag);
+    matches.then(|| {
+        Response::builder()
+            .status(StatusCode::NOT_MODIFIED)
+            .header(header::ETAG, etag)
+            .body(Body::empty())
+            .expect("failed to build 304 response")
+    })
+}
+
+fn stored_headers(
+    filename: &str,
+    file: &SpooledFile,
+    meta: &StreamMeta,
+) -> axum::http::response::Builder {
+    let sha256 = to_hex(&file.sha256);
+    let mut response = Response::builder()
+        .header(header::CONTENT_TYPE, meta.response_content_type())
+        .header(
+            header::CONTENT_DISPOSITION,
+            content_disposition(filename, meta.disposition),
+        )
+        .header(header::ACCEPT_RANGES, "bytes")
+        .header(header::ETAG, etag(file))
+        .header(CHECKSUM_HEADER, &sha256);
+    if let Some(content_encoding) = &meta.content_encoding {
+        response = response.header(header::CONTENT_ENCODING, content_encoding);
+    }
+    for (name, value) in &meta.metadata {
+        response = response.header(name, value);
+    }
+    response
+}
+
+/// Deletes expired spooled uploads, and resumable sessions left idle for the
+/// spool's TTL, every so often until the server shuts down. An upload kept
+/// for less than that is deleted as soon as it expires. Downloads already
+/// reading a deleted file keep their open handle.
+pub(crate) async fn run_reaper(state: AppState, spool: Arc<Spool>) {
+    let period = (spool.ttl / 2).clamp(Duration::from_millis(10), Duration::from_secs(30));
+    let mut ticker = tokio::time::interval(period);
+
+    loop {
+        let next_expiry = state
+            .streams
+            .iter()
+            .filter_map(|entry| match &entry.value().source {
+                StreamSource::Spooled(file) => Some(file.expires_at),
+                _ => None,
+            })
+            .min();
+        let expiry = async {
+            match next_expiry {
+                Some(expires_at) => tokio::time::sleep_until(expires_at).await,
+                None => std::future::pending().await,
+            }
+        };
+        tokio::select! {
+            _ = ticker.tick() => {}
+            _ = expiry => {}
+            // Look again for an earlier expiry than the one waited for.
+            _ = spool.stored.notified() => continue,
+            _ = state.shutdown.cancelled() => return,
+        }
+
+        let now = Instant::now();
+        let mut expired = Vec::new();
+        state
+            .streams
+            .retain(|filename, stream_data| match &stream_data.source {
+                StreamSource::Spooled(file) if file.expires_at <= now => {
+                    info!(%filename, "Spooled upload expired");
+                    expired.push(file.path.clone());
+                    false
+                }
+                _ => true,
+            });
+
+        for path in expired {
+            remove_spool_file(&path).await;
+        }
+        resumable::expire_sessions(&state, spool.ttl).await;
+    }
+}
+
+/// Deletes the file behind a spooled upload that was removed from the map.
+pub(crate) async fn discard(file: &SpooledFile) {
+    remove_spool_file(&file.path).await;
+}
+
+pub(crate) async fn remove_spool_file(path: &Path) {
+    match tokio::fs::remove_file(path).await {
+        Ok(()) => {}
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+        Err(error) => warn!(path = %path.display(), %error, "Failed to remove spool file"),
+    }
+}
@@ -0,0 +1,38 @@
+use std::time::Duration;
+
+use tracing::warn;
+
+use crate::{AppState, StreamSource};
+
+/// Every so often until the server shuts down, evicts live uploads that
+/// have waited longer than `max_age` for their downloaders. Their upload
+/// tasks are cancelled, and an entry whose task already died is freed.
+pub(crate) async fn run_reaper(state: AppState, max_age: Duration) {
+    let period = (max_age / 2).clamp(Duration::from_millis(10), Duration::from_secs(30));
+    let mut ticker = tokio::time::interval(period);
+
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {}
+            _ = state.shutdown.cancelled() => return,
+        }
+
+        state
+            .streams
+            .retain(|filename, stream_data| match &stream_data.source {
+                StreamSource::Live(live)
+                    if live.ready_tx.is_some()
+                        && stream_data.stats.started.elapsed() >= max_age =>
+                {
+                    warn!(
+                        %filename,
+                        age_secs = stream_data.stats.started.elapsed().as_secs(),
+                        "Evicting upload still waiting for its download client"
+                    );
+                    stream_data.cancel.cancel();
+                    false
+                }
+                _ => true,
+            });
+    }
+}
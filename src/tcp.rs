@@ -0,0 +1,63 @@
+use std::{io, net::SocketAddr, time::Duration};
+
+use axum::serve::Listener;
+use socket2::{SockRef, TcpKeepalive};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::debug;
+
+/// Socket options set on every accepted connection.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct TcpOptions {
+    pub(crate) nodelay: bool,
+    /// Idle time before the first keepalive probe, and between probes where
+    /// the platform allows setting it; `None` leaves keepalive off.
+    pub(crate) keepalive: Option<Duration>,
+}
+
+impl TcpOptions {
+    /// Applies the options to `stream`. Failing to is logged and otherwise
+    /// ignored; the connection works either way.
+    pub(crate) fn apply(&self, stream: &TcpStream) {
+        if let Err(error) = stream.set_nodelay(self.nodelay) {
+            debug!(%error, "Failed to set TCP_NODELAY");
+        }
+
+        let Some(keepalive) = self.keepalive else {
+            return;
+        };
+        let params = TcpKeepalive::new().with_time(keepalive);
+        #[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+        let params = params.with_interval(keepalive);
+        if let Err(error) = SockRef::from(stream).set_tcp_keepalive(&params) {
+            debug!(%error, "Failed to enable TCP keepalive");
+        }
+    }
+}
+
+/// Listener applying [`TcpOptions`] to each connection it accepts.
+pub(crate) struct TunedListener {
+    listener: TcpListener,
+    options: TcpOptions,
+}
+
+impl TunedListener {
+    pub(crate) fn new(listener: TcpListener, options: TcpOptions) -> Self {
+        Self { listener, options }
+    }
+}
+
+impl Listener for TunedListener {
+    type Io = TcpStream;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        // Axum's impl retries failed accepts, so this always yields a connection.
+        let (stream, addr) = Listener::accept(&mut self.listener).await;
+        self.options.apply(&stream);
+        (stream, addr)
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        Listener::local_addr(&self.listener)
+    }
+}
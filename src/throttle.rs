@@ -0,0 +1,68 @@
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures_util::{Stream, StreamExt, stream};
+use tokio::time::Instant;
+
+/// Token bucket pacing one transfer to a fixed number of bytes per second.
+/// It starts empty, so even a short transfer cannot burst past the rate,
+/// and saves up at most one second's worth while the transfer is idle.
+pub(crate) struct Throttle {
+    rate: u64,
+    /// Bytes that may be sent right now; negative while in debt.
+    budget: f64,
+    refilled: Instant,
+}
+
+impl Throttle {
+    pub(crate) fn new(rate: u64) -> Self {
+        Self {
+            rate,
+            budget: 0.0,
+            refilled: Instant::now(),
+        }
+    }
+
+    /// Takes `len` bytes from the budget, first waiting for as long as it
+    /// takes the bucket to cover them.
+    pub(crate) async fn consume(&mut self, len: usize) {
+        let now = Instant::now();
+        let rate = self.rate as f64;
+        self.budget =
+            (self.budget + now.duration_since(self.refilled).as_secs_f64() * rate).min(rate);
+        self.refilled = now;
+        self.budget -= len as f64;
+
+        if self.budget < 0.0 {
+            tokio::time::sleep(Duration::from_secs_f64(-self.budget / rate)).await;
+        }
+    }
+}
+
+/// Consumes `len` bytes from `throttle` if the transfer has one.
+pub(crate) async fn pace(throttle: &mut Option<Throttle>, len: usize) {
+    if let Some(throttle) = throttle {
+        throttle.consume(len).await;
+    }
+}
+
+/// Paces `chunks` to `rate` bytes per second, or passes them through.
+pub(crate) fn paced<S, E>(
+    chunks: S,
+    rate: Option<u64>,
+) -> impl Stream<Item = Result<Bytes, E>> + Send + 'static
+where
+    S: Stream<Item = Result<Bytes, E>> + Send + 'static,
+    E: Send + 'static,
+{
+    stream::unfold(
+        (Box::pin(chunks), rate.map(Throttle::new)),
+        |(mut chunks, mut throttle)| async move {
+            let chunk = chunks.next().await?;
+            if let Ok(bytes) = &chunk {
+                pace(&mut throttle, bytes.len()).await;
+            }
+            Some((chunk, (chunks, throttle)))
+        },
+    )
+}
@@ -0,0 +1,122 @@
+use std::{
+    io::{self, ErrorKind},
+    net::SocketAddr,
+    path::Path,
+    sync::Arc,
+    time::Duration,
+};
+
+use axum::serve::Listener;
+use tokio::{net::TcpStream, sync::mpsc};
+use tokio_rustls::{
+    TlsAcceptor,
+    rustls::{
+        self,
+        pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject},
+    },
+    server::TlsStream,
+};
+use tracing::debug;
+
+/// A client that has not finished its handshake in this long is dropped.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Completed handshakes waiting for the server to pick them up.
+const HANDSHAKE_BACKLOG: usize = 64;
+
+/// Loads a PEM certificate chain and private key into a TLS acceptor.
+pub(crate) fn load_acceptor(cert_path: &Path, key_path: &Path) -> io::Result<TlsAcceptor> {
+    let invalid = |error: rustls::Error| io::Error::new(ErrorKind::InvalidData, error);
+    let invalid_pem = |path: &Path, error: rustls::pki_types::pem::Error| {
+        io::Error::new(
+            ErrorKind::InvalidData,
+            format!("{}: {error}", path.display()),
+        )
+    };
+
+    let certs = CertificateDer::pem_file_iter(cert_path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|error| invalid_pem(cert_path, error))?;
+    let key =
+        PrivateKeyDer::from_pem_file(key_path).map_err(|error| invalid_pem(key_path, error))?;
+
+    let mut config = rustls::ServerConfig::builder_with_provider(Arc::new(
+        rustls::crypto::ring::default_provider(),
+    ))
+    .with_safe_default_protocol_versions()
+    .map_err(invalid)?
+    .with_no_client_auth()
+    .with_single_cert(certs, key)
+    .map_err(invalid)?;
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+/// Listener that yields connections once their TLS handshake completes.
+/// Handshakes run in their own tasks so a slow client cannot hold up
+/// everyone else's `accept`.
+pub(crate) struct TlsListener {
+    handshaken: mpsc::Receiver<(TlsStream<TcpStream>, SocketAddr)>,
+    local_addr: SocketAddr,
+}
+
+impl TlsListener {
+    /// Starts accepting on `listener`, which is bound to `local_addr`.
+    pub(crate) fn new(
+        listener: impl Listener<Io = TcpStream, Addr = SocketAddr>,
+        local_addr: SocketAddr,
+        acceptor: TlsAcceptor,
+    ) -> Self {
+        let (tx, handshaken) = mpsc::channel(HANDSHAKE_BACKLOG);
+        tokio::spawn(accept_loop(listener, acceptor, tx));
+        Self {
+            handshaken,
+            local_addr,
+        }
+    }
+}
+
+/// Accepts TCP connections until the [`TlsListener`] is dropped, which also
+/// closes the socket so new connections are refused.
+async fn accept_loop(
+    mut listener: impl Listener<Io = TcpStream, Addr = SocketAddr>,
+    acceptor: TlsAcceptor,
+    tx: mpsc::Sender<(TlsStream<TcpStream>, SocketAddr)>,
+) {
+    loop {
+        let (stream, addr) = tokio::select! {
+            accepted = listener.accept() => accepted,
+            _ = tx.closed() => return,
+        };
+
+        let acceptor = acceptor.clone();
+        let tx = tx.clone();
+        tokio::spawn(async move {
+            match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                Ok(Ok(stream)) => {
+                    let _ = tx.send((stream, addr)).await;
+                }
+                Ok(Err(error)) => debug!(%addr, %error, "TLS handshake failed"),
+                Err(_) => debug!(%addr, "TLS handshake timed out"),
+            }
+        });
+    }
+}
+
+impl Listener for TlsListener {
+    type Io = TlsStream<TcpStream>;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        match self.handshaken.recv().await {
+            Some(connection) => connection,
+            // The accept loop only exits once this listener is dropped.
+            None => std::future::pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        Ok(self.local_addr)
+    }
+}
@@ -0,0 +1,37 @@
+use std::collections::HashMap;
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use rand_core::{OsRng, RngCore};
+use tokio::sync::RwLock;
+
+/// Random bytes per token; 128 bits keeps links unguessable.
+const TOKEN_BYTES: usize = 16;
+
+/// One-time download links minted with `POST /new`, mapping each token to
+/// the filename it unlocks. A token is spent by the first download it
+/// starts; anything else leaves it usable.
+#[derive(Default)]
+pub(crate) struct TokenStore {
+    tokens: RwLock<HashMap<String, String>>,
+}
+
+impl TokenStore {
+    pub(crate) async fn mint(&self, filename: String) -> String {
+        let mut bytes = [0u8; TOKEN_BYTES];
+        OsRng.fill_bytes(&mut bytes);
+        let token = URL_SAFE_NO_PAD.encode(bytes);
+        self.tokens.write().await.insert(token.clone(), filename);
+        token
+    }
+
+    /// Removes `token` so no concurrent request can use it, returning the
+    /// filename it pointed at.
+    pub(crate) async fn take(&self, token: &str) -> Option<String> {
+        self.tokens.write().await.remove(token)
+    }
+
+    /// Puts back a token whose download did not start.
+    pub(crate) async fn restore(&self, token: String, filename: String) {
+        self.tokens.write().await.insert(token, filename);
+    }
+}
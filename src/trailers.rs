@@ -0,0 +1,68 @@
+use axum::http::{HeaderMap, HeaderName, HeaderValue, header};
+
+use crate::{StreamStats, checksum};
+
+/// Trailer carrying how many bytes the upload sent in all.
+pub(crate) const BYTES_TRAILER: HeaderName = HeaderName::from_static("x-bytes");
+
+/// Trailer ending a download whose upload failed, instead of the others.
+pub(crate) const ERROR_TRAILER: HeaderName = HeaderName::from_static("x-upload-error");
+
+/// Whether the downloader sent `TE: trailers`, the only form hyper will
+/// send trailers for.
+pub(crate) fn requested(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::TE)
+        .is_some_and(|value| value == "trailers")
+}
+
+/// Value of the `Trailer` header announcing what [`fields`] or [`failure`]
+/// sends.
+pub(crate) fn announcement() -> HeaderValue {
+    HeaderValue::from_static("x-bytes, x-checksum-sha256, digest, x-upload-error")
+}
+
+/// Trailer fields for a failed upload, carrying why it failed.
+pub(crate) fn failure(error: &axum::Error) -> HeaderMap {
+    let mut trailers = HeaderMap::new();
+    // Messages are beam's own, but keep the value well-formed regardless.
+    let message: String = error
+        .to_string()
+        .chars()
+        .map(|ch| {
+            if ch.is_ascii() && !ch.is_ascii_control() {
+                ch
+            } else {
+                '?'
+            }
+        })
+        .collect();
+    trailers.insert(
+        ERROR_TRAILER,
+        HeaderValue::from_str(&message).expect("sanitized above"),
+    );
+    trailers
+}
+
+/// Trailer fields for a finished upload: its total length, and its
+/// SHA-256 when one was worked out, both as hex and as an RFC 3230
+/// `Digest`.
+pub(crate) fn fields(stats: &StreamStats) -> HeaderMap {
+    let mut trailers = HeaderMap::new();
+    let bytes = stats
+        .bytes_transferred
+        .load(std::sync::atomic::Ordering::Relaxed);
+    trailers.insert(BYTES_TRAILER, HeaderValue::from(bytes));
+    if let Some(sha256) = stats.sha256.get() {
+        trailers.insert(
+            checksum::CHECKSUM_HEADER,
+            HeaderValue::from_str(&checksum::to_hex(sha256)).expect("hex is a valid header value"),
+        );
+        trailers.insert(
+            checksum::DIGEST_HEADER,
+            HeaderValue::from_str(&checksum::digest_value(sha256))
+                .expect("base64 is a valid header value"),
+        );
+    }
+    trailers
+}
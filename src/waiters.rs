@@ -0,0 +1,148 @@
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    sync::{Arc, Mutex, PoisonError},
+    time::Duration,
+};
+
+use axum::{
+    body::Body,
+    http::{HeaderMap, header},
+    response::{
+        IntoResponse, Response,
+        sse::{Event, KeepAlive, Sse},
+    },
+};
+use futures_util::stream;
+use tokio::sync::Notify;
+use tracing::info;
+
+use crate::AppState;
+
+/// Downloaders parked until an upload registers their filename, so a
+/// download can be started before its upload.
+#[derive(Default)]
+pub(crate) struct UploadWaiters {
+    /// Every critical section is one map operation, so a panic while
+    /// holding the lock can't leave the map half-updated and poisoning is
+    /// ignored.
+    waiters: Mutex<HashMap<String, Arc<Notify>>>,
+}
+
+impl UploadWaiters {
+    /// Wakes every downloader waiting for `filename`. Call after the upload
+    /// is in the streams map.
+    pub(crate) fn notify(&self, filename: &str) {
+        let notify = self
+            .waiters
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(filename);
+        if let Some(notify) = notify {
+            notify.notify_waiters();
+        }
+    }
+
+    fn subscribe(self: &Arc<Self>, filename: &str) -> Subscription {
+        let notify = self
+            .waiters
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(filename.to_owned())
+            .or_default()
+            .clone();
+        Subscription {
+            waiters: self.clone(),
+            filename: filename.to_owned(),
+            notify,
+        }
+    }
+}
+
+/// A downloader's interest in one filename; the registry entry is dropped
+/// with the last subscriber so abandoned waits don't accumulate.
+struct Subscription {
+    waiters: Arc<UploadWaiters>,
+    filename: String,
+    notify: Arc<Notify>,
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        let mut waiters = self
+            .waiters
+            .waiters
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        // One reference is ours; the other is the map's, if it still holds
+        // this same entry.
+        if waiters
+            .get(&self.filename)
+            .is_some_and(|notify| Arc::ptr_eq(notify, &self.notify))
+            && Arc::strong_count(&self.notify) == 2
+        {
+            waiters.remove(&self.filename);
+        }
+    }
+}
+
+/// Whether a download asked for an event stream rather than the file.
+pub(crate) fn accepts_event_stream(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|media_type| {
+            let media_type = media_type.split(';').next().unwrap_or_default().trim();
+            media_type.eq_ignore_ascii_case("text/event-stream")
+        })
+}
+
+/// Waits up to `timeout` for an upload of `filename` as an event stream,
+/// commenting every `interval` so the connection never looks idle, and
+/// ending with `ready` or `timeout`. The stream isn't reserved for the
+/// client; it downloads the file with the next request.
+pub(crate) fn heartbeat_response(
+    state: &AppState,
+    filename: String,
+    timeout: Duration,
+    interval: Duration,
+) -> Response<Body> {
+    let state = state.clone();
+    let outcome = stream::once(async move {
+        let event = if wait_for_upload(&state, &filename, timeout).await {
+            "ready"
+        } else {
+            info!(%filename, ?timeout, "Gave up waiting for an upload");
+            "timeout"
+        };
+        Ok::<_, Infallible>(Event::default().event(event).data(filename))
+    });
+
+    Sse::new(outcome)
+        .keep_alive(KeepAlive::new().interval(interval).text("waiting"))
+        .into_response()
+}
+
+/// Waits up to `timeout` for an upload of `filename` to be registered.
+/// Returns whether one was.
+pub(crate) async fn wait_for_upload(state: &AppState, filename: &str, timeout: Duration) -> bool {
+    let deadline = tokio::time::Instant::now() + timeout;
+
+    loop {
+        let subscription = state.upload_waiters.subscribe(filename);
+        let notified = subscription.notify.notified();
+        tokio::pin!(notified);
+        // Register before checking so an upload arriving in between still
+        // wakes us.
+        notified.as_mut().enable();
+
+        if state.streams.has_upload(filename) {
+            return true;
+        }
+        if tokio::time::timeout_at(deadline, notified).await.is_err() {
+            return false;
+        }
+    }
+}
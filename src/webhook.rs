@@ -0,0 +1,126 @@
+use std::{sync::atomic::Ordering, time::Duration};
+
+use axum::http::{Method, Request, StatusCode, Uri, header};
+use bytes::Bytes;
+use http_body_util::Full;
+use ring::hmac;
+use tracing::{info, warn};
+
+use crate::{
+    StreamStats, checksum,
+    config::Webhook,
+    events::EventKind,
+    https::{self, HttpsClient},
+};
+
+/// Header carrying the report's HMAC-SHA256 when a secret is configured.
+const SIGNATURE_HEADER: &str = "x-beam-signature";
+
+/// Deliveries tried per report before giving up on it.
+const ATTEMPTS: u32 = 3;
+
+/// Wait before the first retry, doubled before each one after.
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Time allowed for one delivery.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Posts transfer reports to the configured [`Webhook`].
+pub(crate) struct Notifier {
+    uri: Uri,
+    secret: Option<hmac::Key>,
+    client: HttpsClient<Full<Bytes>>,
+}
+
+#[derive(serde::Serialize)]
+struct Report<'a> {
+    event: EventKind,
+    filename: &'a str,
+    bytes: u64,
+    duration_ms: u64,
+    status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl Notifier {
+    pub(crate) fn new(webhook: &Webhook) -> Result<Self, String> {
+        let uri: Uri = webhook
+            .url
+            .parse()
+            .map_err(|error| format!("URL {}: {error}", webhook.url))?;
+        if !matches!(uri.scheme_str(), Some("http" | "https")) {
+            return Err(format!("URL {} must be http or https", webhook.url));
+        }
+        Ok(Self {
+            uri,
+            secret: webhook
+                .secret
+                .as_ref()
+                .map(|secret| hmac::Key::new(hmac::HMAC_SHA256, secret)),
+            client: https::client().map_err(|error| format!("TLS: {error}"))?,
+        })
+    }
+
+    /// Reports, in the background, how the upload of `filename` ended: with
+    /// its uploader answered `status`, and failing with `error` if it did.
+    pub(crate) fn notify(
+        &self,
+        filename: &str,
+        stats: &StreamStats,
+        status: StatusCode,
+        error: Option<String>,
+    ) {
+        let report = Report {
+            event: if error.is_none() {
+                EventKind::TransferCompleted
+            } else {
+                EventKind::TransferFailed
+            },
+            filename,
+            bytes: stats.bytes_transferred.load(Ordering::Relaxed),
+            duration_ms: stats.started.elapsed().as_millis() as u64,
+            status: status.as_u16(),
+            error,
+        };
+        let body = Bytes::from(serde_json::to_vec(&report).expect("report serializes"));
+        let signature = self.secret.as_ref().map(|key| {
+            let tag = hmac::sign(key, &body);
+            format!("sha256={}", checksum::to_hex(tag.as_ref()))
+        });
+
+        let filename = filename.to_owned();
+        let uri = self.uri.clone();
+        let client = self.client.clone();
+        tokio::spawn(async move {
+            let mut delay = RETRY_DELAY;
+            for attempt in 1..=ATTEMPTS {
+                let mut request = Request::builder()
+                    .method(Method::POST)
+                    .uri(uri.clone())
+                    .header(header::CONTENT_TYPE, "application/json");
+                if let Some(signature) = &signature {
+                    request = request.header(SIGNATURE_HEADER, signature);
+                }
+                let request = request
+                    .body(Full::new(body.clone()))
+                    .expect("failed to build webhook request");
+
+                let failure = match tokio::time::timeout(TIMEOUT, client.request(request)).await {
+                    Ok(Ok(response)) if response.status().is_success() => {
+                        info!(%filename, "Webhook notified");
+                        return;
+                    }
+                    Ok(Ok(response)) => format!("status {}", response.status()),
+                    Ok(Err(error)) => error.to_string(),
+                    Err(_) => "timed out".to_owned(),
+                };
+                warn!(%filename, attempt, %failure, "Webhook delivery failed");
+                if attempt < ATTEMPTS {
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                }
+            }
+        });
+    }
+}
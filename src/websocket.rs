@@ -0,0 +1,182 @@
+use axum::{
+    body::{Body, Bytes},
+    extract::{
+        ConnectInfo, Path, State,
+        ws::{CloseFrame, Message, WebSocket, WebSocketUpgrade, close_code},
+    },
+    http::HeaderMap,
+    response::Response,
+};
+use futures_util::{
+    SinkExt, StreamExt,
+    stream::{self, SplitStream},
+};
+use tokio_util::sync::CancellationToken;
+use tracing::info;
+
+use crate::{
+    AppState, ClientAddr,
+    auth::{Permission, auth_error_response, authenticate_user, extract_credentials},
+    filename::sanitize_filename,
+    invalid_filename_response, receive_upload, serve_download,
+};
+
+/// Longest close frame reason the protocol allows, in bytes.
+const MAX_CLOSE_REASON: usize = 123;
+
+/// `GET /ws/upload/{filename}`: uploads the binary messages sent over the
+/// socket, for clients that cannot stream a `PUT` body. An empty binary
+/// message (or a close frame) ends the upload; beam then closes the socket
+/// with `1000` on success or `4000` plus the HTTP status a `PUT` would have
+/// got, with the same message as the reason.
+pub(crate) async fn upload_handler(
+    State(state): State<AppState>,
+    ConnectInfo(ClientAddr(client)): ConnectInfo<ClientAddr>,
+    Path(filename): Path<String>,
+    headers: HeaderMap,
+    ws: WebSocketUpgrade,
+) -> Response<Body> {
+    let auth = match extract_credentials(&headers) {
+        Ok(auth) => auth,
+        Err(err) => return auth_error_response(&state, err),
+    };
+
+    if let Err(err) = authenticate_user(&state, client, &auth, Permission::Upload).await {
+        return auth_error_response(&state, err);
+    }
+
+    let filename = match sanitize_filename(&filename, state.max_filename_len) {
+        Ok(filename) => filename,
+        Err(message) => return invalid_filename_response(message),
+    };
+
+    ws.on_upgrade(move |socket| async move {
+        info!(%filename, "WebSocket upload connected");
+        let (mut sink, messages) = socket.split();
+        let response = receive_upload(
+            state,
+            filename,
+            &headers,
+            message_body(messages),
+            CancellationToken::new(),
+        )
+        .await;
+        // An uploader that sent a close frame has already hung up.
+        let _ = sink
+            .send(Message::Close(Some(close_frame(response).await)))
+            .await;
+    })
+}
+
+/// `GET /ws/download/{filename}`: relays the upload as binary messages, then
+/// closes with `1000`, or with `1011` if the upload failed partway. Requests
+/// the stream cannot serve are answered over HTTP without upgrading.
+pub(crate) async fn download_handler(
+    State(state): State<AppState>,
+    ConnectInfo(ClientAddr(client)): ConnectInfo<ClientAddr>,
+    Path(filename): Path<String>,
+    headers: HeaderMap,
+    ws: WebSocketUpgrade,
+) -> Response<Body> {
+    if !state.anonymous_downloads {
+        let auth = match extract_credentials(&headers) {
+            Ok(auth) => auth,
+            Err(err) => return auth_error_response(&state, err),
+        };
+
+        if let Err(err) = authenticate_user(&state, client, &auth, Permission::Download).await {
+            return auth_error_response(&state, err);
+        }
+    }
+
+    let filename = match sanitize_filename(&filename, state.max_filename_len) {
+        Ok(filename) => filename,
+        Err(message) => return invalid_filename_response(message),
+    };
+
+    let response = serve_download(&state, filename, None, &headers).await;
+    if !response.status().is_success() {
+        return response;
+    }
+    ws.on_upgrade(move |socket| send_body(socket, response.into_body()))
+}
+
+/// The upload body carried by `messages`. Reading stops once the previous
+/// message has been relayed, so a slow downloader holds back the socket just
+/// as it would a `PUT`.
+fn message_body(messages: SplitStream<WebSocket>) -> Body {
+    Body::from_stream(stream::unfold(Some(messages), |messages| async move {
+        let mut messages = messages?;
+        loop {
+            let error = match messages.next().await {
+                Some(Ok(Message::Binary(bytes))) if bytes.is_empty() => return None,
+                Some(Ok(Message::Binary(bytes))) => return Some((Ok(bytes), Some(messages))),
+                Some(Ok(Message::Close(_))) => return None,
+                Some(Ok(Message::Ping(_) | Message::Pong(_))) => continue,
+                Some(Ok(Message::Text(_))) => axum::Error::new("Expected binary messages"),
+                Some(Err(error)) => error,
+                None => axum::Error::new("WebSocket closed without ending the upload"),
+            };
+            return Some((Err::<Bytes, _>(error), None));
+        }
+    }))
+}
+
+async fn close_frame(response: Response<Body>) -> CloseFrame {
+    let status = response.status();
+    let code = if status.is_success() {
+        close_code::NORMAL
+    } else {
+        4000 + status.as_u16()
+    };
+    let message = axum::body::to_bytes(response.into_body(), 1024)
+        .await
+        .unwrap_or_default();
+    let mut reason = String::from_utf8_lossy(&message).into_owned();
+    truncate_on_char_boundary(&mut reason, MAX_CLOSE_REASON);
+
+    CloseFrame {
+        code,
+        reason: reason.into(),
+    }
+}
+
+fn truncate_on_char_boundary(text: &mut String, max: usize) {
+    if text.len() > max {
+        let end = (0..=max)
+            .rev()
+            .find(|&end| text.is_char_boundary(end))
+            .unwrap_or(0);
+        text.truncate(end);
+    }
+}
+
+async fn send_body(mut socket: WebSocket, body: Body) {
+    let mut chunks = body.into_data_stream();
+    while let Some(chunk) = chunks.next().await {
+        match chunk {
+            Ok(bytes) => {
+                if socket.send(Message::Binary(bytes)).await.is_err() {
+                    // Dropping the body tells the upload its downloader left.
+                    return;
+                }
+            }
+            Err(error) => {
+                let mut reason = error.to_string();
+                truncate_on_char_boundary(&mut reason, MAX_CLOSE_REASON);
+                let frame = CloseFrame {
+                    code: close_code::ERROR,
+                    reason: reason.into(),
+                };
+                let _ = socket.send(Message::Close(Some(frame))).await;
+                return;
+            }
+        }
+    }
+
+    let frame = CloseFrame {
+        code: close_code::NORMAL,
+        reason: "".into(),
+    };
+    let _ = socket.send(Message::Close(Some(frame))).await;
+}
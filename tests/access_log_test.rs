@@ -0,0 +1,218 @@
+mod common;
+
+use std::{
+    io,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::Result;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use beam::{ServerConfig, setup_server_with_config};
+use common::send_when_pending;
+use reqwest::StatusCode;
+use serde_json::Value;
+use tracing_subscriber::fmt::MakeWriter;
+
+const USERNAME: &str = "alice";
+const PASSWORD: &str = "correct-horse-battery";
+
+/// Collects everything the subscriber writes.
+#[derive(Clone, Default)]
+struct Captured(Arc<Mutex<Vec<u8>>>);
+
+impl Captured {
+    fn text(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
+
+    /// The `beam::access` events, as JSON objects.
+    fn access_events(&self) -> Vec<Value> {
+        self.text()
+            .lines()
+            .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+            .filter(|event| event["target"] == "beam::access")
+            .collect()
+    }
+}
+
+impl io::Write for Captured {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for Captured {
+    type Writer = Self;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+#[tokio::test]
+async fn access_log_records_who_did_what_and_never_the_password() -> Result<()> {
+    let logs = Captured::default();
+    let subscriber = tracing_subscriber::fmt()
+        .json()
+        .with_max_level(tracing::Level::TRACE)
+        .with_writer(logs.clone())
+        .finish();
+    // Tests run on a single-threaded runtime, so the server logs here too.
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+        .access_log(tracing::Level::DEBUG)
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let url = format!("http://127.0.0.1:{}/audit%20me.txt", addr.port());
+    let client = reqwest::Client::builder()
+        .user_agent("audit-test/1.0")
+        .build()?;
+
+    let upload = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .body("audited")
+            .send(),
+    );
+    let download = send_when_pending(client.get(&url).basic_auth(USERNAME, Some(PASSWORD))).await?;
+    assert_eq!(download.text().await?, "audited");
+    assert_eq!(upload.await??.status(), StatusCode::OK);
+
+    let refused = client
+        .get(&url)
+        .basic_auth(USERNAME, Some("wrong"))
+        .send()
+        .await?;
+    assert_eq!(refused.status(), StatusCode::UNAUTHORIZED);
+    server_handle.abort();
+
+    let events = logs.access_events();
+    let upload = events
+        .iter()
+        .find(|event| event["fields"]["method"] == "PUT")
+        .expect("upload was logged");
+    assert_eq!(upload["level"], "DEBUG");
+    let fields = &upload["fields"];
+    assert_eq!(fields["client"], "127.0.0.1");
+    assert_eq!(fields["username"], USERNAME);
+    assert_eq!(fields["path"], "/audit%20me.txt");
+    assert_eq!(fields["filename"], "audit me.txt");
+    assert_eq!(fields["status"], 200);
+    assert_eq!(fields["user_agent"], "audit-test/1.0");
+
+    let refused = events
+        .iter()
+        .find(|event| event["fields"]["status"] == 401)
+        .expect("refused download was logged");
+    assert!(refused["fields"].get("username").is_none());
+
+    let text = logs.text();
+    assert!(!text.contains(PASSWORD));
+    let header_value = STANDARD.encode(format!("{USERNAME}:{PASSWORD}"));
+    assert!(!text.contains(&header_value));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn access_log_redacts_resumable_upload_ids() -> Result<()> {
+    let logs = Captured::default();
+    let subscriber = tracing_subscriber::fmt()
+        .json()
+        .with_max_level(tracing::Level::TRACE)
+        .with_writer(logs.clone())
+        .finish();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let spool_dir = tempfile::tempdir()?;
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+        .spool_dir(spool_dir.path())
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let base_url = format!("http://127.0.0.1:{}", addr.port());
+    let client = reqwest::Client::new();
+
+    let session: Value = client
+        .post(format!("{base_url}/upload"))
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .json(&serde_json::json!({ "filename": "secret.txt" }))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    let session_path = session["url"].as_str().unwrap().to_owned();
+    let chunk = client
+        .put(format!("{base_url}{session_path}?offset=0"))
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .body("resumed")
+        .send()
+        .await?;
+    assert_eq!(chunk.status(), StatusCode::OK);
+    let complete = client
+        .post(format!("{base_url}{session_path}/complete"))
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .send()
+        .await?;
+    assert!(complete.status().is_success());
+    // Only the resumable routes hide their id; this one names a file.
+    client
+        .get(format!("{base_url}/ws/upload/kept.txt"))
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .send()
+        .await?;
+    server_handle.abort();
+
+    let paths: Vec<_> = logs
+        .access_events()
+        .iter()
+        .map(|event| event["fields"]["path"].as_str().unwrap().to_owned())
+        .collect();
+    assert!(paths.iter().any(|path| path == "/upload/<session>"));
+    assert!(
+        paths
+            .iter()
+            .any(|path| path == "/upload/<session>/complete")
+    );
+    assert!(paths.iter().any(|path| path == "/ws/upload/kept.txt"));
+    let id = session_path.rsplit('/').next().unwrap();
+    assert!(!logs.text().contains(id));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn access_log_can_be_turned_off() -> Result<()> {
+    let logs = Captured::default();
+    let subscriber = tracing_subscriber::fmt()
+        .json()
+        .with_max_level(tracing::Level::TRACE)
+        .with_writer(logs.clone())
+        .finish();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+        .access_log(None)
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let healthz = reqwest::get(format!("http://127.0.0.1:{}/healthz", addr.port())).await?;
+    assert_eq!(healthz.status(), StatusCode::OK);
+    server_handle.abort();
+
+    assert!(logs.access_events().is_empty());
+
+    Ok(())
+}
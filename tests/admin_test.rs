@@ -0,0 +1,76 @@
+mod common;
+
+use anyhow::Result;
+use beam::{ServerConfig, setup_server_with_config};
+use common::{send_when_pending, wait_for_stream};
+use reqwest::StatusCode;
+
+const USERNAME: &str = "alice";
+const PASSWORD: &str = "secret123";
+const ADMIN_USERNAME: &str = "root";
+const ADMIN_PASSWORD: &str = "hunter2hunter2";
+
+#[tokio::test]
+async fn admin_evicts_a_stuck_stream_and_frees_the_name() -> Result<()> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+        .admin_credentials(ADMIN_USERNAME, ADMIN_PASSWORD)
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let base_url = format!("http://localhost:{}", addr.port());
+    let url = format!("{base_url}/stuck.bin");
+    let evict_url = format!("{base_url}/admin/evict/stuck.bin");
+    let client = reqwest::Client::new();
+
+    let stuck_upload = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .body("never downloaded")
+            .send(),
+    );
+    wait_for_stream(&base_url, "stuck.bin").await;
+
+    let not_admin = client
+        .post(&evict_url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .send()
+        .await?;
+    assert_eq!(not_admin.status(), StatusCode::FORBIDDEN);
+
+    let evicted = client
+        .post(&evict_url)
+        .basic_auth(ADMIN_USERNAME, Some(ADMIN_PASSWORD))
+        .send()
+        .await?;
+    assert_eq!(evicted.status(), StatusCode::OK);
+    let evicted: serde_json::Value = evicted.json().await?;
+    assert_eq!(evicted["filename"], "stuck.bin");
+    assert_eq!(evicted["state"], "live");
+
+    assert_eq!(stuck_upload.await??.status(), StatusCode::CONFLICT);
+
+    let evicted_again = client
+        .post(&evict_url)
+        .basic_auth(ADMIN_USERNAME, Some(ADMIN_PASSWORD))
+        .send()
+        .await?;
+    assert_eq!(evicted_again.status(), StatusCode::NOT_FOUND);
+
+    let upload = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .body("second attempt")
+            .send(),
+    );
+    let download = send_when_pending(client.get(&url).basic_auth(USERNAME, Some(PASSWORD))).await?;
+    assert_eq!(download.status(), StatusCode::OK);
+    assert_eq!(download.text().await?, "second attempt");
+    assert_eq!(upload.await??.status(), StatusCode::OK);
+
+    server_handle.abort();
+
+    Ok(())
+}
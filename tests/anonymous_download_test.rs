@@ -0,0 +1,42 @@
+mod common;
+
+use anyhow::Result;
+use beam::{ServerConfig, setup_server_with_config};
+use common::send_when_pending;
+use reqwest::StatusCode;
+
+#[tokio::test]
+async fn anonymous_downloads_still_require_authenticated_uploads() -> Result<()> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials("alice", "secret123")
+        .anonymous_downloads(true)
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let base_url = format!("http://localhost:{}", addr.port());
+    let url = format!("{base_url}/public.txt");
+    let client = reqwest::Client::new();
+
+    let anonymous_upload = client.put(&url).body("nope").send().await?;
+    assert_eq!(anonymous_upload.status(), StatusCode::UNAUTHORIZED);
+
+    let upload = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth("alice", Some("secret123"))
+            .body("shared publicly")
+            .send(),
+    );
+
+    let download = send_when_pending(client.get(&url)).await?;
+    assert_eq!(download.status(), StatusCode::OK);
+    assert_eq!(download.text().await?, "shared publicly");
+    assert_eq!(upload.await??.status(), StatusCode::OK);
+
+    let dashboard = client.get(&base_url).send().await?.text().await?;
+    assert!(dashboard.contains("Anonymous downloads are enabled"));
+
+    server_handle.abort();
+
+    Ok(())
+}
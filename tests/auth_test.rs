@@ -0,0 +1,289 @@
+mod common;
+
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use beam::{
+    BeamError, DEFAULT_PASSWORD_HASHING, PasswordHashing, ServerConfig, setup_server_with_config,
+};
+use reqwest::{StatusCode, header};
+
+#[tokio::test]
+async fn each_configured_user_can_authenticate() -> Result<()> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials("alice", "secret123")
+        .credentials("bob", "hunter2")
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+
+    let client = reqwest::Client::new();
+    let url = format!("http://localhost:{}/missing.txt", addr.port());
+
+    for (username, password) in [("alice", "secret123"), ("bob", "hunter2")] {
+        let response = client
+            .get(&url)
+            .basic_auth(username, Some(password))
+            .send()
+            .await?;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND, "user {username}");
+    }
+
+    let wrong_password = client
+        .get(&url)
+        .basic_auth("alice", Some("hunter2"))
+        .send()
+        .await?;
+    assert_eq!(wrong_password.status(), StatusCode::UNAUTHORIZED);
+
+    let unknown_user = client
+        .get(&url)
+        .basic_auth("mallory", Some("secret123"))
+        .send()
+        .await?;
+    assert_eq!(unknown_user.status(), StatusCode::UNAUTHORIZED);
+
+    let dashboard = client
+        .get(format!("http://localhost:{}/", addr.port()))
+        .send()
+        .await?
+        .text()
+        .await?;
+    assert!(!dashboard.contains("alice"));
+    assert!(!dashboard.contains("bob"));
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn credentials_file_accepts_passwords_and_hashes() -> Result<()> {
+    use argon2::password_hash::{PasswordHasher, SaltString, rand_core::OsRng};
+    use std::io::Write;
+
+    let salt = SaltString::generate(&mut OsRng);
+    let carol_hash = argon2::Argon2::default()
+        .hash_password(b"pre-hashed", &salt)
+        .expect("hashing should succeed")
+        .to_string();
+
+    let mut file = tempfile::NamedTempFile::new()?;
+    writeln!(file, "# team credentials")?;
+    writeln!(file, "alice:with:colons")?;
+    writeln!(file)?;
+    writeln!(file, "carol:{carol_hash}")?;
+
+    let users = beam::load_credentials_file(file.path())?;
+    assert_eq!(users.len(), 2);
+    assert!(matches!(&users[1].1, beam::Secret::Hash(hash) if *hash == carol_hash));
+
+    let config = ServerConfig::builder().port(0).users(users).build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+
+    let client = reqwest::Client::new();
+    let url = format!("http://localhost:{}/missing.txt", addr.port());
+
+    for (username, password) in [("alice", "with:colons"), ("carol", "pre-hashed")] {
+        let response = client
+            .get(&url)
+            .basic_auth(username, Some(password))
+            .send()
+            .await?;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND, "user {username}");
+    }
+
+    let hash_as_password = client
+        .get(&url)
+        .basic_auth("carol", Some(&carol_hash))
+        .send()
+        .await?;
+    assert_eq!(hash_as_password.status(), StatusCode::UNAUTHORIZED);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[test]
+fn credentials_file_rejects_malformed_lines() -> Result<()> {
+    use std::io::Write;
+
+    let mut file = tempfile::NamedTempFile::new()?;
+    writeln!(file, "alice:secret")?;
+    writeln!(file, "no-separator")?;
+
+    let error = beam::load_credentials_file(file.path()).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    assert!(error.to_string().contains("line 2"), "{error}");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn failed_logins_are_delayed_within_the_configured_range() -> Result<()> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials("alice", "secret123")
+        .auth_failure_delay(Duration::from_millis(500)..=Duration::from_millis(600))
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+
+    let client = reqwest::Client::new();
+    let url = format!("http://localhost:{}/missing.txt", addr.port());
+
+    let started = Instant::now();
+    let failed = client
+        .get(&url)
+        .basic_auth("alice", Some("guess"))
+        .send()
+        .await?;
+    let failed_after = started.elapsed();
+    assert_eq!(failed.status(), StatusCode::UNAUTHORIZED);
+    assert!(
+        (Duration::from_millis(500)..Duration::from_secs(3)).contains(&failed_after),
+        "failure answered after {failed_after:?}"
+    );
+
+    let started = Instant::now();
+    let succeeded = client
+        .get(&url)
+        .basic_auth("alice", Some("secret123"))
+        .send()
+        .await?;
+    let succeeded_after = started.elapsed();
+    assert_eq!(succeeded.status(), StatusCode::NOT_FOUND);
+    assert!(
+        succeeded_after < Duration::from_millis(500),
+        "success answered after {succeeded_after:?}"
+    );
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn unauthorized_responses_name_the_configured_realm() -> Result<()> {
+    for (config, expected) in [
+        (ServerConfig::builder(), "Basic realm=\"beam\""),
+        (
+            ServerConfig::builder().auth_realm("Acme \"files\""),
+            "Basic realm=\"Acme \\\"files\\\"\"",
+        ),
+    ] {
+        let config = config.port(0).credentials("alice", "secret123").build();
+        let (addr, server_handle) = setup_server_with_config(config).await?;
+
+        let response = reqwest::Client::new()
+            .get(format!("http://localhost:{}/report.pdf", addr.port()))
+            .send()
+            .await?;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(response.headers()[header::WWW_AUTHENTICATE], expected);
+
+        server_handle.abort();
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn upload_and_download_credentials_are_kept_apart() -> Result<()> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .upload_credentials("producer", "write-secret")
+        .download_credentials("consumer", "read-secret")
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let client = reqwest::Client::new();
+    let url = format!("http://localhost:{}/feed.csv", addr.port());
+
+    let consumer_upload = client
+        .put(&url)
+        .basic_auth("consumer", Some("read-secret"))
+        .body("a,b\n")
+        .send()
+        .await?;
+    assert_eq!(consumer_upload.status(), StatusCode::FORBIDDEN);
+
+    let producer_download = client
+        .get(&url)
+        .basic_auth("producer", Some("write-secret"))
+        .send()
+        .await?;
+    assert_eq!(producer_download.status(), StatusCode::FORBIDDEN);
+
+    let upload = tokio::spawn({
+        let client = client.clone();
+        let url = url.clone();
+        async move {
+            client
+                .put(&url)
+                .basic_auth("producer", Some("write-secret"))
+                .body("a,b\n")
+                .send()
+                .await
+        }
+    });
+    let download =
+        common::send_when_pending(client.get(&url).basic_auth("consumer", Some("read-secret")))
+            .await?;
+    assert_eq!(download.status(), StatusCode::OK);
+    assert_eq!(download.text().await?, "a,b\n");
+    assert_eq!(upload.await??.status(), StatusCode::OK);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn passwords_hashed_with_custom_parameters_still_verify() -> Result<()> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials("alice", "secret123")
+        .password_hashing(PasswordHashing {
+            memory_kib: 8 * 1024,
+            iterations: 3,
+            parallelism: 2,
+        })
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+
+    let client = reqwest::Client::new();
+    let url = format!("http://localhost:{}/missing.txt", addr.port());
+
+    let authorized = client
+        .get(&url)
+        .basic_auth("alice", Some("secret123"))
+        .send()
+        .await?;
+    assert_eq!(authorized.status(), StatusCode::NOT_FOUND);
+
+    let wrong_password = client
+        .get(&url)
+        .basic_auth("alice", Some("secret124"))
+        .send()
+        .await?;
+    assert_eq!(wrong_password.status(), StatusCode::UNAUTHORIZED);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn invalid_hashing_parameters_fail_startup() {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials("alice", "secret123")
+        .password_hashing(PasswordHashing {
+            memory_kib: 1,
+            ..DEFAULT_PASSWORD_HASHING
+        })
+        .build();
+
+    let error = setup_server_with_config(config).await.unwrap_err();
+    assert!(matches!(error, BeamError::Credentials(_)), "{error}");
+}
@@ -0,0 +1,70 @@
+mod common;
+
+use anyhow::Result;
+use beam::{ServerConfig, setup_server_with_config};
+use common::send_when_pending;
+use reqwest::StatusCode;
+
+#[tokio::test]
+async fn routes_are_mounted_under_the_base_path() -> Result<()> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials("alice", "secret123")
+        .base_path("/beam/")
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let root = format!("http://localhost:{}", addr.port());
+    let url = format!("{root}/beam/file.txt");
+    let client = reqwest::Client::new();
+
+    let upload = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth("alice", Some("secret123"))
+            .body("behind a proxy")
+            .send(),
+    );
+    let download =
+        send_when_pending(client.get(&url).basic_auth("alice", Some("secret123"))).await?;
+    assert_eq!(download.status(), StatusCode::OK);
+    assert_eq!(download.text().await?, "behind a proxy");
+    assert_eq!(upload.await??.status(), StatusCode::OK);
+
+    let unprefixed = client
+        .get(format!("{root}/file.txt"))
+        .basic_auth("alice", Some("secret123"))
+        .send()
+        .await?;
+    assert_eq!(unprefixed.status(), StatusCode::NOT_FOUND);
+
+    let with_slash = client.get(format!("{root}/beam/")).send().await?;
+    assert_eq!(with_slash.status(), StatusCode::OK);
+
+    let dashboard = client.get(format!("{root}/beam")).send().await?;
+    assert_eq!(dashboard.status(), StatusCode::OK);
+    assert!(
+        dashboard
+            .text()
+            .await?
+            .contains("http://localhost:4000/beam/file.zip")
+    );
+
+    let minted: serde_json::Value = client
+        .post(format!("{root}/beam/new"))
+        .basic_auth("alice", Some("secret123"))
+        .json(&serde_json::json!({ "filename": "file.txt" }))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert!(
+        minted["url"]
+            .as_str()
+            .unwrap_or_default()
+            .starts_with("/beam/t/")
+    );
+
+    server_handle.abort();
+
+    Ok(())
+}
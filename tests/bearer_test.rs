@@ -0,0 +1,222 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Result;
+use axum::{Json, Router, routing::get};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use beam::{BearerPolicy, ServerConfig, setup_server_with_config};
+use reqwest::StatusCode;
+use ring::{
+    hmac,
+    rand::SystemRandom,
+    signature::{ECDSA_P256_SHA256_FIXED_SIGNING, EcdsaKeyPair, KeyPair},
+};
+use serde_json::{Value, json};
+
+const SECRET: &[u8] = b"shared-secret-from-the-auth-service";
+const AUDIENCE: &str = "beam";
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+fn claims(expires_in: i64, audience: &str) -> Value {
+    json!({
+        "sub": "svc-uploader",
+        "aud": audience,
+        "exp": now() as i64 + expires_in,
+    })
+}
+
+fn encode(part: &Value) -> String {
+    URL_SAFE_NO_PAD.encode(part.to_string())
+}
+
+fn hmac_token(claims: &Value) -> String {
+    let signing_input = format!("{}.{}", encode(&json!({ "alg": "HS256" })), encode(claims));
+    let tag = hmac::sign(
+        &hmac::Key::new(hmac::HMAC_SHA256, SECRET),
+        signing_input.as_bytes(),
+    );
+    format!("{signing_input}.{}", URL_SAFE_NO_PAD.encode(tag))
+}
+
+async fn list_streams(base_url: &str, token: &str) -> Result<StatusCode> {
+    Ok(reqwest::Client::new()
+        .get(format!("{base_url}/api/streams"))
+        .bearer_auth(token)
+        .send()
+        .await?
+        .status())
+}
+
+#[tokio::test]
+async fn hmac_bearer_tokens_are_checked_for_expiry_and_audience() -> Result<()> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials("alice", "secret123")
+        .bearer_auth(BearerPolicy::hmac(SECRET, AUDIENCE))
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let base_url = format!("http://localhost:{}", addr.port());
+    let client = reqwest::Client::new();
+
+    let valid = hmac_token(&claims(300, AUDIENCE));
+    assert_eq!(list_streams(&base_url, &valid).await?, StatusCode::OK);
+
+    let expired = hmac_token(&claims(-3600, AUDIENCE));
+    assert_eq!(
+        list_streams(&base_url, &expired).await?,
+        StatusCode::UNAUTHORIZED
+    );
+
+    let elsewhere = hmac_token(&claims(300, "another-service"));
+    assert_eq!(
+        list_streams(&base_url, &elsewhere).await?,
+        StatusCode::UNAUTHORIZED
+    );
+
+    let mut anonymous = claims(300, AUDIENCE);
+    anonymous["sub"] = json!("");
+    assert_eq!(
+        list_streams(&base_url, &hmac_token(&anonymous)).await?,
+        StatusCode::UNAUTHORIZED
+    );
+    anonymous.as_object_mut().unwrap().remove("sub");
+    assert_eq!(
+        list_streams(&base_url, &hmac_token(&anonymous)).await?,
+        StatusCode::UNAUTHORIZED
+    );
+
+    let mut forged = valid.clone();
+    forged.replace_range(forged.len() - 4.., "AAAA");
+    assert_eq!(
+        list_streams(&base_url, &forged).await?,
+        StatusCode::UNAUTHORIZED
+    );
+
+    // Basic credentials keep working alongside tokens.
+    let basic = client
+        .get(format!("{base_url}/api/streams"))
+        .basic_auth("alice", Some("secret123"))
+        .send()
+        .await?;
+    assert_eq!(basic.status(), StatusCode::OK);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn jwks_bearer_tokens_are_verified_with_the_published_key() -> Result<()> {
+    let rng = SystemRandom::new();
+    let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng)
+        .expect("failed to generate a P-256 key");
+    let key_pair = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref(), &rng)
+        .expect("generated key is valid");
+    let point = &key_pair.public_key().as_ref()[1..];
+    let jwks = json!({
+        "keys": [{
+            "kty": "EC",
+            "crv": "P-256",
+            "kid": "key-1",
+            "use": "sig",
+            "x": URL_SAFE_NO_PAD.encode(&point[..32]),
+            "y": URL_SAFE_NO_PAD.encode(&point[32..]),
+        }],
+    });
+    let issuer = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let jwks_url = format!("http://{}/.well-known/jwks.json", issuer.local_addr()?);
+    let issuer_handle = tokio::spawn(async move {
+        let app = Router::new().route("/.well-known/jwks.json", get(move || async { Json(jwks) }));
+        axum::serve(issuer, app).await
+    });
+
+    let sign = |claims: &Value| -> String {
+        let header = json!({ "alg": "ES256", "kid": "key-1" });
+        let signing_input = format!("{}.{}", encode(&header), encode(claims));
+        let signature = key_pair
+            .sign(&rng, signing_input.as_bytes())
+            .expect("failed to sign token");
+        format!("{signing_input}.{}", URL_SAFE_NO_PAD.encode(signature))
+    };
+
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials("alice", "secret123")
+        .bearer_auth(BearerPolicy::jwks(jwks_url, AUDIENCE))
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let base_url = format!("http://localhost:{}", addr.port());
+
+    let valid = sign(&claims(300, AUDIENCE));
+    assert_eq!(list_streams(&base_url, &valid).await?, StatusCode::OK);
+
+    let expired = sign(&claims(-3600, AUDIENCE));
+    assert_eq!(
+        list_streams(&base_url, &expired).await?,
+        StatusCode::UNAUTHORIZED
+    );
+
+    // A shared-secret token is no good against a key set.
+    assert_eq!(
+        list_streams(&base_url, &hmac_token(&claims(300, AUDIENCE))).await?,
+        StatusCode::UNAUTHORIZED
+    );
+
+    server_handle.abort();
+    issuer_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn a_failing_jwks_endpoint_is_not_retried_on_every_request() -> Result<()> {
+    let fetches = Arc::new(AtomicUsize::new(0));
+    let issuer = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let jwks_url = format!("http://{}/.well-known/jwks.json", issuer.local_addr()?);
+    let issuer_fetches = fetches.clone();
+    let issuer_handle = tokio::spawn(async move {
+        let app = Router::new().route(
+            "/.well-known/jwks.json",
+            get(move || async move {
+                issuer_fetches.fetch_add(1, Ordering::SeqCst);
+                axum::http::StatusCode::SERVICE_UNAVAILABLE
+            }),
+        );
+        axum::serve(issuer, app).await
+    });
+
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials("alice", "secret123")
+        .bearer_auth(BearerPolicy::jwks(jwks_url, AUDIENCE))
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let base_url = format!("http://localhost:{}", addr.port());
+
+    // With no keys to fall back on, tokens can't be checked, but only the
+    // first one waits on the endpoint.
+    let token = hmac_token(&claims(300, AUDIENCE));
+    for _ in 0..3 {
+        assert_eq!(
+            list_streams(&base_url, &token).await?,
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+    assert_eq!(fetches.load(Ordering::SeqCst), 1);
+
+    server_handle.abort();
+    issuer_handle.abort();
+
+    Ok(())
+}
@@ -0,0 +1,99 @@
+mod common;
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use anyhow::Result;
+use beam::{BeamError, ServerConfig, setup_server_with_config};
+use common::send_when_pending;
+use reqwest::StatusCode;
+
+async fn round_trip(base_url: &str) -> Result<()> {
+    let client = reqwest::Client::new();
+    let url = format!("{base_url}/local.txt");
+
+    let upload = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth("alice", Some("secret123"))
+            .body("stays on this machine")
+            .send(),
+    );
+    let download =
+        send_when_pending(client.get(&url).basic_auth("alice", Some("secret123"))).await?;
+    assert_eq!(download.text().await?, "stays on this machine");
+    assert_eq!(upload.await??.status(), StatusCode::OK);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn loopback_binding_serves_local_clients() -> Result<()> {
+    let config = ServerConfig::builder()
+        .bind_addr(Ipv4Addr::LOCALHOST)
+        .port(0)
+        .credentials("alice", "secret123")
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    assert_eq!(addr.ip(), IpAddr::V4(Ipv4Addr::LOCALHOST));
+
+    round_trip(&format!("http://127.0.0.1:{}", addr.port())).await?;
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn ipv6_wildcard_accepts_both_address_families() -> Result<()> {
+    let config = ServerConfig::builder()
+        .bind_addr(Ipv6Addr::UNSPECIFIED)
+        .port(0)
+        .credentials("alice", "secret123")
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+
+    round_trip(&format!("http://[::1]:{}", addr.port())).await?;
+    round_trip(&format!("http://127.0.0.1:{}", addr.port())).await?;
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn taken_port_is_reported_instead_of_panicking() -> Result<()> {
+    let occupied = std::net::TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+    let config = ServerConfig::builder()
+        .bind_addr(Ipv4Addr::LOCALHOST)
+        .port(occupied.local_addr()?.port())
+        .credentials("alice", "secret123")
+        .build();
+
+    let error = setup_server_with_config(config)
+        .await
+        .expect_err("binding a taken port should fail");
+    assert!(
+        matches!(error, BeamError::Bind(_)),
+        "unexpected error: {error}"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn tuned_connections_still_round_trip() -> Result<()> {
+    let config = ServerConfig::builder()
+        .bind_addr(Ipv4Addr::LOCALHOST)
+        .port(0)
+        .credentials("alice", "secret123")
+        .tcp_nodelay(false)
+        .tcp_keepalive(std::time::Duration::from_secs(30))
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+
+    round_trip(&format!("http://127.0.0.1:{}", addr.port())).await?;
+
+    server_handle.abort();
+
+    Ok(())
+}
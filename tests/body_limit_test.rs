@@ -0,0 +1,105 @@
+mod common;
+
+use anyhow::Result;
+use beam::ServerConfig;
+use bytes::Bytes;
+use common::{PASSWORD, USERNAME, send_when_pending, start, wait_for_stream_gone};
+use reqwest::StatusCode;
+
+const LIMIT: u64 = 1024;
+
+/// A body without Content-Length that sends two chunks totalling more than
+/// [`LIMIT`].
+fn chunked_oversized_body() -> reqwest::Body {
+    let chunks = [Bytes::from(vec![b'a'; 800]), Bytes::from(vec![b'b'; 800])];
+    reqwest::Body::wrap_stream(futures_util::stream::iter(
+        chunks.map(Ok::<_, std::io::Error>),
+    ))
+}
+
+#[tokio::test]
+async fn declared_length_over_the_limit_is_rejected_up_front() -> Result<()> {
+    let (base_url, server_handle) = start(ServerConfig::builder().max_body_size(LIMIT)).await;
+    let client = reqwest::Client::new();
+    let url = format!("{base_url}/big.bin");
+
+    let upload = client
+        .put(&url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .body(vec![0u8; LIMIT as usize + 1])
+        .send()
+        .await?;
+    assert_eq!(upload.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    let download = client
+        .get(&url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .send()
+        .await?;
+    assert_eq!(download.status(), StatusCode::NOT_FOUND);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn streamed_overrun_aborts_the_transfer() -> Result<()> {
+    let (base_url, server_handle) = start(ServerConfig::builder().max_body_size(LIMIT)).await;
+    let client = reqwest::Client::new();
+    let url = format!("{base_url}/overrun.bin");
+
+    let upload = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .body(chunked_oversized_body())
+            .send(),
+    );
+
+    let download = send_when_pending(client.get(&url).basic_auth(USERNAME, Some(PASSWORD))).await?;
+    assert_eq!(download.status(), StatusCode::OK);
+    assert!(
+        download.bytes().await.is_err(),
+        "download should be aborted"
+    );
+    assert_eq!(upload.await??.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    wait_for_stream_gone(&base_url, "overrun.bin").await;
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn spooled_overrun_leaves_nothing_on_disk() -> Result<()> {
+    let spool_dir = tempfile::tempdir()?;
+    let (base_url, server_handle) = start(
+        ServerConfig::builder()
+            .max_body_size(LIMIT)
+            .spool_dir(spool_dir.path()),
+    )
+    .await;
+    let client = reqwest::Client::new();
+    let url = format!("{base_url}/overrun.bin");
+
+    let upload = client
+        .put(&url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .body(chunked_oversized_body())
+        .send()
+        .await?;
+    assert_eq!(upload.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    assert_eq!(std::fs::read_dir(spool_dir.path())?.count(), 0);
+
+    let download = client
+        .get(&url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .send()
+        .await?;
+    assert_eq!(download.status(), StatusCode::NOT_FOUND);
+
+    server_handle.abort();
+
+    Ok(())
+}
@@ -0,0 +1,124 @@
+mod common;
+
+use std::time::Duration;
+
+use anyhow::Result;
+use beam::{LagPolicy, ServerConfig, setup_server_with_config};
+use common::send_when_pending;
+use reqwest::StatusCode;
+
+const USERNAME: &str = "alice";
+const PASSWORD: &str = "secret123";
+
+#[tokio::test]
+async fn broadcast_upload_reaches_every_downloader() -> Result<()> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let client = reqwest::Client::new();
+    let url = format!("http://localhost:{}/artifact.bin", addr.port());
+
+    let payload: Vec<u8> = (0..256 * 1024).map(|i| (i % 251) as u8).collect();
+    let upload = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .header("X-Receivers", "3")
+            .body(payload.clone())
+            .send(),
+    );
+
+    let downloads = (0..3).map(|_| {
+        let request = client.get(&url).basic_auth(USERNAME, Some(PASSWORD));
+        tokio::spawn(async move {
+            let response = send_when_pending(request).await?;
+            assert_eq!(response.status(), StatusCode::OK);
+            response.bytes().await
+        })
+    });
+    for download in futures_util::future::join_all(downloads).await {
+        assert_eq!(download??.to_vec(), payload);
+    }
+
+    let upload_response = upload.await??;
+    assert_eq!(upload_response.status(), StatusCode::OK);
+
+    let late = client
+        .get(&url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .send()
+        .await?;
+    assert_eq!(late.status(), StatusCode::NOT_FOUND);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn invalid_receiver_count_is_rejected() -> Result<()> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let client = reqwest::Client::new();
+    let url = format!("http://localhost:{}/artifact.bin", addr.port());
+
+    for receivers in ["0", "many", "100000"] {
+        let response = client
+            .put(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .header("X-Receivers", receivers)
+            .body("payload")
+            .send()
+            .await?;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{receivers}");
+    }
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn stalled_downloader_is_dropped_under_lag_policy() -> Result<()> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+        .channel_buffer(1)
+        .lag_policy(LagPolicy::Disconnect(Duration::from_millis(200)))
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let client = reqwest::Client::new();
+    let url = format!("http://localhost:{}/artifact.bin", addr.port());
+
+    // Large enough to fill the socket buffers of a downloader that never reads.
+    let payload = vec![7u8; 32 * 1024 * 1024];
+    let upload = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .header("X-Receivers", "2")
+            .body(payload.clone())
+            .send(),
+    );
+
+    let stalled = send_when_pending(client.get(&url).basic_auth(USERNAME, Some(PASSWORD))).await?;
+    assert_eq!(stalled.status(), StatusCode::OK);
+    let reader = send_when_pending(client.get(&url).basic_auth(USERNAME, Some(PASSWORD))).await?;
+    assert_eq!(reader.status(), StatusCode::OK);
+
+    let received = tokio::time::timeout(Duration::from_secs(30), reader.bytes()).await??;
+    assert_eq!(received.len(), payload.len());
+
+    let upload_response = tokio::time::timeout(Duration::from_secs(30), upload).await???;
+    assert_eq!(upload_response.status(), StatusCode::OK);
+
+    drop(stalled);
+    server_handle.abort();
+
+    Ok(())
+}
@@ -0,0 +1,149 @@
+mod common;
+
+use anyhow::{Result, ensure};
+use beam::{ServerConfig, setup_server_with_config};
+use common::wait_for_stream;
+use reqwest::StatusCode;
+
+const USERNAME: &str = "alice";
+const PASSWORD: &str = "secret123";
+
+fn u16_at(bytes: &[u8], at: usize) -> usize {
+    u16::from_le_bytes([bytes[at], bytes[at + 1]]).into()
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+}
+
+/// Reads a zip's stored entries through its central directory, checking
+/// each against its CRC.
+fn unzip(archive: &[u8]) -> Result<Vec<(String, Vec<u8>)>> {
+    let end = archive.len() - 22;
+    ensure!(
+        u32_at(archive, end) == 0x0605_4b50,
+        "no end of central directory"
+    );
+    let count = u16_at(archive, end + 10);
+    let mut at = u32_at(archive, end + 16) as usize;
+
+    let mut entries = Vec::new();
+    for _ in 0..count {
+        ensure!(u32_at(archive, at) == 0x0201_4b50, "bad central header");
+        ensure!(u16_at(archive, at + 10) == 0, "entry is not stored");
+        let crc = u32_at(archive, at + 16);
+        let size = u32_at(archive, at + 20) as usize;
+        let name_len = u16_at(archive, at + 28);
+        let local = u32_at(archive, at + 42) as usize;
+        let name = String::from_utf8(archive[at + 46..at + 46 + name_len].to_vec())?;
+
+        ensure!(u32_at(archive, local) == 0x0403_4b50, "bad local header");
+        let data_start = local + 30 + u16_at(archive, local + 26) + u16_at(archive, local + 28);
+        let data = archive[data_start..data_start + size].to_vec();
+        ensure!(crc32fast::hash(&data) == crc, "CRC mismatch in {name}");
+
+        entries.push((name, data));
+        at += 46 + name_len;
+    }
+    Ok(entries)
+}
+
+#[tokio::test]
+async fn bundle_zips_the_pending_uploads_matching_a_prefix() -> Result<()> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let base_url = format!("http://localhost:{}", addr.port());
+    let client = reqwest::Client::new();
+
+    let files = [
+        ("build-42-b.log", "second file, a little longer"),
+        ("build-42-a.txt", "first file"),
+        ("unrelated.txt", "not part of the bundle"),
+    ];
+    let mut uploads = Vec::new();
+    for (filename, contents) in files {
+        uploads.push(tokio::spawn(
+            client
+                .put(format!("{base_url}/{filename}"))
+                .basic_auth(USERNAME, Some(PASSWORD))
+                .body(contents)
+                .send(),
+        ));
+        wait_for_stream(&base_url, filename).await;
+    }
+
+    let bundle = client
+        .get(format!("{base_url}/bundle?prefix=build-42-"))
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .send()
+        .await?;
+    assert_eq!(bundle.status(), StatusCode::OK);
+    assert_eq!(bundle.headers()["content-type"], "application/zip");
+    let archive = bundle.bytes().await?;
+
+    assert_eq!(
+        unzip(&archive)?,
+        [
+            ("build-42-a.txt".to_owned(), b"first file".to_vec()),
+            (
+                "build-42-b.log".to_owned(),
+                b"second file, a little longer".to_vec()
+            ),
+        ]
+    );
+    for upload in uploads.drain(..2) {
+        assert_eq!(upload.await??.status(), StatusCode::OK);
+    }
+
+    let nothing = client
+        .get(format!("{base_url}/bundle?prefix=build-43-"))
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .send()
+        .await?;
+    assert_eq!(nothing.status(), StatusCode::NOT_FOUND);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn failed_upload_truncates_the_bundle_with_an_error() -> Result<()> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+        .max_body_size(8)
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let base_url = format!("http://localhost:{}", addr.port());
+    let client = reqwest::Client::new();
+
+    let upload = tokio::spawn(
+        client
+            .put(format!("{base_url}/part-1.bin"))
+            // Streamed, so the overrun is only found mid-transfer.
+            .body(reqwest::Body::wrap_stream(futures_util::stream::iter([
+                Ok::<_, std::io::Error>("eight by"),
+                Ok("tes and then some"),
+            ])))
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .send(),
+    );
+    wait_for_stream(&base_url, "part-1.bin").await;
+
+    let bundle = client
+        .get(format!("{base_url}/bundle?prefix=part-"))
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .send()
+        .await?;
+    assert_eq!(bundle.status(), StatusCode::OK);
+    assert!(bundle.bytes().await.is_err(), "bundle should be cut short");
+    assert_eq!(upload.await??.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    server_handle.abort();
+
+    Ok(())
+}
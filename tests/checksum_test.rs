@@ -0,0 +1,125 @@
+mod common;
+
+use anyhow::Result;
+use beam::ServerConfig;
+use common::{PASSWORD, USERNAME, send_when_pending, start};
+use reqwest::StatusCode;
+
+const BODY: &str = "integrity matters";
+/// SHA-256 of [`BODY`].
+const BODY_SHA256: &str = "ced9c5cae31c595581a428832795951e62e49852617226bb910835da4cbcab46";
+
+#[tokio::test]
+async fn matching_checksum_completes_the_transfer() -> Result<()> {
+    let (base_url, server_handle) = start(ServerConfig::builder()).await;
+    let client = reqwest::Client::new();
+    let url = format!("{base_url}/checked.txt");
+
+    let upload = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .header("X-Checksum-SHA256", BODY_SHA256)
+            .body(BODY)
+            .send(),
+    );
+    let download = send_when_pending(client.get(&url).basic_auth(USERNAME, Some(PASSWORD))).await?;
+    assert_eq!(download.status(), StatusCode::OK);
+    assert_eq!(download.headers()["x-checksum-sha256"], BODY_SHA256);
+    assert_eq!(download.text().await?, BODY);
+    assert_eq!(upload.await??.status(), StatusCode::OK);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn mismatching_checksum_fails_both_sides() -> Result<()> {
+    let (base_url, server_handle) = start(ServerConfig::builder()).await;
+    let client = reqwest::Client::new();
+    let url = format!("{base_url}/corrupt.txt");
+
+    let upload = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .header("X-Checksum-SHA256", "00".repeat(32))
+            .body(BODY)
+            .send(),
+    );
+    let download = send_when_pending(client.get(&url).basic_auth(USERNAME, Some(PASSWORD))).await?;
+    assert_eq!(download.status(), StatusCode::OK);
+    assert!(download.bytes().await.is_err(), "download should fail");
+    assert_eq!(upload.await??.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let malformed = client
+        .put(&url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .header("X-Checksum-SHA256", "not-hex")
+        .body(BODY)
+        .send()
+        .await?;
+    assert_eq!(malformed.status(), StatusCode::BAD_REQUEST);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn spooled_uploads_check_digest_and_expose_the_checksum() -> Result<()> {
+    use base64::Engine;
+
+    let spool_dir = tempfile::tempdir()?;
+    let (base_url, server_handle) =
+        start(ServerConfig::builder().spool_dir(spool_dir.path())).await;
+    let client = reqwest::Client::new();
+    let url = format!("{base_url}/stored.txt");
+
+    let wrong_digest = format!(
+        "sha-256={}",
+        base64::engine::general_purpose::STANDARD.encode([0u8; 32])
+    );
+    let rejected = client
+        .put(&url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .header("Digest", wrong_digest)
+        .body(BODY)
+        .send()
+        .await?;
+    assert_eq!(rejected.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(std::fs::read_dir(spool_dir.path())?.count(), 0);
+
+    let hash: Vec<u8> = (0..BODY_SHA256.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&BODY_SHA256[i..i + 2], 16).unwrap())
+        .collect();
+    let digest = format!(
+        "sha-256={}",
+        base64::engine::general_purpose::STANDARD.encode(hash)
+    );
+    let stored = client
+        .put(&url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .header("Digest", digest)
+        .body(BODY)
+        .send()
+        .await?;
+    assert_eq!(stored.status(), StatusCode::CREATED);
+
+    let download = client
+        .get(&url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .send()
+        .await?;
+    assert_eq!(download.headers()["x-checksum-sha256"], BODY_SHA256);
+    assert_eq!(
+        download.headers()[reqwest::header::ETAG],
+        format!("\"{BODY_SHA256}\"").as_str()
+    );
+
+    server_handle.abort();
+
+    Ok(())
+}
@@ -0,0 +1,71 @@
+#![allow(dead_code)]
+
+use std::time::Duration;
+
+use beam::{ServerConfigBuilder, setup_server_with_config};
+use reqwest::{RequestBuilder, Response, StatusCode};
+use tokio::task::JoinHandle;
+
+pub const USERNAME: &str = "alice";
+pub const PASSWORD: &str = "secret123";
+
+/// Starts a server configured by `builder` on an ephemeral port, with
+/// [`USERNAME`] and [`PASSWORD`] as its user, and returns its base URL.
+pub async fn start(builder: ServerConfigBuilder) -> (String, JoinHandle<()>) {
+    let config = builder.port(0).credentials(USERNAME, PASSWORD).build();
+    let (addr, handle) = setup_server_with_config(config)
+        .await
+        .expect("failed to start server");
+    (format!("http://localhost:{}", addr.port()), handle)
+}
+
+/// Retries `request` while the server answers `404`, giving a concurrently
+/// started upload time to register its stream.
+pub async fn send_when_pending(request: RequestBuilder) -> reqwest::Result<Response> {
+    for _ in 0..200 {
+        let response = request
+            .try_clone()
+            .expect("request body must be cloneable")
+            .send()
+            .await?;
+        if response.status() != StatusCode::NOT_FOUND {
+            return Ok(response);
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    request.send().await
+}
+
+/// Polls the dashboard at `base_url` until `filename` is listed as an active
+/// stream.
+pub async fn wait_for_stream(base_url: &str, filename: &str) {
+    for _ in 0..200 {
+        let listed = match reqwest::get(base_url).await {
+            Ok(response) => response.text().await.unwrap_or_default().contains(filename),
+            Err(_) => false,
+        };
+        if listed {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    panic!("stream {filename} never appeared on the dashboard");
+}
+
+/// Polls the dashboard at `base_url` until `filename` is no longer listed.
+pub async fn wait_for_stream_gone(base_url: &str, filename: &str) {
+    for _ in 0..200 {
+        let listed = match reqwest::get(base_url).await {
+            Ok(response) => response.text().await.unwrap_or_default().contains(filename),
+            Err(_) => true,
+        };
+        if !listed {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    panic!("stream {filename} was never removed from the dashboard");
+}
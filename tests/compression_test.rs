@@ -0,0 +1,226 @@
+mod common;
+
+use std::io::Read;
+
+use anyhow::Result;
+use beam::ServerConfig;
+use common::{PASSWORD, USERNAME, send_when_pending, start};
+use reqwest::{StatusCode, header};
+use tokio::io::AsyncReadExt;
+
+#[tokio::test]
+async fn gzip_download_decompresses_to_the_upload() -> Result<()> {
+    let (base_url, server_handle) = start(ServerConfig::builder().compress_downloads(true)).await;
+    let client = reqwest::Client::new();
+    let url = format!("{base_url}/build.log");
+    let payload = "compile step ok\n".repeat(20_000);
+
+    let upload = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .body(payload.clone())
+            .send(),
+    );
+    let download = send_when_pending(
+        client
+            .get(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .header(header::ACCEPT_ENCODING, "gzip"),
+    )
+    .await?;
+    assert_eq!(download.status(), StatusCode::OK);
+    assert_eq!(download.headers()[header::CONTENT_ENCODING], "gzip");
+    assert!(!download.headers().contains_key(header::CONTENT_LENGTH));
+
+    let compressed = download.bytes().await?;
+    assert!(compressed.len() < payload.len() / 10);
+    let mut decompressed = String::new();
+    flate2::read::GzDecoder::new(&compressed[..]).read_to_string(&mut decompressed)?;
+    assert_eq!(decompressed, payload);
+    assert_eq!(upload.await??.status(), StatusCode::OK);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn download_is_uncompressed_without_accept_encoding() -> Result<()> {
+    let (base_url, server_handle) = start(ServerConfig::builder().compress_downloads(true)).await;
+    let client = reqwest::Client::new();
+    let url = format!("{base_url}/plain.txt");
+
+    let upload = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .body("plain text")
+            .send(),
+    );
+    let download = send_when_pending(
+        client
+            .get(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .header(header::ACCEPT_ENCODING, "gzip;q=0, identity"),
+    )
+    .await?;
+    assert!(!download.headers().contains_key(header::CONTENT_ENCODING));
+    assert_eq!(download.headers()[header::CONTENT_LENGTH], "10");
+    assert_eq!(download.text().await?, "plain text");
+    assert_eq!(upload.await??.status(), StatusCode::OK);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn brotli_is_preferred_on_a_tie_and_decompresses_to_the_upload() -> Result<()> {
+    let (base_url, server_handle) = start(ServerConfig::builder().compress_downloads(true)).await;
+    let client = reqwest::Client::new();
+    let url = format!("{base_url}/build.log");
+    let payload = "compile step ok\n".repeat(20_000);
+
+    let upload = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .body(payload.clone())
+            .send(),
+    );
+    let download = send_when_pending(
+        client
+            .get(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .header(header::ACCEPT_ENCODING, "gzip, br"),
+    )
+    .await?;
+    assert_eq!(download.status(), StatusCode::OK);
+    assert_eq!(download.headers()[header::CONTENT_ENCODING], "br");
+
+    let compressed = download.bytes().await?;
+    assert!(compressed.len() < payload.len() / 10);
+    let mut decompressed = String::new();
+    async_compression::tokio::bufread::BrotliDecoder::new(&compressed[..])
+        .read_to_string(&mut decompressed)
+        .await?;
+    assert_eq!(decompressed, payload);
+    assert_eq!(upload.await??.status(), StatusCode::OK);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn coding_follows_q_values_and_skips_compressed_types() -> Result<()> {
+    let (base_url, server_handle) = start(ServerConfig::builder().compress_downloads(true)).await;
+    let client = reqwest::Client::new();
+
+    for (filename, accept_encoding, expected) in [
+        ("weighted.txt", "br;q=0.5, gzip;q=0.9", Some("gzip")),
+        ("wildcard.txt", "gzip;q=0.2, *", Some("br")),
+        ("photo.jpg", "br, gzip", None),
+    ] {
+        let url = format!("{base_url}/{filename}");
+        let upload = tokio::spawn(
+            client
+                .put(&url)
+                .basic_auth(USERNAME, Some(PASSWORD))
+                .body("some bytes")
+                .send(),
+        );
+        let download = send_when_pending(
+            client
+                .get(&url)
+                .basic_auth(USERNAME, Some(PASSWORD))
+                .header(header::ACCEPT_ENCODING, accept_encoding),
+        )
+        .await?;
+        assert_eq!(
+            download
+                .headers()
+                .get(header::CONTENT_ENCODING)
+                .map(|value| value.to_str().unwrap()),
+            expected,
+            "for {accept_encoding}"
+        );
+        download.bytes().await?;
+        assert_eq!(upload.await??.status(), StatusCode::OK);
+    }
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn payloads_under_the_threshold_are_sent_uncompressed() -> Result<()> {
+    let (base_url, server_handle) = start(
+        ServerConfig::builder()
+            .compress_downloads(true)
+            .compress_min_size(1024),
+    )
+    .await;
+    let client = reqwest::Client::new();
+    let large = "compile step ok\n".repeat(1_000);
+
+    // Sized by `Content-Length`, or by what arrives for a streamed body.
+    for (filename, payload, streamed, expected) in [
+        ("small.txt", "some bytes".to_owned(), false, None),
+        ("small-streamed.txt", "some bytes".to_owned(), true, None),
+        ("large.txt", large.clone(), false, Some("gzip")),
+        ("large-streamed.txt", large.clone(), true, Some("gzip")),
+    ] {
+        let url = format!("{base_url}/{filename}");
+        let body = if streamed {
+            let chunks = payload
+                .as_bytes()
+                .chunks(256)
+                .map(|chunk| Ok::<_, std::io::Error>(chunk.to_vec()))
+                .collect::<Vec<_>>();
+            reqwest::Body::wrap_stream(futures_util::stream::iter(chunks))
+        } else {
+            reqwest::Body::from(payload.clone())
+        };
+        let upload = tokio::spawn(
+            client
+                .put(&url)
+                .basic_auth(USERNAME, Some(PASSWORD))
+                .body(body)
+                .send(),
+        );
+        let download = send_when_pending(
+            client
+                .get(&url)
+                .basic_auth(USERNAME, Some(PASSWORD))
+                .header(header::ACCEPT_ENCODING, "gzip"),
+        )
+        .await?;
+        assert_eq!(
+            download
+                .headers()
+                .get(header::CONTENT_ENCODING)
+                .map(|value| value.to_str().unwrap()),
+            expected,
+            "for {filename}"
+        );
+
+        let bytes = download.bytes().await?;
+        let received = match expected {
+            Some(_) => {
+                let mut decompressed = String::new();
+                flate2::read::GzDecoder::new(&bytes[..]).read_to_string(&mut decompressed)?;
+                decompressed
+            }
+            None => String::from_utf8(bytes.to_vec())?,
+        };
+        assert_eq!(received, payload, "for {filename}");
+        assert_eq!(upload.await??.status(), StatusCode::OK);
+    }
+
+    server_handle.abort();
+
+    Ok(())
+}
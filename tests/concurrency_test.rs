@@ -0,0 +1,190 @@
+mod common;
+
+use std::time::Duration;
+
+use anyhow::Result;
+use beam::{ServerConfig, setup_server_with_config};
+use common::{send_when_pending, wait_for_stream};
+use reqwest::StatusCode;
+
+const USERNAME: &str = "alice";
+const PASSWORD: &str = "secret123";
+const TRANSFERS: usize = 32;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn many_distinct_files_transfer_at_once() -> Result<()> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let client = reqwest::Client::new();
+    let base_url = format!("http://localhost:{}", addr.port());
+
+    let transfers = (0..TRANSFERS).map(|index| {
+        let client = client.clone();
+        let url = format!("{base_url}/file-{index}.bin");
+        tokio::spawn(async move {
+            let payload = format!("payload {index} ").repeat(1000);
+            let upload = tokio::spawn(
+                client
+                    .put(&url)
+                    .basic_auth(USERNAME, Some(PASSWORD))
+                    .body(payload.clone())
+                    .send(),
+            );
+            let download =
+                send_when_pending(client.get(&url).basic_auth(USERNAME, Some(PASSWORD))).await?;
+            assert_eq!(download.status(), StatusCode::OK);
+            assert_eq!(download.text().await?, payload);
+            assert_eq!(upload.await??.status(), StatusCode::OK);
+            anyhow::Ok(())
+        })
+    });
+    for transfer in futures_util::future::join_all(transfers).await {
+        transfer??;
+    }
+
+    // Every stream released its filename.
+    let listed: serde_json::Value = client
+        .get(format!("{base_url}/api/streams"))
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(listed, serde_json::json!([]));
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn transfers_sharing_a_small_in_flight_budget_all_complete() -> Result<()> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+        .max_in_flight_bytes(4096)
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let client = reqwest::Client::new();
+    let base_url = format!("http://localhost:{}", addr.port());
+
+    let transfers = (0..2).map(|index| {
+        let client = client.clone();
+        let url = format!("{base_url}/budget-{index}.bin");
+        tokio::spawn(async move {
+            let payload = format!("budgeted payload {index} ").repeat(50_000);
+            let upload = tokio::spawn(
+                client
+                    .put(&url)
+                    .basic_auth(USERNAME, Some(PASSWORD))
+                    .body(payload.clone())
+                    .send(),
+            );
+            let download =
+                send_when_pending(client.get(&url).basic_auth(USERNAME, Some(PASSWORD))).await?;
+            assert_eq!(download.status(), StatusCode::OK);
+            assert_eq!(download.text().await?, payload);
+            assert_eq!(upload.await??.status(), StatusCode::OK);
+            anyhow::Ok(())
+        })
+    });
+    for transfer in futures_util::future::join_all(transfers).await {
+        transfer??;
+    }
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn concurrent_uploads_of_one_filename_admit_exactly_one() -> Result<()> {
+    let spool_dir = tempfile::tempdir()?;
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+        .spool_dir(spool_dir.path())
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let client = reqwest::Client::new();
+    let url = format!("http://localhost:{}/contended.txt", addr.port());
+
+    let uploads = (0..16).map(|_| {
+        client
+            .put(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .body("contended")
+            .send()
+    });
+    let mut statuses = Vec::new();
+    for response in futures_util::future::join_all(uploads).await {
+        statuses.push(response?.status());
+    }
+    assert_eq!(
+        statuses
+            .iter()
+            .filter(|&&s| s == StatusCode::CREATED)
+            .count(),
+        1,
+        "{statuses:?}"
+    );
+    assert!(
+        statuses
+            .iter()
+            .all(|&s| s == StatusCode::CREATED || s == StatusCode::CONFLICT)
+    );
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn conflicting_upload_reports_the_age_of_the_existing_one() -> Result<()> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+        .upload_ready_timeout(None)
+        .max_pending_age(Duration::from_secs(600))
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let base_url = format!("http://localhost:{}", addr.port());
+    let url = format!("{base_url}/taken.txt");
+    let client = reqwest::Client::new();
+
+    let first = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .body("first")
+            .send(),
+    );
+    wait_for_stream(&base_url, "taken.txt").await;
+
+    let second = client
+        .put(&url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .body("second")
+        .send()
+        .await?;
+    assert_eq!(second.status(), StatusCode::CONFLICT);
+    let message = second.text().await?;
+    assert!(message.contains("age 0s"), "{message}");
+    assert!(message.contains("no downloader attached"), "{message}");
+    assert!(message.contains("expires in 599s"), "{message}");
+
+    let download = client
+        .get(&url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .send()
+        .await?;
+    assert_eq!(download.text().await?, "first");
+    assert_eq!(first.await??.status(), StatusCode::OK);
+
+    server_handle.abort();
+
+    Ok(())
+}
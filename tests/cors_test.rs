@@ -0,0 +1,106 @@
+mod common;
+
+use anyhow::Result;
+use beam::{CorsPolicy, ServerConfig, setup_server_with_config};
+use common::{PASSWORD, USERNAME, start};
+use reqwest::{Method, StatusCode, header};
+
+const ORIGIN: &str = "https://app.example.com";
+
+fn preflight(client: &reqwest::Client, base_url: &str, origin: &str) -> reqwest::RequestBuilder {
+    client
+        .request(Method::OPTIONS, format!("{base_url}/report.pdf"))
+        .header(header::ORIGIN, origin)
+        .header(header::ACCESS_CONTROL_REQUEST_METHOD, "PUT")
+        .header(
+            header::ACCESS_CONTROL_REQUEST_HEADERS,
+            "authorization,content-type",
+        )
+}
+
+#[tokio::test]
+async fn preflight_is_answered_for_an_allowed_origin() -> Result<()> {
+    let (base_url, server_handle) =
+        start(ServerConfig::builder().cors(CorsPolicy::new([ORIGIN]))).await;
+    let client = reqwest::Client::new();
+
+    let response = preflight(&client, &base_url, ORIGIN).send().await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let headers = response.headers();
+    assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_ORIGIN], ORIGIN);
+    let methods = headers[header::ACCESS_CONTROL_ALLOW_METHODS].to_str()?;
+    assert!(methods.contains("PUT"), "unexpected methods: {methods}");
+    assert_eq!(
+        headers[header::ACCESS_CONTROL_ALLOW_HEADERS],
+        "authorization,content-type"
+    );
+
+    let upload = client
+        .put(format!("{base_url}/report.pdf"))
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .header(header::ORIGIN, ORIGIN)
+        .body("quarterly numbers");
+    let download = client
+        .get(format!("{base_url}/report.pdf"))
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .header(header::ORIGIN, ORIGIN)
+        .send();
+    let (upload, download) = tokio::join!(upload.send(), download);
+    assert_eq!(upload?.status(), StatusCode::OK);
+    let download = download?;
+    assert_eq!(
+        download.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+        ORIGIN
+    );
+    assert_eq!(
+        download.headers()[header::ACCESS_CONTROL_EXPOSE_HEADERS],
+        "*"
+    );
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn no_cors_headers_unless_configured() -> Result<()> {
+    let (base_url, server_handle) = start(ServerConfig::builder()).await;
+    let client = reqwest::Client::new();
+
+    let response = preflight(&client, &base_url, ORIGIN).send().await?;
+    assert!(
+        !response
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+    );
+
+    let (restricted_url, restricted_handle) =
+        start(ServerConfig::builder().cors(CorsPolicy::new([ORIGIN]))).await;
+    let response = preflight(&client, &restricted_url, "https://evil.example.com")
+        .send()
+        .await?;
+    assert!(
+        !response
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+    );
+
+    server_handle.abort();
+    restricted_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn invalid_origin_fails_setup() {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+        .cors(CorsPolicy::new(["https://bad\norigin"]))
+        .build();
+    let error = setup_server_with_config(config).await.unwrap_err();
+    assert!(
+        error.to_string().contains("CORS"),
+        "unexpected error: {error}"
+    );
+}
@@ -0,0 +1,152 @@
+mod common;
+
+use std::time::Duration;
+
+use anyhow::Result;
+use beam::{ServerConfig, setup_server_with_config};
+use common::{send_when_pending, wait_for_stream};
+use futures_util::StreamExt;
+use reqwest::StatusCode;
+
+const USERNAME: &str = "alice";
+const PASSWORD: &str = "secret123";
+
+async fn wait_for_dashboard(base_url: &str, needle: &str) -> String {
+    for _ in 0..200 {
+        let page = reqwest::get(base_url)
+            .await
+            .expect("dashboard should respond")
+            .text()
+            .await
+            .expect("dashboard body should be text");
+        if page.contains(needle) {
+            return page;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    panic!("dashboard never contained {needle}");
+}
+
+#[tokio::test]
+async fn dashboard_shows_bytes_and_downloader_state() -> Result<()> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let base_url = format!("http://localhost:{}/", addr.port());
+    let url = format!("{base_url}progress.bin");
+    let client = reqwest::Client::new();
+
+    let (chunk_tx, chunk_rx) = tokio::sync::mpsc::channel::<Result<Vec<u8>, std::io::Error>>(1);
+    let upload = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .body(reqwest::Body::wrap_stream(
+                tokio_stream::wrappers::ReceiverStream::new(chunk_rx),
+            ))
+            .send(),
+    );
+
+    wait_for_stream(&base_url, "progress.bin").await;
+    wait_for_dashboard(
+        &base_url,
+        "<tr><td>progress.bin</td><td>0</td><td>0/1</td><td>",
+    )
+    .await;
+
+    let download = send_when_pending(client.get(&url).basic_auth(USERNAME, Some(PASSWORD))).await?;
+    assert_eq!(download.status(), StatusCode::OK);
+    let mut body = download.bytes_stream();
+
+    chunk_tx.send(Ok(vec![1u8; 10])).await?;
+    assert_eq!(
+        body.next().await.transpose()?.map(|chunk| chunk.len()),
+        Some(10)
+    );
+    wait_for_dashboard(
+        &base_url,
+        "<tr><td>progress.bin</td><td>10</td><td>1/1</td><td>",
+    )
+    .await;
+
+    let second = client
+        .get(&url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .send()
+        .await?;
+    assert_eq!(second.status(), StatusCode::CONFLICT);
+
+    drop(chunk_tx);
+    while body.next().await.transpose()?.is_some() {}
+    assert_eq!(upload.await??.status(), StatusCode::OK);
+
+    let page = reqwest::get(&base_url).await?.text().await?;
+    assert!(!page.contains("progress.bin"));
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn dashboard_offers_an_upload_form_that_works_without_javascript() -> Result<()> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let base_url = format!("http://localhost:{}/", addr.port());
+
+    let page = reqwest::get(&base_url).await?.text().await?;
+    assert!(page.contains(
+        r#"<form id="upload-form" method="post" action="/" enctype="multipart/form-data">"#
+    ));
+    assert!(page.contains(r#"<input type="text" name="filename""#));
+    assert!(page.contains(r#"<input type="file" name="file" required />"#));
+
+    // What the form posts without the script: still behind Basic Auth.
+    let form = reqwest::multipart::Form::new().part(
+        "file",
+        reqwest::multipart::Part::text("hi").file_name("hi.txt"),
+    );
+    let response = reqwest::Client::new()
+        .post(&base_url)
+        .multipart(form)
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn dashboard_escapes_filenames() -> Result<()> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let base_url = format!("http://localhost:{}/", addr.port());
+
+    let reserved = reqwest::Client::new()
+        .post(format!("{base_url}reserve"))
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .json(&serde_json::json!({ "filename": "<script>alert('beam')&.txt" }))
+        .send()
+        .await?;
+    assert_eq!(reserved.status(), StatusCode::CREATED);
+
+    let page = wait_for_dashboard(&base_url, "alert").await;
+    assert!(page.starts_with("<!DOCTYPE html>\n<html lang=\"en\">"));
+    assert!(page.contains("<td>&lt;script&gt;alert(&#39;beam&#39;)&amp;.txt</td>"));
+    assert!(!page.contains("<script>alert"));
+
+    server_handle.abort();
+
+    Ok(())
+}
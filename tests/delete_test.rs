@@ -0,0 +1,107 @@
+mod common;
+
+use anyhow::Result;
+use beam::{ServerConfig, setup_server_with_config};
+use common::{send_when_pending, wait_for_stream};
+use reqwest::StatusCode;
+
+const USERNAME: &str = "alice";
+const PASSWORD: &str = "secret123";
+
+#[tokio::test]
+async fn delete_cancels_a_pending_upload_and_frees_the_name() -> Result<()> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let base_url = format!("http://localhost:{}", addr.port());
+    let url = format!("{base_url}/stuck.bin");
+    let client = reqwest::Client::new();
+
+    let stuck_upload = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .body("never downloaded")
+            .send(),
+    );
+    wait_for_stream(&base_url, "stuck.bin").await;
+
+    let anonymous = client.delete(&url).send().await?;
+    assert_eq!(anonymous.status(), StatusCode::UNAUTHORIZED);
+
+    let deleted = client
+        .delete(&url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .send()
+        .await?;
+    assert_eq!(deleted.status(), StatusCode::OK);
+
+    let cancelled = stuck_upload.await??;
+    assert_eq!(cancelled.status(), StatusCode::CONFLICT);
+    assert!(cancelled.text().await?.contains("cancelled"));
+
+    let deleted_again = client
+        .delete(&url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .send()
+        .await?;
+    assert_eq!(deleted_again.status(), StatusCode::NOT_FOUND);
+
+    let upload = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .body("second attempt")
+            .send(),
+    );
+    let download = send_when_pending(client.get(&url).basic_auth(USERNAME, Some(PASSWORD))).await?;
+    assert_eq!(download.status(), StatusCode::OK);
+    assert_eq!(download.text().await?, "second attempt");
+    assert_eq!(upload.await??.status(), StatusCode::OK);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn delete_removes_a_spooled_file() -> Result<()> {
+    let spool_dir = tempfile::tempdir()?;
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+        .spool_dir(spool_dir.path())
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let url = format!("http://localhost:{}/stored.txt", addr.port());
+    let client = reqwest::Client::new();
+
+    let upload = client
+        .put(&url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .body("stored")
+        .send()
+        .await?;
+    assert_eq!(upload.status(), StatusCode::CREATED);
+
+    let deleted = client
+        .delete(&url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .send()
+        .await?;
+    assert_eq!(deleted.status(), StatusCode::OK);
+    assert_eq!(std::fs::read_dir(spool_dir.path())?.count(), 0);
+
+    let download = client
+        .get(&url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .send()
+        .await?;
+    assert_eq!(download.status(), StatusCode::NOT_FOUND);
+
+    server_handle.abort();
+
+    Ok(())
+}
@@ -0,0 +1,118 @@
+mod common;
+
+use std::time::Duration;
+
+use anyhow::Result;
+use beam::{ReconnectGrace, ServerConfig, setup_server_with_config};
+use bytes::Bytes;
+use common::{send_when_pending, wait_for_stream_gone};
+use reqwest::{StatusCode, header};
+use tokio_stream::wrappers::ReceiverStream;
+
+#[tokio::test]
+async fn upload_reports_a_downloader_that_leaves_early() -> Result<()> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials("alice", "secret123")
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let base_url = format!("http://localhost:{}", addr.port());
+    let url = format!("{base_url}/partial.bin");
+    let client = reqwest::Client::new();
+
+    // Feed chunks until beam stops reading, so the upload never finishes on
+    // its own.
+    let (body_tx, body_rx) = tokio::sync::mpsc::channel::<Result<Bytes, std::io::Error>>(1);
+    let feeder = tokio::spawn(async move {
+        for _ in 0..500 {
+            if body_tx
+                .send(Ok(Bytes::from(vec![b'x'; 16 * 1024])))
+                .await
+                .is_err()
+            {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    });
+    let upload = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth("alice", Some("secret123"))
+            .body(reqwest::Body::wrap_stream(ReceiverStream::new(body_rx)))
+            .send(),
+    );
+
+    let mut download =
+        send_when_pending(client.get(&url).basic_auth("alice", Some("secret123"))).await?;
+    assert_eq!(download.status(), StatusCode::OK);
+    assert!(download.chunk().await?.is_some());
+    drop(download);
+
+    let upload = upload.await??;
+    assert_eq!(upload.status(), StatusCode::BAD_GATEWAY);
+    assert!(upload.text().await?.contains("disconnected"));
+    wait_for_stream_gone(&base_url, "partial.bin").await;
+
+    feeder.abort();
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn downloader_reconnecting_within_the_grace_resumes_where_it_left_off() -> Result<()> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials("alice", "secret123")
+        .reconnect_grace(ReconnectGrace {
+            window: Duration::from_secs(5),
+            replay_bytes: 1024,
+        })
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let url = format!("http://localhost:{}/flaky.bin", addr.port());
+    let client = reqwest::Client::new();
+
+    let (body_tx, body_rx) = tokio::sync::mpsc::channel::<Result<Bytes, std::io::Error>>(1);
+    let upload = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth("alice", Some("secret123"))
+            .body(reqwest::Body::wrap_stream(ReceiverStream::new(body_rx)))
+            .send(),
+    );
+    body_tx.send(Ok(Bytes::from_static(b"first-"))).await?;
+
+    let mut download =
+        send_when_pending(client.get(&url).basic_auth("alice", Some("secret123"))).await?;
+    let received = download.chunk().await?.expect("first chunk");
+    assert_eq!(received.as_ref(), b"first-");
+    drop(download);
+
+    // The next chunk finds the downloader gone; beam keeps it for the
+    // reconnect instead of failing the upload.
+    body_tx.send(Ok(Bytes::from_static(b"second-"))).await?;
+    let resumed = loop {
+        let response = client
+            .get(&url)
+            .basic_auth("alice", Some("secret123"))
+            .header(header::RANGE, format!("bytes={}-", received.len()))
+            .send()
+            .await?;
+        if response.status() != StatusCode::CONFLICT {
+            break response;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    };
+    assert_eq!(resumed.status(), StatusCode::OK);
+
+    body_tx.send(Ok(Bytes::from_static(b"third"))).await?;
+    drop(body_tx);
+    assert_eq!(resumed.text().await?, "second-third");
+    assert_eq!(upload.await??.status(), StatusCode::OK);
+
+    server_handle.abort();
+
+    Ok(())
+}
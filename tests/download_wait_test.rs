@@ -0,0 +1,120 @@
+mod common;
+
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use beam::{ParkedDownload, ServerConfig};
+use common::{PASSWORD, USERNAME, start};
+use futures_util::StreamExt;
+use reqwest::{StatusCode, header};
+
+#[tokio::test]
+async fn download_started_first_waits_for_the_upload() -> Result<()> {
+    let (base_url, server_handle) =
+        start(ServerConfig::builder().download_wait_timeout(Duration::from_secs(5))).await;
+    let client = reqwest::Client::new();
+    let url = format!("{base_url}/early.txt");
+
+    let download = tokio::spawn(client.get(&url).basic_auth(USERNAME, Some(PASSWORD)).send());
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(!download.is_finished(), "download should be parked");
+
+    let upload = client
+        .put(&url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .body("worth the wait")
+        .send()
+        .await?;
+    assert_eq!(upload.status(), StatusCode::OK);
+
+    let download = download.await??;
+    assert_eq!(download.status(), StatusCode::OK);
+    assert_eq!(download.text().await?, "worth the wait");
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn waiting_download_gives_up_after_the_timeout() -> Result<()> {
+    let (base_url, server_handle) =
+        start(ServerConfig::builder().download_wait_timeout(Duration::from_millis(200))).await;
+    let client = reqwest::Client::new();
+
+    let started = Instant::now();
+    let download = client
+        .get(format!("{base_url}/never.txt"))
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .send()
+        .await?;
+    assert_eq!(download.status(), StatusCode::NOT_FOUND);
+    assert!(started.elapsed() >= Duration::from_millis(200));
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn heartbeats_keep_a_parked_download_busy_until_the_upload() -> Result<()> {
+    let (base_url, server_handle) = start(
+        ServerConfig::builder()
+            .download_wait_timeout(Duration::from_secs(10))
+            .parked_download(ParkedDownload::Heartbeat(Duration::from_millis(100))),
+    )
+    .await;
+    let url = format!("{base_url}/late.txt");
+    let client = reqwest::Client::new();
+
+    let waiting = client
+        .get(&url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .header(header::ACCEPT, "text/event-stream")
+        .send()
+        .await?;
+    assert_eq!(waiting.status(), StatusCode::OK);
+    assert_eq!(waiting.headers()[header::CONTENT_TYPE], "text/event-stream");
+    let mut events = waiting.bytes_stream();
+
+    // Stand-in for a proxy that drops connections idle for half a second:
+    // over twice that, something arrives well within every half second.
+    let mut received = String::new();
+    let parked = Instant::now();
+    while parked.elapsed() < Duration::from_secs(1) {
+        let chunk = tokio::time::timeout(Duration::from_millis(500), events.next())
+            .await
+            .expect("parked download went idle")
+            .expect("parked download ended early")?;
+        received.push_str(std::str::from_utf8(&chunk)?);
+    }
+    assert!(received.contains(": waiting"), "{received}");
+    assert!(!received.contains("event:"), "{received}");
+
+    let upload = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .body("worth the wait")
+            .send(),
+    );
+    while let Some(chunk) = events.next().await {
+        received.push_str(std::str::from_utf8(&chunk?)?);
+    }
+    assert!(
+        received.contains("event: ready\ndata: late.txt\n"),
+        "{received}"
+    );
+
+    let download = client
+        .get(&url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .send()
+        .await?;
+    assert_eq!(download.text().await?, "worth the wait");
+    assert_eq!(upload.await??.status(), StatusCode::OK);
+
+    server_handle.abort();
+
+    Ok(())
+}
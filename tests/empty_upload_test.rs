@@ -0,0 +1,105 @@
+mod common;
+
+use anyhow::Result;
+use beam::ServerConfig;
+use bytes::Bytes;
+use common::{PASSWORD, USERNAME, send_when_pending, start};
+use reqwest::StatusCode;
+
+/// A chunked body that ends without a single byte, as from an uploader that
+/// connects and hangs up.
+fn chunked_empty_body() -> reqwest::Body {
+    reqwest::Body::wrap_stream(futures_util::stream::empty::<Result<Bytes, std::io::Error>>())
+}
+
+#[tokio::test]
+async fn empty_uploads_are_relayed_by_default() -> Result<()> {
+    let (base_url, server_handle) = start(ServerConfig::builder()).await;
+    let client = reqwest::Client::new();
+    let url = format!("{base_url}/empty.txt");
+
+    let upload = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .body(chunked_empty_body())
+            .send(),
+    );
+    let download = send_when_pending(client.get(&url).basic_auth(USERNAME, Some(PASSWORD))).await?;
+    assert_eq!(download.status(), StatusCode::OK);
+    assert_eq!(download.bytes().await?, Bytes::new());
+    assert_eq!(upload.await??.status(), StatusCode::OK);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn empty_uploads_are_rejected_when_configured() -> Result<()> {
+    let (base_url, server_handle) = start(ServerConfig::builder().reject_empty_uploads(true)).await;
+    let client = reqwest::Client::new();
+    let url = format!("{base_url}/empty.txt");
+
+    // A declared empty body is refused before anyone waits on it.
+    let declared = client
+        .put(&url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .header(reqwest::header::CONTENT_LENGTH, 0)
+        .body("")
+        .send()
+        .await?;
+    assert_eq!(declared.status(), StatusCode::BAD_REQUEST);
+
+    let upload = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .body(chunked_empty_body())
+            .send(),
+    );
+    let download = send_when_pending(client.get(&url).basic_auth(USERNAME, Some(PASSWORD))).await?;
+    assert_eq!(download.status(), StatusCode::OK);
+    assert!(
+        download.bytes().await.is_err(),
+        "download should be aborted"
+    );
+    assert_eq!(upload.await??.status(), StatusCode::BAD_REQUEST);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn spooled_empty_uploads_are_rejected_when_configured() -> Result<()> {
+    let spool_dir = tempfile::tempdir()?;
+    let (base_url, server_handle) = start(
+        ServerConfig::builder()
+            .reject_empty_uploads(true)
+            .spool_dir(spool_dir.path()),
+    )
+    .await;
+    let client = reqwest::Client::new();
+    let url = format!("{base_url}/empty.txt");
+
+    let upload = client
+        .put(&url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .body(chunked_empty_body())
+        .send()
+        .await?;
+    assert_eq!(upload.status(), StatusCode::BAD_REQUEST);
+
+    let download = client
+        .get(&url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .send()
+        .await?;
+    assert_eq!(download.status(), StatusCode::NOT_FOUND);
+    assert_eq!(std::fs::read_dir(spool_dir.path())?.count(), 0);
+
+    server_handle.abort();
+
+    Ok(())
+}
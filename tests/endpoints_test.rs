@@ -0,0 +1,188 @@
+mod common;
+
+use anyhow::Result;
+use beam::ServerConfig;
+use common::{PASSWORD, USERNAME, send_when_pending, start};
+use reqwest::{StatusCode, header};
+
+#[tokio::test]
+async fn healthz_responds_without_credentials() -> Result<()> {
+    let (base_url, server_handle) = start(ServerConfig::builder()).await;
+
+    let response = reqwest::get(format!("{base_url}/healthz")).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+    assert_eq!(response.text().await?, r#"{"status":"ok"}"#);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+fn metric_value(metrics: &str, name: &str) -> u64 {
+    metrics
+        .lines()
+        .find_map(|line| line.strip_prefix(name)?.strip_prefix(' '))
+        .unwrap_or_else(|| panic!("metric {name} missing from:\n{metrics}"))
+        .parse()
+        .expect("metric value should be an integer")
+}
+
+#[tokio::test]
+async fn metrics_count_transfers_and_auth_failures() -> Result<()> {
+    let (base_url, server_handle) = start(ServerConfig::builder()).await;
+    let client = reqwest::Client::new();
+    let metrics_url = format!("{base_url}/metrics");
+
+    let unauthenticated = client.get(&metrics_url).send().await?;
+    assert_eq!(unauthenticated.status(), StatusCode::UNAUTHORIZED);
+
+    let url = format!("{base_url}/counted.bin");
+    let upload = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .body(vec![0u8; 1000])
+            .send(),
+    );
+    let download = send_when_pending(client.get(&url).basic_auth(USERNAME, Some(PASSWORD))).await?;
+    assert_eq!(download.bytes().await?.len(), 1000);
+    assert_eq!(upload.await??.status(), StatusCode::OK);
+
+    let rejected = client
+        .get(&url)
+        .basic_auth(USERNAME, Some("wrong"))
+        .send()
+        .await?;
+    assert_eq!(rejected.status(), StatusCode::UNAUTHORIZED);
+
+    let response = client
+        .get(&metrics_url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let metrics = response.text().await?;
+
+    assert_eq!(metric_value(&metrics, "beam_uploads_total"), 1);
+    assert_eq!(metric_value(&metrics, "beam_downloads_total"), 1);
+    assert_eq!(metric_value(&metrics, "beam_active_streams"), 0);
+    assert_eq!(metric_value(&metrics, "beam_bytes_transferred_total"), 1000);
+    assert_eq!(metric_value(&metrics, "beam_auth_failures_total"), 1);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn api_streams_lists_pending_uploads_as_json() -> Result<()> {
+    let (base_url, server_handle) = start(ServerConfig::builder()).await;
+    let client = reqwest::Client::new();
+    let api_url = format!("{base_url}/api/streams");
+
+    let unauthenticated = client.get(&api_url).send().await?;
+    assert_eq!(unauthenticated.status(), StatusCode::UNAUTHORIZED);
+
+    let url = format!("{base_url}/listed.bin");
+    let upload = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .body(vec![0u8; 100])
+            .send(),
+    );
+    common::wait_for_stream(&base_url, "listed.bin").await;
+
+    let response = client
+        .get(&api_url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+    let streams: serde_json::Value = response.json().await?;
+    let stream = &streams.as_array().expect("a JSON array")[0];
+    assert_eq!(stream["filename"], "listed.bin");
+    assert_eq!(stream["state"], "live");
+    assert_eq!(stream["bytes_transferred"], 0);
+    assert_eq!(stream["downloader_connected"], false);
+    assert!(stream["age_secs"].is_u64());
+
+    let download = client
+        .get(&url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .send()
+        .await?;
+    assert_eq!(download.bytes().await?.len(), 100);
+    assert_eq!(upload.await??.status(), StatusCode::OK);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn version_reports_the_package_version() -> Result<()> {
+    let (base_url, server_handle) = start(ServerConfig::builder()).await;
+
+    let response = reqwest::get(format!("{base_url}/version")).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let version: serde_json::Value = response.json().await?;
+    assert_eq!(version["version"], env!("CARGO_PKG_VERSION"));
+    assert!(version["git"].as_str().is_some_and(|git| !git.is_empty()));
+    assert!(
+        version["rustc"]
+            .as_str()
+            .is_some_and(|rustc| rustc.starts_with("rustc"))
+    );
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+fn allowed_methods(response: &reqwest::Response) -> Vec<String> {
+    response.headers()[header::ALLOW]
+        .to_str()
+        .unwrap()
+        .split(',')
+        .map(|method| method.trim().to_owned())
+        .collect()
+}
+
+#[tokio::test]
+async fn unsupported_methods_get_405_listing_the_allowed_ones() -> Result<()> {
+    let (base_url, server_handle) = start(ServerConfig::builder()).await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .request(reqwest::Method::TRACE, format!("{base_url}/file.txt"))
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(
+        allowed_methods(&response),
+        ["GET", "HEAD", "PUT", "POST", "PATCH", "DELETE"]
+    );
+    assert!(response.text().await?.starts_with("TRACE is not supported"));
+
+    let response = client.delete(format!("{base_url}/healthz")).send().await?;
+    assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(allowed_methods(&response), ["GET", "HEAD"]);
+
+    server_handle.abort();
+
+    let (base_url, server_handle) = start(ServerConfig::builder().base_path("/beam")).await;
+    let response = client
+        .put(format!("{base_url}/beam/api/streams"))
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(allowed_methods(&response), ["GET", "HEAD"]);
+    assert!(response.text().await?.starts_with("PUT is not supported"));
+
+    server_handle.abort();
+
+    Ok(())
+}
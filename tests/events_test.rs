@@ -0,0 +1,94 @@
+mod common;
+
+use std::time::Duration;
+
+use anyhow::Result;
+use beam::{ServerConfig, setup_server_with_config};
+use common::send_when_pending;
+use futures_util::StreamExt;
+use reqwest::{StatusCode, header};
+use tokio::sync::mpsc;
+
+/// Parses the SSE feed from `response` into `(event, data)` pairs.
+fn collect_events(
+    response: reqwest::Response,
+) -> mpsc::UnboundedReceiver<(String, serde_json::Value)> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let mut stream = response.bytes_stream();
+        let mut buffer = String::new();
+        while let Some(Ok(chunk)) = stream.next().await {
+            buffer.push_str(&String::from_utf8_lossy(&chunk));
+            while let Some(end) = buffer.find("\n\n") {
+                let message: String = buffer.drain(..end + 2).collect();
+                let mut event = None;
+                let mut data = None;
+                for line in message.lines() {
+                    if let Some(name) = line.strip_prefix("event: ") {
+                        event = Some(name.to_owned());
+                    } else if let Some(json) = line.strip_prefix("data: ") {
+                        data = serde_json::from_str(json).ok();
+                    }
+                }
+                if let (Some(event), Some(data)) = (event, data) {
+                    let _ = tx.send((event, data));
+                }
+            }
+        }
+    });
+    rx
+}
+
+#[tokio::test]
+async fn events_follow_a_transfer_from_start_to_finish() -> Result<()> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials("alice", "secret123")
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let base_url = format!("http://localhost:{}", addr.port());
+    let client = reqwest::Client::new();
+
+    let unauthenticated = client.get(format!("{base_url}/events")).send().await?;
+    assert_eq!(unauthenticated.status(), StatusCode::UNAUTHORIZED);
+
+    let feed = client
+        .get(format!("{base_url}/events"))
+        .basic_auth("alice", Some("secret123"))
+        .send()
+        .await?;
+    assert_eq!(feed.status(), StatusCode::OK);
+    assert_eq!(feed.headers()[header::CONTENT_TYPE], "text/event-stream");
+    let mut events = collect_events(feed);
+
+    let url = format!("{base_url}/watched.txt");
+    let upload = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth("alice", Some("secret123"))
+            .body("observed")
+            .send(),
+    );
+    let download =
+        send_when_pending(client.get(&url).basic_auth("alice", Some("secret123"))).await?;
+    assert_eq!(download.text().await?, "observed");
+    assert_eq!(upload.await??.status(), StatusCode::OK);
+
+    for expected in [
+        "upload-started",
+        "downloader-connected",
+        "transfer-completed",
+    ] {
+        let (event, data) = tokio::time::timeout(Duration::from_secs(5), events.recv())
+            .await?
+            .expect("event feed ended early");
+        assert_eq!(event, expected);
+        assert_eq!(data["event"], expected);
+        assert_eq!(data["filename"], "watched.txt");
+        assert!(data["timestamp"].as_u64().is_some_and(|ms| ms > 0));
+    }
+
+    server_handle.abort();
+
+    Ok(())
+}
@@ -0,0 +1,195 @@
+mod common;
+
+use std::task::Poll;
+
+use anyhow::Result;
+use beam::{PublishError, ServerConfig, setup_server_with_handle};
+use bytes::Bytes;
+use common::send_when_pending;
+use futures_util::StreamExt;
+use reqwest::StatusCode;
+
+const USERNAME: &str = "alice";
+const PASSWORD: &str = "secret123";
+
+#[tokio::test(flavor = "multi_thread")]
+async fn a_panicking_transfer_leaves_the_others_running() -> Result<()> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+        .build();
+    let (beam, server_handle) = setup_server_with_handle(config).await?;
+    let client = reqwest::Client::new();
+    let healthy_url = beam.download_url("healthy.txt");
+
+    let healthy_upload = tokio::spawn(
+        client
+            .put(&healthy_url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .body("still fine")
+            .send(),
+    );
+
+    let faulty = futures_util::stream::iter([Bytes::from("first chunk")]).chain(
+        futures_util::stream::poll_fn(|_| -> Poll<Option<Bytes>> { panic!("injected fault") }),
+    );
+    let publication = beam.publish("faulty.bin", faulty)?;
+    let faulty_download = send_when_pending(
+        client
+            .get(publication.url())
+            .basic_auth(USERNAME, Some(PASSWORD)),
+    )
+    .await?;
+    assert_eq!(faulty_download.status(), StatusCode::OK);
+    assert!(
+        faulty_download.bytes().await.is_err(),
+        "a download cut short by a panic must not look complete"
+    );
+    match publication.finished().await {
+        Err(PublishError::Failed { status, message }) => {
+            assert_eq!(status, 503);
+            assert!(message.contains("try again"), "{message}");
+        }
+        other => panic!("expected the faulty publication to fail, got {other:?}"),
+    }
+
+    let healthy_download = send_when_pending(
+        client
+            .get(&healthy_url)
+            .basic_auth(USERNAME, Some(PASSWORD)),
+    )
+    .await?;
+    assert_eq!(healthy_download.text().await?, "still fine");
+    assert_eq!(healthy_upload.await??.status(), StatusCode::OK);
+
+    // The faulty stream's name was freed for a fresh upload.
+    let retry = beam.publish(
+        "faulty.bin",
+        futures_util::stream::iter([Bytes::from("retry")]),
+    )?;
+    let retried =
+        send_when_pending(client.get(retry.url()).basic_auth(USERNAME, Some(PASSWORD))).await?;
+    assert_eq!(retried.text().await?, "retry");
+    retry.finished().await?;
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+/// Uploads `prefix` to `filename` and, once beam has relayed all of it,
+/// fails the upload body. Returns what the download, requested with
+/// `accept_encoding`, received before its body ended in an error.
+async fn download_of_failed_upload(
+    base_url: &str,
+    filename: &str,
+    prefix: &[Bytes],
+    accept_encoding: &str,
+) -> Result<Vec<u8>> {
+    let client = reqwest::Client::new();
+    let url = format!("{base_url}/{filename}");
+    let (body_tx, body_rx) = tokio::sync::mpsc::channel::<Result<Bytes, std::io::Error>>(1);
+    let upload = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .body(reqwest::Body::wrap_stream(
+                tokio_stream::wrappers::ReceiverStream::new(body_rx),
+            ))
+            .send(),
+    );
+    let mut download = send_when_pending(
+        client
+            .get(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .header(reqwest::header::ACCEPT_ENCODING, accept_encoding),
+    )
+    .await?;
+    assert_eq!(download.status(), StatusCode::OK);
+
+    for chunk in prefix {
+        body_tx.send(Ok(chunk.clone())).await?;
+    }
+    let prefix_len = prefix.iter().map(Bytes::len).sum::<usize>() as u64;
+    let progress_url = format!("{base_url}/progress/{filename}");
+    loop {
+        let progress: serde_json::Value = client
+            .get(&progress_url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .send()
+            .await?
+            .json()
+            .await?;
+        if progress["bytes_transferred"].as_u64() == Some(prefix_len) {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    body_tx
+        .send(Err(std::io::Error::other("injected fault")))
+        .await?;
+    // The uploader sees its own error rather than beam's answer.
+    let _ = upload.await?;
+
+    let mut received = Vec::new();
+    loop {
+        match download.chunk().await {
+            Ok(Some(chunk)) => received.extend_from_slice(&chunk),
+            Ok(None) => panic!("a download cut short by a failed upload must not look complete"),
+            Err(_) => return Ok(received),
+        }
+    }
+}
+
+fn prefix_chunks() -> Vec<Bytes> {
+    (0..4)
+        .map(|chunk| Bytes::from(format!("chunk {chunk} of the prefix\n").repeat(1_000)))
+        .collect()
+}
+
+#[tokio::test]
+async fn a_failed_upload_still_delivers_the_bytes_before_the_error() -> Result<()> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+        .build();
+    let (beam, server_handle) = setup_server_with_handle(config).await?;
+    let base_url = format!("http://{}", beam.local_addr());
+    let prefix = prefix_chunks();
+
+    let received = download_of_failed_upload(&base_url, "cut.log", &prefix, "identity").await?;
+    assert_eq!(received, prefix.concat());
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn a_failed_compressed_download_still_decompresses_to_the_prefix() -> Result<()> {
+    use std::io::Read;
+
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+        .compress_downloads(true)
+        .build();
+    let (beam, server_handle) = setup_server_with_handle(config).await?;
+    let base_url = format!("http://{}", beam.local_addr());
+    let prefix = prefix_chunks();
+
+    let received = download_of_failed_upload(&base_url, "cut.log", &prefix, "gzip").await?;
+    // The gzip stream has no end, so reading stops at an error; everything
+    // before it must be there.
+    let mut decompressed = Vec::new();
+    let mut decoder = flate2::read::GzDecoder::new(&received[..]);
+    let mut buf = [0; 4096];
+    while let Ok(read @ 1..) = decoder.read(&mut buf) {
+        decompressed.extend_from_slice(&buf[..read]);
+    }
+    assert_eq!(decompressed, prefix.concat());
+
+    server_handle.abort();
+
+    Ok(())
+}
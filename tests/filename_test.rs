@@ -0,0 +1,257 @@
+mod common;
+
+use anyhow::Result;
+use beam::ServerConfig;
+use common::{PASSWORD, USERNAME, send_when_pending, start};
+use reqwest::{StatusCode, header};
+
+/// Uploads `body` to `encoded_name` and returns the download's
+/// Content-Disposition header.
+async fn round_trip_disposition(base_url: &str, encoded_name: &str) -> Result<String> {
+    let client = reqwest::Client::new();
+    let url = format!("{base_url}/{encoded_name}");
+
+    let upload = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .body("contents")
+            .send(),
+    );
+
+    let download = send_when_pending(client.get(&url).basic_auth(USERNAME, Some(PASSWORD))).await?;
+    assert_eq!(download.status(), StatusCode::OK);
+    let disposition = download.headers()[header::CONTENT_DISPOSITION]
+        .to_str()?
+        .to_owned();
+    assert_eq!(download.text().await?, "contents");
+    assert_eq!(upload.await??.status(), StatusCode::OK);
+
+    Ok(disposition)
+}
+
+#[tokio::test]
+async fn path_traversal_names_are_rejected() -> Result<()> {
+    let (base_url, server_handle) = start(ServerConfig::builder()).await;
+    let client = reqwest::Client::new();
+
+    for name in [
+        "..%2Fetc%2Fpasswd",
+        "..%5Cwindows",
+        "bad%00name",
+        "tab%09name",
+    ] {
+        let url = format!("{base_url}/{name}");
+
+        let upload = client
+            .put(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .body("payload")
+            .send()
+            .await?;
+        assert_eq!(upload.status(), StatusCode::BAD_REQUEST, "PUT {name}");
+
+        let download = client
+            .get(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .send()
+            .await?;
+        assert_eq!(download.status(), StatusCode::BAD_REQUEST, "GET {name}");
+    }
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn names_over_the_byte_limit_are_rejected() -> Result<()> {
+    let (base_url, server_handle) = start(ServerConfig::builder()).await;
+    let client = reqwest::Client::new();
+
+    // 128 two-byte characters: under 255 chars, but 256 bytes.
+    let accented = "%C3%A9".repeat(128);
+    for name in ["a".repeat(256), accented] {
+        let url = format!("{base_url}/{name}");
+
+        let upload = client
+            .put(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .body("payload")
+            .send()
+            .await?;
+        assert_eq!(upload.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(upload.text().await?, "Filename is too long");
+
+        let download = client
+            .get(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .send()
+            .await?;
+        assert_eq!(download.status(), StatusCode::BAD_REQUEST);
+    }
+
+    // Exactly at the limit is fine.
+    let url = format!("{base_url}/{}", "a".repeat(255));
+    let download = client
+        .get(&url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .send()
+        .await?;
+    assert_eq!(download.status(), StatusCode::NOT_FOUND);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn embedded_quotes_are_escaped_in_content_disposition() -> Result<()> {
+    let (base_url, server_handle) = start(ServerConfig::builder()).await;
+
+    let disposition = round_trip_disposition(&base_url, "say%22hi%22.txt").await?;
+    assert_eq!(
+        disposition,
+        r#"attachment; filename="say\"hi\".txt"; filename*=UTF-8''say%22hi%22.txt"#
+    );
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn utf8_filenames_get_an_encoded_filename_parameter() -> Result<()> {
+    let (base_url, server_handle) = start(ServerConfig::builder()).await;
+
+    let disposition = round_trip_disposition(&base_url, "r%C3%A9sum%C3%A9.pdf").await?;
+    assert_eq!(
+        disposition,
+        "attachment; filename=\"r_sum_.pdf\"; filename*=UTF-8''r%C3%A9sum%C3%A9.pdf"
+    );
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn spaces_stay_in_the_quoted_filename() -> Result<()> {
+    let (base_url, server_handle) = start(ServerConfig::builder()).await;
+
+    let disposition = round_trip_disposition(&base_url, "report%202024.pdf").await?;
+    assert_eq!(disposition, r#"attachment; filename="report 2024.pdf""#);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn accented_filenames_keep_an_ascii_fallback() -> Result<()> {
+    let (base_url, server_handle) = start(ServerConfig::builder()).await;
+
+    let disposition = round_trip_disposition(&base_url, "posici%C3%B3n.csv").await?;
+    assert_eq!(
+        disposition,
+        "attachment; filename=\"posici_n.csv\"; filename*=UTF-8''posici%C3%B3n.csv"
+    );
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn as_query_overrides_the_suggested_name() -> Result<()> {
+    let (base_url, server_handle) = start(ServerConfig::builder()).await;
+    let client = reqwest::Client::new();
+    let url = format!("{base_url}/upload-7f3a.bin");
+
+    let invalid = client
+        .get(format!("{url}?as=..%2Fescape.zip"))
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .send()
+        .await?;
+    assert_eq!(invalid.status(), StatusCode::BAD_REQUEST);
+
+    let upload = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .body("contents")
+            .send(),
+    );
+    let download = send_when_pending(
+        client
+            .get(format!("{url}?as=pretty%20name.zip"))
+            .basic_auth(USERNAME, Some(PASSWORD)),
+    )
+    .await?;
+    assert_eq!(download.status(), StatusCode::OK);
+    assert_eq!(
+        download.headers()[header::CONTENT_DISPOSITION],
+        r#"attachment; filename="pretty name.zip""#
+    );
+    assert_eq!(download.text().await?, "contents");
+    assert_eq!(upload.await??.status(), StatusCode::OK);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn denied_extensions_are_forbidden() -> Result<()> {
+    let (base_url, server_handle) =
+        start(ServerConfig::builder().denied_extensions([".exe", "tar.gz"])).await;
+    let client = reqwest::Client::new();
+
+    for name in ["setup.exe", "SETUP.EXE", "backup.Tar.Gz"] {
+        let url = format!("{base_url}/{name}");
+        let upload = client
+            .put(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .body("blocked")
+            .send()
+            .await?;
+        assert_eq!(upload.status(), StatusCode::FORBIDDEN, "{name}");
+        let download = client
+            .get(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .send()
+            .await?;
+        assert_eq!(download.status(), StatusCode::FORBIDDEN, "{name}");
+    }
+
+    // Only the compound extension was denied, not every `.gz`.
+    let disposition = round_trip_disposition(&base_url, "notes.gz").await?;
+    assert_eq!(disposition, r#"attachment; filename="notes.gz""#);
+    let disposition = round_trip_disposition(&base_url, "readme.txt").await?;
+    assert_eq!(disposition, r#"attachment; filename="readme.txt""#);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn allowed_extensions_refuse_everything_else() -> Result<()> {
+    let (base_url, server_handle) =
+        start(ServerConfig::builder().allowed_extensions(["txt"])).await;
+
+    let upload = reqwest::Client::new()
+        .put(format!("{base_url}/no-extension"))
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .body("blocked")
+        .send()
+        .await?;
+    assert_eq!(upload.status(), StatusCode::FORBIDDEN);
+
+    let disposition = round_trip_disposition(&base_url, "Readme.TXT").await?;
+    assert_eq!(disposition, r#"attachment; filename="Readme.TXT""#);
+
+    server_handle.abort();
+
+    Ok(())
+}
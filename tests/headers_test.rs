@@ -0,0 +1,338 @@
+mod common;
+
+use anyhow::Result;
+use beam::ServerConfig;
+use common::{PASSWORD, USERNAME, send_when_pending, start};
+use reqwest::{StatusCode, header};
+
+#[tokio::test]
+async fn content_type_round_trips_to_downloader() -> Result<()> {
+    let (base_url, server_handle) = start(ServerConfig::builder()).await;
+    let client = reqwest::Client::new();
+    let url = format!("{base_url}/notes.txt");
+
+    let upload = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .header(header::CONTENT_TYPE, "text/plain; charset=utf-8")
+            .body("plain text")
+            .send(),
+    );
+
+    let download = send_when_pending(client.get(&url).basic_auth(USERNAME, Some(PASSWORD))).await?;
+    assert_eq!(download.status(), StatusCode::OK);
+    assert_eq!(
+        download.headers()[header::CONTENT_TYPE],
+        "text/plain; charset=utf-8"
+    );
+    assert_eq!(download.text().await?, "plain text");
+    assert_eq!(upload.await??.status(), StatusCode::OK);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn missing_content_type_defaults_to_octet_stream() -> Result<()> {
+    let (base_url, server_handle) = start(ServerConfig::builder()).await;
+    let client = reqwest::Client::new();
+    let url = format!("{base_url}/blob");
+
+    let upload = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .body(vec![1u8, 2, 3])
+            .send(),
+    );
+
+    let download = send_when_pending(client.get(&url).basic_auth(USERNAME, Some(PASSWORD))).await?;
+    assert_eq!(
+        download.headers()[header::CONTENT_TYPE],
+        "application/octet-stream"
+    );
+    assert_eq!(download.bytes().await?.as_ref(), [1, 2, 3]);
+    assert_eq!(upload.await??.status(), StatusCode::OK);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn missing_content_type_is_guessed_from_the_extension() -> Result<()> {
+    let (base_url, server_handle) =
+        start(ServerConfig::builder().content_type(".LOG", "text/plain; charset=utf-8")).await;
+    let client = reqwest::Client::new();
+
+    for (filename, expected) in [
+        ("screenshot.png", "image/png"),
+        ("build.log", "text/plain; charset=utf-8"),
+    ] {
+        let url = format!("{base_url}/{filename}");
+        let upload = tokio::spawn(
+            client
+                .put(&url)
+                .basic_auth(USERNAME, Some(PASSWORD))
+                .body(vec![1u8, 2, 3])
+                .send(),
+        );
+
+        let download =
+            send_when_pending(client.get(&url).basic_auth(USERNAME, Some(PASSWORD))).await?;
+        assert_eq!(download.headers()[header::CONTENT_TYPE], expected);
+        assert_eq!(download.bytes().await?.as_ref(), [1, 2, 3]);
+        assert_eq!(upload.await??.status(), StatusCode::OK);
+    }
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn content_length_round_trips_for_fixed_size_body() -> Result<()> {
+    let (base_url, server_handle) = start(ServerConfig::builder()).await;
+    let client = reqwest::Client::new();
+    let url = format!("{base_url}/sized.bin");
+    let payload = vec![42u8; 64 * 1024];
+
+    let upload = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .body(payload.clone())
+            .send(),
+    );
+
+    let download = send_when_pending(client.get(&url).basic_auth(USERNAME, Some(PASSWORD))).await?;
+    assert_eq!(download.status(), StatusCode::OK);
+    assert_eq!(download.headers()[header::CONTENT_LENGTH], "65536");
+    assert_eq!(download.content_length(), Some(payload.len() as u64));
+    assert_eq!(download.bytes().await?.to_vec(), payload);
+    assert_eq!(upload.await??.status(), StatusCode::OK);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn streamed_upload_without_length_stays_chunked() -> Result<()> {
+    let (base_url, server_handle) = start(ServerConfig::builder()).await;
+    let client = reqwest::Client::new();
+    let url = format!("{base_url}/streamed.txt");
+
+    let chunks: Vec<Result<&'static str, std::io::Error>> =
+        vec![Ok("chunk one, "), Ok("chunk two")];
+    let upload = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .body(reqwest::Body::wrap_stream(futures_util::stream::iter(
+                chunks,
+            )))
+            .send(),
+    );
+
+    let download = send_when_pending(client.get(&url).basic_auth(USERNAME, Some(PASSWORD))).await?;
+    assert!(download.headers().get(header::CONTENT_LENGTH).is_none());
+    assert_eq!(download.headers()[header::TRANSFER_ENCODING], "chunked");
+    assert_eq!(download.text().await?, "chunk one, chunk two");
+    assert_eq!(upload.await??.status(), StatusCode::OK);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn head_probes_a_pending_upload_without_consuming_it() -> Result<()> {
+    let (base_url, server_handle) = start(ServerConfig::builder()).await;
+    let client = reqwest::Client::new();
+    let url = format!("{base_url}/probe.txt");
+
+    let missing = client
+        .head(&url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .send()
+        .await?;
+    assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+
+    let upload = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .header(header::CONTENT_TYPE, "text/plain")
+            .body("still here")
+            .send(),
+    );
+
+    let probe = send_when_pending(client.head(&url).basic_auth(USERNAME, Some(PASSWORD))).await?;
+    assert_eq!(probe.status(), StatusCode::OK);
+    assert_eq!(probe.headers()[header::CONTENT_TYPE], "text/plain");
+    assert_eq!(probe.headers()[header::CONTENT_LENGTH], "10");
+
+    let download = client
+        .get(&url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .send()
+        .await?;
+    assert_eq!(download.status(), StatusCode::OK);
+    assert_eq!(download.text().await?, "still here");
+    assert_eq!(upload.await??.status(), StatusCode::OK);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn uploads_can_ask_for_inline_display() -> Result<()> {
+    let (base_url, server_handle) = start(ServerConfig::builder()).await;
+    let client = reqwest::Client::new();
+    let url = format!("{base_url}/chart.png");
+
+    let rejected = client
+        .put(&url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .header("X-Content-Disposition", "sideways")
+        .body("png")
+        .send()
+        .await?;
+    assert_eq!(rejected.status(), StatusCode::BAD_REQUEST);
+
+    let upload = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .header("X-Content-Disposition", "inline")
+            .body("png")
+            .send(),
+    );
+
+    let download = send_when_pending(client.get(&url).basic_auth(USERNAME, Some(PASSWORD))).await?;
+    assert_eq!(
+        download.headers()[header::CONTENT_DISPOSITION],
+        r#"inline; filename="chart.png""#
+    );
+    assert_eq!(download.text().await?, "png");
+    assert_eq!(upload.await??.status(), StatusCode::OK);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn missing_download_suggests_when_to_retry() -> Result<()> {
+    let (base_url, server_handle) = start(ServerConfig::builder()).await;
+    let missing = reqwest::Client::new()
+        .get(format!("{base_url}/later.txt"))
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .send()
+        .await?;
+    assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+    assert!(missing.headers().get(header::RETRY_AFTER).is_none());
+    server_handle.abort();
+
+    let (base_url, server_handle) = start(
+        ServerConfig::builder().not_found_retry_after(std::time::Duration::from_millis(2500)),
+    )
+    .await;
+    let missing = reqwest::Client::new()
+        .get(format!("{base_url}/later.txt"))
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .send()
+        .await?;
+    assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+    assert_eq!(missing.headers()[header::RETRY_AFTER], "3");
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn metadata_headers_round_trip_to_downloader() -> Result<()> {
+    let (base_url, server_handle) = start(ServerConfig::builder()).await;
+    let client = reqwest::Client::new();
+    let url = format!("{base_url}/build.tar");
+
+    let upload = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .header("X-Meta-Source", "ci")
+            .header("X-Meta-Commit", "f7fa97a")
+            .header("X-Unrelated", "dropped")
+            .body("archive")
+            .send(),
+    );
+
+    let download = send_when_pending(client.get(&url).basic_auth(USERNAME, Some(PASSWORD))).await?;
+    assert_eq!(download.headers()["x-meta-source"], "ci");
+    assert_eq!(download.headers()["x-meta-commit"], "f7fa97a");
+    assert!(download.headers().get("x-unrelated").is_none());
+    assert_eq!(download.text().await?, "archive");
+    assert_eq!(upload.await??.status(), StatusCode::OK);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn too_many_metadata_headers_are_refused() -> Result<()> {
+    let (base_url, server_handle) = start(ServerConfig::builder()).await;
+    let client = reqwest::Client::new();
+
+    let mut upload = client
+        .put(format!("{base_url}/noisy.txt"))
+        .basic_auth(USERNAME, Some(PASSWORD));
+    for index in 0..=beam::MAX_METADATA_HEADERS {
+        upload = upload.header(format!("X-Meta-Field-{index}"), "value");
+    }
+    let upload = upload.body("payload").send().await?;
+    assert_eq!(upload.status(), StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn metadata_limits_are_configurable() -> Result<()> {
+    let (base_url, server_handle) = start(ServerConfig::builder().metadata_limits(2, 64)).await;
+    let url = format!("{base_url}/tagged.txt");
+    let client = reqwest::Client::new();
+    let upload = |count: usize, value: &str| {
+        let mut upload = client.put(&url).basic_auth(USERNAME, Some(PASSWORD));
+        for index in 0..count {
+            upload = upload.header(format!("X-Meta-Tag-{index}"), value);
+        }
+        upload.body("payload").send()
+    };
+
+    let too_many = upload(3, "short").await?;
+    assert_eq!(
+        too_many.status(),
+        StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE
+    );
+    let too_long = upload(1, &"x".repeat(64)).await?;
+    assert_eq!(
+        too_long.status(),
+        StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE
+    );
+
+    let within = tokio::spawn(upload(2, "short"));
+    let download = send_when_pending(client.get(&url).basic_auth(USERNAME, Some(PASSWORD))).await?;
+    assert_eq!(download.headers()["x-meta-tag-1"], "short");
+    assert_eq!(download.text().await?, "payload");
+    assert_eq!(within.await??.status(), StatusCode::OK);
+
+    server_handle.abort();
+
+    Ok(())
+}
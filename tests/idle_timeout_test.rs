@@ -0,0 +1,106 @@
+mod common;
+
+use std::time::Duration;
+
+use anyhow::Result;
+use beam::{MinUploadRate, ServerConfig, setup_server_with_config};
+use bytes::Bytes;
+use common::{send_when_pending, wait_for_stream_gone};
+use reqwest::StatusCode;
+use tokio_stream::wrappers::ReceiverStream;
+
+#[tokio::test]
+async fn stalled_upload_is_aborted_and_removed() -> Result<()> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials("alice", "secret123")
+        .idle_timeout(Duration::from_millis(200))
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let base_url = format!("http://localhost:{}", addr.port());
+    let url = format!("{base_url}/stalled.bin");
+    let client = reqwest::Client::new();
+
+    // Keep the sender alive so the body neither ends nor errors.
+    let (body_tx, body_rx) = tokio::sync::mpsc::channel::<Result<Bytes, std::io::Error>>(1);
+    body_tx.send(Ok(Bytes::from_static(b"partial"))).await?;
+    let upload = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth("alice", Some("secret123"))
+            .body(reqwest::Body::wrap_stream(ReceiverStream::new(body_rx)))
+            .send(),
+    );
+
+    let download =
+        send_when_pending(client.get(&url).basic_auth("alice", Some("secret123"))).await?;
+    assert_eq!(download.status(), StatusCode::OK);
+    assert!(
+        download.bytes().await.is_err(),
+        "download should fail once the upload stalls"
+    );
+
+    wait_for_stream_gone(&base_url, "stalled.bin").await;
+    // The uploader may see a reset instead, since it is still sending.
+    if let Ok(Ok(Ok(response))) = tokio::time::timeout(Duration::from_secs(1), upload).await {
+        assert_eq!(response.status(), StatusCode::REQUEST_TIMEOUT);
+    }
+    drop(body_tx);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn trickling_upload_is_cut_off_below_the_minimum_rate() -> Result<()> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials("alice", "secret123")
+        .min_upload_rate(MinUploadRate {
+            bytes_per_sec: 1_000,
+            window: Duration::from_millis(300),
+        })
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let base_url = format!("http://localhost:{}", addr.port());
+    let url = format!("{base_url}/trickle.bin");
+    let client = reqwest::Client::new();
+
+    // 10 bytes every 50ms: never idle, but only 200 bytes per second.
+    let (body_tx, body_rx) = tokio::sync::mpsc::channel::<Result<Bytes, std::io::Error>>(1);
+    let trickle = tokio::spawn(async move {
+        while body_tx
+            .send(Ok(Bytes::from_static(b"0123456789")))
+            .await
+            .is_ok()
+        {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    });
+    let upload = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth("alice", Some("secret123"))
+            .body(reqwest::Body::wrap_stream(ReceiverStream::new(body_rx)))
+            .send(),
+    );
+
+    let download =
+        send_when_pending(client.get(&url).basic_auth("alice", Some("secret123"))).await?;
+    assert_eq!(download.status(), StatusCode::OK);
+    assert!(
+        download.bytes().await.is_err(),
+        "download should fail once the upload falls below the minimum rate"
+    );
+
+    wait_for_stream_gone(&base_url, "trickle.bin").await;
+    if let Ok(Ok(Ok(response))) = tokio::time::timeout(Duration::from_secs(1), upload).await {
+        assert_eq!(response.status(), StatusCode::REQUEST_TIMEOUT);
+    }
+    trickle.abort();
+
+    server_handle.abort();
+
+    Ok(())
+}
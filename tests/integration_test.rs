@@ -1,18 +1,18 @@
-use anyhow::Result;
-use beam::setup_server_with_port;
-use reqwest;
-use tokio;
+mod common;
+
+use std::time::Duration;
 
-type Port = u16;
+use anyhow::Result;
+use beam::{ServerConfig, setup_server_with_config, setup_server_with_port};
+use common::send_when_pending;
 
 #[tokio::test]
 async fn test_upload_download_stream() -> Result<()> {
-    let port: Port = 3001;
     let username = "alice";
     let password = "secret123";
 
-    let server_handle = setup_server_with_port(port, username, password).await;
-    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+    let (addr, server_handle) = setup_server_with_port(0, username, password).await?;
+    let port = addr.port();
 
     let client = reqwest::Client::new();
 
@@ -20,32 +20,29 @@ async fn test_upload_download_stream() -> Result<()> {
     let upload_content = "Hello, world!";
 
     let upload_url = format!("http://localhost:{port}/{}", file_name);
-    let upload_response_future = client
-        .put(&upload_url)
-        .basic_auth(username, Some(password))
-        .body(upload_content)
-        .send();
-
-    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+    let upload_response_future = tokio::spawn(
+        client
+            .put(&upload_url)
+            .basic_auth(username, Some(password))
+            .body(upload_content)
+            .send(),
+    );
 
     let download_url = format!("http://localhost:{port}/{}", file_name);
-    let download_response_future = client
-        .get(&download_url)
-        .basic_auth(username, Some(password))
-        .send();
-
-    let (upload_response, download_response) =
-        tokio::join!(upload_response_future, download_response_future);
-
-    let upload_response = upload_response?;
-    assert_eq!(upload_response.status(), reqwest::StatusCode::OK);
-
-    let download_response = download_response?;
+    let download_response = send_when_pending(
+        client
+            .get(&download_url)
+            .basic_auth(username, Some(password)),
+    )
+    .await?;
     assert_eq!(download_response.status(), reqwest::StatusCode::OK);
 
     let downloaded_content = download_response.text().await?;
     assert_eq!(downloaded_content, upload_content);
 
+    let upload_response = upload_response_future.await??;
+    assert_eq!(upload_response.status(), reqwest::StatusCode::OK);
+
     server_handle.abort();
 
     Ok(())
@@ -53,12 +50,11 @@ async fn test_upload_download_stream() -> Result<()> {
 
 #[tokio::test]
 async fn test_download_without_upload_returns_404() -> Result<()> {
-    let port: Port = 3002;
     let username = "bob";
     let password = "hunter2";
 
-    let server_handle = setup_server_with_port(port, username, password).await;
-    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+    let (addr, server_handle) = setup_server_with_port(0, username, password).await?;
+    let port = addr.port();
 
     let client = reqwest::Client::new();
     let file_name = "nonexistent_file.txt";
@@ -78,12 +74,11 @@ async fn test_download_without_upload_returns_404() -> Result<()> {
 
 #[tokio::test]
 async fn test_upload_download_binary_file() -> Result<()> {
-    let port: Port = 3003;
     let username = "carol";
     let password = "sup3rsecret";
 
-    let server_handle = setup_server_with_port(port, username, password).await;
-    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+    let (addr, server_handle) = setup_server_with_port(0, username, password).await?;
+    let port = addr.port();
 
     let client = reqwest::Client::new();
     let file_name = "test_binary.bin";
@@ -95,31 +90,109 @@ async fn test_upload_download_binary_file() -> Result<()> {
     }
 
     let upload_url = format!("http://localhost:{port}/{}", file_name);
-    let upload_response_future = client
-        .put(&upload_url)
-        .basic_auth(username, Some(password))
-        .body(binary_content.clone())
-        .send();
-
-    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+    let upload_response_future = tokio::spawn(
+        client
+            .put(&upload_url)
+            .basic_auth(username, Some(password))
+            .body(binary_content.clone())
+            .send(),
+    );
 
     let download_url = format!("http://localhost:{port}/{}", file_name);
-    let download_response_future = client
+    let download_response = send_when_pending(
+        client
+            .get(&download_url)
+            .basic_auth(username, Some(password)),
+    )
+    .await?;
+    assert_eq!(download_response.status(), reqwest::StatusCode::OK);
+
+    let downloaded_bytes = download_response.bytes().await?;
+    assert_eq!(downloaded_bytes.to_vec(), binary_content);
+
+    let upload_response = upload_response_future.await??;
+    assert_eq!(upload_response.status(), reqwest::StatusCode::OK);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_download_with_builder_config() -> Result<()> {
+    let username = "dave";
+    let password = "correct-horse";
+
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(username, password)
+        .channel_buffer(4)
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let port = addr.port();
+
+    let client = reqwest::Client::new();
+    let download_url = format!("http://localhost:{port}/missing.txt");
+
+    let unauthorized = client.get(&download_url).send().await?;
+    assert_eq!(unauthorized.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+    let not_found = client
         .get(&download_url)
         .basic_auth(username, Some(password))
-        .send();
+        .send()
+        .await?;
+    assert_eq!(not_found.status(), reqwest::StatusCode::NOT_FOUND);
 
-    let (upload_response, download_response) =
-        tokio::join!(upload_response_future, download_response_future);
+    server_handle.abort();
 
-    let upload_response = upload_response?;
-    assert_eq!(upload_response.status(), reqwest::StatusCode::OK);
+    Ok(())
+}
 
-    let download_response = download_response?;
-    assert_eq!(download_response.status(), reqwest::StatusCode::OK);
+#[tokio::test]
+async fn test_upload_times_out_without_download() -> Result<()> {
+    let username = "erin";
+    let password = "open-sesame";
+
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(username, password)
+        .upload_ready_timeout(Duration::from_millis(200))
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let port = addr.port();
 
-    let downloaded_bytes = download_response.bytes().await?;
-    assert_eq!(downloaded_bytes.to_vec(), binary_content);
+    let client = reqwest::Client::new();
+    let upload_url = format!("http://localhost:{port}/lonely.txt");
+    let upload_response = tokio::time::timeout(
+        Duration::from_secs(5),
+        client
+            .put(&upload_url)
+            .basic_auth(username, Some(password))
+            .body("nobody is listening")
+            .send(),
+    )
+    .await??;
+
+    assert_eq!(
+        upload_response.status(),
+        reqwest::StatusCode::GATEWAY_TIMEOUT
+    );
+    let body = upload_response.text().await?;
+    assert!(body.contains("Timeout"), "unexpected body: {body}");
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_bound_address_reports_ephemeral_port() -> Result<()> {
+    let (addr, server_handle) = setup_server_with_port(0, "frank", "pa55word").await?;
+    assert_ne!(addr.port(), 0);
+
+    let response = reqwest::get(format!("http://localhost:{}/", addr.port())).await?;
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
 
     server_handle.abort();
 
@@ -0,0 +1,62 @@
+use anyhow::Result;
+use beam::{BeamError, ServerConfig, ServerConfigBuilder, setup_server_with_config};
+use reqwest::StatusCode;
+
+const USERNAME: &str = "alice";
+const PASSWORD: &str = "secret123";
+
+fn builder() -> ServerConfigBuilder {
+    ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+}
+
+/// Status of an authenticated request for the stream list.
+async fn list_streams(config: ServerConfig) -> Result<StatusCode> {
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let status = reqwest::Client::new()
+        .get(format!("http://127.0.0.1:{}/api/streams", addr.port()))
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .send()
+        .await?
+        .status();
+    server_handle.abort();
+    Ok(status)
+}
+
+#[tokio::test]
+async fn loopback_allowlist_admits_local_peers_only() -> Result<()> {
+    let loopback = builder().allowed_ips(["127.0.0.0/8", "::1/128"]).build();
+    assert_eq!(list_streams(loopback).await?, StatusCode::OK);
+
+    let single_address = builder().allowed_ips(["127.0.0.1"]).build();
+    assert_eq!(list_streams(single_address).await?, StatusCode::OK);
+
+    let elsewhere = builder().allowed_ips(["10.0.0.0/8", "fd00::/8"]).build();
+    assert_eq!(list_streams(elsewhere).await?, StatusCode::FORBIDDEN);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn denylist_wins_over_allowlist() -> Result<()> {
+    let denied = builder()
+        .allowed_ips(["127.0.0.0/8"])
+        .denied_ips(["127.0.0.1/32"])
+        .build();
+    assert_eq!(list_streams(denied).await?, StatusCode::FORBIDDEN);
+
+    let others_denied = builder().denied_ips(["192.168.0.0/16"]).build();
+    assert_eq!(list_streams(others_denied).await?, StatusCode::OK);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn malformed_blocks_fail_startup() {
+    let config = builder().allowed_ips(["127.0.0.1/33"]).build();
+    assert!(matches!(
+        setup_server_with_config(config).await,
+        Err(BeamError::IpFilter(_))
+    ));
+}
@@ -0,0 +1,59 @@
+mod common;
+
+use anyhow::Result;
+use beam::ServerConfig;
+use common::{PASSWORD, USERNAME, start};
+use reqwest::{StatusCode, header};
+
+#[tokio::test]
+async fn missing_download_answers_in_json_when_asked() -> Result<()> {
+    let (base_url, server_handle) = start(ServerConfig::builder()).await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .get(format!("{base_url}/missing.txt"))
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .header(header::ACCEPT, "application/json")
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+    let body: serde_json::Value = response.json().await?;
+    assert_eq!(
+        body,
+        serde_json::json!({
+            "error": "not_found",
+            "message": "No active upload stream for this file",
+        })
+    );
+
+    let plain = client
+        .get(format!("{base_url}/missing.txt"))
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .send()
+        .await?;
+    assert_eq!(plain.text().await?, "No active upload stream for this file");
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn json_auth_errors_keep_their_challenge() -> Result<()> {
+    let (base_url, server_handle) = start(ServerConfig::builder()).await;
+
+    let response = reqwest::Client::new()
+        .get(format!("{base_url}/missing.txt"))
+        .header(header::ACCEPT, "text/html, application/json;q=0.9")
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert!(response.headers().contains_key(header::WWW_AUTHENTICATE));
+    let body: serde_json::Value = response.json().await?;
+    assert_eq!(body["error"], "unauthorized");
+
+    server_handle.abort();
+
+    Ok(())
+}
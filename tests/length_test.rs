@@ -0,0 +1,123 @@
+mod common;
+
+use anyhow::Result;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use beam::ServerConfig;
+use common::{PASSWORD, USERNAME, send_when_pending, start, wait_for_stream};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+fn authorization() -> String {
+    STANDARD.encode(format!("{USERNAME}:{PASSWORD}"))
+}
+
+/// Opens a raw connection to the server at `base_url`.
+async fn connect(base_url: &str) -> Result<TcpStream> {
+    let authority = base_url
+        .strip_prefix("http://")
+        .expect("server is plain HTTP");
+    Ok(TcpStream::connect(authority).await?)
+}
+
+/// Uploads `body` chunked while declaring a `Content-Length` of `declared`,
+/// which hyper lets through, and returns the raw response.
+async fn put_mislabelled(
+    base_url: String,
+    filename: &str,
+    declared: usize,
+    body: &str,
+) -> Result<String> {
+    let mut stream = connect(&base_url).await?;
+    stream
+        .write_all(
+            format!(
+                "PUT /{filename} HTTP/1.1\r\nHost: localhost\r\nAuthorization: Basic {}\r\nContent-Length: {declared}\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n{:x}\r\n{body}\r\n0\r\n\r\n",
+                authorization(),
+                body.len(),
+            )
+            .as_bytes(),
+        )
+        .await?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await?;
+    Ok(response)
+}
+
+#[tokio::test]
+async fn body_longer_than_its_content_length_fails() -> Result<()> {
+    let (base_url, server_handle) = start(ServerConfig::builder()).await;
+    let url = format!("{base_url}/long.bin");
+
+    let upload = tokio::spawn(put_mislabelled(
+        base_url.clone(),
+        "long.bin",
+        5,
+        "ten bytes!",
+    ));
+    let download = send_when_pending(
+        reqwest::Client::new()
+            .get(&url)
+            .basic_auth(USERNAME, Some(PASSWORD)),
+    )
+    .await?;
+    assert_eq!(download.headers()["content-length"], "5");
+    assert!(download.bytes().await.is_err(), "download should fail");
+
+    let response = upload.await??;
+    assert!(
+        response.starts_with("HTTP/1.1 400"),
+        "unexpected response: {response}"
+    );
+    assert!(response.contains("Content-Length of 5 bytes"));
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn body_shorter_than_its_content_length_ends_with_an_error_trailer() -> Result<()> {
+    let (base_url, server_handle) = start(ServerConfig::builder()).await;
+
+    let upload = tokio::spawn(put_mislabelled(
+        base_url.clone(),
+        "short.bin",
+        20,
+        "ten bytes!",
+    ));
+    wait_for_stream(&base_url, "short.bin").await;
+
+    let mut stream = connect(&base_url).await?;
+    stream
+        .write_all(
+            format!(
+                "GET /short.bin HTTP/1.1\r\nHost: localhost\r\nAuthorization: Basic {}\r\nTE: trailers\r\nConnection: close\r\n\r\n",
+                authorization(),
+            )
+            .as_bytes(),
+        )
+        .await?;
+    let mut download = String::new();
+    stream.read_to_string(&mut download).await?;
+    let (_, trailers) = download
+        .split_once("\r\n0\r\n")
+        .expect("download ends with trailers");
+    assert!(
+        trailers
+            .contains("x-upload-error: Upload body does not match its Content-Length of 20 bytes"),
+        "unexpected trailers: {trailers}"
+    );
+    assert!(!trailers.contains("x-checksum-sha256"));
+
+    let response = upload.await??;
+    assert!(
+        response.starts_with("HTTP/1.1 400"),
+        "unexpected response: {response}"
+    );
+
+    server_handle.abort();
+
+    Ok(())
+}
@@ -0,0 +1,98 @@
+mod common;
+
+use anyhow::Result;
+use beam::ServerConfig;
+use common::{PASSWORD, USERNAME, send_when_pending, start};
+use reqwest::{
+    StatusCode, header,
+    multipart::{Form, Part},
+};
+
+fn form(filename: &str, contents: &'static str) -> Result<Form> {
+    Ok(Form::new().text("note", "ignored").part(
+        "file",
+        Part::text(contents)
+            .file_name(filename.to_owned())
+            .mime_str("text/plain")?,
+    ))
+}
+
+#[tokio::test]
+async fn form_upload_is_stored_under_the_part_filename() -> Result<()> {
+    let (base_url, server_handle) = start(ServerConfig::builder()).await;
+    let client = reqwest::Client::new();
+
+    let upload = tokio::spawn(
+        client
+            .post(format!("{base_url}/"))
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .multipart(form("notes.txt", "sent from a browser")?)
+            .send(),
+    );
+
+    let download = send_when_pending(
+        client
+            .get(format!("{base_url}/notes.txt"))
+            .basic_auth(USERNAME, Some(PASSWORD)),
+    )
+    .await?;
+    assert_eq!(download.status(), StatusCode::OK);
+    assert_eq!(download.headers()[header::CONTENT_TYPE], "text/plain");
+    assert_eq!(download.text().await?, "sent from a browser");
+    assert_eq!(upload.await??.status(), StatusCode::OK);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn path_or_form_field_filename_wins_over_the_part_filename() -> Result<()> {
+    let (base_url, server_handle) = start(ServerConfig::builder()).await;
+    let client = reqwest::Client::new();
+    let url = format!("{base_url}/renamed.txt");
+
+    let upload = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .multipart(form("original.txt", "multipart over PUT")?)
+            .send(),
+    );
+
+    let download = send_when_pending(client.get(&url).basic_auth(USERNAME, Some(PASSWORD))).await?;
+    assert_eq!(download.text().await?, "multipart over PUT");
+    assert_eq!(upload.await??.status(), StatusCode::OK);
+
+    let named = Form::new().text("filename", "chosen.txt").part(
+        "file",
+        Part::text("named in the form").file_name("original.txt"),
+    );
+    let upload = tokio::spawn(
+        client
+            .post(format!("{base_url}/"))
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .multipart(named)
+            .send(),
+    );
+    let download = send_when_pending(
+        client
+            .get(format!("{base_url}/chosen.txt"))
+            .basic_auth(USERNAME, Some(PASSWORD)),
+    )
+    .await?;
+    assert_eq!(download.text().await?, "named in the form");
+    assert_eq!(upload.await??.status(), StatusCode::OK);
+
+    let plain = client
+        .post(format!("{base_url}/"))
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .body("not a form")
+        .send()
+        .await?;
+    assert_eq!(plain.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+    server_handle.abort();
+
+    Ok(())
+}
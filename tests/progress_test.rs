@@ -0,0 +1,111 @@
+mod common;
+
+use std::time::Duration;
+
+use anyhow::Result;
+use beam::{ServerConfig, setup_server_with_config};
+use bytes::Bytes;
+use common::send_when_pending;
+use reqwest::StatusCode;
+use tokio_stream::wrappers::ReceiverStream;
+
+const CHUNK: usize = 64 * 1024;
+
+/// Polls `url` until its progress reports at least `bytes` transferred.
+async fn wait_for_progress(
+    client: &reqwest::Client,
+    url: &str,
+    bytes: u64,
+) -> Result<serde_json::Value> {
+    for _ in 0..200 {
+        let progress: serde_json::Value = client
+            .get(url)
+            .basic_auth("alice", Some("secret123"))
+            .send()
+            .await?
+            .json()
+            .await?;
+        if progress["bytes_transferred"].as_u64() >= Some(bytes) {
+            return Ok(progress);
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    panic!("progress at {url} never reached {bytes} bytes");
+}
+
+#[tokio::test]
+async fn progress_reports_a_transfer_while_it_runs() -> Result<()> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials("alice", "secret123")
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let base_url = format!("http://localhost:{}", addr.port());
+    let url = format!("{base_url}/large.bin");
+    let progress_url = format!("{base_url}/progress/large.bin");
+    let client = reqwest::Client::new();
+
+    let (body_tx, body_rx) = tokio::sync::mpsc::channel::<Result<Bytes, std::io::Error>>(1);
+    let upload = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth("alice", Some("secret123"))
+            .body(reqwest::Body::wrap_stream(ReceiverStream::new(body_rx)))
+            .send(),
+    );
+    let mut download =
+        send_when_pending(client.get(&url).basic_auth("alice", Some("secret123"))).await?;
+    assert_eq!(download.status(), StatusCode::OK);
+
+    body_tx.send(Ok(Bytes::from(vec![b'x'; CHUNK]))).await?;
+    let first = wait_for_progress(&client, &progress_url, CHUNK as u64).await?;
+    assert_eq!(first["downloader_connected"], true);
+    assert_eq!(first["total"], serde_json::Value::Null);
+    assert!(first["elapsed_secs"].is_u64());
+
+    for _ in 0..4 {
+        body_tx.send(Ok(Bytes::from(vec![b'x'; CHUNK]))).await?;
+    }
+    let later = wait_for_progress(&client, &progress_url, 5 * CHUNK as u64).await?;
+    assert!(later["bytes_transferred"].as_u64() > first["bytes_transferred"].as_u64());
+
+    drop(body_tx);
+    let mut received = 0;
+    while let Some(chunk) = download.chunk().await? {
+        received += chunk.len();
+    }
+    assert_eq!(received, 5 * CHUNK);
+    assert_eq!(upload.await??.status(), StatusCode::OK);
+
+    let finished = client
+        .get(&progress_url)
+        .basic_auth("alice", Some("secret123"))
+        .send()
+        .await?;
+    assert_eq!(finished.status(), StatusCode::NOT_FOUND);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn progress_requires_credentials() -> Result<()> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials("alice", "secret123")
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+
+    let response = reqwest::get(format!(
+        "http://localhost:{}/progress/large.bin",
+        addr.port()
+    ))
+    .await?;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    server_handle.abort();
+
+    Ok(())
+}
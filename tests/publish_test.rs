@@ -0,0 +1,112 @@
+mod common;
+
+use anyhow::Result;
+use beam::{PublishError, ServerConfig, setup_server_with_handle};
+use bytes::Bytes;
+use common::send_when_pending;
+use futures_util::StreamExt;
+use reqwest::StatusCode;
+
+const USERNAME: &str = "alice";
+const PASSWORD: &str = "secret123";
+
+#[tokio::test]
+async fn published_stream_downloads_over_http() -> Result<()> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+        .base_path("/beam")
+        .build();
+    let (beam, server_handle) = setup_server_with_handle(config).await?;
+
+    let chunks = ["generated ", "in ", "process"].map(Bytes::from);
+    let publication = beam.publish("report 1.txt", futures_util::stream::iter(chunks))?;
+    assert_eq!(
+        publication.url(),
+        format!(
+            "http://127.0.0.1:{}/beam/report%201.txt",
+            beam.local_addr().port()
+        )
+    );
+
+    let download = send_when_pending(
+        reqwest::Client::new()
+            .get(publication.url())
+            .basic_auth(USERNAME, Some(PASSWORD)),
+    )
+    .await?;
+    assert_eq!(download.status(), StatusCode::OK);
+    assert_eq!(download.text().await?, "generated in process");
+    publication.finished().await?;
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn publishing_reports_refusals() -> Result<()> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+        .build();
+    let (beam, server_handle) = setup_server_with_handle(config).await?;
+
+    assert!(matches!(
+        beam.publish("../escape", futures_util::stream::empty()),
+        Err(PublishError::InvalidFilename(_))
+    ));
+
+    let first = beam.publish("taken.bin", futures_util::stream::pending())?;
+    common::wait_for_stream(&format!("http://{}", beam.local_addr()), "taken.bin").await;
+    let second = beam.publish("taken.bin", futures_util::stream::empty())?;
+    match second.finished().await {
+        Err(PublishError::Failed { status, .. }) => assert_eq!(status, 409),
+        other => panic!("unexpected outcome: {other:?}"),
+    }
+    drop(first);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn cancelled_publication_errors_its_download() -> Result<()> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+        .build();
+    let (beam, server_handle) = setup_server_with_handle(config).await?;
+
+    let stream = futures_util::stream::iter([Bytes::from("first half")])
+        .chain(futures_util::stream::pending());
+    let publication = beam.publish("halted.bin", stream)?;
+    let client = reqwest::Client::new();
+    let mut download = send_when_pending(
+        client
+            .get(publication.url())
+            .basic_auth(USERNAME, Some(PASSWORD)),
+    )
+    .await?;
+    assert_eq!(download.status(), StatusCode::OK);
+    assert_eq!(download.chunk().await?.as_deref(), Some(&b"first half"[..]));
+
+    publication.cancel();
+    assert!(download.chunk().await.is_err(), "download should fail");
+    match publication.finished().await {
+        Err(PublishError::Failed { status, .. }) => assert_eq!(status, 409),
+        other => panic!("unexpected outcome: {other:?}"),
+    }
+
+    let gone = client
+        .get(beam.download_url("halted.bin"))
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .send()
+        .await?;
+    assert_eq!(gone.status(), StatusCode::NOT_FOUND);
+
+    server_handle.abort();
+
+    Ok(())
+}
@@ -0,0 +1,105 @@
+mod common;
+
+use anyhow::Result;
+use beam::{ServerConfig, UploadQuota, setup_server_with_config};
+use common::{send_when_pending, wait_for_stream, wait_for_stream_gone};
+use reqwest::StatusCode;
+
+#[tokio::test]
+async fn second_upload_over_the_stream_quota_is_refused() -> Result<()> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials("alice", "secret123")
+        .credentials("bob", "hunter22")
+        .upload_quota(UploadQuota {
+            max_streams: Some(1),
+            max_bytes: None,
+        })
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let base_url = format!("http://localhost:{}", addr.port());
+    let client = reqwest::Client::new();
+
+    let first_url = format!("{base_url}/first.txt");
+    let first = tokio::spawn(
+        client
+            .put(&first_url)
+            .basic_auth("alice", Some("secret123"))
+            .body("first")
+            .send(),
+    );
+    wait_for_stream(&base_url, "first.txt").await;
+
+    let second = client
+        .put(format!("{base_url}/second.txt"))
+        .basic_auth("alice", Some("secret123"))
+        .body("second")
+        .send()
+        .await?;
+    assert_eq!(second.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    // The quota is per user, so another user's upload still goes through.
+    let other = tokio::spawn(
+        client
+            .put(format!("{base_url}/other.txt"))
+            .basic_auth("bob", Some("hunter22"))
+            .body("other")
+            .send(),
+    );
+    wait_for_stream(&base_url, "other.txt").await;
+
+    // Finishing the first transfer gives the stream back.
+    let download = send_when_pending(
+        client
+            .get(&first_url)
+            .basic_auth("alice", Some("secret123")),
+    )
+    .await?;
+    assert_eq!(download.text().await?, "first");
+    assert_eq!(first.await??.status(), StatusCode::OK);
+    wait_for_stream_gone(&base_url, "first.txt").await;
+
+    let retried = tokio::spawn(
+        client
+            .put(format!("{base_url}/second.txt"))
+            .basic_auth("alice", Some("secret123"))
+            .body("second")
+            .send(),
+    );
+    wait_for_stream(&base_url, "second.txt").await;
+
+    retried.abort();
+    other.abort();
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn upload_over_the_byte_quota_is_refused() -> Result<()> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials("alice", "secret123")
+        .user_upload_quota(
+            "alice",
+            UploadQuota {
+                max_streams: None,
+                max_bytes: Some(8),
+            },
+        )
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let base_url = format!("http://localhost:{}", addr.port());
+
+    let response = reqwest::Client::new()
+        .put(format!("{base_url}/big.txt"))
+        .basic_auth("alice", Some("secret123"))
+        .body("more than eight bytes")
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    server_handle.abort();
+
+    Ok(())
+}
@@ -0,0 +1,94 @@
+mod common;
+
+use anyhow::Result;
+use beam::{ServerConfig, setup_server_with_config};
+use common::wait_for_stream_gone;
+use reqwest::{StatusCode, header};
+
+const USERNAME: &str = "alice";
+const PASSWORD: &str = "secret123";
+
+#[tokio::test]
+async fn put_to_root_assigns_a_name_to_download_by() -> Result<()> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let base_url = format!("http://localhost:{}", addr.port());
+    let client = reqwest::Client::new();
+
+    // Answered as soon as the upload is registered, long before anyone
+    // downloads it.
+    let mut upload = client
+        .put(format!("{base_url}/"))
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .body("shared without a name")
+        .send()
+        .await?;
+    assert_eq!(upload.status(), StatusCode::CREATED);
+    let location = upload.headers()[header::LOCATION].to_str()?.to_owned();
+    let first_line = upload.chunk().await?.expect("the assigned name");
+    let filename = std::str::from_utf8(&first_line)?.trim_end().to_owned();
+    assert_eq!(location, format!("/{filename}"));
+    assert_eq!(filename.len(), 8);
+    assert!(filename.bytes().all(|byte| byte.is_ascii_alphanumeric()));
+
+    let download = client
+        .get(format!("{base_url}{location}"))
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .send()
+        .await?;
+    assert_eq!(download.status(), StatusCode::OK);
+    assert_eq!(download.text().await?, "shared without a name");
+    assert_eq!(upload.text().await?, "Upload completed successfully\n");
+    wait_for_stream_gone(&base_url, &filename).await;
+
+    // Each upload gets a name of its own.
+    let second = client
+        .put(format!("{base_url}/"))
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .body("another")
+        .send()
+        .await?;
+    assert_ne!(second.headers()[header::LOCATION].to_str()?, location);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn refused_random_upload_keeps_its_status() -> Result<()> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+        .max_body_size(4)
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let base_url = format!("http://localhost:{}", addr.port());
+    let client = reqwest::Client::new();
+
+    let upload = client
+        .put(format!("{base_url}/"))
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .body("longer than four bytes")
+        .send()
+        .await?;
+    assert_eq!(upload.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    assert!(upload.headers().get(header::LOCATION).is_none());
+
+    // The name it was given isn't left reserved.
+    let streams: serde_json::Value = client
+        .get(format!("{base_url}/api/streams"))
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(streams, serde_json::json!([]));
+
+    server_handle.abort();
+
+    Ok(())
+}
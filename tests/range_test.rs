@@ -0,0 +1,61 @@
+mod common;
+
+use anyhow::Result;
+use beam::ServerConfig;
+use common::{PASSWORD, USERNAME, send_when_pending, start};
+use reqwest::{StatusCode, header};
+
+const CONTENT: &str = "0123456789abcdefghij";
+
+/// Streams `CONTENT` through a live upload and downloads it with `range`.
+async fn live_download_with_range(base_url: &str, name: &str, range: &str) -> Result<()> {
+    let client = reqwest::Client::new();
+    let url = format!("{base_url}/{name}");
+
+    let upload = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .body(CONTENT)
+            .send(),
+    );
+
+    let download = send_when_pending(
+        client
+            .get(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .header(header::RANGE, range),
+    )
+    .await?;
+    assert_eq!(download.status(), StatusCode::OK, "range {range}");
+    assert_eq!(download.headers()[header::ACCEPT_RANGES], "none");
+    assert!(download.headers().get(header::CONTENT_RANGE).is_none());
+    assert_eq!(download.text().await?, CONTENT);
+    assert_eq!(upload.await??.status(), StatusCode::OK);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn live_stream_ignores_single_range() -> Result<()> {
+    let (base_url, server_handle) = start(ServerConfig::builder()).await;
+    live_download_with_range(&base_url, "single.txt", "bytes=2-5").await?;
+    server_handle.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn live_stream_ignores_open_ended_range() -> Result<()> {
+    let (base_url, server_handle) = start(ServerConfig::builder()).await;
+    live_download_with_range(&base_url, "open.txt", "bytes=10-").await?;
+    server_handle.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn live_stream_ignores_malformed_range() -> Result<()> {
+    let (base_url, server_handle) = start(ServerConfig::builder()).await;
+    live_download_with_range(&base_url, "malformed.txt", "bytes=banana").await?;
+    server_handle.abort();
+    Ok(())
+}
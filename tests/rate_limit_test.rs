@@ -0,0 +1,98 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use beam::{AuthFailureLimit, ServerConfig, setup_server_with_config};
+use reqwest::StatusCode;
+
+#[tokio::test]
+async fn repeated_failures_are_throttled() -> Result<()> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials("alice", "secret123")
+        .auth_failure_limit(AuthFailureLimit {
+            max_failures: 3,
+            window: Duration::from_secs(60),
+        })
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+
+    let client = reqwest::Client::new();
+    let url = format!("http://localhost:{}/missing.txt", addr.port());
+
+    for attempt in 1..=3 {
+        let response = client
+            .get(&url)
+            .basic_auth("alice", Some("guess"))
+            .send()
+            .await?;
+        assert_eq!(
+            response.status(),
+            StatusCode::UNAUTHORIZED,
+            "attempt {attempt}"
+        );
+    }
+
+    let throttled = client
+        .get(&url)
+        .basic_auth("alice", Some("guess"))
+        .send()
+        .await?;
+    assert_eq!(throttled.status(), StatusCode::TOO_MANY_REQUESTS);
+    let retry_after: u64 = throttled.headers()[reqwest::header::RETRY_AFTER]
+        .to_str()?
+        .parse()?;
+    assert!((1..=60).contains(&retry_after), "Retry-After {retry_after}");
+
+    // Even the right password is refused until the window closes.
+    let correct = client
+        .get(&url)
+        .basic_auth("alice", Some("secret123"))
+        .send()
+        .await?;
+    assert_eq!(correct.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn failures_expire_with_the_window() -> Result<()> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials("alice", "secret123")
+        .auth_failure_limit(AuthFailureLimit {
+            max_failures: 1,
+            window: Duration::from_millis(300),
+        })
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+
+    let client = reqwest::Client::new();
+    let url = format!("http://localhost:{}/missing.txt", addr.port());
+
+    let failed = client
+        .get(&url)
+        .basic_auth("alice", Some("guess"))
+        .send()
+        .await?;
+    assert_eq!(failed.status(), StatusCode::UNAUTHORIZED);
+    let throttled = client
+        .get(&url)
+        .basic_auth("alice", Some("secret123"))
+        .send()
+        .await?;
+    assert_eq!(throttled.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    tokio::time::sleep(Duration::from_millis(400)).await;
+    let allowed = client
+        .get(&url)
+        .basic_auth("alice", Some("secret123"))
+        .send()
+        .await?;
+    assert_eq!(allowed.status(), StatusCode::NOT_FOUND);
+
+    server_handle.abort();
+
+    Ok(())
+}
@@ -0,0 +1,65 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use beam::{ServerConfig, setup_server_with_handle};
+use bytes::Bytes;
+use reqwest::{Response, StatusCode, header};
+
+/// Polls `/healthz` until it answers `status`, as slots are taken and freed
+/// by requests running concurrently.
+async fn wait_for_health(client: &reqwest::Client, base_url: &str, status: StatusCode) -> Response {
+    for _ in 0..200 {
+        let response = client
+            .get(format!("{base_url}/healthz"))
+            .send()
+            .await
+            .expect("health check failed");
+        if response.status() == status {
+            return response;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    panic!("health check never answered {status}");
+}
+
+#[tokio::test]
+async fn requests_beyond_the_limit_are_refused_until_a_slot_frees() -> Result<()> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials("alice", "secret123")
+        .max_concurrent_requests(1)
+        .download_wait_timeout(Duration::from_secs(10))
+        .build();
+    let (beam, server_handle) = setup_server_with_handle(config).await?;
+    let base_url = format!("http://localhost:{}", beam.local_addr().port());
+    let client = reqwest::Client::new();
+
+    // A download waiting for its upload holds the only slot.
+    let download = tokio::spawn(
+        client
+            .get(format!("{base_url}/report.txt"))
+            .basic_auth("alice", Some("secret123"))
+            .send(),
+    );
+
+    let refused = wait_for_health(&client, &base_url, StatusCode::SERVICE_UNAVAILABLE).await;
+    assert!(refused.headers().contains_key(header::RETRY_AFTER));
+
+    // Publishing skips HTTP, so it completes the waiting download and with
+    // it frees the slot.
+    let publication = beam.publish(
+        "report.txt",
+        futures_util::stream::iter([Bytes::from("quarterly numbers")]),
+    )?;
+    let download = download.await??;
+    assert_eq!(download.status(), StatusCode::OK);
+    assert_eq!(download.text().await?, "quarterly numbers");
+    publication.finished().await?;
+
+    wait_for_health(&client, &base_url, StatusCode::OK).await;
+
+    server_handle.abort();
+
+    Ok(())
+}
@@ -0,0 +1,110 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use beam::{ServerConfig, setup_server_with_config};
+use reqwest::StatusCode;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+const USERNAME: &str = "alice";
+const PASSWORD: &str = "secret123";
+
+async fn reserve(client: &reqwest::Client, base_url: &str) -> Result<reqwest::Response> {
+    Ok(client
+        .post(format!("{base_url}/reserve"))
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .json(&serde_json::json!({ "filename": "report.pdf" }))
+        .send()
+        .await?)
+}
+
+#[tokio::test]
+async fn reservation_holds_the_name_for_its_upload() -> Result<()> {
+    let spool_dir = tempfile::tempdir()?;
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+        .spool_dir(spool_dir.path())
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let base_url = format!("http://localhost:{}", addr.port());
+    let client = reqwest::Client::new();
+
+    let first = reserve(&client, &base_url).await?;
+    assert_eq!(first.status(), StatusCode::CREATED);
+    let reservation: serde_json::Value = first.json().await?;
+    let token = reservation["reservation"].as_str().unwrap();
+
+    let second = reserve(&client, &base_url).await?;
+    assert_eq!(second.status(), StatusCode::CONFLICT);
+
+    let url = format!("{base_url}/report.pdf");
+    let unreserved = client
+        .put(&url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .body("not mine")
+        .send()
+        .await?;
+    assert_eq!(unreserved.status(), StatusCode::CONFLICT);
+
+    let claimed = client
+        .put(&url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .header("x-reservation", token)
+        .body("quarterly numbers")
+        .send()
+        .await?;
+    assert_eq!(claimed.status(), StatusCode::CREATED);
+
+    let download = client
+        .get(&url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .send()
+        .await?;
+    assert_eq!(download.text().await?, "quarterly numbers");
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn expect_continue_upload_to_a_taken_name_is_refused_before_the_body() -> Result<()> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let base_url = format!("http://localhost:{}", addr.port());
+    assert_eq!(
+        reserve(&reqwest::Client::new(), &base_url).await?.status(),
+        StatusCode::CREATED
+    );
+
+    let mut stream = TcpStream::connect(addr).await?;
+    let credentials = STANDARD.encode(format!("{USERNAME}:{PASSWORD}"));
+    stream
+        .write_all(
+            format!(
+                "PUT /report.pdf HTTP/1.1\r\nHost: localhost\r\nAuthorization: Basic {credentials}\r\nContent-Length: 1048576\r\nExpect: 100-continue\r\n\r\n"
+            )
+            .as_bytes(),
+        )
+        .await?;
+
+    // No body is ever sent; the refusal must come without it.
+    let mut response = vec![0; 512];
+    let read = tokio::time::timeout(Duration::from_secs(5), stream.read(&mut response)).await??;
+    let status_line = String::from_utf8_lossy(&response[..read]);
+    assert!(
+        status_line.starts_with("HTTP/1.1 409"),
+        "unexpected response: {status_line}"
+    );
+
+    server_handle.abort();
+
+    Ok(())
+}
@@ -0,0 +1,199 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use beam::{ServerConfig, setup_server_with_config};
+use futures_util::stream;
+use reqwest::{StatusCode, header};
+
+const USERNAME: &str = "alice";
+const PASSWORD: &str = "secret123";
+
+fn offset(response: &reqwest::Response) -> u64 {
+    response.headers()["upload-offset"]
+        .to_str()
+        .expect("offset header is ASCII")
+        .parse()
+        .expect("offset header is a number")
+}
+
+#[tokio::test]
+async fn interrupted_upload_resumes_from_committed_offset() -> Result<()> {
+    let spool_dir = tempfile::tempdir()?;
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+        .spool_dir(spool_dir.path())
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let base_url = format!("http://localhost:{}", addr.port());
+    let client = reqwest::Client::new();
+
+    let session: serde_json::Value = client
+        .post(format!("{base_url}/upload"))
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .json(&serde_json::json!({ "filename": "resumed.txt", "content_type": "text/plain" }))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    let session_url = format!("{base_url}{}", session["url"].as_str().unwrap());
+
+    let first = client
+        .put(format!("{session_url}?offset=0"))
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .body("first chunk;")
+        .send()
+        .await?;
+    assert_eq!(first.status(), StatusCode::OK);
+    assert_eq!(offset(&first), 12);
+
+    // The connection drops after part of the second chunk has been sent.
+    let interrupted_body = stream::unfold(0, |step| async move {
+        match step {
+            0 => Some((Ok::<_, std::io::Error>("partial ".to_owned()), 1)),
+            1 => {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                Some((Err(std::io::Error::other("connection lost")), 2))
+            }
+            _ => None,
+        }
+    });
+    let interrupted = client
+        .put(format!("{session_url}?offset=12"))
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .body(reqwest::Body::wrap_stream(interrupted_body))
+        .send()
+        .await;
+    assert!(interrupted.is_err());
+
+    let status = client
+        .head(&session_url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .send()
+        .await?;
+    assert_eq!(status.status(), StatusCode::OK);
+    assert_eq!(offset(&status), 20);
+
+    // Replaying the interrupted chunk from its original offset is refused.
+    let duplicate = client
+        .put(format!("{session_url}?offset=12"))
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .body("partial second chunk;")
+        .send()
+        .await?;
+    assert_eq!(duplicate.status(), StatusCode::CONFLICT);
+    assert_eq!(offset(&duplicate), 20);
+
+    let resumed = client
+        .put(format!("{session_url}?offset=20"))
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .body("second chunk;")
+        .send()
+        .await?;
+    assert_eq!(resumed.status(), StatusCode::OK);
+    assert_eq!(offset(&resumed), 33);
+
+    let complete = client
+        .post(format!("{session_url}/complete"))
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .header(
+            "x-checksum-sha256",
+            "8ad1d376b97974b4ae872ab57c749e7a722b28ee50616673255776b7b6d60ae9",
+        )
+        .send()
+        .await?;
+    assert_eq!(complete.status(), StatusCode::CREATED);
+
+    let download = client
+        .get(format!("{base_url}/resumed.txt"))
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .send()
+        .await?;
+    assert_eq!(download.status(), StatusCode::OK);
+    assert_eq!(download.headers()[header::CONTENT_TYPE], "text/plain");
+    assert_eq!(download.text().await?, "first chunk;partial second chunk;");
+
+    // The session is gone once completed.
+    let stale = client
+        .head(&session_url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .send()
+        .await?;
+    assert_eq!(stale.status(), StatusCode::NOT_FOUND);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn sessions_require_the_spool() -> Result<()> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+
+    let response = reqwest::Client::new()
+        .post(format!("http://localhost:{}/upload", addr.port()))
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .json(&serde_json::json!({ "filename": "resumed.txt" }))
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::NOT_IMPLEMENTED);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn patch_segments_append_in_order() -> Result<()> {
+    let spool_dir = tempfile::tempdir()?;
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+        .spool_dir(spool_dir.path())
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let url = format!("http://localhost:{}/segments.txt", addr.port());
+    let client = reqwest::Client::new();
+    let patch = |content_range: &str, body: &'static str| {
+        client
+            .patch(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .header(header::CONTENT_TYPE, "text/plain")
+            .header(header::CONTENT_RANGE, content_range)
+            .body(body)
+            .send()
+    };
+
+    let first = patch("bytes 0-5/*", "first ").await?;
+    assert_eq!(first.status(), StatusCode::OK);
+    assert_eq!(offset(&first), 6);
+
+    // Skipping ahead leaves a gap, and repeating the first segment overlaps.
+    for content_range in ["bytes 8-13/14", "bytes 0-5/14"] {
+        let refused = patch(content_range, "second").await?;
+        assert_eq!(refused.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(refused.headers()[header::CONTENT_RANGE], "bytes */6");
+        assert_eq!(offset(&refused), 6);
+    }
+
+    let last = patch("bytes 6-13/14", "and last").await?;
+    assert_eq!(last.status(), StatusCode::CREATED);
+
+    let download = client
+        .get(&url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .send()
+        .await?;
+    assert_eq!(download.status(), StatusCode::OK);
+    assert_eq!(download.headers()[header::CONTENT_TYPE], "text/plain");
+    assert_eq!(download.text().await?, "first and last");
+
+    server_handle.abort();
+
+    Ok(())
+}
@@ -0,0 +1,57 @@
+mod common;
+
+use anyhow::Result;
+use beam::{ServerConfig, setup_server_with_config};
+use common::send_when_pending;
+use reqwest::StatusCode;
+use tokio::runtime::{Builder, Runtime};
+
+const USERNAME: &str = "alice";
+const PASSWORD: &str = "secret123";
+
+/// A throwaway runtime of the kind an embedder's own code would run on.
+fn caller_runtime() -> Runtime {
+    Builder::new_current_thread().enable_all().build().unwrap()
+}
+
+async fn relay(url: &str, contents: &'static str) -> Result<()> {
+    let client = reqwest::Client::new();
+    let upload = tokio::spawn(
+        client
+            .put(url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .body(contents)
+            .send(),
+    );
+    let download = send_when_pending(client.get(url).basic_auth(USERNAME, Some(PASSWORD))).await?;
+    assert_eq!(download.text().await?, contents);
+    assert_eq!(upload.await??.status(), StatusCode::OK);
+    Ok(())
+}
+
+#[test]
+fn server_runs_on_the_configured_runtime() -> Result<()> {
+    let dedicated = Builder::new_multi_thread()
+        .worker_threads(2)
+        .thread_name("beam-worker")
+        .enable_all()
+        .build()?;
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+        .runtime(dedicated.handle().clone())
+        .build();
+
+    let caller = caller_runtime();
+    let (addr, server_handle) = caller.block_on(setup_server_with_config(config))?;
+    let url = format!("http://localhost:{}/on-its-own-runtime.txt", addr.port());
+    caller.block_on(relay(&url, "first transfer"))?;
+    // Anything beam had put on the caller's runtime goes with it.
+    drop(caller);
+
+    caller_runtime().block_on(relay(&url, "second transfer"))?;
+
+    server_handle.abort();
+
+    Ok(())
+}
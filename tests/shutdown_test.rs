@@ -0,0 +1,194 @@
+mod common;
+
+use std::time::Duration;
+
+use anyhow::Result;
+use beam::{ServerConfig, setup_server_with_config};
+use common::{send_when_pending, wait_for_stream};
+use reqwest::StatusCode;
+use tokio::sync::oneshot;
+
+const USERNAME: &str = "alice";
+const PASSWORD: &str = "secret123";
+
+#[tokio::test]
+async fn shutdown_signal_stops_accepting_connections() -> Result<()> {
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+        .shutdown_signal(async {
+            let _ = shutdown_rx.await;
+        })
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let url = format!("http://localhost:{}/", addr.port());
+
+    let response = reqwest::get(&url).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    shutdown_tx
+        .send(())
+        .expect("server should still be running");
+    tokio::time::timeout(Duration::from_secs(5), server_handle).await??;
+
+    assert!(reqwest::get(&url).await.is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn readyz_reports_not_ready_once_shutdown_begins() -> Result<()> {
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+        .shutdown_signal(async {
+            let _ = shutdown_rx.await;
+        })
+        .drain_delay(Duration::from_secs(1))
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let url = format!("http://localhost:{}/readyz", addr.port());
+
+    let response = reqwest::get(&url).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    shutdown_tx
+        .send(())
+        .expect("server should still be running");
+
+    let mut status = StatusCode::OK;
+    for _ in 0..50 {
+        status = reqwest::get(&url).await?.status();
+        if status != StatusCode::OK {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+
+    tokio::time::timeout(Duration::from_secs(5), server_handle).await??;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn shutdown_rejects_uploads_waiting_for_a_downloader() -> Result<()> {
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+        .shutdown_signal(async {
+            let _ = shutdown_rx.await;
+        })
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let client = reqwest::Client::new();
+    let url = format!("http://localhost:{}/pending.txt", addr.port());
+
+    let upload = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .body("never downloaded")
+            .send(),
+    );
+
+    wait_for_stream(&format!("http://localhost:{}/", addr.port()), "pending.txt").await;
+
+    shutdown_tx
+        .send(())
+        .expect("server should still be running");
+
+    let upload_response = tokio::time::timeout(Duration::from_secs(5), upload).await???;
+    assert_eq!(upload_response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+    tokio::time::timeout(Duration::from_secs(5), server_handle).await??;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn shutdown_lets_active_transfers_finish() -> Result<()> {
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+        .shutdown_signal(async {
+            let _ = shutdown_rx.await;
+        })
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let client = reqwest::Client::new();
+    let url = format!("http://localhost:{}/slow.txt", addr.port());
+
+    let (chunk_tx, chunk_rx) =
+        tokio::sync::mpsc::channel::<Result<&'static str, std::io::Error>>(1);
+    let body = reqwest::Body::wrap_stream(tokio_stream::wrappers::ReceiverStream::new(chunk_rx));
+    let upload = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .body(body)
+            .send(),
+    );
+    chunk_tx.send(Ok("first ")).await?;
+
+    let download = send_when_pending(client.get(&url).basic_auth(USERNAME, Some(PASSWORD))).await?;
+    assert_eq!(download.status(), StatusCode::OK);
+
+    shutdown_tx
+        .send(())
+        .expect("server should still be running");
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    chunk_tx.send(Ok("second")).await?;
+    drop(chunk_tx);
+
+    assert_eq!(download.text().await?, "first second");
+    let upload_response = upload.await??;
+    assert_eq!(upload_response.status(), StatusCode::OK);
+
+    tokio::time::timeout(Duration::from_secs(5), server_handle).await??;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn one_shot_server_stops_after_its_first_transfer() -> Result<()> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+        .one_shot(true)
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let url = format!("http://localhost:{}/once.zip", addr.port());
+    let client = reqwest::Client::new();
+
+    // A failed transfer doesn't use up the server.
+    let missing = client
+        .get(&url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .send()
+        .await?;
+    assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+
+    let upload = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .body("only once")
+            .send(),
+    );
+    let download = send_when_pending(client.get(&url).basic_auth(USERNAME, Some(PASSWORD))).await?;
+    assert_eq!(download.text().await?, "only once");
+    assert_eq!(upload.await??.status(), StatusCode::OK);
+
+    tokio::time::timeout(Duration::from_secs(5), server_handle).await??;
+    assert!(
+        tokio::net::TcpStream::connect(addr).await.is_err(),
+        "server should no longer accept connections"
+    );
+
+    Ok(())
+}
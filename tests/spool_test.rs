@@ -0,0 +1,363 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use beam::{ServerConfig, setup_server_with_config};
+use reqwest::{StatusCode, header};
+
+const USERNAME: &str = "alice";
+const PASSWORD: &str = "secret123";
+const PAYLOAD: &str = "abcdefghijklmnopqrstuvwxyz";
+
+async fn start_spooling_server(
+    spool_dir: &std::path::Path,
+    ttl: Duration,
+) -> (String, tokio::task::JoinHandle<()>) {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+        .spool_dir(spool_dir)
+        .spool_ttl(ttl)
+        .build();
+    let (addr, handle) = setup_server_with_config(config)
+        .await
+        .expect("failed to start server");
+    (format!("http://localhost:{}", addr.port()), handle)
+}
+
+async fn upload(client: &reqwest::Client, url: &str) -> Result<reqwest::Response> {
+    Ok(client
+        .put(url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .header(header::CONTENT_TYPE, "text/plain")
+        .body(PAYLOAD)
+        .send()
+        .await?)
+}
+
+#[tokio::test]
+async fn spooled_upload_completes_before_any_download() -> Result<()> {
+    let spool_dir = tempfile::tempdir()?;
+    let (base_url, server_handle) =
+        start_spooling_server(spool_dir.path(), Duration::from_secs(60)).await;
+    let client = reqwest::Client::new();
+    let url = format!("{base_url}/later.txt");
+
+    let response = upload(&client, &url).await?;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    for _ in 0..2 {
+        let download = client
+            .get(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .send()
+            .await?;
+        assert_eq!(download.status(), StatusCode::OK);
+        assert_eq!(download.headers()[header::CONTENT_TYPE], "text/plain");
+        assert_eq!(download.headers()[header::ACCEPT_RANGES], "bytes");
+        assert_eq!(
+            download.headers()[header::CONTENT_LENGTH],
+            PAYLOAD.len().to_string().as_str()
+        );
+        assert_eq!(download.text().await?, PAYLOAD);
+    }
+
+    let duplicate = upload(&client, &url).await?;
+    assert_eq!(duplicate.status(), StatusCode::CONFLICT);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn spooled_downloads_honour_ranges() -> Result<()> {
+    let spool_dir = tempfile::tempdir()?;
+    let (base_url, server_handle) =
+        start_spooling_server(spool_dir.path(), Duration::from_secs(60)).await;
+    let client = reqwest::Client::new();
+    let url = format!("{base_url}/ranged.txt");
+
+    assert_eq!(upload(&client, &url).await?.status(), StatusCode::CREATED);
+
+    for (range, content_range, body) in [
+        ("bytes=2-5", "bytes 2-5/26", "cdef"),
+        ("bytes=20-", "bytes 20-25/26", "uvwxyz"),
+        ("bytes=-3", "bytes 23-25/26", "xyz"),
+        ("bytes=24-99", "bytes 24-25/26", "yz"),
+    ] {
+        let download = client
+            .get(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .header(header::RANGE, range)
+            .send()
+            .await?;
+        assert_eq!(download.status(), StatusCode::PARTIAL_CONTENT, "{range}");
+        assert_eq!(download.headers()[header::CONTENT_RANGE], content_range);
+        assert_eq!(download.text().await?, body, "{range}");
+    }
+
+    let unsatisfiable = client
+        .get(&url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .header(header::RANGE, "bytes=26-")
+        .send()
+        .await?;
+    assert_eq!(unsatisfiable.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+    assert_eq!(unsatisfiable.headers()[header::CONTENT_RANGE], "bytes */26");
+
+    for range in ["bytes=oops", "bytes=0-1,4-5", "items=0-1"] {
+        let download = client
+            .get(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .header(header::RANGE, range)
+            .send()
+            .await?;
+        assert_eq!(download.status(), StatusCode::OK, "{range}");
+        assert_eq!(download.text().await?, PAYLOAD, "{range}");
+    }
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn matching_if_none_match_gets_not_modified() -> Result<()> {
+    let spool_dir = tempfile::tempdir()?;
+    let (base_url, server_handle) =
+        start_spooling_server(spool_dir.path(), Duration::from_secs(60)).await;
+    let client = reqwest::Client::new();
+    let url = format!("{base_url}/polled.txt");
+
+    assert_eq!(upload(&client, &url).await?.status(), StatusCode::CREATED);
+
+    let download = client
+        .get(&url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .send()
+        .await?;
+    assert_eq!(download.status(), StatusCode::OK);
+    let etag = download.headers()[header::ETAG].clone();
+    assert_eq!(download.text().await?, PAYLOAD);
+
+    for if_none_match in [
+        etag.to_str()?,
+        &format!("\"other\", W/{}", etag.to_str()?),
+        "*",
+    ] {
+        let cached = client
+            .get(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .header(header::IF_NONE_MATCH, if_none_match)
+            .send()
+            .await?;
+        assert_eq!(cached.status(), StatusCode::NOT_MODIFIED, "{if_none_match}");
+        assert_eq!(cached.headers()[header::ETAG], etag);
+        assert!(cached.bytes().await?.is_empty());
+    }
+
+    let changed = client
+        .get(&url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .header(header::IF_NONE_MATCH, "\"stale\"")
+        .send()
+        .await?;
+    assert_eq!(changed.status(), StatusCode::OK);
+    assert_eq!(changed.text().await?, PAYLOAD);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn expired_spool_entries_are_removed() -> Result<()> {
+    let spool_dir = tempfile::tempdir()?;
+    let (base_url, server_handle) =
+        start_spooling_server(spool_dir.path(), Duration::from_millis(200)).await;
+    let client = reqwest::Client::new();
+    let url = format!("{base_url}/short-lived.txt");
+
+    assert_eq!(upload(&client, &url).await?.status(), StatusCode::CREATED);
+    assert_eq!(std::fs::read_dir(spool_dir.path())?.count(), 1);
+
+    tokio::time::sleep(Duration::from_millis(600)).await;
+
+    let download = client
+        .get(&url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .send()
+        .await?;
+    assert_eq!(download.status(), StatusCode::NOT_FOUND);
+    assert_eq!(std::fs::read_dir(spool_dir.path())?.count(), 0);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn peek_reads_the_start_and_leaves_the_file() -> Result<()> {
+    let spool_dir = tempfile::tempdir()?;
+    let (base_url, server_handle) =
+        start_spooling_server(spool_dir.path(), Duration::from_secs(60)).await;
+    let client = reqwest::Client::new();
+    let url = format!("{base_url}/sniff.txt");
+    assert_eq!(upload(&client, &url).await?.status(), StatusCode::CREATED);
+
+    let peek = client
+        .get(format!("{url}?peek=8"))
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .send()
+        .await?;
+    assert_eq!(peek.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(peek.headers()[header::CONTENT_RANGE], "bytes 0-7/26");
+    assert_eq!(peek.text().await?, &PAYLOAD[..8]);
+
+    let download = client
+        .get(&url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .send()
+        .await?;
+    assert_eq!(download.status(), StatusCode::OK);
+    assert_eq!(download.text().await?, PAYLOAD);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn peek_needs_the_spool() -> Result<()> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+
+    let peek = reqwest::Client::new()
+        .get(format!("http://localhost:{}/live.bin?peek=8", addr.port()))
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .send()
+        .await?;
+    assert_eq!(peek.status(), StatusCode::NOT_IMPLEMENTED);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn compressed_spool_stores_less_and_serves_the_original() -> Result<()> {
+    let spool_dir = tempfile::tempdir()?;
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+        .spool_dir(spool_dir.path())
+        .compress_spool(true)
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let url = format!("http://localhost:{}/build.log", addr.port());
+    let client = reqwest::Client::new();
+    let payload = "compiling beam v0.1.0\n".repeat(10_000);
+
+    let response = client
+        .put(&url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .body(payload.clone())
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let stored = std::fs::read_dir(spool_dir.path())?
+        .next()
+        .expect("no spool file")?
+        .metadata()?
+        .len();
+    assert!(
+        stored < payload.len() as u64 / 10,
+        "stored {stored} of {} bytes",
+        payload.len()
+    );
+
+    let download = client
+        .get(&url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .header(header::ACCEPT_ENCODING, "zstd")
+        .send()
+        .await?;
+    assert_eq!(download.status(), StatusCode::OK);
+    assert!(!download.headers().contains_key(header::CONTENT_ENCODING));
+    assert_eq!(
+        download.headers()[header::CONTENT_LENGTH],
+        payload.len().to_string().as_str()
+    );
+    assert_eq!(download.text().await?, payload);
+
+    let ranged = client
+        .get(&url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .header(header::RANGE, "bytes=22-35")
+        .send()
+        .await?;
+    assert_eq!(ranged.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(ranged.text().await?, "compiling beam");
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn upload_can_shorten_its_own_expiry() -> Result<()> {
+    let spool_dir = tempfile::tempdir()?;
+    let (base_url, server_handle) =
+        start_spooling_server(spool_dir.path(), Duration::from_secs(60 * 60)).await;
+    let client = reqwest::Client::new();
+    let brief_url = format!("{base_url}/brief.txt");
+    let kept_url = format!("{base_url}/kept.txt");
+
+    let brief = client
+        .put(&brief_url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .header("X-Expires-In", "1")
+        .body(PAYLOAD)
+        .send()
+        .await?;
+    assert_eq!(brief.status(), StatusCode::CREATED);
+    assert_eq!(
+        upload(&client, &kept_url).await?.status(),
+        StatusCode::CREATED
+    );
+    assert_eq!(std::fs::read_dir(spool_dir.path())?.count(), 2);
+
+    // Well before the hour-long TTL's next sweep.
+    tokio::time::sleep(Duration::from_millis(1_500)).await;
+    assert_eq!(std::fs::read_dir(spool_dir.path())?.count(), 1);
+
+    let brief = client
+        .get(&brief_url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .send()
+        .await?;
+    assert_eq!(brief.status(), StatusCode::NOT_FOUND);
+    let kept = client
+        .get(&kept_url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .send()
+        .await?;
+    assert_eq!(kept.status(), StatusCode::OK);
+
+    let malformed = client
+        .put(format!("{base_url}/malformed.txt"))
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .header("X-Expires-In", "soon")
+        .body(PAYLOAD)
+        .send()
+        .await?;
+    assert_eq!(malformed.status(), StatusCode::BAD_REQUEST);
+
+    server_handle.abort();
+
+    Ok(())
+}
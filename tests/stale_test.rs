@@ -0,0 +1,49 @@
+mod common;
+
+use std::time::Duration;
+
+use anyhow::Result;
+use beam::{ServerConfig, setup_server_with_config};
+use common::{wait_for_stream, wait_for_stream_gone};
+use reqwest::StatusCode;
+
+#[tokio::test]
+async fn pending_upload_past_its_max_age_is_evicted() -> Result<()> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials("alice", "secret123")
+        // Without the sweep this upload would wait for a downloader forever.
+        .upload_ready_timeout(None)
+        .max_pending_age(Duration::from_millis(300))
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let base_url = format!("http://localhost:{}", addr.port());
+    let client = reqwest::Client::new();
+
+    let upload = tokio::spawn(
+        client
+            .put(format!("{base_url}/forgotten.txt"))
+            .basic_auth("alice", Some("secret123"))
+            .body("nobody came")
+            .send(),
+    );
+    wait_for_stream(&base_url, "forgotten.txt").await;
+
+    let streams: serde_json::Value = client
+        .get(format!("{base_url}/api/streams"))
+        .basic_auth("alice", Some("secret123"))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(streams[0]["filename"], "forgotten.txt");
+    assert!(streams[0]["age_secs"].is_u64());
+
+    wait_for_stream_gone(&base_url, "forgotten.txt").await;
+    let upload = tokio::time::timeout(Duration::from_secs(5), upload).await???;
+    assert_eq!(upload.status(), StatusCode::CONFLICT);
+
+    server_handle.abort();
+
+    Ok(())
+}
@@ -0,0 +1,68 @@
+mod common;
+
+use anyhow::Result;
+use beam::{ServerConfig, setup_server_with_config};
+use common::{send_when_pending, wait_for_stream};
+use reqwest::{StatusCode, header};
+
+#[tokio::test]
+async fn uploads_beyond_the_stream_limit_are_turned_away() -> Result<()> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials("alice", "secret123")
+        .max_concurrent_streams(1)
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let base_url = format!("http://localhost:{}", addr.port());
+    let client = reqwest::Client::new();
+
+    let first_url = format!("{base_url}/first.txt");
+    let first = tokio::spawn(
+        client
+            .put(&first_url)
+            .basic_auth("alice", Some("secret123"))
+            .body("first")
+            .send(),
+    );
+    wait_for_stream(&base_url, "first.txt").await;
+
+    let second = client
+        .put(format!("{base_url}/second.txt"))
+        .basic_auth("alice", Some("secret123"))
+        .body("second")
+        .send()
+        .await?;
+    assert_eq!(second.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert!(second.headers().contains_key(header::RETRY_AFTER));
+
+    // Finishing the first transfer frees its slot.
+    let download = send_when_pending(
+        client
+            .get(&first_url)
+            .basic_auth("alice", Some("secret123")),
+    )
+    .await?;
+    assert_eq!(download.text().await?, "first");
+    assert_eq!(first.await??.status(), StatusCode::OK);
+
+    let third_url = format!("{base_url}/third.txt");
+    let third = tokio::spawn(
+        client
+            .put(&third_url)
+            .basic_auth("alice", Some("secret123"))
+            .body("third")
+            .send(),
+    );
+    let download = send_when_pending(
+        client
+            .get(&third_url)
+            .basic_auth("alice", Some("secret123")),
+    )
+    .await?;
+    assert_eq!(download.text().await?, "third");
+    assert_eq!(third.await??.status(), StatusCode::OK);
+
+    server_handle.abort();
+
+    Ok(())
+}
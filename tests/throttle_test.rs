@@ -0,0 +1,88 @@
+mod common;
+
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use beam::{ServerConfig, setup_server_with_config};
+use common::send_when_pending;
+use reqwest::StatusCode;
+
+const USERNAME: &str = "alice";
+const PASSWORD: &str = "secret123";
+const RATE: u64 = 50_000;
+const PAYLOAD_SIZE: usize = 100_000;
+
+/// The slowest a throttled transfer of `PAYLOAD_SIZE` bytes may finish in,
+/// less a little slack for timer rounding.
+fn floor() -> Duration {
+    Duration::from_secs_f64(PAYLOAD_SIZE as f64 / RATE as f64) - Duration::from_millis(50)
+}
+
+#[tokio::test]
+async fn live_transfers_are_held_to_the_configured_rate() -> Result<()> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+        .max_transfer_rate(RATE)
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let client = reqwest::Client::new();
+    let url = format!("http://localhost:{}/paced.bin", addr.port());
+
+    let started = Instant::now();
+    let upload = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .body(vec![7u8; PAYLOAD_SIZE])
+            .send(),
+    );
+    let download = send_when_pending(client.get(&url).basic_auth(USERNAME, Some(PASSWORD))).await?;
+    assert_eq!(download.status(), StatusCode::OK);
+    assert_eq!(download.bytes().await?.len(), PAYLOAD_SIZE);
+    assert_eq!(upload.await??.status(), StatusCode::OK);
+
+    let elapsed = started.elapsed();
+    assert!(elapsed >= floor(), "finished in {elapsed:?}");
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn spooled_downloads_are_paced_too() -> Result<()> {
+    let spool_dir = tempfile::tempdir()?;
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+        .spool_dir(spool_dir.path())
+        .max_transfer_rate(RATE)
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let client = reqwest::Client::new();
+    let url = format!("http://localhost:{}/stored.bin", addr.port());
+
+    let upload = client
+        .put(&url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .body(vec![7u8; PAYLOAD_SIZE])
+        .send()
+        .await?;
+    assert_eq!(upload.status(), StatusCode::CREATED);
+
+    let started = Instant::now();
+    let download = client
+        .get(&url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .send()
+        .await?;
+    assert_eq!(download.bytes().await?.len(), PAYLOAD_SIZE);
+
+    let elapsed = started.elapsed();
+    assert!(elapsed >= floor(), "finished in {elapsed:?}");
+
+    server_handle.abort();
+
+    Ok(())
+}
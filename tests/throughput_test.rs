@@ -0,0 +1,90 @@
+mod common;
+
+use anyhow::Result;
+use beam::{ServerConfig, setup_server_with_config};
+use common::send_when_pending;
+use futures_util::StreamExt;
+use reqwest::StatusCode;
+
+const USERNAME: &str = "alice";
+const PASSWORD: &str = "secret123";
+const PAYLOAD_SIZE: usize = 100 * 1024 * 1024;
+
+fn payload_byte(index: usize) -> u8 {
+    (index.wrapping_mul(31) % 251) as u8
+}
+
+async fn transfer_large_payload(channel_buffer: usize, buffer_frames: Option<&str>) -> Result<()> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+        .channel_buffer(channel_buffer)
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let client = reqwest::Client::new();
+    let url = format!("http://localhost:{}/large.bin", addr.port());
+
+    let payload: Vec<u8> = (0..PAYLOAD_SIZE).map(payload_byte).collect();
+    let mut upload = client
+        .put(&url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .body(payload);
+    if let Some(buffer_frames) = buffer_frames {
+        upload = upload.header("x-buffer-frames", buffer_frames);
+    }
+    let upload = tokio::spawn(upload.send());
+
+    let download = send_when_pending(client.get(&url).basic_auth(USERNAME, Some(PASSWORD))).await?;
+    assert_eq!(download.status(), StatusCode::OK);
+
+    let mut received = 0;
+    let mut chunks = download.bytes_stream();
+    while let Some(chunk) = chunks.next().await {
+        for byte in chunk? {
+            assert_eq!(byte, payload_byte(received), "mismatch at byte {received}");
+            received += 1;
+        }
+    }
+    assert_eq!(received, PAYLOAD_SIZE);
+    assert_eq!(upload.await??.status(), StatusCode::OK);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn large_transfer_with_single_frame_buffer() -> Result<()> {
+    transfer_large_payload(1, None).await
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn large_transfer_with_wide_buffer() -> Result<()> {
+    transfer_large_payload(1024, None).await
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn large_transfer_with_single_frame_header() -> Result<()> {
+    transfer_large_payload(1024, Some("1")).await
+}
+
+#[tokio::test]
+async fn unparseable_buffer_frames_header_is_rejected() -> Result<()> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let response = reqwest::Client::new()
+        .put(format!("http://localhost:{}/frames.bin", addr.port()))
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .header("x-buffer-frames", "lots")
+        .body("data")
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    server_handle.abort();
+
+    Ok(())
+}
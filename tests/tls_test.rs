@@ -0,0 +1,54 @@
+mod common;
+
+use anyhow::Result;
+use beam::{ServerConfig, setup_server_with_config};
+use common::send_when_pending;
+use reqwest::StatusCode;
+
+#[tokio::test]
+async fn upload_and_download_over_https() -> Result<()> {
+    let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_owned()])?;
+    let tls_dir = tempfile::tempdir()?;
+    let cert_path = tls_dir.path().join("cert.pem");
+    let key_path = tls_dir.path().join("key.pem");
+    std::fs::write(&cert_path, certified.cert.pem())?;
+    std::fs::write(&key_path, certified.key_pair.serialize_pem())?;
+
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials("alice", "secret123")
+        .tls(&cert_path, &key_path)
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+
+    let client = reqwest::Client::builder()
+        .add_root_certificate(reqwest::Certificate::from_pem(
+            certified.cert.pem().as_bytes(),
+        )?)
+        .build()?;
+    let url = format!("https://localhost:{}/secure.txt", addr.port());
+
+    let plain = reqwest::Client::new()
+        .get(format!("http://localhost:{}/healthz", addr.port()))
+        .send()
+        .await;
+    assert!(plain.is_err(), "plain HTTP should not be served");
+
+    let upload = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth("alice", Some("secret123"))
+            .body("over tls")
+            .send(),
+    );
+
+    let download =
+        send_when_pending(client.get(&url).basic_auth("alice", Some("secret123"))).await?;
+    assert_eq!(download.status(), StatusCode::OK);
+    assert_eq!(download.text().await?, "over tls");
+    assert_eq!(upload.await??.status(), StatusCode::OK);
+
+    server_handle.abort();
+
+    Ok(())
+}
@@ -0,0 +1,87 @@
+mod common;
+
+use anyhow::Result;
+use beam::ServerConfig;
+use common::{PASSWORD, USERNAME, send_when_pending, start};
+use reqwest::StatusCode;
+
+#[tokio::test]
+async fn token_link_downloads_once_without_credentials() -> Result<()> {
+    let (base_url, server_handle) = start(ServerConfig::builder()).await;
+    let client = reqwest::Client::new();
+
+    let minted = client
+        .post(format!("{base_url}/new"))
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .json(&serde_json::json!({ "filename": "report.txt" }))
+        .send()
+        .await?;
+    assert_eq!(minted.status(), StatusCode::CREATED);
+    let minted: serde_json::Value = minted.json().await?;
+    assert_eq!(minted["filename"], "report.txt");
+    let path = minted["url"].as_str().expect("url should be a string");
+    let token = path
+        .strip_prefix("/t/")
+        .expect("url should be a token link");
+    assert_eq!(token.len(), 22);
+    assert!(
+        token
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'),
+        "{token}"
+    );
+
+    let link = format!("{base_url}{path}");
+    let upload = tokio::spawn(
+        client
+            .put(format!("{base_url}/report.txt"))
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .body("quarterly numbers")
+            .send(),
+    );
+
+    // Polling before the upload registers must not spend the token.
+    let download = send_when_pending(client.get(&link)).await?;
+    assert_eq!(download.status(), StatusCode::OK);
+    assert_eq!(download.text().await?, "quarterly numbers");
+    assert_eq!(upload.await??.status(), StatusCode::OK);
+
+    let reused = client.get(&link).send().await?;
+    assert_eq!(reused.status(), StatusCode::NOT_FOUND);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn minting_requires_credentials_and_a_valid_filename() -> Result<()> {
+    let (base_url, server_handle) = start(ServerConfig::builder()).await;
+    let client = reqwest::Client::new();
+    let url = format!("{base_url}/new");
+
+    let anonymous = client
+        .post(&url)
+        .json(&serde_json::json!({ "filename": "report.txt" }))
+        .send()
+        .await?;
+    assert_eq!(anonymous.status(), StatusCode::UNAUTHORIZED);
+
+    let traversal = client
+        .post(&url)
+        .basic_auth(USERNAME, Some(PASSWORD))
+        .json(&serde_json::json!({ "filename": "../etc/passwd" }))
+        .send()
+        .await?;
+    assert_eq!(traversal.status(), StatusCode::BAD_REQUEST);
+
+    let unknown = client
+        .get(format!("{base_url}/t/not-a-token"))
+        .send()
+        .await?;
+    assert_eq!(unknown.status(), StatusCode::NOT_FOUND);
+
+    server_handle.abort();
+
+    Ok(())
+}
@@ -0,0 +1,104 @@
+mod common;
+
+use anyhow::Result;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use beam::{ServerConfig, setup_server_with_config};
+use common::wait_for_stream;
+use reqwest::StatusCode;
+use sha2::{Digest, Sha256};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+const USERNAME: &str = "alice";
+const PASSWORD: &str = "secret123";
+const PAYLOAD: &str = "quarterly numbers";
+
+/// Downloads `filename` over a raw connection, so the trailers reqwest
+/// would swallow can be read back, and returns the whole response.
+async fn download_raw(addr: std::net::SocketAddr, filename: &str, te: &str) -> Result<String> {
+    let mut stream = TcpStream::connect(addr).await?;
+    let credentials = STANDARD.encode(format!("{USERNAME}:{PASSWORD}"));
+    stream
+        .write_all(
+            format!(
+                "GET /{filename} HTTP/1.1\r\nHost: localhost\r\nAuthorization: Basic {credentials}\r\n{te}Connection: close\r\n\r\n"
+            )
+            .as_bytes(),
+        )
+        .await?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await?;
+    Ok(response)
+}
+
+async fn relay(te: &str) -> Result<String> {
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let base_url = format!("http://localhost:{}", addr.port());
+
+    let upload = tokio::spawn(
+        reqwest::Client::new()
+            .put(format!("{base_url}/report.txt"))
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .body(PAYLOAD)
+            .send(),
+    );
+    wait_for_stream(&base_url, "report.txt").await;
+
+    let response = download_raw(addr, "report.txt", te).await?;
+    assert_eq!(upload.await??.status(), StatusCode::OK);
+
+    server_handle.abort();
+
+    Ok(response.to_ascii_lowercase())
+}
+
+#[tokio::test]
+async fn download_ends_with_length_and_checksum_trailers() -> Result<()> {
+    let response = relay("TE: trailers\r\n").await?;
+
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    assert!(
+        head.starts_with("http/1.1 200"),
+        "unexpected response: {head}"
+    );
+    assert!(head.contains("transfer-encoding: chunked"));
+    assert!(head.contains("trailer: x-bytes, x-checksum-sha256, digest"));
+    assert!(body.contains(PAYLOAD));
+
+    let sha256 = Sha256::digest(PAYLOAD);
+    let digest: String = sha256.iter().map(|byte| format!("{byte:02x}")).collect();
+    let (_, trailers) = body.split_once("\r\n0\r\n").unwrap();
+    assert!(
+        trailers.contains(&format!("x-bytes: {}\r\n", PAYLOAD.len())),
+        "unexpected trailers: {trailers}"
+    );
+    assert!(
+        trailers.contains(&format!("x-checksum-sha256: {digest}\r\n")),
+        "unexpected trailers: {trailers}"
+    );
+    // The response was lowercased, base64 included, so compare likewise.
+    let instance_digest = format!("digest: sha-256={}\r\n", STANDARD.encode(sha256));
+    assert!(
+        trailers.contains(&instance_digest.to_ascii_lowercase()),
+        "unexpected trailers: {trailers}"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn no_trailers_unless_asked_for() -> Result<()> {
+    let response = relay("").await?;
+
+    assert!(!response.contains("trailer:"));
+    assert!(!response.contains("x-bytes"));
+    assert!(response.ends_with(PAYLOAD));
+
+    Ok(())
+}
@@ -0,0 +1,134 @@
+mod common;
+
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+
+use anyhow::Result;
+use axum::{Router, body::Bytes, http::HeaderMap, routing::post};
+use beam::{ServerConfig, Webhook, setup_server_with_config};
+use common::send_when_pending;
+use reqwest::StatusCode;
+use ring::hmac;
+use serde_json::Value;
+use tokio::sync::mpsc;
+
+const USERNAME: &str = "alice";
+const PASSWORD: &str = "secret123";
+const SECRET: &[u8] = b"webhook-signing-secret";
+
+/// Serves a receiver that fails its first `failures` deliveries with `500`
+/// and forwards every delivery it gets.
+async fn mock_receiver(
+    failures: usize,
+) -> Result<(String, mpsc::UnboundedReceiver<(HeaderMap, Bytes)>)> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let app = Router::new().route(
+        "/hook",
+        post(move |headers: HeaderMap, body: Bytes| async move {
+            let _ = tx.send((headers, body));
+            if attempts.fetch_add(1, Ordering::Relaxed) < failures {
+                StatusCode::INTERNAL_SERVER_ERROR
+            } else {
+                StatusCode::NO_CONTENT
+            }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let url = format!("http://{}/hook", listener.local_addr()?);
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    Ok((url, rx))
+}
+
+#[tokio::test]
+async fn completed_transfer_is_reported_signed_and_retried() -> Result<()> {
+    let (hook_url, mut deliveries) = mock_receiver(1).await?;
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+        .webhook(Webhook::new(hook_url).secret(SECRET))
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let url = format!("http://localhost:{}/report.txt", addr.port());
+    let client = reqwest::Client::new();
+
+    let upload = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .body("twelve bytes")
+            .send(),
+    );
+    let download = send_when_pending(client.get(&url).basic_auth(USERNAME, Some(PASSWORD))).await?;
+    assert_eq!(download.text().await?, "twelve bytes");
+    assert_eq!(upload.await??.status(), StatusCode::OK);
+
+    let (_, refused) = deliveries.recv().await.expect("first delivery");
+    let (headers, body) = deliveries.recv().await.expect("retried delivery");
+    assert_eq!(refused, body);
+
+    let payload: Value = serde_json::from_slice(&body)?;
+    assert_eq!(payload["event"], "transfer-completed");
+    assert_eq!(payload["filename"], "report.txt");
+    assert_eq!(payload["bytes"], 12);
+    assert_eq!(payload["status"], 200);
+    assert!(payload["duration_ms"].is_u64());
+    assert!(payload.get("error").is_none());
+
+    assert_eq!(headers["content-type"], "application/json");
+    let tag = hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, SECRET), &body);
+    let hex: String = tag
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    assert_eq!(headers["x-beam-signature"], format!("sha256={hex}"));
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn failed_transfer_is_reported_with_its_error() -> Result<()> {
+    let (hook_url, mut deliveries) = mock_receiver(0).await?;
+    let config = ServerConfig::builder()
+        .port(0)
+        .credentials(USERNAME, PASSWORD)
+        .max_body_size(4)
+        .webhook(Webhook::new(hook_url))
+        .build();
+    let (addr, server_handle) = setup_server_with_config(config).await?;
+    let url = format!("http://localhost:{}/too-big.txt", addr.port());
+    let client = reqwest::Client::new();
+
+    let upload = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            // Streamed, so the overrun is only found mid-transfer.
+            .body(reqwest::Body::wrap_stream(futures_util::stream::iter([
+                Ok::<_, std::io::Error>("far more "),
+                Ok("than four bytes"),
+            ])))
+            .send(),
+    );
+    let download = send_when_pending(client.get(&url).basic_auth(USERNAME, Some(PASSWORD))).await?;
+    assert!(download.bytes().await.is_err());
+    let status = upload.await??.status();
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+
+    let (headers, body) = deliveries.recv().await.expect("delivery");
+    let payload: Value = serde_json::from_slice(&body)?;
+    assert_eq!(payload["event"], "transfer-failed");
+    assert_eq!(payload["filename"], "too-big.txt");
+    assert_eq!(payload["status"], status.as_u16());
+    assert!(payload["error"].is_string());
+    assert!(headers.get("x-beam-signature").is_none());
+
+    server_handle.abort();
+
+    Ok(())
+}
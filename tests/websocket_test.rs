@@ -0,0 +1,136 @@
+mod common;
+
+use anyhow::Result;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use beam::ServerConfig;
+use common::{PASSWORD, USERNAME, send_when_pending, start};
+use futures_util::{SinkExt, StreamExt};
+use reqwest::StatusCode;
+use tokio_tungstenite::{
+    connect_async,
+    tungstenite::{Message, client::IntoClientRequest, handshake::client::Request, http::header},
+};
+
+/// An authenticated WebSocket request for `path` on the server at `base_url`.
+fn ws_request(base_url: &str, path: &str) -> Request {
+    let url = format!("{}{path}", base_url.replacen("http", "ws", 1));
+    let mut request = url.into_client_request().expect("valid WebSocket URL");
+    let credentials = STANDARD.encode(format!("{USERNAME}:{PASSWORD}"));
+    request.headers_mut().insert(
+        header::AUTHORIZATION,
+        format!("Basic {credentials}").parse().unwrap(),
+    );
+    request
+}
+
+#[tokio::test]
+async fn websocket_upload_pipes_to_http_download() -> Result<()> {
+    let (base_url, server_handle) = start(ServerConfig::builder()).await;
+    let chunks: Vec<Vec<u8>> = (0..8u8).map(|i| vec![i; 10_000]).collect();
+    let expected = chunks.concat();
+
+    let request = ws_request(&base_url, "/ws/upload/socket.bin");
+    let (mut socket, _) = connect_async(request).await?;
+    let uploader = tokio::spawn(async move {
+        for chunk in chunks {
+            socket.send(Message::binary(chunk)).await?;
+        }
+        socket.send(Message::binary(Vec::new())).await?;
+        let close = loop {
+            match socket.next().await {
+                Some(Ok(Message::Close(frame))) => break frame,
+                Some(Ok(_)) => continue,
+                other => panic!("expected a close frame, got {other:?}"),
+            }
+        };
+        anyhow::Ok(close.expect("close frame should carry a code"))
+    });
+
+    let client = reqwest::Client::new();
+    let download = send_when_pending(
+        client
+            .get(format!("{base_url}/socket.bin"))
+            .basic_auth(USERNAME, Some(PASSWORD)),
+    )
+    .await?;
+    assert_eq!(download.status(), StatusCode::OK);
+    assert_eq!(download.bytes().await?.to_vec(), expected);
+
+    let close = uploader.await??;
+    assert_eq!(u16::from(close.code), 1000);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn http_upload_pipes_to_websocket_download() -> Result<()> {
+    let (base_url, server_handle) = start(ServerConfig::builder()).await;
+    let client = reqwest::Client::new();
+    let url = format!("{base_url}/to-socket.txt");
+
+    // Without an upload there is nothing to upgrade to.
+    let missing = connect_async(ws_request(&base_url, "/ws/download/to-socket.txt")).await;
+    assert!(missing.is_err(), "download should be refused before upload");
+
+    let upload = tokio::spawn(
+        client
+            .put(&url)
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .body("hello over websocket")
+            .send(),
+    );
+    common::wait_for_stream(&base_url, "to-socket.txt").await;
+
+    let request = ws_request(&base_url, "/ws/download/to-socket.txt");
+    let (mut socket, _) = connect_async(request).await?;
+    let mut received = Vec::new();
+    let close = loop {
+        match socket.next().await {
+            Some(Ok(Message::Binary(bytes))) => received.extend_from_slice(&bytes),
+            Some(Ok(Message::Close(frame))) => break frame,
+            Some(Ok(_)) => continue,
+            other => panic!("unexpected message {other:?}"),
+        }
+    };
+    assert_eq!(received, b"hello over websocket");
+    assert_eq!(u16::from(close.expect("close frame").code), 1000);
+    assert_eq!(upload.await??.status(), StatusCode::OK);
+
+    server_handle.abort();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn websocket_upload_reports_conflicts_in_its_close_code() -> Result<()> {
+    let (base_url, server_handle) = start(ServerConfig::builder()).await;
+    let client = reqwest::Client::new();
+
+    let occupying = tokio::spawn(
+        client
+            .put(format!("{base_url}/taken.txt"))
+            .basic_auth(USERNAME, Some(PASSWORD))
+            .body("first")
+            .send(),
+    );
+    common::wait_for_stream(&base_url, "taken.txt").await;
+
+    let request = ws_request(&base_url, "/ws/upload/taken.txt");
+    let (mut socket, _) = connect_async(request).await?;
+    let close = loop {
+        match socket.next().await {
+            Some(Ok(Message::Close(frame))) => break frame.expect("close frame"),
+            Some(Ok(_)) => continue,
+            other => panic!("expected a close frame, got {other:?}"),
+        }
+    };
+    assert_eq!(u16::from(close.code), 4409);
+    assert!(close.reason.contains("already in progress"));
+
+    occupying.abort();
+    server_handle.abort();
+
+    Ok(())
+}